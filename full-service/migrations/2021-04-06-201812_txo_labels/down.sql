@@ -0,0 +1,21 @@
+-- ALTER TABLE account_txo_statuses REMOVE COLUMN label;
+PRAGMA foreign_keys=OFF;
+CREATE TABLE OLD_account_txo_statuses (
+  account_id_hex VARCHAR NOT NULL,
+  txo_id_hex VARCHAR NOT NULL,
+  txo_status VARCHAR(8) NOT NULL,
+  txo_type VARCHAR(7) NOT NULL,
+  PRIMARY KEY (account_id_hex, txo_id_hex),
+  FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex),
+  FOREIGN KEY (txo_id_hex) REFERENCES txos(txo_id_hex)
+);
+INSERT INTO OLD_account_txo_statuses SELECT
+    account_id_hex,
+    txo_id_hex,
+    txo_status,
+    txo_type
+FROM account_txo_statuses;
+DROP TABLE account_txo_statuses;
+ALTER TABLE OLD_account_txo_statuses RENAME TO account_txo_statuses;
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,2 @@
+ALTER TABLE account_txo_statuses
+ADD COLUMN label VARCHAR;
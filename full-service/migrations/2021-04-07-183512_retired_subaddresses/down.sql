@@ -0,0 +1,28 @@
+-- ALTER TABLE assigned_subaddresses REMOVE COLUMN retired;
+PRAGMA foreign_keys=OFF;
+CREATE TABLE OLD_assigned_subaddresses (
+  id INTEGER NOT NULL PRIMARY KEY,
+  assigned_subaddress_b58 VARCHAR NOT NULL UNIQUE,
+  account_id_hex VARCHAR NOT NULL,
+  address_book_entry UNSIGNED BIG INT,
+  public_address BLOB NOT NULL,
+  subaddress_index UNSIGNED BIG INT NOT NULL,
+  comment VARCHAR NOT NULL DEFAULT '',
+  subaddress_spend_key BLOB NOT NULL,
+  FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex)
+);
+INSERT INTO OLD_assigned_subaddresses SELECT
+    id,
+    assigned_subaddress_b58,
+    account_id_hex,
+    address_book_entry,
+    public_address,
+    subaddress_index,
+    comment,
+    subaddress_spend_key
+FROM assigned_subaddresses;
+DROP TABLE assigned_subaddresses;
+ALTER TABLE OLD_assigned_subaddresses RENAME TO assigned_subaddresses;
+CREATE UNIQUE INDEX idx_assigned_subaddresses__assigned_subaddress_b58 ON assigned_subaddresses (assigned_subaddress_b58);
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,2 @@
+ALTER TABLE assigned_subaddresses
+ADD COLUMN retired BOOLEAN NOT NULL DEFAULT FALSE;
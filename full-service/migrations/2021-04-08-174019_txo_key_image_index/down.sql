@@ -0,0 +1 @@
+DROP INDEX idx_txos__key_image;
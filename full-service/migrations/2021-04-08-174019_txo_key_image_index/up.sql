@@ -0,0 +1 @@
+CREATE INDEX idx_txos__key_image ON txos (key_image);
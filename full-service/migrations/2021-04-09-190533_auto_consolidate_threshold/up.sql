@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+ADD COLUMN auto_consolidate_threshold UNSIGNED BIG INT;
@@ -0,0 +1 @@
+DROP TABLE txo_status_history;
@@ -0,0 +1,10 @@
+CREATE TABLE txo_status_history (
+  id INTEGER NOT NULL PRIMARY KEY,
+  account_id_hex VARCHAR NOT NULL,
+  txo_id_hex VARCHAR NOT NULL,
+  txo_status VARCHAR(8) NOT NULL,
+  changed_at UNSIGNED BIG INT NOT NULL,
+  FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex),
+  FOREIGN KEY (txo_id_hex) REFERENCES txos(txo_id_hex)
+);
+CREATE INDEX idx_txo_status_history__account_id_hex__txo_id_hex ON txo_status_history (account_id_hex, txo_id_hex);
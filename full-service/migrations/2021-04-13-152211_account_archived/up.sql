@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
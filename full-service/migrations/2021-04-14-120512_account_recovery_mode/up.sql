@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+ADD COLUMN recovery_mode BOOLEAN NOT NULL DEFAULT FALSE;
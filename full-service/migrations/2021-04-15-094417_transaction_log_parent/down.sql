@@ -0,0 +1,42 @@
+-- ALTER TABLE transaction_logs REMOVE COLUMN parent_transaction_id_hex;
+DROP INDEX idx_transaction_logs__parent_transaction_id_hex;
+PRAGMA foreign_keys=OFF;
+CREATE TABLE OLD_transaction_logs (
+    id INTEGER NOT NULL PRIMARY KEY,
+    transaction_id_hex VARCHAR NOT NULL UNIQUE,
+    account_id_hex VARCHAR NOT NULL,
+    recipient_public_address_b58 VARCHAR NOT NULL DEFAULT '',
+    assigned_subaddress_b58 VARCHAR NOT NULL DEFAULT '',
+    value UNSIGNED BIG INT NOT NULL,
+    fee UNSIGNED BIG INT,
+    status VARCHAR(8) NOT NULL,
+    sent_time UNSIGNED BIG INT,
+    submitted_block_index UNSIGNED BIG INT,
+    finalized_block_index UNSIGNED BIG INT,
+    comment TEXT NOT NULL DEFAULT '',
+    direction VARCHAR(8) NOT NULL,
+    tx BLOB,
+    FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex),
+    FOREIGN KEY (assigned_subaddress_b58) REFERENCES assigned_subaddresses(assigned_subaddress_b58)
+);
+INSERT INTO OLD_transaction_logs SELECT
+    id,
+    transaction_id_hex,
+    account_id_hex,
+    recipient_public_address_b58,
+    assigned_subaddress_b58,
+    value,
+    fee,
+    status,
+    sent_time,
+    submitted_block_index,
+    finalized_block_index,
+    comment,
+    direction,
+    tx
+FROM transaction_logs;
+DROP TABLE transaction_logs;
+ALTER TABLE OLD_transaction_logs RENAME TO transaction_logs;
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
+CREATE INDEX idx_transaction_logs__finalized_block_index ON transaction_logs (finalized_block_index);
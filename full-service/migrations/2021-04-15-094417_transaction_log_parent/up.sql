@@ -0,0 +1,3 @@
+ALTER TABLE transaction_logs
+ADD COLUMN parent_transaction_id_hex VARCHAR;
+CREATE INDEX idx_transaction_logs__parent_transaction_id_hex ON transaction_logs (parent_transaction_id_hex);
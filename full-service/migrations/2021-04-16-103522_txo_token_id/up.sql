@@ -0,0 +1,2 @@
+ALTER TABLE txos
+ADD COLUMN token_id UNSIGNED BIG INT NOT NULL DEFAULT 0;
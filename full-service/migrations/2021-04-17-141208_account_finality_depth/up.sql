@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+ADD COLUMN finality_depth UNSIGNED BIG INT;
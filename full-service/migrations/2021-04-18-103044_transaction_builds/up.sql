@@ -0,0 +1,8 @@
+CREATE TABLE transaction_builds (
+  id INTEGER NOT NULL PRIMARY KEY,
+  build_id_hex VARCHAR NOT NULL UNIQUE,
+  account_id_hex VARCHAR NOT NULL,
+  tx_proposal BLOB NOT NULL,
+  built_at UNSIGNED BIG INT NOT NULL,
+  FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex)
+);
@@ -0,0 +1,4 @@
+ALTER TABLE accounts
+ADD COLUMN webhook_url VARCHAR;
+ALTER TABLE accounts
+ADD COLUMN webhook_failures INTEGER NOT NULL DEFAULT 0;
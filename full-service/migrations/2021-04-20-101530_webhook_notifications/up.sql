@@ -0,0 +1,8 @@
+CREATE TABLE webhook_notifications (
+  id INTEGER NOT NULL PRIMARY KEY,
+  account_id_hex VARCHAR NOT NULL,
+  txo_id_hex VARCHAR NOT NULL,
+  received_block_index UNSIGNED BIG INT NOT NULL,
+  UNIQUE (account_id_hex, txo_id_hex),
+  FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex)
+);
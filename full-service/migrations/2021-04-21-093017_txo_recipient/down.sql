@@ -0,0 +1,39 @@
+-- ALTER TABLE txos REMOVE COLUMN recipient_public_address_b58;
+PRAGMA foreign_keys=OFF;
+CREATE TABLE OLD_txos (
+    id INTEGER NOT NULL PRIMARY KEY,
+    txo_id_hex VARCHAR NOT NULL UNIQUE,
+    value UNSIGNED BIG INT NOT NULL,
+    target_key BLOB NOT NULL,
+    public_key BLOB NOT NULL,
+    e_fog_hint BLOB NOT NULL,
+    txo BLOB NOT NULL,
+    subaddress_index UNSIGNED BIG INT,
+    key_image BLOB,
+    received_block_index UNSIGNED BIG INT,
+    pending_tombstone_block_index UNSIGNED BIG INT,
+    spent_block_index UNSIGNED BIG INT,
+    confirmation BLOB,
+    token_id UNSIGNED BIG INT NOT NULL DEFAULT 0
+);
+INSERT INTO OLD_txos SELECT
+    id,
+    txo_id_hex,
+    value,
+    target_key,
+    public_key,
+    e_fog_hint,
+    txo,
+    subaddress_index,
+    key_image,
+    received_block_index,
+    pending_tombstone_block_index,
+    spent_block_index,
+    confirmation,
+    token_id
+FROM txos;
+DROP TABLE txos;
+ALTER TABLE OLD_txos RENAME TO txos;
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
+CREATE INDEX idx_txos__key_image ON txos (key_image);
@@ -0,0 +1,2 @@
+ALTER TABLE txos
+ADD COLUMN recipient_public_address_b58 VARCHAR;
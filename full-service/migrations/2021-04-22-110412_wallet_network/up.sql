@@ -0,0 +1,4 @@
+CREATE TABLE wallet_network (
+  id INTEGER NOT NULL PRIMARY KEY,
+  network VARCHAR NOT NULL
+);
@@ -0,0 +1,21 @@
+-- ALTER TABLE transaction_builds REMOVE COLUMN tombstone_block_index;
+PRAGMA foreign_keys=OFF;
+CREATE TABLE OLD_transaction_builds (
+  id INTEGER NOT NULL PRIMARY KEY,
+  build_id_hex VARCHAR NOT NULL UNIQUE,
+  account_id_hex VARCHAR NOT NULL,
+  tx_proposal BLOB NOT NULL,
+  built_at UNSIGNED BIG INT NOT NULL,
+  FOREIGN KEY (account_id_hex) REFERENCES accounts(account_id_hex)
+);
+INSERT INTO OLD_transaction_builds SELECT
+    id,
+    build_id_hex,
+    account_id_hex,
+    tx_proposal,
+    built_at
+FROM transaction_builds;
+DROP TABLE transaction_builds;
+ALTER TABLE OLD_transaction_builds RENAME TO transaction_builds;
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,3 @@
+-- Builds recorded before this migration have no known tombstone block, so they
+-- are treated as expired and removed on the next sync.
+ALTER TABLE transaction_builds ADD COLUMN tombstone_block_index UNSIGNED BIG INT NOT NULL DEFAULT 0;
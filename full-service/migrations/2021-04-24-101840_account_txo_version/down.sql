@@ -0,0 +1,46 @@
+-- ALTER TABLE accounts REMOVE COLUMN txo_version;
+PRAGMA foreign_keys=OFF;
+CREATE TABLE OLD_accounts (
+    id INTEGER NOT NULL PRIMARY KEY,
+    account_id_hex VARCHAR NOT NULL UNIQUE,
+    account_key BLOB NOT NULL,
+    entropy BLOB NOT NULL,
+    main_subaddress_index UNSIGNED BIG INT NOT NULL,
+    change_subaddress_index UNSIGNED BIG INT NOT NULL,
+    next_subaddress_index UNSIGNED BIG INT NOT NULL,
+    first_block_index UNSIGNED BIG INT NOT NULL,
+    next_block_index UNSIGNED BIG INT NOT NULL,
+    import_block_index UNSIGNED BIG INT,
+    name VARCHAR NOT NULL DEFAULT '',
+    key_derivation_version INTEGER NOT NULL DEFAULT 1,
+    auto_consolidate_threshold UNSIGNED BIG INT,
+    archived BOOLEAN NOT NULL DEFAULT FALSE,
+    recovery_mode BOOLEAN NOT NULL DEFAULT FALSE,
+    finality_depth UNSIGNED BIG INT,
+    webhook_url VARCHAR,
+    webhook_failures INTEGER NOT NULL DEFAULT 0
+);
+INSERT INTO OLD_accounts SELECT
+    id,
+    account_id_hex,
+    account_key,
+    entropy,
+    main_subaddress_index,
+    change_subaddress_index,
+    next_subaddress_index,
+    first_block_index,
+    next_block_index,
+    import_block_index,
+    name,
+    key_derivation_version,
+    auto_consolidate_threshold,
+    archived,
+    recovery_mode,
+    finality_depth,
+    webhook_url,
+    webhook_failures
+FROM accounts;
+DROP TABLE accounts;
+ALTER TABLE OLD_accounts RENAME TO accounts;
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
@@ -0,0 +1,2 @@
+ALTER TABLE accounts
+ADD COLUMN txo_version UNSIGNED BIG INT NOT NULL DEFAULT 0;
@@ -11,7 +11,7 @@ use mc_common::logger::{create_app_logger, log, o};
 use mc_full_service::{
     config::APIConfig,
     wallet::{rocket, WalletState},
-    WalletDb, WalletService,
+    SyncThreadConfig, WalletDb, WalletService,
 };
 use mc_ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
 use std::sync::{Arc, RwLock};
@@ -58,10 +58,16 @@ fn main() {
             .wallet_db
             .to_str()
             .expect("Could not get wallet_db path"),
-        10,
+        config.wallet_db_connections,
+        config.wallet_db_connection_options(),
         logger.clone(),
     )
     .expect("Could not access wallet db");
+    wallet_db
+        .check_network(config.network)
+        .expect("Wallet db is not for this network");
+    wallet_db.set_dust_threshold(config.dust_threshold);
+    wallet_db.set_invalidated_txo_purge_depth(config.invalidated_txo_purge_depth);
 
     let mut mr_signer_verifier =
         MrSignerVerifier::from(mc_consensus_enclave_measurement::sigstruct());
@@ -113,6 +119,10 @@ fn main() {
             network_state,
             config.get_fog_resolver_factory(logger.clone()),
             config.num_workers,
+            SyncThreadConfig {
+                skip_archived_accounts: config.skip_archived_accounts,
+                scan_batch_size: config.scan_batch_size,
+            },
             config.offline,
             logger,
         ),
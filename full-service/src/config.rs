@@ -16,6 +16,8 @@ use mc_ledger_sync::ReqwestTransactionsFetcher;
 use mc_sgx_css::Signature;
 use mc_util_uri::{ConnectionUri, ConsensusClientUri, FogUri};
 
+use crate::db::{ConnectionOptions, Network, SynchronousMode};
+
 use displaydoc::Display;
 #[cfg(feature = "ip-check")]
 use reqwest::{
@@ -75,6 +77,35 @@ pub struct APIConfig {
     #[structopt(long, parse(from_os_str))]
     pub wallet_db: PathBuf,
 
+    /// Maximum number of connections in the WalletDb connection pool.
+    #[structopt(long, default_value = "10")]
+    pub wallet_db_connections: u32,
+
+    /// How many milliseconds a WalletDb connection waits for a lock held by
+    /// another connection before failing.
+    #[structopt(long, default_value = "30000", parse(try_from_str=parse_duration_in_milliseconds))]
+    pub wallet_db_busy_timeout: Duration,
+
+    /// Disable SQLite WAL journaling for the WalletDb. WAL lets API reads
+    /// proceed while the sync thread is writing.
+    #[structopt(long)]
+    pub wallet_db_disable_wal: bool,
+
+    /// SQLite synchronous level for the WalletDb: OFF, NORMAL, FULL or EXTRA.
+    #[structopt(long, default_value = "NORMAL")]
+    pub wallet_db_synchronous: SynchronousMode,
+
+    /// Log each WalletDb query and its duration at debug level. Requires
+    /// building with the sql-query-logging feature.
+    #[structopt(long)]
+    pub wallet_db_log_queries: bool,
+
+    /// Network that the wallet runs against: mainnet or testnet. The wallet
+    /// database records the network it was created for, and refuses to open
+    /// for another.
+    #[structopt(long, default_value = "mainnet")]
+    pub network: Network,
+
     /// Path to LedgerDB
     #[structopt(long, parse(from_os_str))]
     pub ledger_db: PathBuf,
@@ -107,6 +138,27 @@ pub struct APIConfig {
     #[structopt(long)]
     pub num_workers: Option<usize>,
 
+    /// Do not scan the ledger for archived accounts. They catch up once
+    /// unarchived.
+    #[structopt(long)]
+    pub skip_archived_accounts: bool,
+
+    /// Number of blocks to scan in one database transaction. Larger batches
+    /// speed up initial sync, but hold the database write lock for longer.
+    #[structopt(long, default_value = "1", parse(try_from_str=parse_scan_batch_size))]
+    pub scan_batch_size: usize,
+
+    /// Received Txos with a value in picoMOB below this threshold are recorded
+    /// as dust, which is not spendable and not counted in the unspent balance.
+    /// Zero disables the policy.
+    #[structopt(long, default_value = "0")]
+    pub dust_threshold: u64,
+
+    /// Invalidated Txos which are not in the ledger are only purged once this
+    /// many blocks follow the block they were received in.
+    #[structopt(long, default_value = "100")]
+    pub invalidated_txo_purge_depth: u64,
+
     /// How many seconds to wait between polling.
     #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
     pub poll_interval: Duration,
@@ -125,6 +177,19 @@ fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntEr
     Ok(Duration::from_secs(u64::from_str(src)?))
 }
 
+fn parse_duration_in_milliseconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_millis(u64::from_str(src)?))
+}
+
+fn parse_scan_batch_size(src: &str) -> Result<usize, String> {
+    let scan_batch_size = usize::from_str(src)
+        .map_err(|err| format!("Error parsing scan batch size {}: {:?}", src, err))?;
+    if scan_batch_size == 0 {
+        return Err("Scan batch size must be at least 1".to_string());
+    }
+    Ok(scan_batch_size)
+}
+
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
     let quorum_set: QuorumSet<ResponderId> = serde_json::from_str(src)
         .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?;
@@ -171,6 +236,17 @@ impl APIConfig {
         QuorumSet::new_with_node_ids(node_ids.len() as u32, node_ids)
     }
 
+    /// Get the options applied to each WalletDb connection.
+    pub fn wallet_db_connection_options(&self) -> ConnectionOptions {
+        ConnectionOptions {
+            enable_wal: !self.wallet_db_disable_wal,
+            synchronous: self.wallet_db_synchronous,
+            busy_timeout: Some(self.wallet_db_busy_timeout),
+            log_queries: self.wallet_db_log_queries,
+            ..Default::default()
+        }
+    }
+
     /// Get the attestation verifier used to verify fog reports when sending to
     /// fog recipients.
     pub fn get_fog_ingest_verifier(&self) -> Option<Verifier> {
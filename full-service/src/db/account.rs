@@ -6,11 +6,15 @@ use crate::db::{
     account_txo_status::AccountTxoStatusModel,
     assigned_subaddress::AssignedSubaddressModel,
     models::{
-        Account, AccountTxoStatus, AssignedSubaddress, NewAccount, TransactionLog, Txo,
-        TXO_STATUS_SPENT,
+        Account, AccountTxoStatus, AssignedSubaddress, NewAccount, TransactionBuild,
+        TransactionLog, Txo, TxoStatusChange, WebhookNotification, TXO_STATUS_SECRETED,
+        TXO_STATUS_SPENT, TXO_TYPE_MINTED, TXO_TYPE_RECEIVED,
     },
+    transaction_build::TransactionBuildModel,
     transaction_log::TransactionLogModel,
-    WalletDbError,
+    txo_status_history::TxoStatusChangeModel,
+    webhook_notification::WebhookNotificationModel,
+    WalletDbConnection, WalletDbError,
 };
 
 use mc_account_keys::{AccountKey, RootEntropy, RootIdentity, DEFAULT_SUBADDRESS_INDEX};
@@ -21,15 +25,22 @@ use mc_transaction_core::ring_signature::KeyImage;
 use bip39::Mnemonic;
 use diesel::{
     prelude::*,
+    query_builder::QueryFragment,
+    query_dsl::LoadQuery,
     r2d2::{ConnectionManager, PooledConnection},
+    sqlite::Sqlite,
     RunQueryDsl,
 };
+use mc_common::HashSet;
 use std::fmt;
 
 pub const DEFAULT_CHANGE_SUBADDRESS_INDEX: u64 = 1;
 pub const DEFAULT_NEXT_SUBADDRESS_INDEX: u64 = 2;
 pub const DEFAULT_FIRST_BLOCK_INDEX: u64 = 0;
 
+/// Accounts imported from their private keys alone, with no entropy to
+/// derive them from.
+pub const NO_ENTROPY_KEY_DERIVATION_VERSION: u8 = 0;
 pub const ROOT_ENTROPY_KEY_DERIVATION_VERSION: u8 = 1;
 pub const MNEMONIC_KEY_DERIVATION_VERSION: u8 = 2;
 
@@ -65,7 +76,7 @@ pub trait AccountModel {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(AccountID, String), WalletDbError>;
 
     /// Create an account.
@@ -82,7 +93,7 @@ pub trait AccountModel {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(AccountID, String), WalletDbError>;
 
     /// Create an account.
@@ -98,10 +109,13 @@ pub trait AccountModel {
         import_block_index: Option<u64>,
         next_subaddress_index: Option<u64>,
         name: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(AccountID, String), WalletDbError>;
 
     /// Import account.
+    ///
+    /// If recovery_mode is set, the account is marked as being in recovery
+    /// mode.
     #[allow(clippy::too_many_arguments)]
     fn import(
         mnemonic: &Mnemonic,
@@ -112,10 +126,14 @@ pub trait AccountModel {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        recovery_mode: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Account, WalletDbError>;
 
-    /// Import account.
+    /// Import a legacy account from its root entropy.
+    ///
+    /// If recovery_mode is set, the account is marked as being in recovery
+    /// mode.
     #[allow(clippy::too_many_arguments)]
     fn import_legacy(
         entropy: &RootEntropy,
@@ -126,7 +144,17 @@ pub trait AccountModel {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        recovery_mode: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Account, WalletDbError>;
+
+    /// Import account from its private keys, without entropy.
+    fn import_from_account_key(
+        account_key: &AccountKey,
+        name: Option<String>,
+        import_block_index: u64,
+        first_block_index: Option<u64>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Account, WalletDbError>;
 
     /// List all accounts.
@@ -134,7 +162,7 @@ pub trait AccountModel {
     /// Returns:
     /// * Vector of all Accounts in the DB
     fn list_all(
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Account>, WalletDbError>;
 
     /// Get a specific account.
@@ -143,13 +171,13 @@ pub trait AccountModel {
     /// * Account
     fn get(
         account_id: &AccountID,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Account, WalletDbError>;
 
     /// Get the accounts associated with the given Txo.
     fn get_by_txo_id(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Account>, WalletDbError>;
 
     /// Update an account.
@@ -158,22 +186,107 @@ pub trait AccountModel {
     fn update_name(
         &self,
         new_name: String,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the subaddress index that change is sent to.
+    fn update_change_subaddress_index(
+        &self,
+        subaddress_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the subaddress reported as the account's main address.
+    fn update_main_subaddress_index(
+        &self,
+        subaddress_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the unspent Txo count above which sends consolidate Txos.
+    fn update_auto_consolidate_threshold(
+        &self,
+        threshold: Option<i64>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the number of blocks which must follow a sent transaction before
+    /// it is reported as succeeded.
+    fn update_finality_depth(
+        &self,
+        finality_depth: Option<i64>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the URL notified of received Txos, clearing any failures to
+    /// deliver to the previous URL.
+    fn update_webhook_url(
+        &self,
+        webhook_url: Option<&str>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the number of consecutive notifications which could not be
+    /// delivered to the webhook URL.
+    fn update_webhook_failures(
+        &self,
+        webhook_failures: i32,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Get the version of an account's Txos, without loading the account.
+    fn get_txo_version(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<i64, WalletDbError>;
+
+    /// Increment the version of an account's Txos.
+    fn increment_txo_version(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Archive or unarchive an account.
+    fn update_archived(
+        &self,
+        archived: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Set whether an account is in recovery mode.
+    fn update_recovery_mode(
+        &self,
+        recovery_mode: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the index of the next block to scan for this account.
+    fn update_next_block_index(
+        &self,
+        next_block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     /// Update key-image-matching txos associated with this account to spent for
     /// a given block height.
+    ///
+    /// A matched Txo is also marked spent for every other account which
+    /// received it, so that accounts sharing the Txo agree on its status
+    /// without waiting to sync the block themselves. Likewise, an account which
+    /// minted the Txo and still has it secreted resolves it to spent, since the
+    /// minting account may have synced the block before the key image was
+    /// known.
     fn update_spent_and_increment_next_block(
         &self,
         spent_block_index: i64,
         key_images: Vec<KeyImage>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     /// Delete an account.
     fn delete(
         self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 }
 
@@ -187,7 +300,7 @@ impl AccountModel for Account {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(AccountID, String), WalletDbError> {
         let account_key = Slip10Key::from(mnemonic.clone())
             .try_into_account_key(
@@ -219,7 +332,7 @@ impl AccountModel for Account {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(AccountID, String), WalletDbError> {
         let root_id = RootIdentity {
             root_entropy: entropy.clone(),
@@ -250,7 +363,7 @@ impl AccountModel for Account {
         import_block_index: Option<u64>,
         next_subaddress_index: Option<u64>,
         name: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(AccountID, String), WalletDbError> {
         use crate::db::schema::accounts;
 
@@ -318,7 +431,8 @@ impl AccountModel for Account {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        recovery_mode: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Account, WalletDbError> {
         Ok(conn.transaction::<Account, WalletDbError, _>(|| {
             let (account_id, _public_address_b58) = Account::create_from_mnemonic(
@@ -332,6 +446,9 @@ impl AccountModel for Account {
                 fog_authority_spki,
                 conn,
             )?;
+            if recovery_mode {
+                Account::get(&account_id, &conn)?.update_recovery_mode(true, conn)?;
+            }
             Ok(Account::get(&account_id, &conn)?)
         })?)
     }
@@ -345,7 +462,8 @@ impl AccountModel for Account {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        recovery_mode: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Account, WalletDbError> {
         Ok(conn.transaction::<Account, WalletDbError, _>(|| {
             let (account_id, _public_address_b58) = Account::create_from_root_entropy(
@@ -359,12 +477,37 @@ impl AccountModel for Account {
                 fog_authority_spki,
                 conn,
             )?;
+            if recovery_mode {
+                Account::get(&account_id, &conn)?.update_recovery_mode(true, conn)?;
+            }
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn import_from_account_key(
+        account_key: &AccountKey,
+        name: Option<String>,
+        import_block_index: u64,
+        first_block_index: Option<u64>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Account, WalletDbError> {
+        Ok(conn.transaction::<Account, WalletDbError, _>(|| {
+            let (account_id, _public_address_b58) = Account::create(
+                &[],
+                NO_ENTROPY_KEY_DERIVATION_VERSION,
+                account_key,
+                first_block_index,
+                Some(import_block_index),
+                None,
+                &name.unwrap_or_else(|| "".to_string()),
+                conn,
+            )?;
             Ok(Account::get(&account_id, &conn)?)
         })?)
     }
 
     fn list_all(
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Account>, WalletDbError> {
         use crate::db::schema::accounts;
 
@@ -375,7 +518,7 @@ impl AccountModel for Account {
 
     fn get(
         account_id: &AccountID,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Account, WalletDbError> {
         use crate::db::schema::accounts::dsl::{account_id_hex as dsl_account_id_hex, accounts};
 
@@ -394,7 +537,7 @@ impl AccountModel for Account {
 
     fn get_by_txo_id(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Account>, WalletDbError> {
         use crate::db::schema::account_txo_statuses::dsl::account_txo_statuses;
 
@@ -418,7 +561,7 @@ impl AccountModel for Account {
     fn update_name(
         &self,
         new_name: String,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
 
@@ -428,62 +571,240 @@ impl AccountModel for Account {
         Ok(())
     }
 
+    fn update_change_subaddress_index(
+        &self,
+        subaddress_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::change_subaddress_index.eq(subaddress_index))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_main_subaddress_index(
+        &self,
+        subaddress_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::main_subaddress_index.eq(subaddress_index))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_auto_consolidate_threshold(
+        &self,
+        threshold: Option<i64>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::auto_consolidate_threshold.eq(threshold))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_finality_depth(
+        &self,
+        finality_depth: Option<i64>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::finality_depth.eq(finality_depth))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_webhook_url(
+        &self,
+        webhook_url: Option<&str>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set((
+                crate::db::schema::accounts::webhook_url.eq(webhook_url),
+                crate::db::schema::accounts::webhook_failures.eq(0),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_webhook_failures(
+        &self,
+        webhook_failures: i32,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::webhook_failures.eq(webhook_failures))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn get_txo_version(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<i64, WalletDbError> {
+        use crate::db::schema::accounts::dsl::{
+            account_id_hex as dsl_account_id_hex, accounts, txo_version,
+        };
+
+        match accounts
+            .filter(dsl_account_id_hex.eq(account_id_hex))
+            .select(txo_version)
+            .first(conn)
+        {
+            Ok(version) => Ok(version),
+            Err(diesel::result::Error::NotFound) => {
+                Err(WalletDbError::AccountNotFound(account_id_hex.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn increment_txo_version(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{
+            account_id_hex as dsl_account_id_hex, accounts, txo_version,
+        };
+
+        diesel::update(accounts.filter(dsl_account_id_hex.eq(account_id_hex)))
+            .set(txo_version.eq(txo_version + 1))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_archived(
+        &self,
+        archived: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::archived.eq(archived))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_recovery_mode(
+        &self,
+        recovery_mode: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::recovery_mode.eq(recovery_mode))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn update_next_block_index(
+        &self,
+        next_block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
+
+        diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
+            .set(crate::db::schema::accounts::next_block_index.eq(next_block_index))
+            .execute(conn)?;
+        Ok(())
+    }
+
     fn update_spent_and_increment_next_block(
         &self,
         spent_block_index: i64,
         key_images: Vec<KeyImage>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::{
-            account_txo_statuses::dsl::account_txo_statuses,
+            account_txo_statuses,
             accounts::dsl::{account_id_hex, accounts},
-            txos::dsl::{txo_id_hex, txos},
+            txos,
         };
 
         Ok(conn.transaction::<(), WalletDbError, _>(|| {
-            for key_image in key_images {
-                // Get the txo by key_image
-                let matches = crate::db::schema::txos::table
-                    .select(crate::db::schema::txos::all_columns)
-                    .filter(
-                        crate::db::schema::txos::key_image.eq(mc_util_serial::encode(&key_image)),
-                    )
+            // Look up all of this block's key images at once, using the key image index,
+            // and only consider the Txos associated with this account.
+            let mut matches: Vec<Txo> =
+                txos_with_key_images(key_images.iter().map(mc_util_serial::encode).collect())
                     .load::<Txo>(conn)?;
-
-                if matches.is_empty() {
-                    // Not Found is ok - this means it's a key_image not associated with any of our
-                    // txos
-                    continue;
-                } else if matches.len() > 1 {
+            let matched_txo_ids: Vec<String> =
+                matches.iter().map(|txo| txo.txo_id_hex.clone()).collect();
+            let account_txo_ids: HashSet<String> = account_txo_statuses::table
+                .filter(account_txo_statuses::account_id_hex.eq(&self.account_id_hex))
+                .filter(account_txo_statuses::txo_id_hex.eq_any(matched_txo_ids))
+                .select(account_txo_statuses::txo_id_hex)
+                .load::<String>(conn)?
+                .into_iter()
+                .collect();
+            matches.retain(|txo| account_txo_ids.contains(&txo.txo_id_hex));
+
+            let mut seen_key_images = HashSet::default();
+            for txo in matches.iter() {
+                if !seen_key_images.insert(txo.key_image.clone()) {
                     return Err(WalletDbError::DuplicateEntries(format!(
                         "Key Image: {:?}",
-                        key_image
+                        txo.key_image
                     )));
-                } else {
-                    // Update the TXO
-                    diesel::update(txos.filter(txo_id_hex.eq(&matches[0].txo_id_hex)))
-                        .set(crate::db::schema::txos::spent_block_index.eq(Some(spent_block_index)))
-                        .execute(conn)?;
+                }
+            }
+
+            for txo in matches {
+                // Update the TXO
+                diesel::update(txos::table.filter(txos::txo_id_hex.eq(&txo.txo_id_hex)))
+                    .set(txos::spent_block_index.eq(Some(spent_block_index)))
+                    .execute(conn)?;
 
-                    // Update the AccountTxoStatus
+                // Update the AccountTxoStatus for this account, and for every other
+                // account which received the Txo.
+                for status in AccountTxoStatus::get_all_associated_accounts(&txo.txo_id_hex, conn)?
+                {
+                    let is_secreted = status.txo_type == TXO_TYPE_MINTED
+                        && status.txo_status == TXO_STATUS_SECRETED;
+                    if status.txo_status == TXO_STATUS_SPENT
+                        || (status.account_id_hex != self.account_id_hex
+                            && status.txo_type != TXO_TYPE_RECEIVED
+                            && !is_secreted)
+                    {
+                        continue;
+                    }
                     diesel::update(
-                        account_txo_statuses.find((&self.account_id_hex, &matches[0].txo_id_hex)),
-                    )
-                    .set(
-                        crate::db::schema::account_txo_statuses::txo_status
-                            .eq(TXO_STATUS_SPENT.to_string()),
+                        account_txo_statuses::table.find((&status.account_id_hex, &txo.txo_id_hex)),
                     )
+                    .set(account_txo_statuses::txo_status.eq(TXO_STATUS_SPENT.to_string()))
                     .execute(conn)?;
-
-                    // FIXME: WS-13 - make sure the path for all txo_statuses and txo_types exist
-                    // and are tested Update the transaction status if the txos
-                    // are all spent
-                    TransactionLog::update_transactions_associated_to_txo(
-                        &matches[0].txo_id_hex,
-                        spent_block_index,
+                    TxoStatusChange::record(
+                        &status.account_id_hex,
+                        &txo.txo_id_hex,
+                        TXO_STATUS_SPENT,
                         conn,
                     )?;
                 }
+
+                // FIXME: WS-13 - make sure the path for all txo_statuses and txo_types exist
+                // and are tested Update the transaction status if the txos
+                // are all spent
+                TransactionLog::update_transactions_associated_to_txo(
+                    &txo.txo_id_hex,
+                    spent_block_index,
+                    conn,
+                )?;
             }
             diesel::update(accounts.filter(account_id_hex.eq(&self.account_id_hex)))
                 .set(crate::db::schema::accounts::next_block_index.eq(spent_block_index + 1))
@@ -495,7 +816,7 @@ impl AccountModel for Account {
     /// Delete an account.
     fn delete(
         self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::accounts::dsl::{account_id_hex, accounts};
 
@@ -503,23 +824,43 @@ impl AccountModel for Account {
 
         // Also delete transaction logs associated with this account
         TransactionLog::delete_all_for_account(&self.account_id_hex, conn)?;
+        TransactionBuild::delete_all_for_account(&self.account_id_hex, conn)?;
+        WebhookNotification::delete_all_for_account(&self.account_id_hex, conn)?;
 
         // Also delete the associated assigned subaddresses
         AssignedSubaddress::delete_all(&self.account_id_hex, conn)?;
 
         // Also delete txo statuses associated with this account.
         AccountTxoStatus::delete_all_for_account(&self.account_id_hex, conn)?;
+        TxoStatusChange::delete_all_for_account(&self.account_id_hex, conn)?;
 
         Ok(())
     }
 }
 
+/// The Txos whose key image is one of `encoded_key_images`, looked up with the
+/// key image index.
+fn txos_with_key_images(
+    encoded_key_images: Vec<Vec<u8>>,
+) -> impl RunQueryDsl<WalletDbConnection> + LoadQuery<WalletDbConnection, Txo> + QueryFragment<Sqlite>
+{
+    use crate::db::schema::txos;
+
+    txos::table
+        .filter(txos::key_image.eq_any(encoded_key_images))
+        .select(txos::all_columns)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::WalletDbTestContext;
+    use crate::{
+        db::{models::TXO_STATUS_UNSPENT, txo::TxoModel},
+        test_utils::{create_test_received_txo, WalletDbTestContext, MOB},
+    };
     use mc_account_keys::RootIdentity;
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
     use std::{collections::HashSet, convert::TryFrom, iter::FromIterator};
@@ -570,6 +911,13 @@ mod tests {
             next_block_index: 0,
             import_block_index: None,
             name: "Alice's Main Account".to_string(),
+            auto_consolidate_threshold: None,
+            archived: false,
+            recovery_mode: false,
+            finality_depth: None,
+            webhook_url: None,
+            webhook_failures: 0,
+            txo_version: 0,
         };
         assert_eq!(expected_account, acc);
 
@@ -630,6 +978,13 @@ mod tests {
             next_block_index: 51,
             import_block_index: Some(50),
             name: "".to_string(),
+            auto_consolidate_threshold: None,
+            archived: false,
+            recovery_mode: false,
+            finality_depth: None,
+            webhook_url: None,
+            webhook_failures: 0,
+            txo_version: 0,
         };
         assert_eq!(expected_account_secondary, acc_secondary);
 
@@ -698,4 +1053,164 @@ mod tests {
         let decoded_account_key: AccountKey = mc_util_serial::decode(&account.account_key).unwrap();
         assert_eq!(decoded_account_key, account_key);
     }
+
+    #[test_with_logger]
+    fn test_update_spent_with_many_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        // Create many accounts, each with one received Txo.
+        let mut accounts = Vec::new();
+        let mut key_images = Vec::new();
+        for i in 0..20 {
+            let root_id = RootIdentity::from_random(&mut rng);
+            let account_key = AccountKey::from(&root_id);
+            let (account_id_hex, _public_address_b58) = Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                &format!("Account {}", i),
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+            let (txo_id_hex, _txo, key_image) = create_test_received_txo(
+                &account_key,
+                0,
+                (i + 1) * MOB as u64,
+                12,
+                &mut rng,
+                &wallet_db,
+            );
+            accounts.push((account_id_hex.to_string(), txo_id_hex));
+            key_images.push(key_image);
+        }
+
+        // A block spending half of the Txos, plus key images we know nothing about.
+        let mut block_key_images: Vec<KeyImage> = key_images.iter().step_by(2).cloned().collect();
+        block_key_images.extend((0..100).map(|_| KeyImage::from(rng.next_u64())));
+
+        for (i, (account_id_hex, txo_id_hex)) in accounts.iter().enumerate() {
+            let account = Account::get(&AccountID(account_id_hex.clone()), &conn).unwrap();
+            account
+                .update_spent_and_increment_next_block(13, block_key_images.clone(), &conn)
+                .unwrap();
+
+            let account = Account::get(&AccountID(account_id_hex.clone()), &conn).unwrap();
+            assert_eq!(account.next_block_index, 14);
+
+            let status = AccountTxoStatus::get(account_id_hex, txo_id_hex, &conn).unwrap();
+            if i % 2 == 0 {
+                assert_eq!(status.txo_status, TXO_STATUS_SPENT);
+                let txo = Txo::get(txo_id_hex, &conn).unwrap();
+                assert_eq!(txo.txo.spent_block_index, Some(13));
+            } else {
+                assert_eq!(status.txo_status, TXO_STATUS_UNSPENT);
+            }
+        }
+
+        // Spent detection looks up key images with the index, rather than scanning
+        // all Txos.
+        #[derive(QueryableByName)]
+        struct QueryPlanRow {
+            #[sql_type = "diesel::sql_types::Text"]
+            detail: String,
+        }
+        let query = txos_with_key_images(key_images.iter().map(mc_util_serial::encode).collect());
+        // The binds are rendered after the statement as a comment, and are left
+        // unbound for the plan.
+        let sql = diesel::debug_query::<Sqlite, _>(&query).to_string();
+        let plan: Vec<QueryPlanRow> = diesel::sql_query(format!("EXPLAIN QUERY PLAN {}", sql))
+            .load(&conn)
+            .unwrap();
+        assert!(plan
+            .iter()
+            .any(|row| row.detail.contains("idx_txos__key_image")));
+    }
+
+    // Spending a Txo received by two accounts should mark it spent for both, even
+    // before the second account syncs the spending block.
+    #[test_with_logger]
+    fn test_update_spent_for_shared_txo(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let mut account_ids = Vec::new();
+        let mut account_keys = Vec::new();
+        for name in ["Alice", "Bob"].iter() {
+            let root_id = RootIdentity::from_random(&mut rng);
+            let (account_id_hex, _public_address_b58) = Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                name,
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+            account_ids.push(account_id_hex.to_string());
+            account_keys.push(AccountKey::from(&root_id));
+        }
+
+        // A Txo received by Alice, which is also found for Bob, as can happen when
+        // imported accounts overlap.
+        let (txo_id_hex, txo, key_image) = create_test_received_txo(
+            &account_keys[0],
+            0,
+            (10 * MOB) as u64,
+            12,
+            &mut rng,
+            &wallet_db,
+        );
+        Txo::create_received(
+            txo,
+            Some(0),
+            Some(key_image),
+            (10 * MOB) as u64,
+            12,
+            &account_ids[1],
+            &conn,
+        )
+        .unwrap();
+
+        // Only Alice syncs the block spending the Txo.
+        let alice = Account::get(&AccountID(account_ids[0].clone()), &conn).unwrap();
+        alice
+            .update_spent_and_increment_next_block(13, vec![key_image], &conn)
+            .unwrap();
+
+        for account_id_hex in account_ids.iter() {
+            let status = AccountTxoStatus::get(account_id_hex, &txo_id_hex, &conn).unwrap();
+            assert_eq!(status.txo_status, TXO_STATUS_SPENT);
+        }
+        let txo = Txo::get(&txo_id_hex, &conn).unwrap();
+        assert_eq!(txo.txo.spent_block_index, Some(13));
+
+        // Bob's status is only moved to spent once, even after he syncs the block too.
+        let bob = Account::get(&AccountID(account_ids[1].clone()), &conn).unwrap();
+        bob.update_spent_and_increment_next_block(13, vec![key_image], &conn)
+            .unwrap();
+        let bob = Account::get(&AccountID(account_ids[1].clone()), &conn).unwrap();
+        assert_eq!(bob.next_block_index, 14);
+        for account_id_hex in account_ids.iter() {
+            let spent_changes = TxoStatusChange::list_for_txo(account_id_hex, &txo_id_hex, &conn)
+                .unwrap()
+                .into_iter()
+                .filter(|change| change.txo_status == TXO_STATUS_SPENT)
+                .count();
+            assert_eq!(spent_changes, 1);
+        }
+    }
 }
@@ -2,11 +2,17 @@
 
 //! DB impl for the AccountTxoStatus model.
 
-use crate::db::models::{
-    AccountTxoStatus, NewAccountTxoStatus, TXO_STATUS_ORPHANED, TXO_STATUS_UNSPENT,
+use crate::db::{
+    account::AccountModel,
+    models::{
+        Account, AccountTxoStatus, NewAccountTxoStatus, TxoStatusChange, TXO_STATUS_DUST,
+        TXO_STATUS_INVALIDATED, TXO_STATUS_ORPHANED, TXO_STATUS_SPENT, TXO_STATUS_UNSPENT,
+        TXO_TYPE_RECEIVED,
+    },
+    txo_status_history::TxoStatusChangeModel,
 };
 
-use crate::db::WalletDbError;
+use crate::db::{WalletDbConnection, WalletDbError};
 use diesel::{
     debug_query,
     prelude::*,
@@ -20,38 +26,83 @@ pub trait AccountTxoStatusModel {
         txo_id_hex: &str,
         txo_status: &str,
         txo_type: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     fn get(
         account_id_hex: &str,
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<AccountTxoStatus, WalletDbError>;
 
     fn get_all_associated_accounts(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<AccountTxoStatus>, WalletDbError>;
 
     fn get_all_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<AccountTxoStatus>, WalletDbError>;
 
+    /// Get the ids of the Txos received by this account which were also
+    /// received by at least one other account in the wallet.
+    fn get_shared_txo_ids_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<String>, WalletDbError>;
+
+    /// Count the Txos associated with this account in each status, without
+    /// loading them.
+    ///
+    /// Returns:
+    /// * Vec<(txo_status, count)>, for the statuses with at least one Txo
+    fn count_by_status(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<(String, i64)>, WalletDbError>;
+
     fn set_unspent(
         &self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     fn set_orphaned(
         &self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    fn set_dust(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    fn set_spent(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    fn set_invalidated(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    fn set_label(
+        &self,
+        label: Option<&str>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     fn delete_all_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Delete the association between a Txo and an account, and increment the
+    /// account's Txo version. The Txo itself is not deleted.
+    fn delete(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 }
 
@@ -61,7 +112,7 @@ impl AccountTxoStatusModel for AccountTxoStatus {
         txo_id_hex: &str,
         txo_status: &str,
         txo_type: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::account_txo_statuses;
 
@@ -75,6 +126,7 @@ impl AccountTxoStatusModel for AccountTxoStatus {
         diesel::insert_into(account_txo_statuses::table)
             .values(&new_account_txo_status)
             .execute(conn)?;
+        TxoStatusChange::record(account_id_hex, txo_id_hex, txo_status, conn)?;
 
         Ok(())
     }
@@ -82,7 +134,7 @@ impl AccountTxoStatusModel for AccountTxoStatus {
     fn get(
         account_id_hex: &str,
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<AccountTxoStatus, WalletDbError> {
         use crate::db::schema::account_txo_statuses::dsl::account_txo_statuses;
 
@@ -101,7 +153,7 @@ impl AccountTxoStatusModel for AccountTxoStatus {
 
     fn get_all_associated_accounts(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<AccountTxoStatus>, WalletDbError> {
         use crate::db::schema::{
             account_txo_statuses as cols, account_txo_statuses::dsl::account_txo_statuses,
@@ -117,7 +169,7 @@ impl AccountTxoStatusModel for AccountTxoStatus {
 
     fn get_all_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<AccountTxoStatus>, WalletDbError> {
         use crate::db::schema::{
             account_txo_statuses as cols, account_txo_statuses::dsl::account_txo_statuses,
@@ -138,33 +190,159 @@ impl AccountTxoStatusModel for AccountTxoStatus {
         }
     }
 
+    fn get_shared_txo_ids_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<String>, WalletDbError> {
+        use diesel::sql_types::Text;
+
+        #[derive(QueryableByName)]
+        struct SharedTxo {
+            #[sql_type = "Text"]
+            txo_id_hex: String,
+        }
+
+        // Diesel cannot join a table to itself, so the self-join is written out.
+        let shared: Vec<SharedTxo> = diesel::sql_query(
+            "SELECT DISTINCT mine.txo_id_hex AS txo_id_hex
+             FROM account_txo_statuses AS mine
+             INNER JOIN account_txo_statuses AS other
+                 ON other.txo_id_hex = mine.txo_id_hex
+             WHERE mine.account_id_hex = ?
+                 AND mine.txo_type = ?
+                 AND other.account_id_hex != ?
+                 AND other.txo_type = ?",
+        )
+        .bind::<Text, _>(account_id_hex)
+        .bind::<Text, _>(TXO_TYPE_RECEIVED)
+        .bind::<Text, _>(account_id_hex)
+        .bind::<Text, _>(TXO_TYPE_RECEIVED)
+        .load(conn)?;
+
+        Ok(shared.into_iter().map(|txo| txo.txo_id_hex).collect())
+    }
+
+    fn count_by_status(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<(String, i64)>, WalletDbError> {
+        use crate::db::schema::{
+            account_txo_statuses as cols, account_txo_statuses::dsl::account_txo_statuses,
+        };
+
+        Ok(account_txo_statuses
+            .filter(cols::account_id_hex.eq(account_id_hex))
+            .group_by(cols::txo_status)
+            .select((cols::txo_status, diesel::dsl::count_star()))
+            .load(conn)?)
+    }
+
     fn set_unspent(
         &self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::account_txo_statuses::txo_status;
 
         diesel::update(self)
             .set(txo_status.eq(TXO_STATUS_UNSPENT))
             .execute(conn)?;
+        TxoStatusChange::record(
+            &self.account_id_hex,
+            &self.txo_id_hex,
+            TXO_STATUS_UNSPENT,
+            conn,
+        )?;
         Ok(())
     }
 
     fn set_orphaned(
         &self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::account_txo_statuses::txo_status;
 
         diesel::update(self)
             .set(txo_status.eq(TXO_STATUS_ORPHANED))
             .execute(conn)?;
+        TxoStatusChange::record(
+            &self.account_id_hex,
+            &self.txo_id_hex,
+            TXO_STATUS_ORPHANED,
+            conn,
+        )?;
+        Ok(())
+    }
+
+    fn set_dust(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_txo_statuses::txo_status;
+
+        diesel::update(self)
+            .set(txo_status.eq(TXO_STATUS_DUST))
+            .execute(conn)?;
+        TxoStatusChange::record(
+            &self.account_id_hex,
+            &self.txo_id_hex,
+            TXO_STATUS_DUST,
+            conn,
+        )?;
+        Ok(())
+    }
+
+    fn set_spent(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_txo_statuses::txo_status;
+
+        diesel::update(self)
+            .set(txo_status.eq(TXO_STATUS_SPENT))
+            .execute(conn)?;
+        TxoStatusChange::record(
+            &self.account_id_hex,
+            &self.txo_id_hex,
+            TXO_STATUS_SPENT,
+            conn,
+        )?;
+        Ok(())
+    }
+
+    fn set_invalidated(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_txo_statuses::txo_status;
+
+        diesel::update(self)
+            .set(txo_status.eq(TXO_STATUS_INVALIDATED))
+            .execute(conn)?;
+        TxoStatusChange::record(
+            &self.account_id_hex,
+            &self.txo_id_hex,
+            TXO_STATUS_INVALIDATED,
+            conn,
+        )?;
+        Ok(())
+    }
+
+    fn set_label(
+        &self,
+        label: Option<&str>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::account_txo_statuses::label as label_col;
+
+        diesel::update(self)
+            .set(label_col.eq(label))
+            .execute(conn)?;
         Ok(())
     }
 
     fn delete_all_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::{
             account_txo_statuses as cols, account_txo_statuses::dsl::account_txo_statuses,
@@ -191,4 +369,13 @@ impl AccountTxoStatusModel for AccountTxoStatus {
 
         Ok(())
     }
+
+    fn delete(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        diesel::delete(self).execute(conn)?;
+        Account::increment_txo_version(&self.account_id_hex, conn)?;
+        Ok(())
+    }
 }
@@ -12,7 +12,7 @@ use crate::db::{
 use mc_account_keys::AccountKey;
 use mc_crypto_keys::RistrettoPublic;
 
-use crate::db::WalletDbError;
+use crate::db::{WalletDbConnection, WalletDbError};
 use diesel::{
     prelude::*,
     r2d2::{ConnectionManager, PooledConnection},
@@ -37,7 +37,7 @@ pub trait AssignedSubaddressModel {
         address_book_entry: Option<i64>,
         subaddress_index: u64,
         comment: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<String, WalletDbError>;
 
     /// Create the next subaddress for a given account.
@@ -47,13 +47,13 @@ pub trait AssignedSubaddressModel {
     fn create_next_for_account(
         account_id_hex: &str,
         comment: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(String, i64), WalletDbError>;
 
     /// Get the AssignedSubaddress for a given assigned_subaddress_b58
     fn get(
         public_address_b58: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<AssignedSubaddress, WalletDbError>;
 
     /// Find an AssignedSubaddress by the subaddress spend public key
@@ -62,19 +62,29 @@ pub trait AssignedSubaddressModel {
     /// * (subaddress_index, assigned_subaddress_b58)
     fn find_by_subaddress_spend_public_key(
         subaddress_spend_public_key: &RistrettoPublic,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(i64, String), WalletDbError>;
 
     /// List all AssignedSubaddresses for a given account.
     fn list_all(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<AssignedSubaddress>, WalletDbError>;
 
+    /// Set whether an AssignedSubaddress is retired.
+    ///
+    /// Retired subaddresses are hidden from listings, but are still used to
+    /// match received Txos.
+    fn set_retired(
+        public_address_b58: &str,
+        retired: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<AssignedSubaddress, WalletDbError>;
+
     /// Delete all AssignedSubaddresses for a given account.
     fn delete_all(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 }
 
@@ -84,7 +94,7 @@ impl AssignedSubaddressModel for AssignedSubaddress {
         address_book_entry: Option<i64>,
         subaddress_index: u64,
         comment: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<String, WalletDbError> {
         use crate::db::schema::assigned_subaddresses;
 
@@ -112,7 +122,7 @@ impl AssignedSubaddressModel for AssignedSubaddress {
     fn create_next_for_account(
         account_id_hex: &str,
         comment: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(String, i64), WalletDbError> {
         use crate::db::schema::{
             accounts::dsl::{account_id_hex as dsl_account_id_hex, accounts},
@@ -153,7 +163,7 @@ impl AssignedSubaddressModel for AssignedSubaddress {
 
     fn get(
         public_address_b58: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<AssignedSubaddress, WalletDbError> {
         use crate::db::schema::assigned_subaddresses::dsl::{
             assigned_subaddress_b58, assigned_subaddresses,
@@ -179,7 +189,7 @@ impl AssignedSubaddressModel for AssignedSubaddress {
 
     fn find_by_subaddress_spend_public_key(
         subaddress_spend_public_key: &RistrettoPublic,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(i64, String), WalletDbError> {
         use crate::db::schema::assigned_subaddresses::{
             account_id_hex, dsl::assigned_subaddresses, subaddress_index, subaddress_spend_key,
@@ -207,7 +217,7 @@ impl AssignedSubaddressModel for AssignedSubaddress {
 
     fn list_all(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<AssignedSubaddress>, WalletDbError> {
         use crate::db::schema::assigned_subaddresses::{
             account_id_hex as schema_account_id_hex, all_columns, dsl::assigned_subaddresses,
@@ -221,9 +231,34 @@ impl AssignedSubaddressModel for AssignedSubaddress {
         Ok(matches)
     }
 
+    fn set_retired(
+        public_address_b58: &str,
+        retired: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<AssignedSubaddress, WalletDbError> {
+        use crate::db::schema::assigned_subaddresses::dsl::{
+            assigned_subaddress_b58, assigned_subaddresses, retired as dsl_retired,
+        };
+
+        Ok(
+            conn.transaction::<AssignedSubaddress, WalletDbError, _>(|| {
+                // Ensure the subaddress exists.
+                AssignedSubaddress::get(public_address_b58, conn)?;
+
+                diesel::update(
+                    assigned_subaddresses.filter(assigned_subaddress_b58.eq(public_address_b58)),
+                )
+                .set(dsl_retired.eq(retired))
+                .execute(conn)?;
+
+                AssignedSubaddress::get(public_address_b58, conn)
+            })?,
+        )
+    }
+
     fn delete_all(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::assigned_subaddresses::dsl::{
             account_id_hex as schema_account_id_hex, assigned_subaddresses,
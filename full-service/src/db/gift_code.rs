@@ -7,7 +7,7 @@ use crate::{
         account::AccountID,
         models::{GiftCode, NewGiftCode},
         txo::TxoID,
-        WalletDbError,
+        WalletDbConnection, WalletDbError,
     },
     service::gift_code::EncodedGiftCode,
 };
@@ -50,24 +50,24 @@ pub trait GiftCodeModel {
         memo: String,
         account_id: &AccountID,
         txo_id: &TxoID,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<GiftCode, WalletDbError>;
 
     /// Get the details of a specific Gift Code.
     fn get(
         gift_code_b58: &EncodedGiftCode,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<GiftCode, WalletDbError>;
 
     /// Get all Gift Codes in this wallet.
     fn list_all(
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<GiftCode>, WalletDbError>;
 
     /// Delete a gift code.
     fn delete(
         self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 }
 
@@ -80,7 +80,7 @@ impl GiftCodeModel for GiftCode {
         memo: String,
         account_id: &AccountID,
         txo_id: &TxoID,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<GiftCode, WalletDbError> {
         use crate::db::schema::gift_codes;
 
@@ -105,7 +105,7 @@ impl GiftCodeModel for GiftCode {
 
     fn get(
         gift_code_b58: &EncodedGiftCode,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<GiftCode, WalletDbError> {
         use crate::db::schema::gift_codes::dsl::{gift_code_b58 as dsl_gift_code_b58, gift_codes};
 
@@ -123,7 +123,7 @@ impl GiftCodeModel for GiftCode {
     }
 
     fn list_all(
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<GiftCode>, WalletDbError> {
         use crate::db::schema::gift_codes;
 
@@ -134,7 +134,7 @@ impl GiftCodeModel for GiftCode {
 
     fn delete(
         self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::gift_codes::dsl::{gift_code_b58, gift_codes};
 
@@ -9,12 +9,22 @@ pub mod assigned_subaddress;
 mod b58;
 pub mod gift_code;
 pub mod models;
+#[cfg(feature = "sql-query-logging")]
+mod query_logger;
 pub mod schema;
+pub mod spendable_txos_cache;
+pub mod transaction_build;
 pub mod transaction_log;
 pub mod txo;
+pub mod txo_reservations;
+pub mod txo_selection;
+pub mod txo_status_history;
 mod wallet_db;
 mod wallet_db_error;
+pub mod wallet_network;
+pub mod webhook_notification;
 
 pub use b58::{b58_decode, b58_encode};
-pub use wallet_db::WalletDb;
+pub use wallet_db::{ConnectionOptions, SynchronousMode, WalletDb, WalletDbConnection};
 pub use wallet_db_error::WalletDbError;
+pub use wallet_network::Network;
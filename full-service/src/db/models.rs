@@ -3,8 +3,8 @@
 //! DB Models
 
 use super::schema::{
-    account_txo_statuses, accounts, assigned_subaddresses, gift_codes, transaction_logs,
-    transaction_txo_types, txos,
+    account_txo_statuses, accounts, assigned_subaddresses, gift_codes, transaction_builds,
+    transaction_logs, transaction_txo_types, txo_status_history, txos, webhook_notifications,
 };
 
 use serde::Serialize;
@@ -27,6 +27,15 @@ pub const TXO_STATUS_SECRETED: &str = "txo_status_secreted";
 /// subaddress is unknown).
 pub const TXO_STATUS_ORPHANED: &str = "txo_status_orphaned";
 
+/// The TXO is no longer in the ledger at the block it was received in (e.g.,
+/// after a ledger rollback), so it is not counted in balances or spendable.
+pub const TXO_STATUS_INVALIDATED: &str = "txo_status_invalidated";
+
+/// A TXO received by an account in this wallet with a value below the wallet's
+/// dust threshold. It is not spendable, and is not counted in the unspent
+/// balance unless requested.
+pub const TXO_STATUS_DUST: &str = "txo_status_dust";
+
 /// A Txo that has been created locally, but is not yet in the ledger.
 pub const TXO_TYPE_MINTED: &str = "txo_type_minted";
 
@@ -42,6 +51,10 @@ pub const TX_STATUS_PENDING: &str = "tx_status_pending";
 /// A transaction that appears to have been processed by the MobileCoin network.
 pub const TX_STATUS_SUCCEEDED: &str = "tx_status_succeeded";
 
+/// A succeeded transaction which does not yet have its account's
+/// finality_depth of blocks on top of it. Only reported, never stored.
+pub const TX_STATUS_LANDING: &str = "tx_status_landing";
+
 /// A transaction that was rejected by the MobileCoin network, or that expired
 /// before it could be processed.
 pub const TX_STATUS_FAILED: &str = "tx_status_failed";
@@ -61,6 +74,10 @@ pub const TXO_USED_AS_OUTPUT: &str = "txo_used_as_output";
 /// A transaction output used as a change output of a new transaction.
 pub const TXO_USED_AS_CHANGE: &str = "txo_used_as_change";
 
+/// The token id of MOB. TxOuts do not yet carry a token id, so every Txo in
+/// the wallet has this token id.
+pub const MOB_TOKEN_ID: u64 = 0;
+
 /// An Account entity.
 ///
 /// Contains the account private keys, subaddress configuration, and ...
@@ -97,6 +114,28 @@ pub struct Account {
     pub import_block_index: Option<i64>,
     /// Name of this account.
     pub name: String, /* empty string for nullable */
+    /// If set, sends from this account include extra inputs, up to
+    /// MAX_INPUTS, whenever the account holds more unspent Txos than this.
+    pub auto_consolidate_threshold: Option<i64>,
+    /// Archived accounts are hidden from account listings by default.
+    pub archived: bool,
+    /// Whether this account was imported to recover its Txos, including
+    /// orphaned Txos received at subaddresses that are not yet assigned.
+    pub recovery_mode: bool,
+    /// If set, a transaction sent from this account is reported as landing,
+    /// rather than succeeded, until this many blocks follow the block it
+    /// landed in.
+    pub finality_depth: Option<i64>,
+    /// If set, the wallet POSTs a notification to this URL for each Txo the
+    /// account receives.
+    pub webhook_url: Option<String>,
+    /// The number of consecutive notifications which could not be delivered
+    /// to the webhook_url. Notifications stop once this reaches
+    /// MAX_WEBHOOK_FAILURES.
+    pub webhook_failures: i32,
+    /// Incremented whenever one of the account's Txos changes status or key
+    /// image, so that cached spendable Txos can tell whether they are stale.
+    pub txo_version: i64,
 }
 
 /// A structure that can be inserted to create a new entity in the `accounts`
@@ -126,7 +165,9 @@ pub struct Txo {
     pub id: i32,
     /// An additional ID derived from the contents of the ledger TxOut.
     pub txo_id_hex: String,
-    /// The value of this transaction output, in picoMob.
+    /// The value of this transaction output, in picoMob. SQLite only stores
+    /// signed integers, so values above i64::MAX wrap around, and must be read
+    /// back `as u64`.
     pub value: i64,
     /// The serialized target_key of the TxOut.
     pub target_key: Vec<u8>,
@@ -145,6 +186,11 @@ pub struct Txo {
     pub pending_tombstone_block_index: Option<i64>,
     pub spent_block_index: Option<i64>,
     pub confirmation: Option<Vec<u8>>,
+    /// The token id of the value, MOB_TOKEN_ID unless stated otherwise.
+    pub token_id: i64,
+    /// The recipient a minted Txo pays, if it is an outlay of a transaction
+    /// this wallet sent.
+    pub recipient_public_address_b58: Option<String>,
 }
 
 /// A structure that can be inserted to create a new entity in the `txos` table.
@@ -163,6 +209,7 @@ pub struct NewTxo<'a> {
     pub pending_tombstone_block_index: Option<i64>,
     pub spent_block_index: Option<i64>,
     pub confirmation: Option<&'a [u8]>,
+    pub recipient_public_address_b58: Option<&'a str>,
 }
 
 #[derive(Clone, Serialize, Associations, Identifiable, Queryable, PartialEq, Debug)]
@@ -177,6 +224,8 @@ pub struct AccountTxoStatus {
     pub txo_status: String,
     // Types: minted, received
     pub txo_type: String,
+    // User-provided annotation, specific to this account
+    pub label: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -188,6 +237,96 @@ pub struct NewAccountTxoStatus<'a> {
     pub txo_type: &'a str,
 }
 
+/// A change to a Txo's status for an account, recorded as an audit trail.
+#[derive(Clone, Serialize, Associations, Identifiable, Queryable, PartialEq, Debug)]
+#[belongs_to(Account, foreign_key = "account_id_hex")]
+#[belongs_to(Txo, foreign_key = "txo_id_hex")]
+#[table_name = "txo_status_history"]
+#[primary_key(id)]
+pub struct TxoStatusChange {
+    pub id: i32,
+    pub account_id_hex: String,
+    pub txo_id_hex: String,
+    // The status the Txo moved to
+    pub txo_status: String,
+    // Unix timestamp of the change
+    pub changed_at: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "txo_status_history"]
+pub struct NewTxoStatusChange<'a> {
+    pub account_id_hex: &'a str,
+    pub txo_id_hex: &'a str,
+    pub txo_status: &'a str,
+    pub changed_at: i64,
+}
+
+/// A TxProposal built for an account, so that a later submission can be
+/// checked against it.
+#[derive(Clone, Serialize, Associations, Identifiable, Queryable, PartialEq, Debug)]
+#[belongs_to(Account, foreign_key = "account_id_hex")]
+#[table_name = "transaction_builds"]
+#[primary_key(id)]
+pub struct TransactionBuild {
+    pub id: i32,
+    pub build_id_hex: String,
+    pub account_id_hex: String,
+    // The TxProposal, as encoded by serialize_tx_proposal
+    pub tx_proposal: Vec<u8>,
+    // Unix timestamp of the build
+    pub built_at: i64,
+    // The proposal cannot land in this block or later
+    pub tombstone_block_index: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "transaction_builds"]
+pub struct NewTransactionBuild<'a> {
+    pub build_id_hex: &'a str,
+    pub account_id_hex: &'a str,
+    pub tx_proposal: &'a [u8],
+    pub built_at: i64,
+    pub tombstone_block_index: i64,
+}
+
+/// A received Txo which an account's webhook has not been notified of yet.
+#[derive(Clone, Serialize, Associations, Identifiable, Queryable, PartialEq, Debug)]
+#[belongs_to(Account, foreign_key = "account_id_hex")]
+#[table_name = "webhook_notifications"]
+#[primary_key(id)]
+pub struct WebhookNotification {
+    pub id: i32,
+    pub account_id_hex: String,
+    pub txo_id_hex: String,
+    pub received_block_index: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhook_notifications"]
+pub struct NewWebhookNotification<'a> {
+    pub account_id_hex: &'a str,
+    pub txo_id_hex: &'a str,
+    pub received_block_index: i64,
+}
+
+/// The network that a wallet database was created for. The table holds at
+/// most one row.
+#[derive(Clone, Serialize, Identifiable, Queryable, PartialEq, Debug)]
+#[table_name = "wallet_network"]
+#[primary_key(id)]
+pub struct WalletNetwork {
+    pub id: i32,
+    pub network: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "wallet_network"]
+pub struct NewWalletNetwork<'a> {
+    pub id: i32,
+    pub network: &'a str,
+}
+
 /// A subaddress given to a particular contact, for the purpose of tracking
 /// funds received from that contact.
 #[derive(Clone, Serialize, Associations, Identifiable, Queryable, PartialEq, Debug)]
@@ -203,6 +342,7 @@ pub struct AssignedSubaddress {
     pub subaddress_index: i64,
     pub comment: String,               // empty string for nullable
     pub subaddress_spend_key: Vec<u8>, // FIXME: WS-28 - Index on subaddress_spend_key?
+    pub retired: bool,                 // hidden from listings, but still matched on receive
 }
 
 /// A structure that can be inserted to create a new AssignedSubaddress entity.
@@ -241,6 +381,8 @@ pub struct TransactionLog {
     // Directions: sent, received
     pub direction: String,
     pub tx: Option<Vec<u8>>,
+    // The log this transaction replaced, if any.
+    pub parent_transaction_id_hex: Option<String>,
 }
 
 /// A structure that can be inserted to create a new TransactionLog entity.
@@ -0,0 +1,160 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! A wallet database connection which logs each query and its duration, for
+//! debugging slow paths.
+
+use diesel::{
+    connection::{AnsiTransactionManager, Connection, SimpleConnection},
+    debug_query,
+    deserialize::{Queryable, QueryableByName},
+    query_builder::{AsQuery, QueryFragment, QueryId},
+    sql_types::HasSqlType,
+    sqlite::{Sqlite, SqliteConnection},
+    ConnectionResult, QueryResult,
+};
+use mc_common::logger::{log, Logger};
+use std::time::Instant;
+
+/// A SqliteConnection which logs each query and its duration at debug level,
+/// once it has a logger.
+pub struct LoggingConnection {
+    conn: SqliteConnection,
+    logger: Option<Logger>,
+}
+
+impl LoggingConnection {
+    /// Start logging queries to the given logger, or stop with None.
+    pub fn set_logger(&mut self, logger: Option<Logger>) {
+        self.logger = logger;
+    }
+
+    /// Renders the query, if queries are logged.
+    fn render<T: QueryFragment<Sqlite>>(&self, query: &T) -> Option<String> {
+        self.logger
+            .as_ref()
+            .map(|_| debug_query::<Sqlite, _>(query).to_string())
+    }
+
+    /// Runs `f`, logging the rendered `query` with how long `f` took.
+    fn timed<R>(&self, query: Option<String>, f: impl FnOnce(&SqliteConnection) -> R) -> R {
+        let start = Instant::now();
+        let result = f(&self.conn);
+        if let (Some(logger), Some(query)) = (&self.logger, query) {
+            log::debug!(
+                logger,
+                "SQL query took {} us: {}",
+                start.elapsed().as_micros(),
+                query
+            );
+        }
+        result
+    }
+}
+
+impl SimpleConnection for LoggingConnection {
+    fn batch_execute(&self, query: &str) -> QueryResult<()> {
+        self.timed(Some(query.to_string()), |conn| conn.batch_execute(query))
+    }
+}
+
+impl Connection for LoggingConnection {
+    type Backend = Sqlite;
+    type TransactionManager = AnsiTransactionManager;
+
+    fn establish(database_url: &str) -> ConnectionResult<Self> {
+        Ok(Self {
+            conn: SqliteConnection::establish(database_url)?,
+            logger: None,
+        })
+    }
+
+    fn execute(&self, query: &str) -> QueryResult<usize> {
+        self.timed(Some(query.to_string()), |conn| conn.execute(query))
+    }
+
+    fn query_by_index<T, U>(&self, source: T) -> QueryResult<Vec<U>>
+    where
+        T: AsQuery,
+        T::Query: QueryFragment<Self::Backend> + QueryId,
+        Self::Backend: HasSqlType<T::SqlType>,
+        U: Queryable<T::SqlType, Self::Backend>,
+    {
+        let query = source.as_query();
+        let rendered = self.render(&query);
+        self.timed(rendered, |conn| conn.query_by_index(query))
+    }
+
+    fn query_by_name<T, U>(&self, source: &T) -> QueryResult<Vec<U>>
+    where
+        T: QueryFragment<Self::Backend> + QueryId,
+        U: QueryableByName<Self::Backend>,
+    {
+        self.timed(self.render(source), |conn| conn.query_by_name(source))
+    }
+
+    fn execute_returning_count<T>(&self, source: &T) -> QueryResult<usize>
+    where
+        T: QueryFragment<Self::Backend> + QueryId,
+    {
+        self.timed(self.render(source), |conn| {
+            conn.execute_returning_count(source)
+        })
+    }
+
+    fn transaction_manager(&self) -> &Self::TransactionManager {
+        self.conn.transaction_manager()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{account::AccountModel, models::Account, ConnectionOptions},
+        test_utils::WalletDbTestContext,
+    };
+    use mc_common::logger::Logger;
+    use slog::{o, Drain, Never, OwnedKVList, Record};
+    use std::sync::{Arc, Mutex};
+
+    /// Collects the messages logged to it.
+    struct CaptureDrain(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for CaptureDrain {
+        type Ok = ();
+        type Err = Never;
+
+        fn log(&self, record: &Record, _: &OwnedKVList) -> Result<(), Never> {
+            self.0.lock().unwrap().push(record.msg().to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_queries_are_logged_with_duration() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let logger = Logger::root(CaptureDrain(messages.clone()), o!());
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance_with_options(
+            1,
+            ConnectionOptions {
+                log_queries: true,
+                ..Default::default()
+            },
+            logger,
+        );
+        let conn = wallet_db.get_conn().unwrap();
+        assert_eq!(Account::list_all(&conn).unwrap(), vec![]);
+
+        let messages = messages.lock().unwrap();
+        let line = messages
+            .iter()
+            .find(|m| m.contains("FROM `accounts`"))
+            .expect("Query on accounts was not logged");
+        let micros = line
+            .strip_prefix("SQL query took ")
+            .and_then(|rest| rest.split(" us: ").next())
+            .expect("Query was logged without a duration");
+        assert!(micros.parse::<u128>().is_ok());
+    }
+}
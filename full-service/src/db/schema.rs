@@ -4,6 +4,7 @@ table! {
         txo_id_hex -> Text,
         txo_status -> Text,
         txo_type -> Text,
+        label -> Nullable<Text>,
     }
 }
 
@@ -21,6 +22,13 @@ table! {
         next_block_index -> BigInt,
         import_block_index -> Nullable<BigInt>,
         name -> Text,
+        auto_consolidate_threshold -> Nullable<BigInt>,
+        archived -> Bool,
+        recovery_mode -> Bool,
+        finality_depth -> Nullable<BigInt>,
+        webhook_url -> Nullable<Text>,
+        webhook_failures -> Integer,
+        txo_version -> BigInt,
     }
 }
 
@@ -34,6 +42,7 @@ table! {
         subaddress_index -> BigInt,
         comment -> Text,
         subaddress_spend_key -> Binary,
+        retired -> Bool,
     }
 }
 
@@ -50,6 +59,17 @@ table! {
     }
 }
 
+table! {
+    transaction_builds (id) {
+        id -> Integer,
+        build_id_hex -> Text,
+        account_id_hex -> Text,
+        tx_proposal -> Binary,
+        built_at -> BigInt,
+        tombstone_block_index -> BigInt,
+    }
+}
+
 table! {
     transaction_logs (id) {
         id -> Integer,
@@ -66,6 +86,7 @@ table! {
         comment -> Text,
         direction -> Text,
         tx -> Nullable<Binary>,
+        parent_transaction_id_hex -> Nullable<Text>,
     }
 }
 
@@ -77,6 +98,16 @@ table! {
     }
 }
 
+table! {
+    txo_status_history (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        txo_id_hex -> Text,
+        txo_status -> Text,
+        changed_at -> BigInt,
+    }
+}
+
 table! {
     txos (id) {
         id -> Integer,
@@ -92,6 +123,24 @@ table! {
         pending_tombstone_block_index -> Nullable<BigInt>,
         spent_block_index -> Nullable<BigInt>,
         confirmation -> Nullable<Binary>,
+        token_id -> BigInt,
+        recipient_public_address_b58 -> Nullable<Text>,
+    }
+}
+
+table! {
+    wallet_network (id) {
+        id -> Integer,
+        network -> Text,
+    }
+}
+
+table! {
+    webhook_notifications (id) {
+        id -> Integer,
+        account_id_hex -> Text,
+        txo_id_hex -> Text,
+        received_block_index -> BigInt,
     }
 }
 
@@ -100,7 +149,11 @@ allow_tables_to_appear_in_same_query!(
     accounts,
     assigned_subaddresses,
     gift_codes,
+    transaction_builds,
     transaction_logs,
     transaction_txo_types,
+    txo_status_history,
     txos,
+    wallet_network,
+    webhook_notifications,
 );
@@ -0,0 +1,203 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! A short-lived cache of each account's spendable Txos, so that repeated
+//! selections, such as previewing and then building a transaction, do not
+//! each reload them from the database.
+//!
+//! An account's entry is keyed by the ledger height and the account's Txo
+//! version it was loaded at, so it is reloaded when a new block arrives or any
+//! of the account's Txos changes status or key image, such as becoming pending
+//! when a transaction is submitted.
+
+use crate::db::{
+    account::AccountModel,
+    models::{Account, Txo},
+    txo::TxoModel,
+    WalletDbConnection, WalletDbError,
+};
+use diesel::{
+    prelude::*,
+    r2d2::{ConnectionManager, PooledConnection},
+};
+use mc_common::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The ledger height and Txo version an entry was loaded at.
+type CacheKey = (u64, i64);
+
+/// Spendable Txos, in decreasing order of value, shared between the clones of
+/// a WalletDb.
+#[derive(Clone, Default)]
+pub struct SpendableTxosCache {
+    inner: Arc<Mutex<SpendableTxosCacheInner>>,
+}
+
+#[derive(Default)]
+struct SpendableTxosCacheInner {
+    /// Cached spendable Txos, with the key they were loaded at, by account.
+    entries: HashMap<String, (CacheKey, Arc<Vec<Txo>>)>,
+
+    /// The number of times spendable Txos were loaded from the database.
+    num_loads: u64,
+}
+
+impl SpendableTxosCache {
+    /// Get the spendable Txos for an account, loading them from the database
+    /// if the cached entry was loaded at a different ledger height or Txo
+    /// version.
+    pub fn get_spendable_txos(
+        &self,
+        account_id_hex: &str,
+        num_blocks: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Arc<Vec<Txo>>, WalletDbError> {
+        // Read the version and the Txos in one transaction, so that a change in
+        // between cannot be cached under the older version.
+        conn.transaction::<Arc<Vec<Txo>>, WalletDbError, _>(|| {
+            let key = (num_blocks, Account::get_txo_version(account_id_hex, conn)?);
+            if let Some((cached_key, txos)) = self
+                .inner
+                .lock()
+                .expect("mutex poisoned")
+                .entries
+                .get(account_id_hex)
+            {
+                if *cached_key == key {
+                    return Ok(txos.clone());
+                }
+            }
+
+            let txos = Arc::new(Txo::list_spendable(account_id_hex, conn)?);
+
+            let mut inner = self.inner.lock().expect("mutex poisoned");
+            inner.num_loads += 1;
+            inner
+                .entries
+                .insert(account_id_hex.to_string(), (key, txos.clone()));
+            Ok(txos)
+        })
+    }
+
+    /// Drop the cached entry for an account, for example because the account
+    /// was removed.
+    pub fn invalidate(&self, account_id_hex: &str) {
+        self.inner
+            .lock()
+            .expect("mutex poisoned")
+            .entries
+            .remove(account_id_hex);
+    }
+
+    /// The number of times spendable Txos were loaded from the database so
+    /// far.
+    pub fn num_loads(&self) -> u64 {
+        self.inner.lock().expect("mutex poisoned").num_loads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{account_txo_status::AccountTxoStatusModel, models::AccountTxoStatus, txo::TxoID},
+        test_utils::{create_test_received_txo, WalletDbTestContext, MOB},
+    };
+    use mc_account_keys::{AccountKey, RootIdentity};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    #[test_with_logger]
+    fn test_cache_invalidated_on_status_change(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+        let cache = wallet_db.spendable_txos_cache();
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let (account_id, _public_address_b58) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "Alice's Main Account",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+        let account_id_hex = account_id.to_string();
+
+        let (small_txo_id_hex, _txo, _key_image) =
+            create_test_received_txo(&account_key, 0, (10 * MOB) as u64, 12, &mut rng, &wallet_db);
+        let (large_txo_id_hex, _txo, _key_image) =
+            create_test_received_txo(&account_key, 0, (20 * MOB) as u64, 12, &mut rng, &wallet_db);
+
+        let txos = cache
+            .get_spendable_txos(&account_id_hex, 13, &conn)
+            .unwrap();
+        assert_eq!(
+            txos.iter()
+                .map(|t| t.txo_id_hex.clone())
+                .collect::<Vec<_>>(),
+            vec![large_txo_id_hex.clone(), small_txo_id_hex.clone()]
+        );
+        assert_eq!(cache.num_loads(), 1);
+
+        // Nothing changed, so the cached Txos are returned.
+        cache
+            .get_spendable_txos(&account_id_hex, 13, &conn)
+            .unwrap();
+        assert_eq!(cache.num_loads(), 1);
+
+        // Once a Txo is pending, it is no longer spendable.
+        Txo::update_to_pending(&TxoID(large_txo_id_hex), &conn).unwrap();
+        let txos = cache
+            .get_spendable_txos(&account_id_hex, 13, &conn)
+            .unwrap();
+        assert_eq!(cache.num_loads(), 2);
+        assert_eq!(
+            txos.iter()
+                .map(|t| t.txo_id_hex.clone())
+                .collect::<Vec<_>>(),
+            vec![small_txo_id_hex]
+        );
+
+        // A new block also reloads the Txos.
+        cache
+            .get_spendable_txos(&account_id_hex, 14, &conn)
+            .unwrap();
+        assert_eq!(cache.num_loads(), 3);
+
+        // As does setting a key image, which changes no status.
+        let small_txo = Txo::get(&small_txo_id_hex, &conn).unwrap().txo;
+        small_txo
+            .update_to_spendable(
+                small_txo.subaddress_index,
+                Some(KeyImage::from(rng.next_u64())),
+                12,
+                &conn,
+            )
+            .unwrap();
+        cache
+            .get_spendable_txos(&account_id_hex, 14, &conn)
+            .unwrap();
+        assert_eq!(cache.num_loads(), 4);
+
+        // And purging a Txo's status.
+        AccountTxoStatus::get(&account_id_hex, &small_txo_id_hex, &conn)
+            .unwrap()
+            .delete(&conn)
+            .unwrap();
+        let txos = cache
+            .get_spendable_txos(&account_id_hex, 14, &conn)
+            .unwrap();
+        assert_eq!(cache.num_loads(), 5);
+        assert!(txos.is_empty());
+    }
+}
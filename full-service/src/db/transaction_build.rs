@@ -0,0 +1,137 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! DB impl for the TransactionBuild model.
+
+use crate::db::{
+    models::{NewTransactionBuild, TransactionBuild},
+    WalletDbConnection, WalletDbError,
+};
+use chrono::Utc;
+use diesel::{
+    prelude::*,
+    r2d2::{ConnectionManager, PooledConnection},
+    RunQueryDsl,
+};
+
+pub trait TransactionBuildModel {
+    /// Record the encoded TxProposal built for an account, with the tombstone
+    /// block after which it can no longer be submitted.
+    fn record(
+        build_id_hex: &str,
+        account_id_hex: &str,
+        tx_proposal: &[u8],
+        tombstone_block_index: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Get a build by its ID.
+    fn get(
+        build_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<TransactionBuild, WalletDbError>;
+
+    /// Delete a build, once its proposal has been submitted.
+    fn delete(
+        build_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Delete the builds of an account whose tombstone block is before
+    /// `block_index`, as their proposals can no longer land.
+    fn delete_expired(
+        account_id_hex: &str,
+        block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Delete the builds of an account.
+    fn delete_all_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+}
+
+impl TransactionBuildModel for TransactionBuild {
+    fn record(
+        build_id_hex: &str,
+        account_id_hex: &str,
+        tx_proposal: &[u8],
+        tombstone_block_index: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_builds;
+
+        let new_transaction_build = NewTransactionBuild {
+            build_id_hex,
+            account_id_hex,
+            tx_proposal,
+            built_at: Utc::now().timestamp(),
+            tombstone_block_index: tombstone_block_index as i64,
+        };
+
+        diesel::insert_into(transaction_builds::table)
+            .values(&new_transaction_build)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn get(
+        build_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<TransactionBuild, WalletDbError> {
+        use crate::db::schema::transaction_builds as cols;
+
+        match cols::table
+            .filter(cols::build_id_hex.eq(build_id_hex))
+            .get_result::<TransactionBuild>(conn)
+        {
+            Ok(b) => Ok(b),
+            // Match on NotFound to get a more informative NotFound Error
+            Err(diesel::result::Error::NotFound) => Err(WalletDbError::TransactionBuildNotFound(
+                build_id_hex.to_string(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn delete(
+        build_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_builds as cols;
+
+        diesel::delete(cols::table.filter(cols::build_id_hex.eq(build_id_hex))).execute(conn)?;
+
+        Ok(())
+    }
+
+    fn delete_expired(
+        account_id_hex: &str,
+        block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_builds as cols;
+
+        diesel::delete(
+            cols::table
+                .filter(cols::account_id_hex.eq(account_id_hex))
+                .filter(cols::tombstone_block_index.lt(block_index)),
+        )
+        .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn delete_all_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_builds as cols;
+
+        diesel::delete(cols::table.filter(cols::account_id_hex.eq(account_id_hex)))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -3,23 +3,24 @@
 //! DB impl for the Transaction model.
 
 use crate::db::{
+    account_txo_status::AccountTxoStatusModel,
     b58_encode,
     models::{
         Account, NewTransactionLog, NewTransactionTxoType, TransactionLog, TransactionTxoType, Txo,
-        TXO_USED_AS_CHANGE, TXO_USED_AS_INPUT, TXO_USED_AS_OUTPUT, TX_DIRECTION_RECEIVED,
-        TX_DIRECTION_SENT, TX_STATUS_BUILT, TX_STATUS_FAILED, TX_STATUS_PENDING,
-        TX_STATUS_SUCCEEDED,
+        TXO_STATUS_PENDING, TXO_USED_AS_CHANGE, TXO_USED_AS_INPUT, TXO_USED_AS_OUTPUT,
+        TX_DIRECTION_RECEIVED, TX_DIRECTION_SENT, TX_STATUS_BUILT, TX_STATUS_FAILED,
+        TX_STATUS_PENDING, TX_STATUS_SUCCEEDED,
     },
     txo::{TxoID, TxoModel},
 };
 
 use mc_account_keys::AccountKey;
-use mc_common::HashMap;
+use mc_common::{HashMap, HashSet};
 use mc_crypto_digestible::{Digestible, MerlinTranscript};
 use mc_mobilecoind::payments::TxProposal;
-use mc_transaction_core::tx::Tx;
+use mc_transaction_core::tx::{Tx, TxOut};
 
-use crate::db::WalletDbError;
+use crate::db::{WalletDbConnection, WalletDbError};
 use chrono::Utc;
 use diesel::{
     prelude::*,
@@ -28,6 +29,9 @@ use diesel::{
 };
 use std::fmt;
 
+/// The maximum number of change outputs in a transaction built by this wallet.
+pub const MAX_CHANGE_OUTPUTS: usize = 8;
+
 #[derive(Debug)]
 pub struct TransactionID(String);
 
@@ -57,40 +61,50 @@ pub struct AssociatedTxos {
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
     pub change: Vec<String>,
+    /// The total value of the change Txos.
+    pub change_value: u64,
 }
 
 pub trait TransactionLogModel {
     /// Get a transaction log from the TransactionId.
     fn get(
         transaction_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<TransactionLog, WalletDbError>;
 
     /// Get all transaction logs for the given block index.
     fn get_all_for_block_index(
         block_index: u64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TransactionLog>, WalletDbError>;
 
     /// Get all transaction logs ordered by finalized_block_index.
     fn get_all_ordered_by_block_index(
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TransactionLog>, WalletDbError>;
+
+    /// Get a page of the transaction logs of every account, ordered by
+    /// finalized_block_index and then by id, skipping the first `offset`.
+    fn list_page_ordered_by_block_index(
+        offset: i64,
+        limit: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TransactionLog>, WalletDbError>;
 
     /// Get the Txos associated with a given TransactionId, grouped according to
     /// their type.
     ///
     /// Returns:
-    /// * AssoiatedTxos(inputs, outputs, change)
+    /// * AssoiatedTxos(inputs, outputs, change, change_value)
     fn get_associated_txos(
         &self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<AssociatedTxos, WalletDbError>;
 
     /// Select the TransactionLogs associated with a given TxoId.
     fn select_for_txo(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TransactionLog>, WalletDbError>;
 
     /// List all TransactionLogs and their associated Txos for a given account.
@@ -99,14 +113,14 @@ pub trait TransactionLogModel {
     /// * Vec(TransactionLog, AssociatedTxos(inputs, outputs, change))
     fn list_all(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletDbError>;
 
     /// Update the transactions associated with a Txo for a given block index.
     fn update_transactions_associated_to_txo(
         txo_id_hex: &str,
         cur_block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     /// Log a received transaction.
@@ -114,7 +128,7 @@ pub trait TransactionLogModel {
         subaddress_to_output_txo_ids: &HashMap<i64, Vec<String>>,
         account: &Account,
         block_index: u64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     /// Log a submitted transaction.
@@ -132,20 +146,100 @@ pub trait TransactionLogModel {
         block_index: u64,
         comment: String,
         account_id_hex: Option<&str>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<TransactionLog, WalletDbError>;
 
     /// Remove all logs for an account
     fn delete_all_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Remove a single TransactionLog and its Txo associations.
+    ///
+    /// The Txos themselves, and their statuses, are left untouched. A log that
+    /// is still built or pending cannot be removed while its inputs are
+    /// reserved as pending.
+    fn delete(
+        transaction_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
+
+    /// Whether this TransactionLog replaced, or was replaced by, a log which is
+    /// still built or pending.
+    ///
+    /// Such logs spend the same inputs, so at most one of them can land, and
+    /// the inputs being spent does not say which. They are settled by
+    /// resolve_replacements instead.
+    fn has_pending_replacement(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<bool, WalletDbError>;
+
+    /// Settle the built or pending logs of an account which have a pending
+    /// replacement, once their inputs are spent in the given block. The log
+    /// whose outputs are in the block succeeded, and the others failed.
+    fn resolve_replacements(
+        account_id_hex: &str,
+        block_index: i64,
+        block_outputs: &[TxOut],
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Mark a TransactionLog as failed because consensus rejected it.
+    ///
+    /// The rejected transaction can never land, so its inputs that are still
+    /// pending are returned to unspent.
+    fn mark_rejected(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Mark a failed TransactionLog as pending again, because its transaction
+    /// was submitted again at `block_index`.
+    ///
+    /// Its inputs that were returned to unspent are made pending again.
+    fn mark_resubmitted(
+        &self,
+        block_index: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Replace the comment stored with a TransactionLog.
+    fn update_comment(
+        &self,
+        comment: String,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Link a TransactionLog to the log of the transaction it was derived
+    /// from, such as the pending transaction it replaced, so that multi-step
+    /// operations read as a tree.
+    fn set_parent(
+        &self,
+        parent_transaction_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// List the TransactionLogs whose parent is this log, oldest first.
+    fn list_children(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TransactionLog>, WalletDbError>;
+
+    /// List the TransactionLogs an account sent to a given recipient, oldest
+    /// first.
+    fn list_sent_to_recipient(
+        account_id_hex: &str,
+        recipient_public_address_b58: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TransactionLog>, WalletDbError>;
 }
 
 impl TransactionLogModel for TransactionLog {
     fn get(
         transaction_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<TransactionLog, WalletDbError> {
         use crate::db::schema::transaction_logs::dsl::{
             transaction_id_hex as dsl_transaction_id_hex, transaction_logs,
@@ -166,7 +260,7 @@ impl TransactionLogModel for TransactionLog {
 
     fn get_all_for_block_index(
         block_index: u64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TransactionLog>, WalletDbError> {
         use crate::db::schema::transaction_logs::{
             all_columns, dsl::transaction_logs, finalized_block_index,
@@ -181,7 +275,7 @@ impl TransactionLogModel for TransactionLog {
     }
 
     fn get_all_ordered_by_block_index(
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TransactionLog>, WalletDbError> {
         use crate::db::schema::transaction_logs::{
             all_columns, dsl::transaction_logs, finalized_block_index,
@@ -195,35 +289,56 @@ impl TransactionLogModel for TransactionLog {
         Ok(matches)
     }
 
+    fn list_page_ordered_by_block_index(
+        offset: i64,
+        limit: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TransactionLog>, WalletDbError> {
+        use crate::db::schema::transaction_logs as cols;
+
+        Ok(cols::table
+            .order_by((cols::finalized_block_index.asc(), cols::id.asc()))
+            .offset(offset)
+            .limit(limit)
+            .load(conn)?)
+    }
+
     fn get_associated_txos(
         &self,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<AssociatedTxos, WalletDbError> {
-        use crate::db::schema::{transaction_logs, transaction_txo_types};
+        use crate::db::schema::{transaction_logs, transaction_txo_types, txos};
 
         // FIXME: WS-29 - use group_by rather than the processing below:
         // https://docs.diesel.rs/diesel/associations/trait.GroupedBy.html
-        let transaction_txos: Vec<(TransactionLog, TransactionTxoType)> = transaction_logs::table
-            .inner_join(
-                transaction_txo_types::table.on(transaction_logs::transaction_id_hex
-                    .eq(transaction_txo_types::transaction_id_hex)
-                    .and(transaction_logs::transaction_id_hex.eq(&self.transaction_id_hex))),
-            )
-            .select((
-                transaction_logs::all_columns,
-                transaction_txo_types::all_columns,
-            ))
-            .load(conn)?;
+        let transaction_txos: Vec<(TransactionLog, TransactionTxoType, i64)> =
+            transaction_logs::table
+                .inner_join(
+                    transaction_txo_types::table.on(transaction_logs::transaction_id_hex
+                        .eq(transaction_txo_types::transaction_id_hex)
+                        .and(transaction_logs::transaction_id_hex.eq(&self.transaction_id_hex))),
+                )
+                .inner_join(txos::table.on(transaction_txo_types::txo_id_hex.eq(txos::txo_id_hex)))
+                .select((
+                    transaction_logs::all_columns,
+                    transaction_txo_types::all_columns,
+                    txos::value,
+                ))
+                .load(conn)?;
 
         let mut inputs: Vec<String> = Vec::new();
         let mut outputs: Vec<String> = Vec::new();
         let mut change: Vec<String> = Vec::new();
+        let mut change_value: u64 = 0;
 
-        for (_transaction, transaction_txo_type) in transaction_txos {
+        for (_transaction, transaction_txo_type, value) in transaction_txos {
             match transaction_txo_type.transaction_txo_type.as_str() {
                 TXO_USED_AS_INPUT => inputs.push(transaction_txo_type.txo_id_hex),
                 TXO_USED_AS_OUTPUT => outputs.push(transaction_txo_type.txo_id_hex),
-                TXO_USED_AS_CHANGE => change.push(transaction_txo_type.txo_id_hex),
+                TXO_USED_AS_CHANGE => {
+                    change.push(transaction_txo_type.txo_id_hex);
+                    change_value += value as u64;
+                }
                 _ => {
                     return Err(WalletDbError::UnexpectedTransactionTxoType(
                         transaction_txo_type.transaction_txo_type,
@@ -236,12 +351,13 @@ impl TransactionLogModel for TransactionLog {
             inputs,
             outputs,
             change,
+            change_value,
         })
     }
 
     fn select_for_txo(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TransactionLog>, WalletDbError> {
         use crate::db::schema::{transaction_logs, transaction_txo_types};
 
@@ -257,21 +373,23 @@ impl TransactionLogModel for TransactionLog {
 
     fn list_all(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletDbError> {
-        use crate::db::schema::{transaction_logs, transaction_txo_types};
+        use crate::db::schema::{transaction_logs, transaction_txo_types, txos};
 
         // FIXME: use group_by rather than the processing below:
         // https://docs.diesel.rs/diesel/associations/trait.GroupedBy.html
-        let transactions: Vec<(TransactionLog, TransactionTxoType)> = transaction_logs::table
+        let transactions: Vec<(TransactionLog, TransactionTxoType, i64)> = transaction_logs::table
             .inner_join(
                 transaction_txo_types::table.on(transaction_logs::transaction_id_hex
                     .eq(transaction_txo_types::transaction_id_hex)
                     .and(transaction_logs::account_id_hex.eq(account_id_hex))),
             )
+            .inner_join(txos::table.on(transaction_txo_types::txo_id_hex.eq(txos::txo_id_hex)))
             .select((
                 transaction_logs::all_columns,
                 transaction_txo_types::all_columns,
+                txos::value,
             ))
             .load(conn)?;
 
@@ -281,9 +399,10 @@ impl TransactionLogModel for TransactionLog {
             inputs: Vec<String>,
             outputs: Vec<String>,
             change: Vec<String>,
+            change_value: u64,
         }
         let mut results: HashMap<String, TransactionContents> = HashMap::default();
-        for (transaction, transaction_txo_type) in transactions {
+        for (transaction, transaction_txo_type, value) in transactions {
             if results.get(&transaction.transaction_id_hex).is_none() {
                 results.insert(
                     transaction.transaction_id_hex.clone(),
@@ -292,6 +411,7 @@ impl TransactionLogModel for TransactionLog {
                         inputs: Vec::new(),
                         outputs: Vec::new(),
                         change: Vec::new(),
+                        change_value: 0,
                     },
                 );
             };
@@ -305,7 +425,10 @@ impl TransactionLogModel for TransactionLog {
             match transaction_txo_type.transaction_txo_type.as_str() {
                 TXO_USED_AS_INPUT => entry.inputs.push(transaction_txo_type.txo_id_hex),
                 TXO_USED_AS_OUTPUT => entry.outputs.push(transaction_txo_type.txo_id_hex),
-                TXO_USED_AS_CHANGE => entry.change.push(transaction_txo_type.txo_id_hex),
+                TXO_USED_AS_CHANGE => {
+                    entry.change.push(transaction_txo_type.txo_id_hex);
+                    entry.change_value += value as u64;
+                }
                 _ => {
                     return Err(WalletDbError::UnexpectedTransactionTxoType(
                         transaction_txo_type.transaction_txo_type,
@@ -323,6 +446,7 @@ impl TransactionLogModel for TransactionLog {
                         inputs: t.inputs,
                         outputs: t.outputs,
                         change: t.change,
+                        change_value: t.change_value,
                     },
                 )
             })
@@ -333,7 +457,7 @@ impl TransactionLogModel for TransactionLog {
     fn update_transactions_associated_to_txo(
         txo_id_hex: &str,
         cur_block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::transaction_logs::dsl::{transaction_id_hex, transaction_logs};
 
@@ -353,6 +477,11 @@ impl TransactionLogModel for TransactionLog {
                 // Check whether all the inputs have been spent or if any failed, and update
                 // accordingly
                 if Txo::are_all_spent(&associated.inputs, conn)? {
+                    // Which of a log and its replacement landed is settled by
+                    // resolve_replacements, as they spend the same inputs.
+                    if transaction_log.has_pending_replacement(conn)? {
+                        continue;
+                    }
                     diesel::update(
                         transaction_logs
                             .filter(transaction_id_hex.eq(&transaction_log.transaction_id_hex)),
@@ -382,7 +511,7 @@ impl TransactionLogModel for TransactionLog {
         subaddress_to_output_txo_ids: &HashMap<i64, Vec<String>>,
         account: &Account,
         block_index: u64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::transaction_txo_types;
 
@@ -451,7 +580,7 @@ impl TransactionLogModel for TransactionLog {
         block_index: u64,
         comment: String,
         account_id_hex: Option<&str>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<TransactionLog, WalletDbError> {
         let transaction_log_id = conn.transaction::<String, WalletDbError, _>(|| {
             // Store the txo_id_hex -> transaction_txo_type
@@ -459,15 +588,23 @@ impl TransactionLogModel for TransactionLog {
 
             // Verify that the TxProposal is well-formed according to our assumptions about
             // how to store the sent data in our wallet.
-            if tx_proposal.tx.prefix.outputs.len() - tx_proposal.outlays.len() > 1 {
+            if tx_proposal.tx.prefix.outputs.len() - tx_proposal.outlays.len() > MAX_CHANGE_OUTPUTS
+            {
                 return Err(WalletDbError::UnexpectedNumberOfChangeOutputs);
             }
 
             // First update all inputs to "pending." They will remain pending until their
-            // key_image hits the ledger or their tombstone block is exceeded.
+            // key_image hits the ledger or their tombstone block is exceeded, so record
+            // the tombstone block on them as well.
             for utxo in tx_proposal.utxos.iter() {
                 let txo_id = TxoID::from(&utxo.tx_out);
                 Txo::update_to_pending(&txo_id, conn)?;
+                diesel::update(crate::db::schema::txos::table.find(&txo_id.to_string()))
+                    .set(
+                        crate::db::schema::txos::pending_tombstone_block_index
+                            .eq(Some(tx_proposal.tx.prefix.tombstone_block as i64)),
+                    )
+                    .execute(conn)?;
                 txo_ids.push((txo_id.to_string(), TXO_USED_AS_INPUT.to_string()));
             }
 
@@ -478,13 +615,13 @@ impl TransactionLogModel for TransactionLog {
                     let processed_output =
                         Txo::create_minted(account_id_hex, &output, &tx_proposal, i, conn)?;
 
-                    // Currently, the wallet enforces only one recipient per TransactionLog.
+                    // A TransactionLog has a single recipient. A transaction paying several,
+                    // such as one funding a batch of gift codes, is logged with the recipient
+                    // of its first payment.
                     if let Some(found_recipient) = processed_output.recipient {
-                        if let Some(cur_recipient) = recipient_address.clone() {
-                            if found_recipient != cur_recipient {
-                                return Err(WalletDbError::MultipleRecipientsInTransaction);
-                            }
-                        } else {
+                        let is_first_outlay =
+                            tx_proposal.outlay_index_to_tx_out_index.get(&0) == Some(&i);
+                        if recipient_address.is_none() || is_first_outlay {
                             recipient_address = Some(found_recipient);
                         }
                     }
@@ -553,7 +690,7 @@ impl TransactionLogModel for TransactionLog {
 
     fn delete_all_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::{
             transaction_logs as cols, transaction_logs::dsl::transaction_logs,
@@ -577,6 +714,241 @@ impl TransactionLogModel for TransactionLog {
 
         Ok(())
     }
+
+    fn delete(
+        transaction_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::{
+            transaction_logs as cols, transaction_logs::dsl::transaction_logs,
+            transaction_txo_types as types_cols, transaction_txo_types::dsl::transaction_txo_types,
+        };
+
+        Ok(conn.transaction::<(), WalletDbError, _>(|| {
+            let transaction_log = TransactionLog::get(transaction_id_hex, conn)?;
+
+            // Do not allow removing a log whose inputs are still reserved, otherwise we
+            // would lose track of why they are pending.
+            if transaction_log.status == TX_STATUS_BUILT
+                || transaction_log.status == TX_STATUS_PENDING
+            {
+                let associated = transaction_log.get_associated_txos(conn)?;
+                let has_pending_inputs = Txo::select_by_id(&associated.inputs, conn)?
+                    .iter()
+                    .any(|(_txo, status)| status.txo_status == TXO_STATUS_PENDING);
+                if has_pending_inputs {
+                    return Err(WalletDbError::TransactionLogHasPendingInputs(
+                        transaction_id_hex.to_string(),
+                    ));
+                }
+            }
+
+            diesel::delete(
+                transaction_txo_types.filter(types_cols::transaction_id_hex.eq(transaction_id_hex)),
+            )
+            .execute(conn)?;
+
+            diesel::delete(
+                transaction_logs.filter(cols::transaction_id_hex.eq(transaction_id_hex)),
+            )
+            .execute(conn)?;
+
+            Ok(())
+        })?)
+    }
+
+    fn has_pending_replacement(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<bool, WalletDbError> {
+        use crate::db::schema::transaction_logs as cols;
+
+        let num_pending: i64 = cols::table
+            .filter(cols::status.eq_any(vec![TX_STATUS_BUILT, TX_STATUS_PENDING]))
+            .filter(
+                cols::parent_transaction_id_hex
+                    .eq(&self.transaction_id_hex)
+                    .or(cols::transaction_id_hex
+                        .nullable()
+                        .eq(&self.parent_transaction_id_hex)),
+            )
+            .count()
+            .get_result(conn)?;
+        Ok(num_pending > 0)
+    }
+
+    fn resolve_replacements(
+        account_id_hex: &str,
+        block_index: i64,
+        block_outputs: &[TxOut],
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs as cols;
+
+        Ok(conn.transaction::<(), WalletDbError, _>(|| {
+            // Find every replaced or replacing log before settling any, as settling
+            // one ends the pending replacement of the others.
+            let mut replaced_logs = Vec::new();
+            let pending_logs: Vec<TransactionLog> = cols::table
+                .filter(cols::account_id_hex.eq(account_id_hex))
+                .filter(cols::direction.eq(TX_DIRECTION_SENT))
+                .filter(cols::status.eq_any(vec![TX_STATUS_BUILT, TX_STATUS_PENDING]))
+                .load(conn)?;
+            for transaction_log in pending_logs {
+                if transaction_log.has_pending_replacement(conn)? {
+                    replaced_logs.push(transaction_log);
+                }
+            }
+            if replaced_logs.is_empty() {
+                return Ok(());
+            }
+
+            let landed_public_keys: HashSet<Vec<u8>> = block_outputs
+                .iter()
+                .map(|output| mc_util_serial::encode(&output.public_key))
+                .collect();
+            for transaction_log in replaced_logs {
+                let associated = transaction_log.get_associated_txos(conn)?;
+                if !Txo::are_all_spent(&associated.inputs, conn)? {
+                    continue;
+                }
+                let output_txo_ids: Vec<String> = associated
+                    .outputs
+                    .iter()
+                    .chain(associated.change.iter())
+                    .cloned()
+                    .collect();
+                let landed = Txo::select_by_id(&output_txo_ids, conn)?
+                    .iter()
+                    .any(|(txo, _status)| landed_public_keys.contains(&txo.public_key));
+
+                let target = cols::table
+                    .filter(cols::transaction_id_hex.eq(&transaction_log.transaction_id_hex));
+                if landed {
+                    diesel::update(target)
+                        .set((
+                            cols::status.eq(TX_STATUS_SUCCEEDED),
+                            cols::finalized_block_index.eq(Some(block_index)),
+                        ))
+                        .execute(conn)?;
+                } else {
+                    diesel::update(target)
+                        .set(cols::status.eq(TX_STATUS_FAILED))
+                        .execute(conn)?;
+                }
+            }
+            Ok(())
+        })?)
+    }
+
+    fn mark_rejected(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs::dsl::{transaction_id_hex, transaction_logs};
+
+        Ok(conn.transaction::<(), WalletDbError, _>(|| {
+            diesel::update(
+                transaction_logs.filter(transaction_id_hex.eq(&self.transaction_id_hex)),
+            )
+            .set(crate::db::schema::transaction_logs::status.eq(TX_STATUS_FAILED))
+            .execute(conn)?;
+
+            let associated = self.get_associated_txos(conn)?;
+            for (_txo, status) in Txo::select_by_id(&associated.inputs, conn)? {
+                if status.account_id_hex == self.account_id_hex
+                    && status.txo_status == TXO_STATUS_PENDING
+                {
+                    status.set_unspent(conn)?;
+                }
+            }
+            Ok(())
+        })?)
+    }
+
+    fn mark_resubmitted(
+        &self,
+        block_index: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs::dsl::{transaction_id_hex, transaction_logs};
+
+        Ok(conn.transaction::<(), WalletDbError, _>(|| {
+            diesel::update(
+                transaction_logs.filter(transaction_id_hex.eq(&self.transaction_id_hex)),
+            )
+            .set((
+                crate::db::schema::transaction_logs::status.eq(TX_STATUS_PENDING),
+                crate::db::schema::transaction_logs::submitted_block_index
+                    .eq(Some(block_index as i64)),
+            ))
+            .execute(conn)?;
+
+            // Only unspent inputs are made pending, so an input another transaction
+            // has spent since is left as it is.
+            let associated = self.get_associated_txos(conn)?;
+            for txo_id_hex in &associated.inputs {
+                Txo::update_to_pending(&TxoID(txo_id_hex.to_string()), conn)?;
+            }
+            Ok(())
+        })?)
+    }
+
+    fn update_comment(
+        &self,
+        comment: String,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs::dsl::{transaction_id_hex, transaction_logs};
+
+        diesel::update(transaction_logs.filter(transaction_id_hex.eq(&self.transaction_id_hex)))
+            .set(crate::db::schema::transaction_logs::comment.eq(comment))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn set_parent(
+        &self,
+        parent_transaction_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::transaction_logs::dsl::{transaction_id_hex, transaction_logs};
+
+        diesel::update(transaction_logs.filter(transaction_id_hex.eq(&self.transaction_id_hex)))
+            .set(
+                crate::db::schema::transaction_logs::parent_transaction_id_hex
+                    .eq(parent_transaction_id_hex),
+            )
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn list_children(
+        &self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TransactionLog>, WalletDbError> {
+        use crate::db::schema::transaction_logs as cols;
+
+        Ok(cols::table
+            .filter(cols::parent_transaction_id_hex.eq(&self.transaction_id_hex))
+            .order_by(cols::id.asc())
+            .load(conn)?)
+    }
+
+    fn list_sent_to_recipient(
+        account_id_hex: &str,
+        recipient_public_address_b58: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TransactionLog>, WalletDbError> {
+        use crate::db::schema::transaction_logs as cols;
+
+        Ok(cols::table
+            .filter(cols::account_id_hex.eq(account_id_hex))
+            .filter(cols::direction.eq(TX_DIRECTION_SENT))
+            .filter(cols::recipient_public_address_b58.eq(recipient_public_address_b58))
+            .order_by(cols::id.asc())
+            .load(conn)?)
+    }
 }
 
 #[cfg(test)]
@@ -585,9 +957,9 @@ mod tests {
     use crate::{
         db::{
             account::{AccountID, AccountModel},
-            models::{TXO_STATUS_PENDING, TXO_STATUS_SECRETED, TXO_TYPE_MINTED, TXO_TYPE_RECEIVED},
+            models::{TXO_STATUS_SECRETED, TXO_TYPE_MINTED, TXO_TYPE_RECEIVED},
         },
-        service::sync::SyncThread,
+        service::sync::{SyncThread, SyncThreadConfig},
         test_utils::{
             builder_for_random_recipient, create_test_received_txo, get_test_ledger,
             random_account_with_seed_values, WalletDbTestContext, MOB,
@@ -668,6 +1040,79 @@ mod tests {
         }
     }
 
+    #[test_with_logger]
+    fn test_list_page_across_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        // Alice receives in blocks 140 and 150, and Bob in block 145.
+        let mut received = Vec::new();
+        for (name, block_indices) in &[("Alice", vec![150, 140]), ("Bob", vec![145])] {
+            let root_id = RootIdentity::from_random(&mut rng);
+            let account_key = AccountKey::from(&root_id);
+            let mut block_txo_ids = Vec::new();
+            for block_index in block_indices {
+                let (txo_id_hex, _txo, _key_image) = create_test_received_txo(
+                    &account_key,
+                    0,
+                    (100 * MOB) as u64,
+                    *block_index,
+                    &mut rng,
+                    &wallet_db,
+                );
+                block_txo_ids.push((*block_index, txo_id_hex));
+            }
+
+            let (account_id, _address) = Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                name,
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+            let account = Account::get(&account_id, &conn).unwrap();
+            for (block_index, txo_id_hex) in block_txo_ids {
+                let mut synced: HashMap<i64, Vec<String>> = HashMap::default();
+                synced.insert(0, vec![txo_id_hex.clone()]);
+                TransactionLog::log_received(&synced, &account, block_index, &conn).unwrap();
+                received.push((block_index, account_id.to_string(), txo_id_hex));
+            }
+        }
+        received.sort();
+
+        // Logs of both accounts are listed, ordered by block.
+        let logs = TransactionLog::list_page_ordered_by_block_index(0, 10, &conn).unwrap();
+        assert_eq!(
+            logs.iter()
+                .map(|t| (
+                    t.finalized_block_index.unwrap() as u64,
+                    t.account_id_hex.clone(),
+                    t.transaction_id_hex.clone()
+                ))
+                .collect::<Vec<_>>(),
+            received
+        );
+
+        // And can be paged through.
+        let page = TransactionLog::list_page_ordered_by_block_index(1, 1, &conn).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].transaction_id_hex, received[1].2);
+        assert_eq!(page[0].account_id_hex, received[1].1);
+        assert!(
+            TransactionLog::list_page_ordered_by_block_index(3, 10, &conn)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test_with_logger]
     fn test_log_submitted(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -678,8 +1123,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -808,6 +1258,71 @@ mod tests {
         // verify the above statements
     }
 
+    // A sent transaction's net effect on the balance is its value plus the fee,
+    // and its change is the value of its change output.
+    #[test_with_logger]
+    fn test_log_submitted_net_and_change(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder
+            .add_recipient(recipient.clone(), 50 * MOB as u64)
+            .unwrap();
+        builder.set_tombstone(0).unwrap();
+        builder.select_txos(None).unwrap();
+        let tx_proposal = builder.build().unwrap();
+
+        let tx_log = TransactionLog::log_submitted(
+            tx_proposal,
+            ledger_db.num_blocks().unwrap(),
+            "".to_string(),
+            Some(&AccountID::from(&account_key).to_string()),
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        let associated = tx_log
+            .get_associated_txos(&wallet_db.get_conn().unwrap())
+            .unwrap();
+        assert_eq!(associated.change.len(), 1);
+        let change_details =
+            Txo::get(&associated.change[0], &wallet_db.get_conn().unwrap()).unwrap();
+        assert_eq!(associated.change_value, change_details.txo.value as u64);
+
+        let json_tx_log =
+            crate::json_rpc::transaction_log::TransactionLog::new(&tx_log, &associated);
+        assert_eq!(
+            json_tx_log.net_pmob,
+            (50 * MOB as u64 + MINIMUM_FEE).to_string()
+        );
+        assert_eq!(
+            json_tx_log.change_pmob,
+            change_details.txo.value.to_string()
+        );
+    }
+
     #[test_with_logger]
     fn test_log_submitted_no_change(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -818,8 +1333,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -881,6 +1401,215 @@ mod tests {
         assert_eq!(associated.inputs.len(), 1);
         assert_eq!(associated.outputs.len(), 1);
         assert_eq!(associated.change.len(), 0);
+        assert_eq!(associated.change_value, 0);
+    }
+
+    #[test_with_logger]
+    fn test_delete_failed_log_keeps_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder.add_recipient(recipient, 50 * MOB as u64).unwrap();
+        builder.set_tombstone(0).unwrap();
+        builder.select_txos(None).unwrap();
+        let tx_proposal = builder.build().unwrap();
+
+        let conn = wallet_db.get_conn().unwrap();
+        let tx_log = TransactionLog::log_submitted(
+            tx_proposal,
+            ledger_db.num_blocks().unwrap(),
+            "".to_string(),
+            Some(&AccountID::from(&account_key).to_string()),
+            &conn,
+        )
+        .unwrap();
+        let associated = tx_log.get_associated_txos(&conn).unwrap();
+
+        // The log is pending and its input is reserved, so it cannot be removed.
+        match TransactionLog::delete(&tx_log.transaction_id_hex, &conn) {
+            Err(WalletDbError::TransactionLogHasPendingInputs(_)) => {}
+            Ok(_) => panic!("Should not be able to remove a pending log"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Mark the log as failed, as the sync thread would once the tombstone passes.
+        diesel::update(crate::db::schema::transaction_logs::table.filter(
+            crate::db::schema::transaction_logs::transaction_id_hex.eq(&tx_log.transaction_id_hex),
+        ))
+        .set(crate::db::schema::transaction_logs::status.eq(TX_STATUS_FAILED))
+        .execute(&conn)
+        .unwrap();
+
+        let txo_ids: Vec<String> = associated
+            .inputs
+            .iter()
+            .chain(associated.outputs.iter())
+            .chain(associated.change.iter())
+            .cloned()
+            .collect();
+        let txos_before = Txo::select_by_id(&txo_ids, &conn).unwrap();
+
+        TransactionLog::delete(&tx_log.transaction_id_hex, &conn).unwrap();
+
+        match TransactionLog::get(&tx_log.transaction_id_hex, &conn) {
+            Err(WalletDbError::TransactionLogNotFound(_)) => {}
+            Ok(_) => panic!("Should not find removed log"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        for txo_id_hex in txo_ids.iter() {
+            assert!(TransactionLog::select_for_txo(txo_id_hex, &conn)
+                .unwrap()
+                .is_empty());
+        }
+
+        // The Txos and their statuses are untouched.
+        let txos_after = Txo::select_by_id(&txo_ids, &conn).unwrap();
+        assert_eq!(txos_before.len(), txos_after.len());
+        for (before, after) in txos_before.iter().zip(txos_after.iter()) {
+            assert_eq!(before.0, after.0);
+            assert_eq!(before.1, after.1);
+        }
+        assert_eq!(
+            Txo::get(&associated.inputs[0], &conn)
+                .unwrap()
+                .received_to_account
+                .unwrap()
+                .txo_status,
+            TXO_STATUS_PENDING
+        );
+    }
+
+    #[test_with_logger]
+    fn test_update_comment(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder.add_recipient(recipient, 50 * MOB as u64).unwrap();
+        builder.set_tombstone(0).unwrap();
+        builder.select_txos(None).unwrap();
+        let tx_proposal = builder.build().unwrap();
+
+        // Submit without a comment.
+        let conn = wallet_db.get_conn().unwrap();
+        let tx_log = TransactionLog::log_submitted(
+            tx_proposal,
+            ledger_db.num_blocks().unwrap(),
+            "".to_string(),
+            Some(&AccountID::from(&account_key).to_string()),
+            &conn,
+        )
+        .unwrap();
+        assert_eq!(tx_log.comment, "");
+
+        tx_log
+            .update_comment("Rent for March".to_string(), &conn)
+            .unwrap();
+
+        let updated = TransactionLog::get(&tx_log.transaction_id_hex, &conn).unwrap();
+        assert_eq!(updated.comment, "Rent for March");
+
+        // Nothing else about the log changes.
+        assert_eq!(
+            TransactionLog {
+                comment: "".to_string(),
+                ..updated
+            },
+            tx_log
+        );
+    }
+
+    #[test_with_logger]
+    fn test_list_sent_to_recipient(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64, 80 * MOB as u64, 90 * MOB as u64],
+            &mut rng,
+        );
+        let account_id_hex = AccountID::from(&account_key).to_string();
+        let conn = wallet_db.get_conn().unwrap();
+
+        // Send twice to the same recipient, and once to another.
+        let (recipient, _builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        let (other_recipient, _builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        let mut sent_ids = Vec::new();
+        for (to, value) in &[
+            (&recipient, 50 * MOB),
+            (&other_recipient, 40 * MOB),
+            (&recipient, 30 * MOB),
+        ] {
+            let (_random_recipient, mut builder) = builder_for_random_recipient(
+                &account_key,
+                &wallet_db,
+                &ledger_db,
+                &mut rng,
+                &logger,
+            );
+            builder.add_recipient((*to).clone(), *value as u64).unwrap();
+            builder.set_tombstone(0).unwrap();
+            builder.select_txos(None).unwrap();
+            let tx_proposal = builder.build().unwrap();
+            let tx_log = TransactionLog::log_submitted(
+                tx_proposal,
+                ledger_db.num_blocks().unwrap(),
+                "".to_string(),
+                Some(&account_id_hex),
+                &conn,
+            )
+            .unwrap();
+            sent_ids.push(tx_log.transaction_id_hex);
+        }
+
+        let to_recipient = TransactionLog::list_sent_to_recipient(
+            &account_id_hex,
+            &b58_encode(&recipient).unwrap(),
+            &conn,
+        )
+        .unwrap();
+        assert_eq!(
+            to_recipient
+                .iter()
+                .map(|t| t.transaction_id_hex.clone())
+                .collect::<Vec<_>>(),
+            vec![sent_ids[0].clone(), sent_ids[2].clone()]
+        );
+        assert_eq!(to_recipient[0].value, 50 * MOB);
+        assert_eq!(to_recipient[1].value, 30 * MOB);
     }
 
     // FIXME: WS-9 - test log_submitted for transaction value > i64::Max
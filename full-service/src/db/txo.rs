@@ -9,11 +9,14 @@ use crate::db::{
     b58_encode,
     models::{
         Account, AccountTxoStatus, AssignedSubaddress, NewAccountTxoStatus, NewTxo, Txo,
+        TxoStatusChange, MOB_TOKEN_ID, TXO_STATUS_DUST, TXO_STATUS_INVALIDATED,
         TXO_STATUS_ORPHANED, TXO_STATUS_PENDING, TXO_STATUS_SECRETED, TXO_STATUS_SPENT,
         TXO_STATUS_UNSPENT, TXO_TYPE_MINTED, TXO_TYPE_RECEIVED, TXO_USED_AS_CHANGE,
         TXO_USED_AS_OUTPUT,
     },
-    WalletDbError,
+    txo_selection::{select_preferring_denominations, SelectionOptions},
+    txo_status_history::TxoStatusChangeModel,
+    WalletDbConnection, WalletDbError,
 };
 use mc_account_keys::{AccountKey, PublicAddress};
 use mc_crypto_digestible::{Digestible, MerlinTranscript};
@@ -21,6 +24,8 @@ use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
 use mc_mobilecoind::payments::TxProposal;
 use mc_transaction_core::{
     constants::MAX_INPUTS,
+    get_tx_out_shared_secret,
+    onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
     tx::{TxOut, TxOutConfirmationNumber},
 };
@@ -30,7 +35,7 @@ use diesel::{
     r2d2::{ConnectionManager, PooledConnection},
     RunQueryDsl,
 };
-use std::fmt;
+use std::{collections::HashSet, convert::TryFrom, fmt};
 
 /// A unique ID derived from a TxOut in the ledger.
 #[derive(Debug)]
@@ -55,6 +60,8 @@ pub struct TxoDetails {
     pub received_to_account: Option<AccountTxoStatus>,
     pub received_to_assigned_subaddress: Option<AssignedSubaddress>,
     pub minted_from_account: Option<AccountTxoStatus>,
+    /// Whether this Txo was minted as change by a transaction in this wallet.
+    pub is_change: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -91,7 +98,7 @@ pub trait TxoModel {
         value: u64,
         received_block_index: i64,
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<String, WalletDbError>;
 
     /// Processes a TxProposal to create a new minted Txo and a change Txo.
@@ -103,7 +110,7 @@ pub trait TxoModel {
         txo: &TxOut,
         tx_proposal: &TxProposal,
         outlay_index: usize,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<ProcessedTxProposalOutput, WalletDbError>;
 
     /// Update an existing Txo to spendable by including its subaddress_index
@@ -113,54 +120,123 @@ pub trait TxoModel {
         received_subaddress_index: Option<i64>,
         received_key_image: Option<KeyImage>,
         block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
+    /// Recompute a received Txo's key image from the account key and the
+    /// Txo's subaddress, and store it with update_to_spendable, so that a Txo
+    /// received without a key image becomes spendable.
+    ///
+    /// Returns:
+    /// * KeyImage
+    fn recompute_key_image(
+        account_id: &AccountID,
+        txo_id: &TxoID,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<KeyImage, WalletDbError>;
+
     /// Update a Txo's received block count.
     fn update_received_block_index(
         &self,
         block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Update the index of the block in which a Txo was spent.
+    fn update_spent_block_index(
+        &self,
+        block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     /// Update a Txo's status to pending
     fn update_to_pending(
         txo_id_hex: &TxoID,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError>;
 
     /// Get all Txos associated with a given account.
+    ///
+    /// If exclude_spent is true, Txos which are spent or secreted for the
+    /// account are omitted.
     fn list_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        exclude_spent: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TxoDetails>, WalletDbError>;
 
     fn list_for_address(
         assigned_subaddress_b58: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TxoDetails>, WalletDbError>;
 
+    /// Get a page of the Txos in the wallet, across all accounts, ordered by id
+    /// and starting after the given id, along with the status of each Txo for
+    /// every account it is associated with.
+    fn list_all(
+        offset: Option<i32>,
+        limit: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<(Txo, Vec<AccountTxoStatus>)>, WalletDbError>;
+
+    /// List the distinct token ids of the Txos in a given account, in
+    /// ascending order.
+    fn list_token_ids(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<u64>, WalletDbError>;
+
     /// Get a Vec<Txo> for all txos in a given account with a given txo_status.
     fn list_by_status(
         account_id_hex: &str,
         status: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
     /// Get a Vec<Txo> for all txos in a given account with a given txo_type.
     fn list_by_type(
         account_id_hex: &str,
         txo_type: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
+    /// Get the Txos minted by an account, which are secreted until the wallet
+    /// sees them land or be spent.
+    ///
+    /// If resolved is false, only the Txos which are still secreted are
+    /// listed. Otherwise, only the Txos which have since been received or
+    /// spent in the wallet are listed.
+    fn list_secreted(
+        account_id_hex: &str,
+        resolved: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// Get the Txos an account spent in a given block.
+    ///
+    /// A Txo the account minted for another account in the wallet is not
+    /// listed, even though its secreted status resolves to spent for the
+    /// account, since the other account spent it.
+    fn list_spent_in_block(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// Whether a Txo was minted as change by a transaction logged in this
+    /// wallet.
+    fn is_change(
+        txo_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<bool, WalletDbError>;
+
     /// Get the details for a specific Txo.
     ///
     /// Returns:
     /// * TxoDetails
     fn get(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<TxoDetails, WalletDbError>;
 
     /// Get several Txos by Txo public_keys, specific to an account.
@@ -170,7 +246,7 @@ pub trait TxoModel {
     fn select_by_public_key(
         account_id: &AccountID,
         public_keys: &[&CompressedRistrettoPublic],
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<(Txo, AccountTxoStatus)>, WalletDbError>;
 
     /// Select several Txos by their TxoIds
@@ -179,31 +255,68 @@ pub trait TxoModel {
     /// * Vec<(Txo, TxoStatus)>
     fn select_by_id(
         txo_ids: &[String],
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<(Txo, AccountTxoStatus)>, WalletDbError>;
 
     /// Check whether all of the given Txos are spent.
     fn are_all_spent(
         txo_ids: &[String],
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<bool, WalletDbError>;
 
-    /// Check whether any of the given Txos failed.
+    /// Check whether any of the given Txos failed, i.e. is still unspent or
+    /// pending although its pending tombstone block is before block_index.
     fn any_failed(
         txo_ids: &[String],
         block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<bool, WalletDbError>;
 
     /// Select a set of unspent Txos to reach a given value.
     ///
+    /// The options restrict which Txos may be selected, and how the Txos
+    /// reaching the value are chosen.
+    ///
     /// Returns:
     /// * Vec<Txo>
     fn select_unspent_txos_for_value(
         account_id_hex: &str,
         target_value: u64,
         max_spendable_value: Option<i64>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        options: &SelectionOptions,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// List the unspent Txos for an account that have a subaddress and key
+    /// image, and so can be spent, in decreasing order of value.
+    fn list_spendable(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// Select Txos for a value from an account's spendable Txos, as listed by
+    /// list_spendable, with the same options as select_unspent_txos_for_value.
+    fn select_from_spendable(
+        spendable_txos: &[Txo],
+        target_value: u64,
+        max_spendable_value: Option<i64>,
+        options: &SelectionOptions,
+    ) -> Result<Vec<Txo>, WalletDbError>;
+
+    /// Select the most valuable unspent Txos for an account, up to
+    /// MAX_INPUTS, e.g. to empty the account in a single transaction.
+    ///
+    /// If max_received_block_index is provided, only Txos received at or
+    /// before that block are selected. The excluded Txos, e.g. those reserved
+    /// for another transaction, are never selected.
+    ///
+    /// Returns:
+    /// * Vec<Txo>
+    fn select_max_spendable_txos(
+        account_id_hex: &str,
+        max_received_block_index: Option<i64>,
+        excluded_txo_ids: &[String],
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Txo>, WalletDbError>;
 
     /// Validate a confirmation number for a Txo
@@ -214,7 +327,7 @@ pub trait TxoModel {
         account_id: &AccountID,
         txo_id_hex: &str,
         confirmation: &TxOutConfirmationNumber,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<bool, WalletDbError>;
 }
 
@@ -226,7 +339,7 @@ impl TxoModel for Txo {
         value: u64,
         received_block_index: i64,
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<String, WalletDbError> {
         let txo_id = TxoID::from(&txo);
         conn.transaction::<(), WalletDbError, _>(|| {
@@ -294,7 +407,27 @@ impl TxoModel for Txo {
                                         account_txo_status.set_unspent(conn)?;
                                     }
                                 }
+                                TXO_STATUS_INVALIDATED => {
+                                    // The Txo is back in the ledger, so it is spendable again if
+                                    // we know its subaddress, and orphaned otherwise.
+                                    if subaddress_index.is_some() {
+                                        txo_details.txo.update_to_spendable(
+                                            subaddress_index,
+                                            key_image,
+                                            received_block_index,
+                                            &conn,
+                                        )?;
+                                        account_txo_status.set_unspent(conn)?;
+                                    } else {
+                                        txo_details.txo.update_received_block_index(
+                                            received_block_index,
+                                            conn,
+                                        )?;
+                                        account_txo_status.set_orphaned(conn)?;
+                                    }
+                                }
                                 TXO_STATUS_UNSPENT => {}
+                                TXO_STATUS_DUST => {}
                                 TXO_STATUS_PENDING => {}
                                 TXO_STATUS_SPENT => {}
                                 _ => {
@@ -355,6 +488,7 @@ impl TxoModel for Txo {
                         pending_tombstone_block_index: None,
                         spent_block_index: None,
                         confirmation: None,
+                        recipient_public_address_b58: None,
                     };
 
                     diesel::insert_into(crate::db::schema::txos::table)
@@ -391,7 +525,7 @@ impl TxoModel for Txo {
         output: &TxOut,
         tx_proposal: &TxProposal,
         output_index: usize,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<ProcessedTxProposalOutput, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -412,11 +546,24 @@ impl TxoModel for Txo {
                 Some(*outlay_index),
                 Some(outlay.receiver.clone()),
             )
+        } else if tx_proposal.tx.prefix.outputs.len() - tx_proposal.outlays.len() > 1 {
+            // This is one of several change outputs, so its value is only part of the
+            // change. The sending account can recover it with its own view key.
+            let account_id_hex =
+                account_id_hex.ok_or(WalletDbError::UnexpectedNumberOfChangeOutputs)?;
+            let account = Account::get(&AccountID(account_id_hex.to_string()), conn)?;
+            let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+            let tx_public_key = RistrettoPublic::try_from(&output.public_key)
+                .map_err(|e| WalletDbError::ChangeValue(format!("{:?}", e)))?;
+            let shared_secret =
+                get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+            let (value, _blinding) = output
+                .amount
+                .get_value(&shared_secret)
+                .map_err(|e| WalletDbError::ChangeValue(format!("{:?}", e)))?;
+            (value, None, None)
         } else {
-            // This is the change output. Note: there should only be one change output
-            // per transaction, based on how we construct transactions. If we change
-            // how we construct transactions, these assumptions will change, and should be
-            // reflected in the TxProposal.
+            // This is the only change output, so it holds all of the change.
             (change_value, None, None)
         };
 
@@ -427,11 +574,12 @@ impl TxoModel for Txo {
         } else {
             // If not in an outlay, this output is change, according to how we build
             // transactions.
-            (TXO_USED_AS_CHANGE, change_value)
+            (TXO_USED_AS_CHANGE, value)
         };
 
         let encoded_confirmation = confirmation
             .map(|p| mc_util_serial::encode(&tx_proposal.outlay_confirmation_numbers[p]));
+        let recipient_public_address_b58 = outlay_receiver.as_ref().map(b58_encode).transpose()?;
 
         conn.transaction::<(), WalletDbError, _>(|| {
             let new_txo = NewTxo {
@@ -448,6 +596,7 @@ impl TxoModel for Txo {
                 pending_tombstone_block_index: Some(tx_proposal.tx.prefix.tombstone_block as i64),
                 spent_block_index: None,
                 confirmation: encoded_confirmation.as_deref(),
+                recipient_public_address_b58: recipient_public_address_b58.as_deref(),
             };
 
             diesel::insert_into(txos::table)
@@ -468,6 +617,12 @@ impl TxoModel for Txo {
                 diesel::insert_into(account_txo_statuses::table)
                     .values(&new_account_txo_status)
                     .execute(conn)?;
+                TxoStatusChange::record(
+                    &account_id_hex,
+                    &txo_id.to_string(),
+                    TXO_STATUS_SECRETED,
+                    conn,
+                )?;
             }
             Ok(())
         })?;
@@ -485,7 +640,7 @@ impl TxoModel for Txo {
         received_subaddress_index: Option<i64>,
         received_key_image: Option<KeyImage>,
         block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::txos::{key_image, received_block_index, subaddress_index};
 
@@ -504,13 +659,58 @@ impl TxoModel for Txo {
                 key_image.eq(encoded_key_image),
             ))
             .execute(conn)?;
+        // The key image decides whether the Txo is spendable.
+        for status in AccountTxoStatus::get_all_associated_accounts(&self.txo_id_hex, conn)? {
+            Account::increment_txo_version(&status.account_id_hex, conn)?;
+        }
         Ok(())
     }
 
+    fn recompute_key_image(
+        account_id: &AccountID,
+        txo_id: &TxoID,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<KeyImage, WalletDbError> {
+        conn.transaction::<KeyImage, WalletDbError, _>(|| {
+            let account = Account::get(account_id, conn)?;
+            let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+
+            // The Txo must belong to this account.
+            AccountTxoStatus::get(&account_id.to_string(), &txo_id.to_string(), conn)?;
+            let txo = Txo::get(&txo_id.to_string(), conn)?.txo;
+
+            // Without the subaddress we cannot recover the spend key.
+            let subaddress_index = txo
+                .subaddress_index
+                .ok_or(WalletDbError::NullSubaddressOnReceived)?;
+            let received_block_index = txo
+                .received_block_index
+                .ok_or(WalletDbError::MalformedTxoDatabaseEntry)?;
+
+            let tx_out: TxOut = mc_util_serial::decode(&txo.txo)?;
+            let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key)
+                .map_err(|_| WalletDbError::MalformedTxoDatabaseEntry)?;
+            let onetime_private_key = recover_onetime_private_key(
+                &tx_public_key,
+                account_key.view_private_key(),
+                &account_key.subaddress_spend_private(subaddress_index as u64),
+            );
+            let key_image = KeyImage::from(&onetime_private_key);
+
+            txo.update_to_spendable(
+                Some(subaddress_index),
+                Some(key_image),
+                received_block_index,
+                conn,
+            )?;
+            Ok(key_image)
+        })
+    }
+
     fn update_received_block_index(
         &self,
         block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::txos::received_block_index;
 
@@ -520,9 +720,22 @@ impl TxoModel for Txo {
         Ok(())
     }
 
+    fn update_spent_block_index(
+        &self,
+        block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txos::spent_block_index;
+
+        diesel::update(self)
+            .set(spent_block_index.eq(Some(block_index)))
+            .execute(conn)?;
+        Ok(())
+    }
+
     fn update_to_pending(
         txo_id: &TxoID,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<(), WalletDbError> {
         use crate::db::schema::account_txo_statuses::dsl::account_txo_statuses;
 
@@ -555,6 +768,12 @@ impl TxoModel for Txo {
                             .eq(TXO_STATUS_PENDING.to_string()),
                     )
                     .execute(conn)?;
+                    TxoStatusChange::record(
+                        &account.account_id_hex,
+                        &txo_id.to_string(),
+                        TXO_STATUS_PENDING,
+                        conn,
+                    )?;
                 }
             }
             Ok(())
@@ -571,16 +790,22 @@ impl TxoModel for Txo {
 
     fn list_for_account(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        exclude_spent: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TxoDetails>, WalletDbError> {
         use crate::db::schema::{
             account_txo_statuses as cols, account_txo_statuses::dsl::account_txo_statuses,
         };
 
-        let results: Vec<String> = account_txo_statuses
+        let mut query = account_txo_statuses
             .filter(cols::account_id_hex.eq(account_id_hex))
             .select(cols::txo_id_hex)
-            .load(conn)?;
+            .into_boxed();
+        if exclude_spent {
+            query =
+                query.filter(cols::txo_status.ne_all(vec![TXO_STATUS_SPENT, TXO_STATUS_SECRETED]));
+        }
+        let results: Vec<String> = query.load(conn)?;
 
         let details: Result<Vec<TxoDetails>, WalletDbError> =
             results.iter().map(|t| Txo::get(t, &conn)).collect();
@@ -589,7 +814,7 @@ impl TxoModel for Txo {
 
     fn list_for_address(
         assigned_subaddress_b58: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<TxoDetails>, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
         let subaddress = AssignedSubaddress::get(&assigned_subaddress_b58, conn)?;
@@ -616,10 +841,64 @@ impl TxoModel for Txo {
         details
     }
 
+    fn list_all(
+        offset: Option<i32>,
+        limit: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<(Txo, Vec<AccountTxoStatus>)>, WalletDbError> {
+        use crate::db::schema::{account_txo_statuses, txos};
+
+        let page = txos::table
+            .select(txos::id)
+            .filter(txos::id.gt(offset.unwrap_or(0)))
+            .order(txos::id.asc())
+            .limit(limit);
+
+        let rows: Vec<(Txo, AccountTxoStatus)> = txos::table
+            .inner_join(
+                account_txo_statuses::table
+                    .on(txos::txo_id_hex.eq(account_txo_statuses::txo_id_hex)),
+            )
+            .filter(txos::id.eq_any(page))
+            .order(txos::id.asc())
+            .select((txos::all_columns, account_txo_statuses::all_columns))
+            .load(conn)?;
+
+        // Rows for the same Txo are adjacent, so group them as we go.
+        let mut results: Vec<(Txo, Vec<AccountTxoStatus>)> = Vec::new();
+        for (txo, status) in rows {
+            match results.last_mut() {
+                Some((last, statuses)) if last.id == txo.id => statuses.push(status),
+                _ => results.push((txo, vec![status])),
+            }
+        }
+        Ok(results)
+    }
+
+    fn list_token_ids(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<u64>, WalletDbError> {
+        use crate::db::schema::{account_txo_statuses, txos};
+
+        let token_ids: Vec<i64> = txos::table
+            .inner_join(
+                account_txo_statuses::table.on(txos::txo_id_hex
+                    .eq(account_txo_statuses::txo_id_hex)
+                    .and(account_txo_statuses::account_id_hex.eq(account_id_hex))),
+            )
+            .select(txos::token_id)
+            .distinct()
+            .order_by(txos::token_id.asc())
+            .load(conn)?;
+
+        Ok(token_ids.into_iter().map(|t| t as u64).collect())
+    }
+
     fn list_by_status(
         account_id_hex: &str,
         status: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Txo>, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -639,7 +918,7 @@ impl TxoModel for Txo {
     fn list_by_type(
         account_id_hex: &str,
         txo_type: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Txo>, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -656,9 +935,85 @@ impl TxoModel for Txo {
         Ok(results)
     }
 
+    fn list_secreted(
+        account_id_hex: &str,
+        resolved: bool,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::{account_txo_statuses, txos};
+
+        let query = txos::table
+            .inner_join(
+                account_txo_statuses::table.on(txos::txo_id_hex
+                    .eq(account_txo_statuses::txo_id_hex)
+                    .and(account_txo_statuses::account_id_hex.eq(account_id_hex))
+                    .and(account_txo_statuses::txo_type.eq(TXO_TYPE_MINTED))),
+            )
+            .select(txos::all_columns);
+        let results: Vec<Txo> = if resolved {
+            query
+                .filter(account_txo_statuses::txo_status.ne(TXO_STATUS_SECRETED))
+                .load(conn)?
+        } else {
+            query
+                .filter(account_txo_statuses::txo_status.eq(TXO_STATUS_SECRETED))
+                .load(conn)?
+        };
+
+        Ok(results)
+    }
+
+    fn list_spent_in_block(
+        account_id_hex: &str,
+        block_index: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::{account_txo_statuses, txos};
+
+        let results: Vec<(Txo, AccountTxoStatus)> = txos::table
+            .inner_join(
+                account_txo_statuses::table.on(txos::txo_id_hex
+                    .eq(account_txo_statuses::txo_id_hex)
+                    .and(account_txo_statuses::account_id_hex.eq(account_id_hex))
+                    .and(account_txo_statuses::txo_status.eq(TXO_STATUS_SPENT))),
+            )
+            .filter(txos::spent_block_index.eq(Some(block_index as i64)))
+            .select((txos::all_columns, account_txo_statuses::all_columns))
+            .load(conn)?;
+
+        let mut spent = Vec::new();
+        for (txo, status) in results {
+            // A minted Txo was only spent by this account if no other account
+            // received it.
+            if status.txo_type == TXO_TYPE_MINTED
+                && AccountTxoStatus::get_all_associated_accounts(&txo.txo_id_hex, conn)?
+                    .iter()
+                    .any(|s| s.txo_type == TXO_TYPE_RECEIVED)
+            {
+                continue;
+            }
+            spent.push(txo);
+        }
+        Ok(spent)
+    }
+
+    fn is_change(
+        txo_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<bool, WalletDbError> {
+        use crate::db::schema::transaction_txo_types;
+
+        let num_change_types: i64 = transaction_txo_types::table
+            .filter(transaction_txo_types::txo_id_hex.eq(txo_id_hex))
+            .filter(transaction_txo_types::transaction_txo_type.eq(TXO_USED_AS_CHANGE))
+            .count()
+            .get_result(conn)?;
+        Ok(num_change_types > 0)
+    }
+
     fn get(
         txo_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<TxoDetails, WalletDbError> {
         use crate::db::schema::txos::dsl::{txo_id_hex as dsl_txo_id_hex, txos};
 
@@ -691,6 +1046,7 @@ impl TxoModel for Txo {
             received_to_account: None,
             received_to_assigned_subaddress: None,
             minted_from_account: None,
+            is_change: Txo::is_change(txo_id_hex, conn)?,
         };
 
         for account_txo_status in account_txo_statuses {
@@ -746,7 +1102,7 @@ impl TxoModel for Txo {
     fn select_by_public_key(
         account_id: &AccountID,
         public_keys: &[&CompressedRistrettoPublic],
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<(Txo, AccountTxoStatus)>, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -768,7 +1124,7 @@ impl TxoModel for Txo {
 
     fn select_by_id(
         txo_ids: &[String],
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<(Txo, AccountTxoStatus)>, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -785,7 +1141,7 @@ impl TxoModel for Txo {
 
     fn are_all_spent(
         txo_ids: &[String],
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<bool, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -805,7 +1161,7 @@ impl TxoModel for Txo {
     fn any_failed(
         txo_ids: &[String],
         block_index: i64,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<bool, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
@@ -831,27 +1187,69 @@ impl TxoModel for Txo {
         account_id_hex: &str,
         target_value: u64,
         max_spendable_value: Option<i64>,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        options: &SelectionOptions,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        Self::select_from_spendable(
+            &Self::list_spendable(account_id_hex, conn)?,
+            target_value,
+            max_spendable_value,
+            options,
+        )
+    }
+
+    fn list_spendable(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<Vec<Txo>, WalletDbError> {
         use crate::db::schema::{account_txo_statuses, txos};
 
-        let mut spendable_txos: Vec<Txo> = txos::table
+        Ok(txos::table
             .inner_join(
                 account_txo_statuses::table.on(txos::txo_id_hex
                     .eq(account_txo_statuses::txo_id_hex)
                     .and(account_txo_statuses::account_id_hex.eq(account_id_hex))
                     .and(account_txo_statuses::txo_status.eq(TXO_STATUS_UNSPENT))
                     .and(txos::subaddress_index.is_not_null())
-                    .and(txos::key_image.is_not_null()) // Could technically recreate with subaddress
-                    .and(txos::value.le(max_spendable_value.unwrap_or(i64::MAX)))),
+                    // Could technically recreate with subaddress
+                    .and(txos::key_image.is_not_null())
+                    // Transactions only spend MOB.
+                    .and(txos::token_id.eq(MOB_TOKEN_ID as i64))),
             )
             .select(txos::all_columns)
             .order_by(txos::value.desc())
-            .load(conn)?;
+            .load(conn)?)
+    }
+
+    fn select_from_spendable(
+        spendable_txos: &[Txo],
+        target_value: u64,
+        max_spendable_value: Option<i64>,
+        options: &SelectionOptions,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        let spendable_txos: Vec<&Txo> = spendable_txos
+            .iter()
+            .filter(|txo| txo.value <= max_spendable_value.unwrap_or(i64::MAX))
+            .filter(|txo| {
+                options.max_received_block_index.map_or(true, |max_index| {
+                    txo.received_block_index
+                        .map_or(false, |index| index <= max_index)
+                })
+            })
+            .filter(|txo| {
+                options
+                    .subaddress_index
+                    .map_or(true, |index| txo.subaddress_index == Some(index))
+            })
+            .collect();
 
         if spendable_txos.is_empty() {
             return Err(WalletDbError::NoSpendableTxos);
         }
+        let consolidate = options
+            .auto_consolidate_threshold
+            .map(|threshold| spendable_txos.len() as i64 > threshold)
+            .unwrap_or(false);
 
         // The maximum spendable is limited by the maximal number of inputs we can use.
         // Since the txos are sorted by decreasing value, this is the maximum
@@ -875,50 +1273,95 @@ impl TxoModel for Txo {
             }
         }
 
-        // Select the actual Txos to spend. We want to opportunistically fill up the
-        // input slots with dust, from any subaddress, so we take from the back
-        // of the Txo vec. This is a knapsack problem, and the selection could
-        // be improved. For now, we simply move the window of MAX_INPUTS up from
-        // the back of the sorted vector until we have a window with
-        // a large enough sum.
-        let mut selected_utxos: Vec<Txo> = Vec::new();
-        let mut total: u64 = 0;
-        loop {
-            if total >= target_value {
-                break;
-            }
+        // Select the actual Txos to spend, from any subaddress, within
+        // MAX_INPUTS.
+        let values: Vec<u64> = spendable_txos
+            .iter()
+            .map(|utxo| utxo.value as u64)
+            .collect();
+        let preferred_denominations: Vec<u64> = options
+            .preferred_denominations
+            .iter()
+            .map(|d| *d as u64)
+            .collect();
+        let selected_indices = select_preferring_denominations(
+            &values,
+            &preferred_denominations,
+            target_value,
+            MAX_INPUTS as usize,
+            options.strategy,
+        )
+        .ok_or(WalletDbError::TxoSelectionFailed)?;
+        let mut selected_utxos: Vec<Txo> = selected_indices
+            .iter()
+            .map(|i| Txo::clone(spendable_txos[*i]))
+            .collect();
 
-            // Grab the next (smallest) utxo, in order to opportunistically sweep up dust
-            let next_utxo = spendable_txos.pop().ok_or_else(|| {
-                WalletDbError::InsufficientFunds(format!(
-                    "Not enough Txos to sum to target value: {:?}",
-                    target_value
-                ))
-            })?;
-            selected_utxos.push(next_utxo.clone());
-            total += next_utxo.value as u64;
-
-            // Cap at maximum allowed inputs.
-            if selected_utxos.len() > MAX_INPUTS as usize {
-                // Remove the lowest utxo.
-                selected_utxos.remove(0);
+        // If the account is fragmented past its threshold, fill the remaining
+        // input slots with the smallest leftover Txos. Their value returns to
+        // the account as change.
+        if consolidate {
+            let selected: HashSet<usize> = selected_indices.into_iter().collect();
+            for (index, utxo) in spendable_txos.iter().enumerate().rev() {
+                if selected_utxos.len() >= MAX_INPUTS as usize {
+                    break;
+                }
+                if !selected.contains(&index) {
+                    selected_utxos.push(Txo::clone(utxo));
+                }
             }
         }
 
         if selected_utxos.is_empty() || selected_utxos.len() > MAX_INPUTS as usize {
-            return Err(WalletDbError::InsufficientFunds(
-                "Logic error. Could not select Txos despite having sufficient funds".to_string(),
-            ));
+            return Err(WalletDbError::TxoSelectionFailed);
         }
 
         Ok(selected_utxos)
     }
 
+    fn select_max_spendable_txos(
+        account_id_hex: &str,
+        max_received_block_index: Option<i64>,
+        excluded_txo_ids: &[String],
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<Txo>, WalletDbError> {
+        use crate::db::schema::{account_txo_statuses, txos};
+
+        let mut query = txos::table
+            .inner_join(
+                account_txo_statuses::table.on(txos::txo_id_hex
+                    .eq(account_txo_statuses::txo_id_hex)
+                    .and(account_txo_statuses::account_id_hex.eq(account_id_hex))
+                    .and(account_txo_statuses::txo_status.eq(TXO_STATUS_UNSPENT))
+                    .and(txos::subaddress_index.is_not_null())
+                    .and(txos::key_image.is_not_null())
+                    .and(txos::token_id.eq(MOB_TOKEN_ID as i64))),
+            )
+            .select(txos::all_columns)
+            .order_by(txos::value.desc())
+            .into_boxed();
+        if let Some(max_index) = max_received_block_index {
+            query = query.filter(txos::received_block_index.le(Some(max_index)));
+        }
+        // Exclude before limiting, so that excluded Txos do not take the place of
+        // spendable ones.
+        if !excluded_txo_ids.is_empty() {
+            query = query.filter(txos::txo_id_hex.ne_all(excluded_txo_ids));
+        }
+        let selected_txos: Vec<Txo> = query.limit(MAX_INPUTS as i64).load(conn)?;
+
+        if selected_txos.is_empty() {
+            return Err(WalletDbError::NoSpendableTxos);
+        }
+
+        Ok(selected_txos)
+    }
+
     fn validate_confirmation(
         account_id: &AccountID,
         txo_id_hex: &str,
         confirmation: &TxOutConfirmationNumber,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     ) -> Result<bool, WalletDbError> {
         Ok(conn.transaction::<bool, WalletDbError, _>(|| {
             let txo_details = Txo::get(txo_id_hex, conn)?;
@@ -940,7 +1383,7 @@ mod tests {
             transaction_log::TransactionLogModel,
         },
         service::{
-            sync::{sync_account, SyncThread},
+            sync::{sync_account, SyncThread, SyncThreadConfig},
             transaction_builder::WalletTransactionBuilder,
         },
         test_utils::{
@@ -1009,6 +1452,7 @@ mod tests {
 
         let txos = Txo::list_for_account(
             &alice_account_id.to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1029,6 +1473,8 @@ mod tests {
             pending_tombstone_block_index: None,
             spent_block_index: None,
             confirmation: None,
+            token_id: MOB_TOKEN_ID as i64,
+            recipient_public_address_b58: None,
         };
         // Verify that the statuses table was updated correctly
         let expected_txo_status = AccountTxoStatus {
@@ -1036,6 +1482,7 @@ mod tests {
             txo_id_hex: TxoID::from(&for_alice_txo).to_string(),
             txo_status: TXO_STATUS_UNSPENT.to_string(),
             txo_type: TXO_TYPE_RECEIVED.to_string(),
+            label: None,
         };
         assert_eq!(txos[0].txo, expected_txo);
         assert_eq!(
@@ -1084,6 +1531,7 @@ mod tests {
         // and one minted (destined for alice).
         let txos = Txo::list_for_account(
             &alice_account_id.to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1221,6 +1669,7 @@ mod tests {
 
         let updated_txos = Txo::list_for_account(
             &alice_account_id.to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1287,6 +1736,7 @@ mod tests {
         // We should now have 1 txo in Bob's account.
         let txos = Txo::list_for_account(
             &AccountID::from(&bob_account_key).to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1337,6 +1787,7 @@ mod tests {
             &account_id_hex.to_string(),
             300 * MOB as u64,
             None,
+            &SelectionOptions::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1351,6 +1802,7 @@ mod tests {
             &account_id_hex.to_string(),
             300 * MOB as u64 + MINIMUM_FEE,
             None,
+            &SelectionOptions::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1365,6 +1817,7 @@ mod tests {
             &account_id_hex.to_string(),
             300 * MOB as u64 + MINIMUM_FEE,
             Some(200 * MOB),
+            &SelectionOptions::default(),
             &wallet_db.get_conn().unwrap(),
         );
         match res {
@@ -1379,6 +1832,7 @@ mod tests {
             &account_id_hex.to_string(),
             16800 * MOB as u64,
             None,
+            &SelectionOptions::default(),
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1406,6 +1860,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_insufficient_funds_shortfall() {
+        match WalletDbError::insufficient_funds(20000 * MOB as u64, 19000 * MOB as u64) {
+            WalletDbError::InsufficientFunds {
+                requested,
+                available,
+                shortfall,
+            } => {
+                assert_eq!(requested, 20000 * MOB as u64);
+                assert_eq!(available, 19000 * MOB as u64);
+                assert_eq!(shortfall, requested - available);
+            }
+            e => panic!("Unexpected error {:?}", e),
+        }
+
+        // The message is unchanged from the unstructured error.
+        assert_eq!(
+            WalletDbError::insufficient_funds(42, 0).to_string(),
+            "Insufficient Funds: Not enough Txos to sum to target value: 42"
+        );
+    }
+
     #[test_with_logger]
     fn test_select_txos_fragmented(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -1445,6 +1921,7 @@ mod tests {
             &account_id_hex.to_string(), // FIXME: WS-11 - take AccountID
             1800 * MOB as u64,
             None,
+            &SelectionOptions::default(),
             &wallet_db.get_conn().unwrap(),
         );
         match res {
@@ -1457,6 +1934,57 @@ mod tests {
         }
     }
 
+    // Excluded Txos should not count towards the MAX_INPUTS selected.
+    #[test_with_logger]
+    fn test_select_max_spendable_txos_excluded(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let (account_id_hex, _public_address_b58) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "Alice's Main Account",
+            None,
+            None,
+            None,
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        // The two most valuable Txos are excluded.
+        let mut txo_ids = Vec::new();
+        for i in 0..MAX_INPUTS + 2 {
+            let (txo_id_hex, _txo, _key_image) = create_test_received_txo(
+                &account_key,
+                0,
+                ((100 - i as i64) * MOB) as u64,
+                (144 + i) as u64,
+                &mut rng,
+                &wallet_db,
+            );
+            txo_ids.push(txo_id_hex);
+        }
+        let excluded_txo_ids = txo_ids[..2].to_vec();
+
+        let selected = Txo::select_max_spendable_txos(
+            &account_id_hex.to_string(),
+            None,
+            &excluded_txo_ids,
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(selected.len(), MAX_INPUTS as usize);
+        assert!(selected
+            .iter()
+            .all(|txo| !excluded_txo_ids.contains(&txo.txo_id_hex)));
+    }
+
     #[test_with_logger]
     fn test_create_minted(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -1525,6 +2053,208 @@ mod tests {
         assert!(change_txo_details.received_to_assigned_subaddress.is_none()); // Note: This gets updated on sync
     }
 
+    // Alice sends a Txo to Bob, another account in the wallet, and syncs the
+    // block in which Bob spends it before the wallet has seen Bob receive it.
+    // Alice's secreted Txo should still resolve to spent once Bob's sync
+    // catches up.
+    #[test_with_logger]
+    fn test_secreted_txo_resolved_when_received_and_spent(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let alice_root_id = RootIdentity::from_random(&mut rng);
+        let alice_account_key = AccountKey::from(&alice_root_id);
+        let bob_root_id = RootIdentity::from_random(&mut rng);
+        let bob_account_key = AccountKey::from(&bob_root_id);
+
+        let known_recipients = vec![alice_account_key.subaddress(0)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        let mut account_ids = Vec::new();
+        for (root_id, name) in [(&alice_root_id, "Alice"), (&bob_root_id, "Bob")].iter() {
+            let (account_id, _public_address_b58) = Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                name,
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+            account_ids.push(account_id.to_string());
+        }
+        sync_account(&ledger_db, &wallet_db, &account_ids[0], &logger).unwrap();
+
+        let ((output_txo_id, output_value), (_change_txo_id, _change_value)) =
+            create_test_minted_and_change_txos(
+                alice_account_key,
+                bob_account_key.subaddress(0),
+                10 * MOB as u64,
+                wallet_db.clone(),
+                ledger_db,
+                logger,
+            );
+        let secreted = Txo::list_secreted(&account_ids[0], false, &conn).unwrap();
+        assert!(secreted.iter().any(|t| t.txo_id_hex == output_txo_id));
+        assert!(Txo::list_secreted(&account_ids[0], true, &conn)
+            .unwrap()
+            .is_empty());
+
+        let output: TxOut =
+            mc_util_serial::decode(&Txo::get(&output_txo_id, &conn).unwrap().txo.txo).unwrap();
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&output.public_key).unwrap(),
+            bob_account_key.view_private_key(),
+            &bob_account_key.subaddress_spend_private(0),
+        );
+        let key_image = KeyImage::from(&onetime_private_key);
+
+        // Alice syncs the block spending the Txo while its key image is unknown,
+        // so it stays secreted for her.
+        let alice = Account::get(&AccountID(account_ids[0].clone()), &conn).unwrap();
+        alice
+            .update_spent_and_increment_next_block(14, vec![key_image], &conn)
+            .unwrap();
+        let status = AccountTxoStatus::get(&account_ids[0], &output_txo_id, &conn).unwrap();
+        assert_eq!(status.txo_status, TXO_STATUS_SECRETED);
+
+        // Bob receives the Txo, and then syncs the block spending it.
+        Txo::create_received(
+            output,
+            Some(0),
+            Some(key_image),
+            output_value as u64,
+            13,
+            &account_ids[1],
+            &conn,
+        )
+        .unwrap();
+        let bob = Account::get(&AccountID(account_ids[1].clone()), &conn).unwrap();
+        bob.update_spent_and_increment_next_block(14, vec![key_image], &conn)
+            .unwrap();
+
+        for account_id_hex in account_ids.iter() {
+            let status = AccountTxoStatus::get(account_id_hex, &output_txo_id, &conn).unwrap();
+            assert_eq!(status.txo_status, TXO_STATUS_SPENT);
+        }
+        let secreted = Txo::list_secreted(&account_ids[0], false, &conn).unwrap();
+        assert!(!secreted.iter().any(|t| t.txo_id_hex == output_txo_id));
+        let resolved = Txo::list_secreted(&account_ids[0], true, &conn).unwrap();
+        assert_eq!(
+            resolved
+                .iter()
+                .map(|t| t.txo_id_hex.clone())
+                .collect::<Vec<String>>(),
+            vec![output_txo_id]
+        );
+    }
+
+    #[test_with_logger]
+    fn test_list_spent_in_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let mut account_ids = Vec::new();
+        let mut account_keys = Vec::new();
+        for name in ["Alice", "Bob"].iter() {
+            let root_id = RootIdentity::from_random(&mut rng);
+            let (account_id, _public_address_b58) = Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                name,
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+            account_ids.push(account_id.to_string());
+            account_keys.push(AccountKey::from(&root_id));
+        }
+
+        let (spent_txo_id_hex, _txo, spent_key_image) = create_test_received_txo(
+            &account_keys[0],
+            0,
+            (10 * MOB) as u64,
+            12,
+            &mut rng,
+            &wallet_db,
+        );
+        let (_unspent_txo_id_hex, _txo, _key_image) = create_test_received_txo(
+            &account_keys[0],
+            0,
+            (20 * MOB) as u64,
+            12,
+            &mut rng,
+            &wallet_db,
+        );
+
+        // A Txo Alice minted for Bob, which Bob spends in the same block.
+        let (bob_txo_id_hex, _txo, bob_key_image) = create_test_received_txo(
+            &account_keys[1],
+            0,
+            (5 * MOB) as u64,
+            12,
+            &mut rng,
+            &wallet_db,
+        );
+        AccountTxoStatus::create(
+            &account_ids[0],
+            &bob_txo_id_hex,
+            TXO_STATUS_SECRETED,
+            TXO_TYPE_MINTED,
+            &conn,
+        )
+        .unwrap();
+
+        for account_id_hex in account_ids.iter() {
+            let account = Account::get(&AccountID(account_id_hex.clone()), &conn).unwrap();
+            account
+                .update_spent_and_increment_next_block(
+                    13,
+                    vec![spent_key_image, bob_key_image],
+                    &conn,
+                )
+                .unwrap();
+        }
+
+        let spent = Txo::list_spent_in_block(&account_ids[0], 13, &conn).unwrap();
+        assert_eq!(
+            spent
+                .iter()
+                .map(|t| t.txo_id_hex.clone())
+                .collect::<Vec<String>>(),
+            vec![spent_txo_id_hex]
+        );
+        let spent = Txo::list_spent_in_block(&account_ids[1], 13, &conn).unwrap();
+        assert_eq!(
+            spent
+                .iter()
+                .map(|t| t.txo_id_hex.clone())
+                .collect::<Vec<String>>(),
+            vec![bob_txo_id_hex]
+        );
+
+        // Nothing was spent in any other block.
+        assert!(Txo::list_spent_in_block(&account_ids[0], 12, &conn)
+            .unwrap()
+            .is_empty());
+        assert!(Txo::list_spent_in_block(&account_ids[0], 14, &conn)
+            .unwrap()
+            .is_empty());
+    }
+
     // Test that the confirmation number validates correctly.
     #[test_with_logger]
     fn test_validate_confirmation(logger: Logger) {
@@ -1555,8 +2285,13 @@ mod tests {
 
         // Start sync thread
         log::info!(logger, "Starting sync thread");
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         log::info!(logger, "Creating a random sender account");
         let sender_account_key = random_account_with_seed_values(
@@ -1614,6 +2349,7 @@ mod tests {
         log::info!(logger, "Listing all Txos for recipient account");
         let txos = Txo::list_for_account(
             &recipient_account_id.to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1630,6 +2366,7 @@ mod tests {
         log::info!(logger, "Listing all Txos for sender account");
         let sender_txos = Txo::list_for_account(
             &sender_account_id.to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap();
@@ -1696,10 +2433,166 @@ mod tests {
         assert_eq!(txos_and_status.len(), 5);
     }
 
+    #[test_with_logger]
+    fn test_recompute_key_image(logger: Logger) {
+        use crate::db::schema::txos;
+
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let (account_id, _public_address_b58) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(1),
+            None,
+            None,
+            "Alice's Main Account",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        let (txo_id_hex, txo, key_image) =
+            create_test_received_txo(&account_key, 0, 100 * MOB as u64, 144, &mut rng, &wallet_db);
+
+        // Clear the key image, leaving the Txo unspendable.
+        diesel::update(txos::table.filter(txos::txo_id_hex.eq(&txo_id_hex)))
+            .set(txos::key_image.eq(None::<Vec<u8>>))
+            .execute(&conn)
+            .unwrap();
+        let cleared = Txo::get(&txo_id_hex, &conn).unwrap().txo;
+        assert!(cleared.key_image.is_none());
+        match Txo::select_unspent_txos_for_value(
+            &account_id.to_string(),
+            10 * MOB as u64,
+            None,
+            &SelectionOptions::default(),
+            &conn,
+        ) {
+            Err(WalletDbError::NoSpendableTxos) => {}
+            Ok(_) => panic!("Should not select a Txo without a key image"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        let recomputed = Txo::recompute_key_image(&account_id, &TxoID::from(&txo), &conn).unwrap();
+        assert_eq!(recomputed, key_image);
+
+        let stored = Txo::get(&txo_id_hex, &conn).unwrap().txo;
+        assert_eq!(stored.key_image, Some(mc_util_serial::encode(&key_image)));
+        assert_eq!(stored.subaddress_index, Some(0));
+        assert_eq!(stored.received_block_index, Some(144));
+
+        // The Txo is spendable again.
+        let selected = Txo::select_unspent_txos_for_value(
+            &account_id.to_string(),
+            10 * MOB as u64,
+            None,
+            &SelectionOptions::default(),
+            &conn,
+        )
+        .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].txo_id_hex, txo_id_hex);
+
+        // A Txo that does not belong to the account cannot be recomputed.
+        let other_key = AccountKey::random(&mut rng);
+        let (_other_txo_id_hex, other_txo, _other_key_image) =
+            create_test_received_txo(&other_key, 0, MOB as u64, 144, &mut rng, &wallet_db);
+        match Txo::recompute_key_image(&account_id, &TxoID::from(&other_txo), &conn) {
+            Err(WalletDbError::AccountTxoStatusNotFound(_)) => {}
+            Ok(_) => panic!("Should not recompute a key image for another account's Txo"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_are_all_spent_and_any_failed(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let sender_account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64, 80 * MOB as u64],
+            &mut rng,
+        );
+        let sender_account_id = AccountID::from(&sender_account_key);
+
+        // Nothing to check is trivially all spent.
+        assert!(Txo::are_all_spent(&[], &wallet_db.get_conn().unwrap()).unwrap());
+
+        let tombstone_block = ledger_db.num_blocks().unwrap() + 3;
+        let mut builder: WalletTransactionBuilder<MockFogPubkeyResolver> =
+            WalletTransactionBuilder::new(
+                sender_account_id.to_string(),
+                wallet_db.clone(),
+                ledger_db.clone(),
+                get_resolver_factory(&mut rng).unwrap(),
+                logger.clone(),
+            );
+        builder
+            .add_recipient(
+                AccountKey::random(&mut rng).default_subaddress(),
+                100 * MOB as u64,
+            )
+            .unwrap();
+        builder.select_txos(None).unwrap();
+        builder.set_tombstone(tombstone_block).unwrap();
+        let proposal = builder.build().unwrap();
+
+        let tx_log = TransactionLog::log_submitted(
+            proposal.clone(),
+            ledger_db.num_blocks().unwrap(),
+            "".to_string(),
+            Some(&sender_account_id.to_string()),
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        let inputs = tx_log
+            .get_associated_txos(&wallet_db.get_conn().unwrap())
+            .unwrap()
+            .inputs;
+        assert_eq!(inputs.len(), 2);
+
+        // The inputs are pending until the tombstone block.
+        let conn = wallet_db.get_conn().unwrap();
+        assert!(!Txo::are_all_spent(&inputs, &conn).unwrap());
+        assert!(!Txo::any_failed(&inputs, tombstone_block as i64, &conn).unwrap());
+        assert!(Txo::any_failed(&inputs, tombstone_block as i64 + 1, &conn).unwrap());
+
+        // Once the transaction lands, the inputs are spent and can no longer fail.
+        add_block_with_tx_proposal(&mut ledger_db, proposal);
+        wait_for_sync(
+            &ledger_db,
+            &wallet_db,
+            &sender_account_id,
+            ledger_db.num_blocks().unwrap(),
+        );
+        assert!(Txo::are_all_spent(&inputs, &conn).unwrap());
+        assert!(!Txo::any_failed(&inputs, tombstone_block as i64 + 1, &conn).unwrap());
+    }
+
     // FIXME: once we have create_minted, then select_txos test with no
     // FIXME: test update txo after tombstone block is exceeded
     // FIXME: test update txo after it has landed via key_image update
-    // FIXME: test any_failed and are_all_spent
     // FIXME: test max_spendable
     // FIXME: test for selecting utxos from multiple subaddresses in one account
     // FIXME: test for one TXO belonging to multiple accounts with get
@@ -0,0 +1,123 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! Txos reserved for a transaction that has not been built yet, so that
+//! concurrent senders do not select the same Txos.
+//!
+//! Reservations are held in memory and expire after a timeout, so that the
+//! Txos of a reservation which is never built are released for selection.
+
+use crate::db::WalletDbError;
+use mc_common::{HashMap, HashSet};
+use rand::RngCore;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long a reservation holds its Txos by default.
+pub const DEFAULT_RESERVATION_EXPIRY: Duration = Duration::from_secs(60);
+
+/// The longest a reservation may hold its Txos.
+pub const MAX_RESERVATION_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// Txos reserved for an account, to be spent by a single transaction.
+#[derive(Clone, Debug)]
+pub struct TxoReservation {
+    /// A random ID, hex-encoded.
+    pub reservation_id: String,
+
+    /// The account the Txos belong to.
+    pub account_id_hex: String,
+
+    /// The reserved Txos.
+    pub txo_ids: Vec<String>,
+
+    /// The value, not including the fee, the Txos were reserved for.
+    pub value: u64,
+
+    /// When the reservation releases its Txos.
+    pub expires_at: Instant,
+}
+
+/// The unexpired reservations, shared between the clones of a WalletDb.
+#[derive(Clone, Default)]
+pub struct TxoReservations {
+    inner: Arc<Mutex<HashMap<String, TxoReservation>>>,
+}
+
+impl TxoReservations {
+    /// Reserve the Txos chosen by `select` for an account.
+    ///
+    /// `select` is passed the IDs of the Txos already reserved, which it must
+    /// not choose. It runs while the reservations are locked, so that two
+    /// reservations never hold the same Txo.
+    ///
+    /// The expiry may be at most MAX_RESERVATION_EXPIRY.
+    pub fn reserve<E: From<WalletDbError>>(
+        &self,
+        account_id_hex: &str,
+        value: u64,
+        expiry: Duration,
+        select: impl FnOnce(&HashSet<String>) -> Result<Vec<String>, E>,
+    ) -> Result<TxoReservation, E> {
+        let expires_at = Some(expiry)
+            .filter(|expiry| *expiry <= MAX_RESERVATION_EXPIRY)
+            .and_then(|expiry| Instant::now().checked_add(expiry))
+            .ok_or_else(|| {
+                WalletDbError::ReservationExpiryTooLong(MAX_RESERVATION_EXPIRY.as_secs())
+            })?;
+
+        let mut reservations = self.inner.lock().expect("mutex poisoned");
+        Self::prune_expired(&mut reservations);
+
+        let reserved = Self::txo_ids_of(&reservations);
+        let txo_ids = select(&reserved)?;
+
+        let mut reservation_id_bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut reservation_id_bytes);
+        let reservation = TxoReservation {
+            reservation_id: hex::encode(reservation_id_bytes),
+            account_id_hex: account_id_hex.to_string(),
+            txo_ids,
+            value,
+            expires_at,
+        };
+        reservations.insert(reservation.reservation_id.clone(), reservation.clone());
+        Ok(reservation)
+    }
+
+    /// Get a reservation, unless it has expired.
+    pub fn get(&self, reservation_id: &str) -> Option<TxoReservation> {
+        let mut reservations = self.inner.lock().expect("mutex poisoned");
+        Self::prune_expired(&mut reservations);
+        reservations.get(reservation_id).cloned()
+    }
+
+    /// Release a reservation's Txos, for example because they have been spent
+    /// by the transaction built with it.
+    pub fn release(&self, reservation_id: &str) {
+        self.inner
+            .lock()
+            .expect("mutex poisoned")
+            .remove(reservation_id);
+    }
+
+    /// The IDs of the Txos held by unexpired reservations.
+    pub fn reserved_txo_ids(&self) -> HashSet<String> {
+        let mut reservations = self.inner.lock().expect("mutex poisoned");
+        Self::prune_expired(&mut reservations);
+        Self::txo_ids_of(&reservations)
+    }
+
+    fn prune_expired(reservations: &mut HashMap<String, TxoReservation>) {
+        let now = Instant::now();
+        reservations.retain(|_id, reservation| reservation.expires_at > now);
+    }
+
+    fn txo_ids_of(reservations: &HashMap<String, TxoReservation>) -> HashSet<String> {
+        reservations
+            .values()
+            .flat_map(|reservation| reservation.txo_ids.iter().cloned())
+            .collect()
+    }
+}
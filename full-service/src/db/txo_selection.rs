@@ -0,0 +1,378 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! Choosing which Txo values to spend to reach a target value.
+//!
+//! Both selections take values sorted in decreasing order and return the
+//! indices of the selected values, using at most `max_inputs` of them.
+
+use crate::db::WalletDbError;
+use std::str::FromStr;
+
+/// The maximum number of candidates select_min_overshoot examines before it
+/// settles for the best selection found so far.
+pub const MAX_SELECTION_STEPS: usize = 100_000;
+
+/// Which selection chooses the Txos to spend.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionStrategy {
+    /// select_min_overshoot, which minimizes the change returned.
+    MinOvershoot,
+
+    /// select_sliding_window, which spends the smallest values first and
+    /// sweeps up dust.
+    SlidingWindow,
+}
+
+impl Default for SelectionStrategy {
+    fn default() -> Self {
+        SelectionStrategy::SlidingWindow
+    }
+}
+
+/// Options restricting and shaping the choice of Txos to spend, beyond the
+/// target value and max_spendable_value.
+#[derive(Clone, Debug, Default)]
+pub struct SelectionOptions {
+    /// Only Txos received at or before this block are selected, e.g. to
+    /// require a minimum number of confirmations.
+    pub max_received_block_index: Option<i64>,
+
+    /// Only Txos received at this subaddress are selected.
+    pub subaddress_index: Option<i64>,
+
+    /// If the account has more spendable Txos than this, the selection is
+    /// topped up with the smallest remaining Txos, up to MAX_INPUTS.
+    pub auto_consolidate_threshold: Option<i64>,
+
+    /// Txo values which are selected from first, if they reach the target.
+    pub preferred_denominations: Vec<i64>,
+
+    /// How the Txos reaching the target are chosen.
+    pub strategy: SelectionStrategy,
+}
+
+impl SelectionStrategy {
+    /// Selects values with this strategy.
+    pub fn select(
+        &self,
+        values: &[u64],
+        target_value: u64,
+        max_inputs: usize,
+    ) -> Option<Vec<usize>> {
+        match self {
+            SelectionStrategy::MinOvershoot => {
+                select_min_overshoot(values, target_value, max_inputs)
+            }
+            SelectionStrategy::SlidingWindow => {
+                select_sliding_window(values, target_value, max_inputs)
+            }
+        }
+    }
+}
+
+impl FromStr for SelectionStrategy {
+    type Err = WalletDbError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "min_overshoot" => Ok(SelectionStrategy::MinOvershoot),
+            "sliding_window" => Ok(SelectionStrategy::SlidingWindow),
+            _ => Err(WalletDbError::InvalidSelectionStrategy(src.to_string())),
+        }
+    }
+}
+
+/// Selects values with `strategy` from only the values in
+/// `preferred_denominations`, falling back to all of the values if those
+/// cannot reach the target. Spending whole denominations first keeps the
+/// remaining values round.
+///
+/// Returns None if no selection reaches the target.
+pub fn select_preferring_denominations(
+    values: &[u64],
+    preferred_denominations: &[u64],
+    target_value: u64,
+    max_inputs: usize,
+    strategy: SelectionStrategy,
+) -> Option<Vec<usize>> {
+    if !preferred_denominations.is_empty() {
+        let preferred: Vec<usize> = (0..values.len())
+            .filter(|i| preferred_denominations.contains(&values[*i]))
+            .collect();
+        let preferred_values: Vec<u64> = preferred.iter().map(|i| values[*i]).collect();
+        if let Some(selected) = strategy.select(&preferred_values, target_value, max_inputs) {
+            return Some(selected.into_iter().map(|i| preferred[i]).collect());
+        }
+    }
+    strategy.select(values, target_value, max_inputs)
+}
+
+/// Selects values by sliding a window of up to `max_inputs` values up from the
+/// smallest, until the window sums to at least `target_value`. Starting from
+/// the smallest values opportunistically sweeps up dust.
+///
+/// Returns None if no window reaches the target.
+pub fn select_sliding_window(
+    values: &[u64],
+    target_value: u64,
+    max_inputs: usize,
+) -> Option<Vec<usize>> {
+    if max_inputs == 0 {
+        return None;
+    }
+    let mut selected: Vec<usize> = Vec::new();
+    let mut total: u128 = 0;
+    for index in (0..values.len()).rev() {
+        if total >= target_value as u128 {
+            break;
+        }
+        selected.push(index);
+        total += values[index] as u128;
+
+        // Cap at maximum allowed inputs by dropping the smallest value.
+        if selected.len() > max_inputs {
+            total -= values[selected.remove(0)] as u128;
+        }
+    }
+    if total >= target_value as u128 && !selected.is_empty() {
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+/// Selects values summing to at least `target_value` while minimizing the
+/// overshoot, and thus the change returned.
+///
+/// The sliding window selection is the starting point, and is kept unless a
+/// selection with strictly less overshoot is found, so this never overshoots
+/// by more than select_sliding_window. The search is a branch and bound over
+/// the values, bounded by MAX_SELECTION_STEPS.
+///
+/// Returns None if no selection reaches the target.
+pub fn select_min_overshoot(
+    values: &[u64],
+    target_value: u64,
+    max_inputs: usize,
+) -> Option<Vec<usize>> {
+    let window = select_sliding_window(values, target_value, max_inputs)?;
+    let window_total: u128 = window.iter().map(|i| values[*i] as u128).sum();
+
+    let mut search = Search {
+        values,
+        target_value: target_value as u128,
+        max_inputs,
+        steps: 0,
+        current: Vec::new(),
+        best_overshoot: window_total - target_value as u128,
+        best: window,
+    };
+    search.search(0, 0);
+    Some(search.best)
+}
+
+/// The state of a select_min_overshoot search.
+struct Search<'a> {
+    values: &'a [u64],
+    target_value: u128,
+    max_inputs: usize,
+    steps: usize,
+    current: Vec<usize>,
+    best_overshoot: u128,
+    best: Vec<usize>,
+}
+
+impl<'a> Search<'a> {
+    /// Extends the current selection, which sums to `total`, with values at
+    /// or after `start`.
+    fn search(&mut self, start: usize, total: u128) {
+        let remaining_inputs = self.max_inputs - self.current.len();
+        for index in start..self.values.len() {
+            if self.best_overshoot == 0 || self.steps >= MAX_SELECTION_STEPS {
+                return;
+            }
+            self.steps += 1;
+
+            // The values are sorted in decreasing order, so the most the
+            // remaining inputs can add is the sum of the next values. Later
+            // starting points can only add less.
+            let reachable: u128 = total
+                + self.values[index..]
+                    .iter()
+                    .take(remaining_inputs)
+                    .map(|v| *v as u128)
+                    .sum::<u128>();
+            if reachable < self.target_value {
+                return;
+            }
+
+            let new_total = total + self.values[index] as u128;
+            self.current.push(index);
+            if new_total >= self.target_value {
+                // Adding more would only increase the overshoot, but a smaller
+                // value in this position might reduce it.
+                let overshoot = new_total - self.target_value;
+                if overshoot < self.best_overshoot {
+                    self.best_overshoot = overshoot;
+                    self.best = self.current.clone();
+                }
+            } else if remaining_inputs > 1 {
+                self.search(index + 1, new_total);
+            }
+            self.current.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::time::{Duration, Instant};
+
+    const MOB: u64 = 1_000_000_000_000;
+
+    fn overshoot(values: &[u64], selected: &[usize], target_value: u64) -> u64 {
+        let total: u64 = selected.iter().map(|i| values[*i]).sum();
+        assert!(total >= target_value);
+        total - target_value
+    }
+
+    fn sorted_desc(mut values: Vec<u64>) -> Vec<u64> {
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        values
+    }
+
+    // Asserts that both selections succeed together, respect max_inputs, and
+    // that the new selection never overshoots by more than the window.
+    fn compare(values: &[u64], target_value: u64, max_inputs: usize) {
+        let window = select_sliding_window(values, target_value, max_inputs);
+        let selected = select_min_overshoot(values, target_value, max_inputs);
+        match (window, selected) {
+            (None, None) => {}
+            (Some(window), Some(selected)) => {
+                assert!(selected.len() <= max_inputs);
+                let mut unique = selected.clone();
+                unique.sort_unstable();
+                unique.dedup();
+                assert_eq!(unique.len(), selected.len());
+                assert!(
+                    overshoot(values, &selected, target_value)
+                        <= overshoot(values, &window, target_value)
+                );
+            }
+            (window, selected) => panic!(
+                "Selections disagree on feasibility: {:?} {:?}",
+                window, selected
+            ),
+        }
+    }
+
+    #[test]
+    fn test_finds_exact_match() {
+        // The window takes the dust first and overshoots.
+        let values = sorted_desc(vec![1, 2, 50 * MOB, 60 * MOB]);
+        let target_value = 60 * MOB;
+
+        let window = select_sliding_window(&values, target_value, 16).unwrap();
+        assert_eq!(overshoot(&values, &window, target_value), 50 * MOB + 3);
+
+        let selected = select_min_overshoot(&values, target_value, 16).unwrap();
+        assert_eq!(selected, vec![0]);
+    }
+
+    #[test]
+    fn test_prefers_denominations() {
+        let values = sorted_desc(vec![
+            7 * MOB,
+            10 * MOB,
+            10 * MOB,
+            13 * MOB,
+            20 * MOB,
+            25 * MOB,
+        ]);
+        let strategy = SelectionStrategy::MinOvershoot;
+
+        // Without a preference, the 20 MOB value is an exact match.
+        let selected = select_preferring_denominations(&values, &[], 20 * MOB, 16, strategy);
+        assert_eq!(selected, Some(vec![1]));
+
+        // The two 10 MOB values also reach the target, so they are chosen.
+        let selected =
+            select_preferring_denominations(&values, &[10 * MOB], 20 * MOB, 16, strategy).unwrap();
+        assert_eq!(selected.len(), 2);
+        assert!(selected.iter().all(|i| values[*i] == 10 * MOB));
+
+        // Otherwise the selection falls back to all of the values.
+        let selected =
+            select_preferring_denominations(&values, &[10 * MOB], 30 * MOB, 16, strategy).unwrap();
+        assert_eq!(overshoot(&values, &selected, 30 * MOB), 0);
+        assert!(
+            select_preferring_denominations(&values, &[10 * MOB], 100 * MOB, 16, strategy)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_respects_max_inputs() {
+        let values = sorted_desc(vec![10; 20]);
+        assert_eq!(select_min_overshoot(&values, 160, 16).unwrap().len(), 16);
+        assert!(select_min_overshoot(&values, 161, 16).is_none());
+        assert!(select_sliding_window(&values, 161, 16).is_none());
+    }
+
+    #[test]
+    fn test_never_overshoots_more_than_window_on_fixtures() {
+        // [100, 200, 300, ... 1900] MOB, as in the Txo selection tests.
+        let values = sorted_desc((1..20).map(|i| 100 * MOB * i).collect());
+        for target_value in (1..200).map(|i| 100 * MOB * i + i * 7) {
+            compare(&values, target_value, 16);
+        }
+
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        for _ in 0..50 {
+            let num_values = rng.gen_range(1, 200);
+            let values = sorted_desc(
+                (0..num_values)
+                    .map(|_| rng.gen_range(1, 1000 * MOB))
+                    .collect(),
+            );
+            let total: u64 = values.iter().sum();
+            for _ in 0..20 {
+                compare(&values, rng.gen_range(1, total + 1), 16);
+            }
+        }
+    }
+
+    // The search is bounded by MAX_SELECTION_STEPS, so even a large account is
+    // selected from quickly.
+    #[test]
+    fn test_selection_time() {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        for num_values in [16, 100, 1000, 10_000].iter() {
+            let values = sorted_desc(
+                (0..*num_values)
+                    .map(|_| rng.gen_range(1, 1000 * MOB))
+                    .collect(),
+            );
+            let max_spendable: u64 = values.iter().take(16).sum();
+            let target_value = max_spendable / 3;
+
+            let start = Instant::now();
+            let window = select_sliding_window(&values, target_value, 16);
+            let window_elapsed = start.elapsed();
+
+            let start = Instant::now();
+            let selected = select_min_overshoot(&values, target_value, 16);
+            let selected_elapsed = start.elapsed();
+
+            println!(
+                "{} values: sliding_window {:?}, min_overshoot {:?}",
+                num_values, window_elapsed, selected_elapsed
+            );
+            assert!(window.is_some());
+            assert!(selected.is_some());
+            assert!(selected_elapsed < Duration::from_secs(5));
+        }
+    }
+}
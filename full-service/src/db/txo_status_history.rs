@@ -0,0 +1,112 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! DB impl for the TxoStatusChange model.
+
+use crate::db::{
+    account::AccountModel,
+    models::{Account, NewTxoStatusChange, TxoStatusChange},
+    WalletDbConnection, WalletDbError,
+};
+use chrono::Utc;
+use diesel::{
+    prelude::*,
+    r2d2::{ConnectionManager, PooledConnection},
+    RunQueryDsl,
+};
+
+pub trait TxoStatusChangeModel {
+    /// Record that a Txo's status changed for an account, and increment the
+    /// account's Txo version.
+    fn record(
+        account_id_hex: &str,
+        txo_id_hex: &str,
+        txo_status: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// List the status changes of a Txo for an account, oldest first.
+    fn list_for_txo(
+        account_id_hex: &str,
+        txo_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TxoStatusChange>, WalletDbError>;
+
+    /// List the status changes of every Txo for an account with an id greater
+    /// than `since_id`, oldest first.
+    fn list_since(
+        account_id_hex: &str,
+        since_id: i32,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TxoStatusChange>, WalletDbError>;
+
+    /// Delete the status history of every Txo for an account.
+    fn delete_all_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+}
+
+impl TxoStatusChangeModel for TxoStatusChange {
+    fn record(
+        account_id_hex: &str,
+        txo_id_hex: &str,
+        txo_status: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txo_status_history;
+
+        let new_txo_status_change = NewTxoStatusChange {
+            account_id_hex,
+            txo_id_hex,
+            txo_status,
+            changed_at: Utc::now().timestamp(),
+        };
+
+        diesel::insert_into(txo_status_history::table)
+            .values(&new_txo_status_change)
+            .execute(conn)?;
+        Account::increment_txo_version(account_id_hex, conn)?;
+
+        Ok(())
+    }
+
+    fn list_for_txo(
+        account_id_hex: &str,
+        txo_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TxoStatusChange>, WalletDbError> {
+        use crate::db::schema::txo_status_history as cols;
+
+        Ok(cols::table
+            .filter(cols::account_id_hex.eq(account_id_hex))
+            .filter(cols::txo_id_hex.eq(txo_id_hex))
+            .order_by(cols::id.asc())
+            .load(conn)?)
+    }
+
+    fn list_since(
+        account_id_hex: &str,
+        since_id: i32,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<TxoStatusChange>, WalletDbError> {
+        use crate::db::schema::txo_status_history as cols;
+
+        Ok(cols::table
+            .filter(cols::account_id_hex.eq(account_id_hex))
+            .filter(cols::id.gt(since_id))
+            .order_by(cols::id.asc())
+            .load(conn)?)
+    }
+
+    fn delete_all_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::txo_status_history as cols;
+
+        diesel::delete(cols::table.filter(cols::account_id_hex.eq(account_id_hex)))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
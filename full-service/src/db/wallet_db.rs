@@ -1,36 +1,134 @@
-use crate::db::WalletDbError;
+use crate::db::{
+    models::WalletNetwork,
+    spendable_txos_cache::SpendableTxosCache,
+    txo_reservations::TxoReservations,
+    wallet_network::{Network, WalletNetworkModel},
+    WalletDbError,
+};
 use diesel::{
     connection::SimpleConnection,
     prelude::*,
     r2d2::{ConnectionManager, Pool, PooledConnection},
 };
-use mc_common::logger::Logger;
-use std::time::Duration;
+use mc_common::logger::{log, Logger};
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::Duration,
+};
 
-#[derive(Debug)]
+/// The default number of blocks which must follow an invalidated Txo's
+/// received block before the Txo may be purged.
+pub const DEFAULT_INVALIDATED_TXO_PURGE_DEPTH: u64 = 100;
+
+/// The connection type of the wallet database.
+#[cfg(not(feature = "sql-query-logging"))]
+pub type WalletDbConnection = SqliteConnection;
+
+/// The connection type of the wallet database, which logs each query and its
+/// duration when enabled with ConnectionOptions::log_queries.
+#[cfg(feature = "sql-query-logging")]
+pub type WalletDbConnection = crate::db::query_logger::LoggingConnection;
+
+/// The SQLite `synchronous` setting, trading durability for write speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SynchronousMode {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl fmt::Display for SynchronousMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self {
+            SynchronousMode::Off => "OFF",
+            SynchronousMode::Normal => "NORMAL",
+            SynchronousMode::Full => "FULL",
+            SynchronousMode::Extra => "EXTRA",
+        };
+        write!(f, "{}", mode)
+    }
+}
+
+impl FromStr for SynchronousMode {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src.to_uppercase().as_str() {
+            "OFF" => Ok(SynchronousMode::Off),
+            "NORMAL" => Ok(SynchronousMode::Normal),
+            "FULL" => Ok(SynchronousMode::Full),
+            "EXTRA" => Ok(SynchronousMode::Extra),
+            _ => Err(format!("Unknown synchronous mode: {}", src)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct ConnectionOptions {
     pub enable_wal: bool,
+    pub synchronous: SynchronousMode,
     pub enable_foreign_keys: bool,
     pub busy_timeout: Option<Duration>,
+    /// Log each query and its duration. Requires the `sql-query-logging`
+    /// feature.
+    pub log_queries: bool,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            enable_wal: true,
+            synchronous: SynchronousMode::Normal,
+            enable_foreign_keys: false,
+            busy_timeout: Some(Duration::from_secs(30)),
+            log_queries: false,
+        }
+    }
+}
+
+/// Applies the ConnectionOptions to each connection in the pool.
+#[derive(Debug)]
+struct ConnectionCustomizer {
+    options: ConnectionOptions,
+    #[cfg(feature = "sql-query-logging")]
+    logger: Logger,
 }
 
-impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
-    for ConnectionOptions
+impl diesel::r2d2::CustomizeConnection<WalletDbConnection, diesel::r2d2::Error>
+    for ConnectionCustomizer
 {
-    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+    fn on_acquire(&self, conn: &mut WalletDbConnection) -> Result<(), diesel::r2d2::Error> {
+        #[cfg(feature = "sql-query-logging")]
+        if self.options.log_queries {
+            conn.set_logger(Some(self.logger.clone()));
+        }
+
         (|| {
-            if self.enable_wal {
+            if self.options.enable_wal {
                 conn.batch_execute("
                     PRAGMA journal_mode = WAL;          -- better write-concurrency
-                    PRAGMA synchronous = NORMAL;        -- fsync only in critical moments
                     PRAGMA wal_autocheckpoint = 1000;   -- write WAL changes back every 1000 pages, for an in average 1MB WAL file. May affect readers if number is increased
                     PRAGMA wal_checkpoint(TRUNCATE);    -- free some space by truncating possibly massive WAL files from the last run.
                 ")?;
+            } else {
+                // The journal mode is persisted in the database file, so switch back
+                // explicitly in case WAL was enabled on a previous run.
+                conn.batch_execute("PRAGMA journal_mode = DELETE;")?;
             }
-            if self.enable_foreign_keys {
+            conn.batch_execute(&format!(
+                "PRAGMA synchronous = {};",
+                self.options.synchronous
+            ))?;
+            if self.options.enable_foreign_keys {
                 conn.batch_execute("PRAGMA foreign_keys = ON;")?;
             }
-            if let Some(d) = self.busy_timeout {
+            if let Some(d) = self.options.busy_timeout {
                 conn.batch_execute(&format!("PRAGMA busy_timeout = {};", d.as_millis()))?;
             }
             Ok(())
@@ -41,27 +139,60 @@ impl diesel::r2d2::CustomizeConnection<SqliteConnection, diesel::r2d2::Error>
 
 #[derive(Clone)]
 pub struct WalletDb {
-    pool: Pool<ConnectionManager<SqliteConnection>>,
+    pool: Pool<ConnectionManager<WalletDbConnection>>,
+    spendable_txos_cache: SpendableTxosCache,
+    txo_reservations: TxoReservations,
+    dust_threshold: Arc<AtomicU64>,
+    invalidated_txo_purge_depth: Arc<AtomicU64>,
+    /// Held shared while an account syncs, and exclusively while sync is
+    /// paused.
+    sync_lock: Arc<RwLock<()>>,
+    /// Taken before sync_lock, so that a pending pause is not held off by
+    /// accounts which start syncing after it.
+    sync_lock_gate: Arc<Mutex<()>>,
     logger: Logger,
 }
 
 impl WalletDb {
-    pub fn new(pool: Pool<ConnectionManager<SqliteConnection>>, logger: Logger) -> Self {
-        Self { pool, logger }
+    pub fn new(pool: Pool<ConnectionManager<WalletDbConnection>>, logger: Logger) -> Self {
+        Self {
+            pool,
+            spendable_txos_cache: SpendableTxosCache::default(),
+            txo_reservations: TxoReservations::default(),
+            dust_threshold: Arc::new(AtomicU64::new(0)),
+            invalidated_txo_purge_depth: Arc::new(AtomicU64::new(
+                DEFAULT_INVALIDATED_TXO_PURGE_DEPTH,
+            )),
+            sync_lock: Arc::new(RwLock::new(())),
+            sync_lock_gate: Arc::new(Mutex::new(())),
+            logger,
+        }
     }
 
+    /// Create a WalletDb backed by a pool of at most `db_connections`
+    /// connections, each configured with `connection_options`. With a
+    /// `busy_timeout`, a connection waits that long for a lock held by another
+    /// connection before failing with `database is locked`. With
+    /// `log_queries`, each query and its duration are logged at debug level.
     pub fn new_from_url(
         database_url: &str,
         db_connections: u32,
+        connection_options: ConnectionOptions,
         logger: Logger,
     ) -> Result<Self, WalletDbError> {
-        let manager = ConnectionManager::<SqliteConnection>::new(database_url);
+        if connection_options.log_queries && !cfg!(feature = "sql-query-logging") {
+            log::warn!(
+                logger,
+                "Query logging requires the sql-query-logging feature, and is disabled"
+            );
+        }
+        let manager = ConnectionManager::<WalletDbConnection>::new(database_url);
         let pool = Pool::builder()
             .max_size(db_connections)
-            .connection_customizer(Box::new(ConnectionOptions {
-                enable_wal: true,
-                enable_foreign_keys: false,
-                busy_timeout: Some(Duration::from_secs(30)),
+            .connection_customizer(Box::new(ConnectionCustomizer {
+                options: connection_options,
+                #[cfg(feature = "sql-query-logging")]
+                logger: logger.clone(),
             }))
             .test_on_check_out(true)
             .build(manager)?;
@@ -70,7 +201,322 @@ impl WalletDb {
 
     pub fn get_conn(
         &self,
-    ) -> Result<PooledConnection<ConnectionManager<SqliteConnection>>, WalletDbError> {
+    ) -> Result<PooledConnection<ConnectionManager<WalletDbConnection>>, WalletDbError> {
         Ok(self.pool.get()?)
     }
+
+    /// The spendable Txos of each account, cached between selections.
+    pub fn spendable_txos_cache(&self) -> &SpendableTxosCache {
+        &self.spendable_txos_cache
+    }
+
+    /// The Txos reserved for transactions that have not been built yet.
+    pub fn txo_reservations(&self) -> &TxoReservations {
+        &self.txo_reservations
+    }
+
+    /// The value below which received Txos are categorized as dust. Zero
+    /// disables the policy.
+    pub fn dust_threshold(&self) -> u64 {
+        self.dust_threshold.load(Ordering::SeqCst)
+    }
+
+    /// Set the dust threshold for Txos received from now on, shared with every
+    /// clone of this WalletDb.
+    pub fn set_dust_threshold(&self, dust_threshold: u64) {
+        self.dust_threshold.store(dust_threshold, Ordering::SeqCst);
+    }
+
+    /// The number of blocks which must follow an invalidated Txo's received
+    /// block before the Txo may be purged, since a Txo invalidated by a
+    /// rollback may return while the ledger is still settling.
+    pub fn invalidated_txo_purge_depth(&self) -> u64 {
+        self.invalidated_txo_purge_depth.load(Ordering::SeqCst)
+    }
+
+    /// Set the invalidated Txo purge depth, shared with every clone of this
+    /// WalletDb.
+    pub fn set_invalidated_txo_purge_depth(&self, invalidated_txo_purge_depth: u64) {
+        self.invalidated_txo_purge_depth
+            .store(invalidated_txo_purge_depth, Ordering::SeqCst);
+    }
+
+    /// Hold while syncing an account. Waits while sync is paused.
+    pub fn sync_guard(&self) -> RwLockReadGuard<()> {
+        let _gate = self.sync_lock_gate.lock().expect("mutex poisoned");
+        self.sync_lock.read().expect("lock poisoned")
+    }
+
+    /// Pause the sync of all accounts until the returned guard is dropped,
+    /// once the accounts currently syncing have finished.
+    pub fn pause_sync(&self) -> RwLockWriteGuard<()> {
+        let _gate = self.sync_lock_gate.lock().expect("mutex poisoned");
+        self.sync_lock.write().expect("lock poisoned")
+    }
+
+    /// Check that the database was created for the network the wallet runs
+    /// against, recording it if the database has no network yet.
+    pub fn check_network(&self, network: Network) -> Result<(), WalletDbError> {
+        WalletNetwork::check_or_record(network, &self.get_conn()?)
+    }
+
+    /// Rebuild the database file to reclaim the space left by deleted rows,
+    /// and refresh the query planner's statistics.
+    ///
+    /// VACUUM cannot run inside a transaction, and needs the write lock for
+    /// its duration, so account sync is paused until it completes. Other
+    /// writers wait up to their connection's busy_timeout.
+    ///
+    /// Returns:
+    /// * (size in bytes before compaction, size in bytes after compaction)
+    pub fn compact(&self) -> Result<(u64, u64), WalletDbError> {
+        let _sync_paused = self.pause_sync();
+        let conn = self.get_conn()?;
+
+        let size_before = Self::size(&conn)?;
+        conn.batch_execute("VACUUM; ANALYZE;")?;
+        // In WAL mode, VACUUM writes the rebuilt pages to the WAL, so write
+        // them back to shrink the database file itself.
+        conn.batch_execute("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        let size_after = Self::size(&conn)?;
+
+        log::info!(
+            self.logger,
+            "Compacted wallet database from {} to {} bytes",
+            size_before,
+            size_after
+        );
+        Ok((size_before, size_after))
+    }
+
+    /// The size of the database, in bytes.
+    fn size(
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<u64, WalletDbError> {
+        #[derive(QueryableByName)]
+        struct DbSize {
+            #[sql_type = "diesel::sql_types::BigInt"]
+            size: i64,
+        }
+        let db_size: DbSize = diesel::sql_query(
+            "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .get_result(conn)?;
+        Ok(db_size.size as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{account::AccountModel, models::Account},
+        test_utils::WalletDbTestContext,
+    };
+    use mc_account_keys::RootEntropy;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::thread;
+
+    #[test_with_logger]
+    fn test_concurrent_writers_wait_for_lock(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance_with_options(
+            4,
+            ConnectionOptions {
+                busy_timeout: Some(Duration::from_secs(10)),
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+        let impatient_wallet_db = db_test_context.get_db_instance_with_options(
+            1,
+            ConnectionOptions {
+                busy_timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+
+        // Another connection holds the write lock for a second.
+        let lock_holder = db_test_context
+            .get_db_instance_with_options(1, ConnectionOptions::default(), logger)
+            .get_conn()
+            .unwrap();
+        lock_holder.batch_execute("BEGIN IMMEDIATE;").unwrap();
+        let lock_holder_handle = thread::spawn(move || {
+            thread::sleep(Duration::from_secs(1));
+            lock_holder.batch_execute("COMMIT;").unwrap();
+        });
+
+        // A writer with a shorter busy timeout gives up.
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        match Account::create_from_root_entropy(
+            &RootEntropy::from_random(&mut rng),
+            Some(0),
+            None,
+            None,
+            "Impatient",
+            None,
+            None,
+            None,
+            &impatient_wallet_db.get_conn().unwrap(),
+        ) {
+            Err(WalletDbError::Diesel(diesel::result::Error::DatabaseError(_kind, info))) => {
+                assert_eq!(info.message(), "database is locked")
+            }
+            Ok(_) => panic!("Should not write while the database is locked"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Writers with a longer busy timeout wait for the lock, and succeed.
+        let handles: Vec<_> = (0..8u8)
+            .map(|i| {
+                let wallet_db = wallet_db.clone();
+                thread::spawn(move || {
+                    let mut rng: StdRng = SeedableRng::from_seed([i; 32]);
+                    Account::create_from_root_entropy(
+                        &RootEntropy::from_random(&mut rng),
+                        Some(0),
+                        None,
+                        None,
+                        &format!("Account {}", i),
+                        None,
+                        None,
+                        None,
+                        &wallet_db.get_conn().unwrap(),
+                    )
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+        lock_holder_handle.join().unwrap();
+
+        let accounts = Account::list_all(&wallet_db.get_conn().unwrap()).unwrap();
+        assert_eq!(accounts.len(), 8);
+    }
+
+    #[test_with_logger]
+    fn test_wal_reads_during_write(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        // No busy timeout, so that a read blocked by the writer fails immediately.
+        let wallet_db = db_test_context.get_db_instance_with_options(
+            2,
+            ConnectionOptions {
+                enable_wal: true,
+                busy_timeout: None,
+                ..Default::default()
+            },
+            logger,
+        );
+
+        let (account_id, _address) = Account::create_from_root_entropy(
+            &RootEntropy::from_random(&mut rng),
+            Some(0),
+            None,
+            None,
+            "Alice",
+            None,
+            None,
+            None,
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        // Hold a write transaction open on one connection.
+        let writer = wallet_db.get_conn().unwrap();
+        writer
+            .batch_execute("BEGIN IMMEDIATE; UPDATE accounts SET name = 'Bob';")
+            .unwrap();
+
+        // Reads on another connection proceed, and see the last committed state.
+        let reader = wallet_db.get_conn().unwrap();
+        let account = Account::get(&account_id, &reader).unwrap();
+        assert_eq!(account.name, "Alice");
+
+        writer.batch_execute("COMMIT;").unwrap();
+        let account = Account::get(&account_id, &reader).unwrap();
+        assert_eq!(account.name, "Bob");
+    }
+
+    #[test_with_logger]
+    fn test_compact_reclaims_deleted_rows(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+
+        let account_ids: Vec<_> = (0..50)
+            .map(|i| {
+                Account::create_from_root_entropy(
+                    &RootEntropy::from_random(&mut rng),
+                    Some(0),
+                    None,
+                    None,
+                    &format!("Account {}", i),
+                    None,
+                    None,
+                    None,
+                    &wallet_db.get_conn().unwrap(),
+                )
+                .unwrap()
+                .0
+            })
+            .collect();
+
+        // Nothing to reclaim before anything is deleted.
+        let (_size_before, size_full) = wallet_db.compact().unwrap();
+        let file_size_full = std::fs::metadata(db_test_context.db_path()).unwrap().len();
+
+        // Deleting rows leaves free pages in the file.
+        for account_id in &account_ids[1..] {
+            let conn = wallet_db.get_conn().unwrap();
+            Account::get(account_id, &conn)
+                .unwrap()
+                .delete(&conn)
+                .unwrap();
+        }
+        let (size_before, size_after) = wallet_db.compact().unwrap();
+        assert_eq!(size_before, size_full);
+        assert!(size_after < size_before);
+
+        // The database file itself shrinks.
+        let file_size_after = std::fs::metadata(db_test_context.db_path()).unwrap().len();
+        assert!(file_size_after < file_size_full);
+        assert_eq!(file_size_after, size_after);
+
+        // The remaining data is intact.
+        let accounts = Account::list_all(&wallet_db.get_conn().unwrap()).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].name, "Account 0");
+    }
+
+    #[test_with_logger]
+    fn test_compact_waits_for_sync(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+
+        // An account is syncing.
+        let sync_guard = wallet_db.sync_guard();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let thread_wallet_db = wallet_db.clone();
+        let handle = thread::spawn(move || {
+            sender.send(thread_wallet_db.compact()).unwrap();
+        });
+
+        // Compaction waits for the sync to finish.
+        thread::sleep(Duration::from_millis(500));
+        assert!(receiver.try_recv().is_err());
+
+        drop(sync_guard);
+        handle.join().unwrap();
+        receiver.recv().unwrap().unwrap();
+    }
 }
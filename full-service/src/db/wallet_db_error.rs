@@ -21,13 +21,12 @@ pub enum WalletDbError {
     /// Error decoding b58: No public address in wrapper.
     B58Decode,
 
+    /// The wallet database was created for the {0} network, not {1}
+    WrongNetwork(String, String),
+
     /// Constructed a malformed transaction with multiple account IDs
     MultipleAccountIDsInTransaction,
 
-    /// Constructed a transaction with multiple recipients (not currently
-    /// supported for transaction logs)
-    MultipleRecipientsInTransaction,
-
     /// Constructed a transaction with no recipient
     TransactionLacksRecipient,
 
@@ -38,9 +37,12 @@ pub enum WalletDbError {
     /// Error decoding prost: {0}
     ProstDecode(prost::DecodeError),
 
-    /// We expect one change output per TxProposal
+    /// We expect at most MAX_CHANGE_OUTPUTS change outputs per TxProposal
     UnexpectedNumberOfChangeOutputs,
 
+    /// Could not recover the value of a change output: {0}
+    ChangeValue(String),
+
     /// Key Image missing when recovering orphaned Txo
     MissingKeyImage,
 
@@ -54,12 +56,23 @@ pub enum WalletDbError {
     /// Please combine txos.
     InsufficientFundsFragmentedTxos,
 
-    /// Insufficient Funds: {0}
-    InsufficientFunds(String),
+    /// Insufficient Funds: Not enough Txos to sum to target value: {requested}
+    InsufficientFunds {
+        requested: u64,
+        available: u64,
+        shortfall: u64,
+    },
+
+    /// Insufficient Funds: Logic error. Could not select Txos despite having
+    /// sufficient funds
+    TxoSelectionFailed,
 
     /// Insufficient funds from Txos under max_spendable_value: {0}
     InsufficientFundsUnderMaxSpendable(String),
 
+    /// Unknown Txo selection strategy: {0}
+    InvalidSelectionStrategy(String),
+
     /// Multiple AccountTxoStatus entries for Txo
     MultipleStatusesForTxo,
 
@@ -84,12 +97,24 @@ pub enum WalletDbError {
     /// Txo Not Found: {0}
     TxoNotFound(String),
 
+    /// Txo {0} cannot be spent: {1}
+    TxoNotSpendable(String, String),
+
+    /// TransactionBuild Not Found: {0}
+    TransactionBuildNotFound(String),
+
     /// TransactionLog Not Found: {0}
     TransactionLogNotFound(String),
 
+    /// Cannot remove a TransactionLog whose inputs are still pending: {0}
+    TransactionLogHasPendingInputs(String),
+
     /// AccountTxoStatus not found: {0}
     AccountTxoStatusNotFound(String),
 
+    /// Txo reservation expiry must be at most {0} seconds
+    ReservationExpiryTooLong(u64),
+
     /// Cannot log a transaction with a value > i64::MAX
     TransactionValueExceedsMax,
 
@@ -113,6 +138,18 @@ pub enum WalletDbError {
     GiftCode(GiftCodeDbError),
 }
 
+impl WalletDbError {
+    /// An InsufficientFunds error for a request of `requested` pMOB when only
+    /// `available` pMOB can be spent.
+    pub fn insufficient_funds(requested: u64, available: u64) -> Self {
+        Self::InsufficientFunds {
+            requested,
+            available,
+            shortfall: requested.saturating_sub(available),
+        }
+    }
+}
+
 impl From<diesel::result::Error> for WalletDbError {
     fn from(src: diesel::result::Error) -> Self {
         Self::Diesel(src)
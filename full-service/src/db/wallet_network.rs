@@ -0,0 +1,122 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! DB impl for the WalletNetwork model.
+//!
+//! Addresses use the standard b58 encoding on every network, so a wallet
+//! database opened against the wrong network could not tell its addresses
+//! and Txos apart from those of the right one. Instead, the database records
+//! the network it was created for, and refuses to open for another.
+
+use crate::db::{
+    models::{NewWalletNetwork, WalletNetwork},
+    WalletDbConnection, WalletDbError,
+};
+use diesel::{
+    prelude::*,
+    r2d2::{ConnectionManager, PooledConnection},
+    RunQueryDsl,
+};
+use std::{fmt, str::FromStr};
+
+/// The id of the single row of the wallet_network table.
+const WALLET_NETWORK_ID: i32 = 0;
+
+/// The network that a wallet runs against.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Network {
+    MainNet,
+    TestNet,
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let network = match self {
+            Network::MainNet => "mainnet",
+            Network::TestNet => "testnet",
+        };
+        write!(f, "{}", network)
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src.to_lowercase().as_str() {
+            "mainnet" => Ok(Network::MainNet),
+            "testnet" => Ok(Network::TestNet),
+            _ => Err(format!("Unknown network: {}", src)),
+        }
+    }
+}
+
+pub trait WalletNetworkModel {
+    /// Check that the wallet database was created for the given network. A
+    /// database which has not recorded a network yet, because it was just
+    /// created or predates the record, is recorded as being for it.
+    fn check_or_record(
+        network: Network,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+}
+
+impl WalletNetworkModel for WalletNetwork {
+    fn check_or_record(
+        network: Network,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::wallet_network;
+
+        let network = network.to_string();
+        conn.transaction::<(), WalletDbError, _>(|| {
+            match wallet_network::table
+                .find(WALLET_NETWORK_ID)
+                .get_result::<WalletNetwork>(conn)
+            {
+                Ok(recorded) => {
+                    if recorded.network != network {
+                        return Err(WalletDbError::WrongNetwork(recorded.network, network));
+                    }
+                }
+                Err(diesel::result::Error::NotFound) => {
+                    let new_wallet_network = NewWalletNetwork {
+                        id: WALLET_NETWORK_ID,
+                        network: &network,
+                    };
+                    diesel::insert_into(wallet_network::table)
+                        .values(&new_wallet_network)
+                        .execute(conn)?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletDbTestContext;
+    use mc_common::logger::{test_with_logger, Logger};
+
+    #[test_with_logger]
+    fn test_wallet_network_recorded_on_first_open(logger: Logger) {
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger);
+        let conn = wallet_db.get_conn().unwrap();
+
+        // The first open records the network, and later opens for it succeed.
+        WalletNetwork::check_or_record(Network::TestNet, &conn).unwrap();
+        WalletNetwork::check_or_record(Network::TestNet, &conn).unwrap();
+
+        match WalletNetwork::check_or_record(Network::MainNet, &conn) {
+            Err(WalletDbError::WrongNetwork(recorded, requested)) => {
+                assert_eq!(recorded, "testnet");
+                assert_eq!(requested, "mainnet");
+            }
+            Ok(_) => panic!("Should not open a testnet wallet on mainnet"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+}
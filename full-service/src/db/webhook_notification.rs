@@ -0,0 +1,100 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! DB impl for the WebhookNotification model.
+
+use crate::db::{
+    models::{NewWebhookNotification, WebhookNotification},
+    WalletDbConnection, WalletDbError,
+};
+use diesel::{
+    prelude::*,
+    r2d2::{ConnectionManager, PooledConnection},
+    RunQueryDsl,
+};
+
+pub trait WebhookNotificationModel {
+    /// Queue a notification of a received Txo for an account's webhook. A Txo
+    /// which is already queued for the account is not queued again.
+    fn queue(
+        account_id_hex: &str,
+        txo_id_hex: &str,
+        received_block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// List the queued notifications for an account, oldest first.
+    fn list_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<WebhookNotification>, WalletDbError>;
+
+    /// Remove a notification from the queue, once it has been delivered.
+    fn delete(
+        self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+
+    /// Delete the queued notifications of an account.
+    fn delete_all_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError>;
+}
+
+impl WebhookNotificationModel for WebhookNotification {
+    fn queue(
+        account_id_hex: &str,
+        txo_id_hex: &str,
+        received_block_index: i64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::webhook_notifications;
+
+        let new_notification = NewWebhookNotification {
+            account_id_hex,
+            txo_id_hex,
+            received_block_index,
+        };
+
+        diesel::insert_or_ignore_into(webhook_notifications::table)
+            .values(&new_notification)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn list_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Vec<WebhookNotification>, WalletDbError> {
+        use crate::db::schema::webhook_notifications as cols;
+
+        Ok(cols::table
+            .filter(cols::account_id_hex.eq(account_id_hex))
+            .order(cols::id.asc())
+            .load::<WebhookNotification>(conn)?)
+    }
+
+    fn delete(
+        self,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::webhook_notifications as cols;
+
+        diesel::delete(cols::table.filter(cols::id.eq(self.id))).execute(conn)?;
+
+        Ok(())
+    }
+
+    fn delete_all_for_account(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(), WalletDbError> {
+        use crate::db::schema::webhook_notifications as cols;
+
+        diesel::delete(cols::table.filter(cols::account_id_hex.eq(account_id_hex)))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
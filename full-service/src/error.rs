@@ -270,6 +270,9 @@ pub enum WalletTransactionBuilderError {
     /// default.
     TombstoneNotSet,
 
+    /// No change subaddress is configured for account: {0}
+    NoChangeAddressConfigured(String),
+
     /// Fee must be at least MINIMUM_FEE: {0}
     InsufficientFee(String),
 
@@ -282,6 +285,13 @@ pub enum WalletTransactionBuilderError {
 
     /// Error generating FogPubkeyResolver {0}
     FogPubkeyResolver(String),
+
+    /// Could not resolve the fog pubkey of a recipient using fog report url
+    /// {0}: {1}
+    FogRecipientUnresolved(String, String),
+
+    /// Txos do not yet have enough confirmations to be spent: {0:?}
+    InsufficientConfirmations(Vec<String>),
 }
 
 impl From<mc_ledger_db::Error> for WalletTransactionBuilderError {
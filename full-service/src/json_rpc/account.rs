@@ -2,7 +2,7 @@
 
 //! API definition for the Account object.
 
-use crate::db;
+use crate::{db, service::webhook::is_webhook_disabled};
 use serde_derive::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
@@ -31,6 +31,10 @@ pub struct Account {
     /// recipient, and should be consider a free-for-all address.
     pub main_address: String,
 
+    /// B58 Address Code for the account's change address. Change from every
+    /// transaction sent by this account lands at this address.
+    pub change_address: String,
+
     /// This index represents the next subaddress to be assigned as an address.
     /// This is useful information in case the account is imported elsewhere.
     pub next_subaddress_index: String,
@@ -43,6 +47,25 @@ pub struct Account {
     /// found TXOs. It is recommended to move all MOB to another account after
     /// recovery if the user is unsure of the assigned addresses.
     pub recovery_mode: bool,
+
+    /// If set, sends from this account include extra inputs to consolidate
+    /// Txos whenever the account holds more unspent Txos than this.
+    pub auto_consolidate_threshold: Option<String>,
+
+    /// Whether this account is hidden from get_all_accounts by default.
+    pub archived: bool,
+
+    /// If set, transactions sent from this account are reported as landing
+    /// until this many blocks follow the block they landed in.
+    pub finality_depth: Option<String>,
+
+    /// If set, the wallet POSTs a notification to this URL for each Txo the
+    /// account receives.
+    pub webhook_url: Option<String>,
+
+    /// Whether notifications to the webhook_url stopped after repeated
+    /// failures to deliver them.
+    pub webhook_disabled: bool,
 }
 
 impl TryFrom<&db::models::Account> for Account {
@@ -54,6 +77,9 @@ impl TryFrom<&db::models::Account> for Account {
         let main_address =
             db::b58_encode(&account_key.subaddress(src.main_subaddress_index as u64))
                 .map_err(|e| format!("Could not b58 encode public address {:?}", e))?;
+        let change_address =
+            db::b58_encode(&account_key.subaddress(src.change_subaddress_index as u64))
+                .map_err(|e| format!("Could not b58 encode public address {:?}", e))?;
 
         Ok(Account {
             object: "account".to_string(),
@@ -61,9 +87,15 @@ impl TryFrom<&db::models::Account> for Account {
             key_derivation_version: src.key_derivation_version.to_string(),
             name: src.name.clone(),
             main_address,
+            change_address,
             next_subaddress_index: src.next_subaddress_index.to_string(),
             first_block_index: src.first_block_index.to_string(),
-            recovery_mode: false,
+            recovery_mode: src.recovery_mode,
+            auto_consolidate_threshold: src.auto_consolidate_threshold.map(|t| t.to_string()),
+            archived: src.archived,
+            finality_depth: src.finality_depth.map(|d| d.to_string()),
+            webhook_url: src.webhook_url.clone(),
+            webhook_disabled: is_webhook_disabled(src),
         })
     }
 }
@@ -9,7 +9,8 @@ use serde_derive::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
 /// The AccountSecrets contains the entropy and the account key derived from
-/// that entropy.
+/// that entropy. Accounts imported from their account key alone have no
+/// entropy or mnemonic.
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct AccountSecrets {
     /// String representing the object's type. Objects of the same type share
@@ -32,6 +32,10 @@ pub struct Address {
     /// The index of this address in the subaddress space for the account.
     pub subaddress_index: String,
 
+    /// Whether this address is retired. Retired addresses are hidden from
+    /// listings, but Txos received at them are still tracked.
+    pub retired: bool,
+
     /// The offset in the database (used for pagination).
     pub offset_count: String,
 }
@@ -44,6 +48,7 @@ impl From<&AssignedSubaddress> for Address {
             account_id: src.account_id_hex.clone(),
             metadata: src.comment.clone(),
             subaddress_index: src.subaddress_index.to_string(),
+            retired: src.retired,
             offset_count: src.id.to_string(),
         }
     }
@@ -6,7 +6,7 @@ use crate::{
         json_rpc_response::JsonRPCResponse,
         wallet::wallet_api_inner,
     },
-    service::WalletService,
+    service::{sync::SyncThreadConfig, WalletService},
     test_utils::{
         get_resolver_factory, get_test_ledger, setup_peer_manager_and_network_state,
         WalletDbTestContext,
@@ -96,6 +96,7 @@ pub fn setup(
             network_state.clone(),
             get_resolver_factory(&mut rng).unwrap(),
             None,
+            SyncThreadConfig::default(),
             false,
             logger,
         );
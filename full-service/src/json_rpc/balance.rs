@@ -2,7 +2,10 @@
 
 //! API definition for the Balance object.
 
-use crate::service;
+use crate::{
+    db::models::{Txo, TxoStatusChange},
+    service,
+};
 
 use serde_derive::{Deserialize, Serialize};
 
@@ -36,6 +39,11 @@ pub struct Balance {
     /// the account is syncing, this value may change.
     pub unspent_pmob: String,
 
+    /// The most pico MOB that can be sent in a single transaction, after the
+    /// minimum fee. A transaction may use at most MAX_INPUTS Txos, so this may
+    /// be less than unspent_pmob if the account holds many small Txos.
+    pub spendable_pmob: String,
+
     /// Pending, out-going pico MOB. The pending value will clear once the
     /// ledger processes the outgoing txos. The available_pmob will reflect the
     /// change.
@@ -49,10 +57,15 @@ pub struct Balance {
     /// been created in the wallet for outgoing transactions.
     pub secreted_pmob: String,
 
-    /// rphaned pico MOB. The orphaned value represents the Txos which were
+    /// Orphaned pico MOB. The orphaned value represents the Txos which were
     /// view-key matched, but which can not be spent until their subaddress
     /// index is recovered.
     pub orphaned_pmob: String,
+
+    /// Dust pico MOB. This is the sum of the received Txos below the wallet's
+    /// dust threshold, which are not spendable and not included in
+    /// unspent_pmob.
+    pub dust_pmob: String,
 }
 
 impl From<&service::balance::Balance> for Balance {
@@ -64,10 +77,45 @@ impl From<&service::balance::Balance> for Balance {
             account_block_index: src.synced_blocks.to_string(),
             is_synced: src.synced_blocks == src.network_block_index,
             unspent_pmob: src.unspent.to_string(),
+            spendable_pmob: src.spendable.to_string(),
             pending_pmob: src.pending.to_string(),
             spent_pmob: src.spent.to_string(),
             secreted_pmob: src.secreted.to_string(),
             orphaned_pmob: src.orphaned.to_string(),
+            dust_pmob: src.dust.to_string(),
+        }
+    }
+}
+
+/// A Txo whose status changed after the cursor given to get_balance_delta.
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct TxoBalanceChange {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// Unique identifier for the Txo.
+    pub txo_id: String,
+
+    /// The value of the Txo in pico MOB.
+    pub value_pmob: String,
+
+    /// The latest status of the Txo with respect to the account.
+    pub txo_status: String,
+
+    /// The id of the latest status change of the Txo.
+    pub event_id: String,
+}
+
+impl From<&(Txo, TxoStatusChange)> for TxoBalanceChange {
+    fn from(src: &(Txo, TxoStatusChange)) -> TxoBalanceChange {
+        let (txo, change) = src;
+        TxoBalanceChange {
+            object: "txo_balance_change".to_string(),
+            txo_id: txo.txo_id_hex.clone(),
+            value_pmob: (txo.value as u64).to_string(),
+            txo_status: change.txo_status.clone(),
+            event_id: change.id.to_string(),
         }
     }
 }
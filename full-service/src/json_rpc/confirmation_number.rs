@@ -21,6 +21,13 @@ pub struct Confirmation {
     /// The index of the Txo in the ledger.
     txo_index: String,
 
+    /// Value in pico MOB of the Txo.
+    value_pmob: String,
+
+    /// The b58 address the Txo was sent to. Only available if the Txo was sent
+    /// by this wallet.
+    recipient_address_id: Option<String>,
+
     /// A string with a confirmation number that can be validated to confirm
     /// that another party constructed or had knowledge of the construction
     /// of the associated Txo.
@@ -33,6 +40,8 @@ impl From<&service::confirmation_number::Confirmation> for Confirmation {
             object: "confirmation".to_string(),
             txo_id_hex: src.txo_id.to_string(),
             txo_index: src.txo_index.to_string(),
+            value_pmob: src.value.to_string(),
+            recipient_address_id: src.recipient_address.clone(),
             confirmation: hex::encode(mc_util_serial::encode(&src.confirmation)),
         }
     }
@@ -19,7 +19,7 @@ mod e2e {
     use mc_common::logger::{test_with_logger, Logger};
     use mc_crypto_rand::rand_core::RngCore;
     use mc_ledger_db::Ledger;
-    use mc_transaction_core::ring_signature::KeyImage;
+    use mc_transaction_core::{constants::MINIMUM_FEE, ring_signature::KeyImage};
     use rand::{rngs::StdRng, SeedableRng};
     use std::convert::TryFrom;
 
@@ -379,6 +379,78 @@ mod e2e {
         );
     }
 
+    #[test_with_logger]
+    fn test_import_account_from_account_key(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, _ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        // Import from the entropy, and export the keys derived from it.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_account_from_legacy_root_entropy",
+            "params": {
+                "entropy": "c593274dc6f6eb94242e34ae5f0ab16bc3085d45d49d9e18b8a8c6f057e6b56b",
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let entropy_account_id = res["result"]["account"]["account_id"].clone();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "export_account_secrets",
+            "params": {
+                "account_id": entropy_account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_key = res["result"]["account_secrets"]["account_key"].clone();
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "remove_account",
+            "params": {
+                "account_id": entropy_account_id,
+            }
+        });
+        dispatch(&client, body, &logger);
+
+        // Importing from the keys alone derives the same account id.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "import_account_from_account_key",
+            "params": {
+                "view_private_key": account_key["view_private_key"],
+                "spend_private_key": account_key["spend_private_key"],
+                "name": "Alice Main Account",
+                "first_block_index": "0",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let account_id = res["result"]["account"]["account_id"].clone();
+        assert_eq!(account_id, entropy_account_id);
+
+        // The keys can still be exported, but there is no entropy.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "export_account_secrets",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let secrets = &res["result"]["account_secrets"];
+        assert_eq!(secrets["account_key"], account_key);
+        assert_eq!(secrets["entropy"], serde_json::Value::Null);
+        assert_eq!(secrets["mnemonic"], serde_json::Value::Null);
+        assert_eq!(secrets["key_derivation_version"], serde_json::json!("0"));
+    }
+
     #[test_with_logger]
     fn test_e2e_import_account_fog(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -532,6 +604,113 @@ mod e2e {
         );
     }
 
+    #[test_with_logger]
+    fn test_get_network_status(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_network_status",
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let status = result.get("network_status").unwrap();
+        assert_eq!(status.get("object").unwrap(), "network_status");
+        assert_eq!(status.get("network_block_index").unwrap(), "12");
+        assert_eq!(status.get("local_block_index").unwrap(), "12");
+        let latest_block = ledger_db
+            .get_block(ledger_db.num_blocks().unwrap() - 1)
+            .unwrap();
+        assert_eq!(
+            status.get("block_version").unwrap(),
+            &latest_block.version.to_string()
+        );
+        assert_eq!(
+            status.get("default_fee_pmob").unwrap(),
+            &MINIMUM_FEE.to_string()
+        );
+    }
+
+    #[test_with_logger]
+    fn test_get_block_contents_since(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, _db_ctx, _network_state) = setup(&mut rng, logger.clone());
+
+        let public_address = AccountKey::random(&mut rng).default_subaddress();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![public_address],
+            100,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), 13);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_block_contents_since",
+            "params": {
+                "block_index": "10",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let blocks = result.get("blocks").unwrap().as_array().unwrap();
+        let block_contents = result.get("block_contents").unwrap().as_array().unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(block_contents.len(), 3);
+        for (i, (block, contents)) in blocks.iter().zip(block_contents.iter()).enumerate() {
+            let block_index = 10 + i as u64;
+            assert_eq!(block.get("index").unwrap(), &block_index.to_string());
+            let ledger_contents = ledger_db.get_block_contents(block_index).unwrap();
+            assert_eq!(
+                contents.get("outputs").unwrap().as_array().unwrap().len(),
+                ledger_contents.outputs.len()
+            );
+            assert_eq!(
+                contents
+                    .get("key_images")
+                    .unwrap()
+                    .as_array()
+                    .unwrap()
+                    .len(),
+                ledger_contents.key_images.len()
+            );
+        }
+
+        // The number of blocks returned can be limited.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_block_contents_since",
+            "params": {
+                "block_index": "10",
+                "limit": "1",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let blocks = result.get("blocks").unwrap().as_array().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].get("index").unwrap(), "10");
+
+        // Nothing is returned past the tip.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_block_contents_since",
+            "params": {
+                "block_index": "13",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        assert!(result.get("blocks").unwrap().as_array().unwrap().is_empty());
+    }
+
     #[test_with_logger]
     fn test_account_status(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -680,8 +859,9 @@ mod e2e {
         // Assert the fee is correct in both places
         let prefix_fee = tx_prefix.get("fee").unwrap().as_str().unwrap();
         let fee = tx_proposal.get("fee").unwrap();
-        // FIXME: WS-9 - Note, minimum fee does not fit into i32 - need to make sure we
-        // are not losing precision with the JsonTxProposal treating Fee as number
+        // pMOB values are decimal strings, so they are not limited to the
+        // precision of a JSON number.
+        assert!(fee.is_string());
         assert_eq!(fee, "10000000000");
         assert_eq!(fee, prefix_fee);
 
@@ -950,6 +1130,89 @@ mod e2e {
         // presented in ascending order of block_index
     }
 
+    #[test_with_logger]
+    fn test_build_transaction_input_summary(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, _db_ctx, network_state) = setup(&mut rng, logger.clone());
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let public_address = b58_decode(b58_public_address).unwrap();
+
+        // Receive 30 and 50 MOB, so that sending 60 MOB needs both.
+        for value in &[30 * MOB, 50 * MOB] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![public_address.clone()],
+                *value as u64,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        wait_for_sync(&client, &ledger_db, &network_state, &logger);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "build_transaction",
+            "params": {
+                "account_id": account_id,
+                "recipient_public_address": b58_public_address,
+                "value_pmob": (60 * MOB).to_string(),
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let tx_proposal = result.get("tx_proposal").unwrap();
+
+        // The summary matches the inputs in the proposal.
+        let input_list = tx_proposal.get("input_list").unwrap().as_array().unwrap();
+        let input_txo_ids = result.get("input_txo_ids").unwrap().as_array().unwrap();
+        assert_eq!(input_txo_ids.len(), 2);
+        assert_eq!(input_txo_ids.len(), input_list.len());
+        let input_value: u64 = input_list
+            .iter()
+            .map(|input| input["value"].as_str().unwrap().parse::<u64>().unwrap())
+            .sum();
+        assert_eq!(input_value, 80 * MOB as u64);
+        assert_eq!(
+            result["input_value_pmob"].as_str().unwrap(),
+            input_value.to_string()
+        );
+        assert_eq!(result["fee_pmob"], tx_proposal["fee"]);
+        assert_eq!(
+            result["change_value_pmob"].as_str().unwrap(),
+            (20 * MOB as u64 - MINIMUM_FEE).to_string()
+        );
+
+        // The input ids are the account's two Txos.
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_txos_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let mut txo_ids = res["result"]["txo_ids"].as_array().unwrap().clone();
+        let mut input_txo_ids = input_txo_ids.clone();
+        txo_ids.sort_by_key(|id| id.as_str().unwrap().to_string());
+        input_txo_ids.sort_by_key(|id| id.as_str().unwrap().to_string());
+        assert_eq!(input_txo_ids, txo_ids);
+    }
+
     #[test_with_logger]
     fn test_import_account_with_next_subaddress_index(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -1605,6 +1868,78 @@ mod e2e {
         assert_eq!(unspent, "100");
     }
 
+    #[test_with_logger]
+    fn test_get_txo_id(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, _db_ctx, network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let public_address = b58_decode(b58_public_address).unwrap();
+
+        // Add a block with a txo for this address
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![public_address],
+            100,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&client, &ledger_db, &network_state, &logger);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_txos_for_account",
+            "params": {
+                "account_id": account_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let txos = result.get("txo_ids").unwrap().as_array().unwrap();
+        assert_eq!(txos.len(), 1);
+        let txo_id = txos[0].as_str().unwrap();
+
+        // Get the TxOut as stored in the ledger
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_mc_protocol_txo",
+            "params": {
+                "txo_id": txo_id,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let tx_out = res.get("result").unwrap().get("txo").unwrap();
+
+        // Computing the id from the TxOut should match the id stored during sync
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_txo_id",
+            "params": {
+                "tx_out": tx_out,
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        assert_eq!(result.get("txo_id").unwrap().as_str().unwrap(), txo_id);
+    }
+
     #[test_with_logger]
     fn test_receipts(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
@@ -1897,4 +2232,101 @@ mod e2e {
         let result = res["result"]["gift_codes"].as_array().unwrap();
         assert_eq!(result.len(), 0);
     }
+
+    #[test_with_logger]
+    fn test_list_gift_codes(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let (client, mut ledger_db, _db_ctx, network_state) = setup(&mut rng, logger.clone());
+
+        // Add an account
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_account",
+            "params": {
+                "name": "Alice Main Account",
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res.get("result").unwrap();
+        let account_obj = result.get("account").unwrap();
+        let alice_account_id = account_obj.get("account_id").unwrap().as_str().unwrap();
+        let alice_b58_public_address = account_obj.get("main_address").unwrap().as_str().unwrap();
+        let alice_public_address = b58_decode(alice_b58_public_address).unwrap();
+
+        // Add a block with two txos for this address, one to fund each gift code
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone(), alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&client, &ledger_db, &network_state, &logger);
+
+        // Build and submit two gift codes
+        let mut gift_codes = Vec::new();
+        for memo in &["Happy Birthday!", "Happy New Year!"] {
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "build_gift_code",
+                "params": {
+                    "account_id": alice_account_id,
+                    "value_pmob": "42000000000000",
+                    "memo": memo,
+                }
+            });
+            let res = dispatch(&client, body, &logger);
+            let result = res["result"].clone();
+            let gift_code_b58 = result["gift_code_b58"].as_str().unwrap().to_string();
+
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "submit_gift_code",
+                "params": {
+                    "from_account_id": alice_account_id,
+                    "gift_code_b58": gift_code_b58,
+                    "tx_proposal": result["tx_proposal"],
+                }
+            });
+            dispatch(&client, body, &logger);
+            gift_codes.push(gift_code_b58);
+        }
+
+        // The listing includes both gift codes, with their status and without entropy
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_all_gift_codes",
+        });
+        let res = dispatch(&client, body, &logger);
+        let result = res["result"]["gift_codes"].as_array().unwrap();
+        assert_eq!(result.len(), 2);
+        for gift_code in result {
+            assert!(gift_codes.contains(&gift_code["gift_code_b58"].as_str().unwrap().to_string()));
+            assert_eq!(gift_code["value_pmob"], "42000000000000");
+            assert!(gift_code["entropy"].is_null());
+            assert_eq!(gift_code["status"], "GiftCodeSubmittedPending");
+        }
+        let memos: Vec<&str> = result.iter().map(|g| g["memo"].as_str().unwrap()).collect();
+        assert!(memos.contains(&"Happy Birthday!"));
+        assert!(memos.contains(&"Happy New Year!"));
+
+        // The detail view includes the entropy
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "get_gift_code",
+            "params": {
+                "gift_code_b58": gift_codes[0],
+            }
+        });
+        let res = dispatch(&client, body, &logger);
+        let gift_code = res["result"]["gift_code"].clone();
+        assert_eq!(gift_code["gift_code_b58"], gift_codes[0].as_str());
+        assert_eq!(gift_code["entropy"].as_str().unwrap().len(), 64);
+        assert_eq!(gift_code["status"], "GiftCodeSubmittedPending");
+    }
 }
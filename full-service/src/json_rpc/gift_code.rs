@@ -2,7 +2,7 @@
 
 //! API definition for the GiftCode object.
 
-use crate::db;
+use crate::{db, service::gift_code::GiftCodeStatus};
 
 use serde::{Deserialize, Serialize};
 
@@ -18,8 +18,9 @@ pub struct GiftCode {
     /// The base58-encoded gift code string to share.
     pub gift_code_b58: String,
 
-    /// The entropy for the account in this gift code.
-    pub entropy: String,
+    /// The entropy for the account in this gift code. Redacted when listing
+    /// gift codes.
+    pub entropy: Option<String>,
 
     /// The amount of MOB contained in the gift code account.
     pub value_pmob: String,
@@ -33,6 +34,20 @@ pub struct GiftCode {
 
     /// The Txo ID of the Txo in the Gift Code.
     pub txo_id_hex: String,
+
+    /// Whether the gift code is pending, available, or claimed, if known.
+    pub status: Option<GiftCodeStatus>,
+}
+
+impl GiftCode {
+    /// A gift code for listings, which omits the entropy.
+    pub fn redacted(src: &db::models::GiftCode, status: GiftCodeStatus) -> GiftCode {
+        GiftCode {
+            entropy: None,
+            status: Some(status),
+            ..GiftCode::from(src)
+        }
+    }
 }
 
 impl From<&db::models::GiftCode> for GiftCode {
@@ -40,11 +55,12 @@ impl From<&db::models::GiftCode> for GiftCode {
         GiftCode {
             object: "gift_code".to_string(),
             gift_code_b58: src.gift_code_b58.clone(),
-            entropy: hex::encode(&src.entropy),
-            value_pmob: src.value.to_string(),
+            entropy: Some(hex::encode(&src.entropy)),
+            value_pmob: (src.value as u64).to_string(),
             memo: src.memo.clone(),
             account_id: src.account_id_hex.to_string(),
             txo_id_hex: src.txo_id_hex.to_string(),
+            status: None,
         }
     }
 }
@@ -5,6 +5,7 @@
 use crate::json_rpc::tx_proposal::TxProposal;
 
 use crate::json_rpc::receiver_receipt::ReceiverReceipt;
+use mc_mobilecoind_json::data_types::JsonTxOut;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use strum::IntoEnumIterator;
@@ -45,7 +46,14 @@ impl TryFrom<&JsonRPCRequest> for JsonCommandRequest {
     type Error = String;
 
     fn try_from(src: &JsonRPCRequest) -> Result<JsonCommandRequest, String> {
-        let src_json: serde_json::Value = serde_json::json!(src);
+        let mut src_json: serde_json::Value = serde_json::json!(src);
+        if let Ok(request) = serde_json::from_value(src_json.clone()) {
+            return Ok(request);
+        }
+        // Methods whose params are all optional may be called without params.
+        if src.params.is_none() {
+            src_json["params"] = serde_json::json!({});
+        }
         Ok(serde_json::from_value(src_json).map_err(|e| format!("Could not get value {:?}", e))?)
     }
 }
@@ -57,6 +65,10 @@ impl TryFrom<&JsonRPCRequest> for JsonCommandRequest {
 pub enum JsonCommandRequest {
     create_account {
         name: Option<String>,
+        first_block_index: Option<String>,
+        fog_report_url: Option<String>,
+        fog_report_id: Option<String>,
+        fog_authority_spki: Option<String>,
     },
     import_account {
         mnemonic: String,
@@ -67,6 +79,7 @@ pub enum JsonCommandRequest {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
+        recovery_mode: Option<bool>,
     },
     import_account_from_legacy_root_entropy {
         entropy: String,
@@ -76,11 +89,23 @@ pub enum JsonCommandRequest {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
+        recovery_mode: Option<bool>,
+    },
+    import_account_from_account_key {
+        view_private_key: String,
+        spend_private_key: String,
+        name: Option<String>,
+        first_block_index: Option<String>,
     },
     export_account_secrets {
         account_id: String,
     },
-    get_all_accounts,
+    export_view_only_credentials {
+        account_id: String,
+    },
+    get_all_accounts {
+        include_archived: Option<bool>,
+    },
     get_account {
         account_id: String,
     },
@@ -88,11 +113,54 @@ pub enum JsonCommandRequest {
         account_id: String,
         name: String,
     },
+    set_change_subaddress {
+        account_id: String,
+        subaddress_index: String,
+    },
+    set_default_subaddress {
+        account_id: String,
+        subaddress_index: String,
+    },
+    set_auto_consolidate_threshold {
+        account_id: String,
+        threshold: Option<String>,
+    },
+    set_finality_depth {
+        account_id: String,
+        finality_depth: Option<String>,
+    },
+    set_webhook_url {
+        account_id: String,
+        webhook_url: Option<String>,
+    },
+    archive_account {
+        account_id: String,
+    },
+    unarchive_account {
+        account_id: String,
+    },
     remove_account {
         account_id: String,
     },
+    get_import_summary {
+        account_id: String,
+    },
+    get_account_fingerprint {
+        account_id: String,
+    },
     get_balance_for_account {
         account_id: String,
+        include_dust: Option<bool>,
+    },
+    get_balances_by_token {
+        account_id: String,
+    },
+    get_projected_balance {
+        account_id: String,
+    },
+    get_balance_delta {
+        account_id: String,
+        since_event_id: Option<String>,
     },
     build_and_submit_transaction {
         account_id: String,
@@ -102,6 +170,13 @@ pub enum JsonCommandRequest {
         fee: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        min_confirmations: Option<String>,
+        strict_confirmations: Option<bool>,
+        send_all: Option<bool>,
+        num_outputs: Option<String>,
+        num_change_outputs: Option<String>,
+        prefer_single_subaddress: Option<bool>,
+        preferred_denominations: Option<Vec<String>>,
         comment: Option<String>,
     },
     build_transaction {
@@ -112,11 +187,54 @@ pub enum JsonCommandRequest {
         fee: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        min_confirmations: Option<String>,
+        strict_confirmations: Option<bool>,
+        send_all: Option<bool>,
+        num_outputs: Option<String>,
+        num_change_outputs: Option<String>,
+        prefer_single_subaddress: Option<bool>,
+        preferred_denominations: Option<Vec<String>>,
+    },
+    reserve_txos {
+        account_id: String,
+        value_pmob: String,
+        expiry_seconds: Option<String>,
+    },
+    build_with_reservation {
+        reservation_id: String,
+        recipient_public_address: String,
+        value_pmob: String,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
     },
     submit_transaction {
         tx_proposal: TxProposal,
         comment: Option<String>,
         account_id: Option<String>,
+        wait_for_landing: Option<bool>,
+        landing_timeout_seconds: Option<String>,
+        build_id: Option<String>,
+    },
+    bump_fee {
+        transaction_log_id: String,
+        new_fee: String,
+    },
+    sweep_account {
+        account_id: String,
+        destination_address: Option<String>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        comment: Option<String>,
+    },
+    estimate_consolidation {
+        account_id: String,
+        fee: Option<String>,
+    },
+    serialize_tx_proposal {
+        tx_proposal: TxProposal,
+    },
+    deserialize_tx_proposal {
+        tx_proposal_bytes: String,
     },
     get_all_transaction_logs_for_account {
         account_id: String,
@@ -124,11 +242,32 @@ pub enum JsonCommandRequest {
     get_transaction_log {
         transaction_log_id: String,
     },
+    get_transaction_status {
+        transaction_log_id: String,
+    },
     get_all_transaction_logs_for_block {
         block_index: String,
     },
     get_all_transaction_logs_ordered_by_block,
+    get_all_transaction_logs {
+        offset: Option<String>,
+        limit: Option<String>,
+    },
+    get_transactions_to_recipient {
+        account_id: String,
+        recipient_public_address: String,
+    },
+    remove_transaction_log {
+        transaction_log_id: String,
+    },
+    update_transaction_comment {
+        transaction_log_id: String,
+        comment: String,
+    },
     get_wallet_status,
+    get_network_status,
+    refresh_network_status,
+    compact_db,
     get_account_status {
         account_id: String,
     },
@@ -136,27 +275,108 @@ pub enum JsonCommandRequest {
         account_id: String,
         metadata: Option<String>,
     },
+    create_addresses {
+        account_id: String,
+        count: String,
+        comment_prefix: Option<String>,
+    },
     get_all_addresses_for_account {
         account_id: String,
+        include_retired: Option<bool>,
+    },
+    get_address {
+        address: String,
+    },
+    get_account_for_address {
+        address: String,
+    },
+    get_addresses_with_balances {
+        account_id: String,
+        include_retired: Option<bool>,
+    },
+    retire_address {
+        address: String,
+    },
+    unretire_address {
+        address: String,
     },
     verify_address {
         address: String,
     },
+    get_subaddress_keys {
+        account_id: String,
+        subaddress_index: String,
+    },
     get_balance_for_address {
         address: String,
     },
     get_all_txos_for_account {
         account_id: String,
+        exclude_spent: Option<bool>,
+    },
+    get_secreted_txos_for_account {
+        account_id: String,
+        resolved: Option<bool>,
+    },
+    get_spent_txos_in_block {
+        account_id: String,
+        block_index: String,
     },
     get_txo {
         txo_id: String,
     },
+    get_txo_global {
+        txo_id: String,
+    },
     get_all_txos_for_address {
         address: String,
     },
+    get_all_txos {
+        offset: Option<String>,
+        limit: Option<String>,
+    },
+    set_txo_label {
+        account_id: String,
+        txo_id: String,
+        label: Option<String>,
+    },
+    get_txo_status_history {
+        account_id: String,
+        txo_id: String,
+    },
+    select_unspent_txos_for_value_from_address {
+        account_id: String,
+        address: String,
+        target_value: String,
+    },
+    preview_selection {
+        account_id: String,
+        value: String,
+        max_spendable_value: Option<String>,
+        strategy: Option<String>,
+    },
+    can_afford {
+        account_id: String,
+        value: String,
+        max_spendable_value: Option<String>,
+    },
+    get_txo_counts {
+        account_id: String,
+    },
+    purge_unspendable_txos {
+        account_id: String,
+        dry_run: Option<bool>,
+    },
+    release_dust {
+        account_id: String,
+    },
     get_confirmations {
         transaction_log_id: String,
     },
+    get_txo_confirmation {
+        account_id: String,
+        txo_id: String,
+    },
     validate_confirmation {
         account_id: String,
         txo_id: String,
@@ -165,16 +385,30 @@ pub enum JsonCommandRequest {
     get_mc_protocol_transaction {
         transaction_log_id: String,
     },
+    get_mc_protocol_transaction_for_txo {
+        txo_id: String,
+    },
     get_mc_protocol_txo {
         txo_id: String,
     },
+    get_txo_id {
+        tx_out: JsonTxOut,
+    },
     get_block {
         block_index: String,
     },
+    get_block_contents_since {
+        block_index: String,
+        limit: Option<String>,
+    },
     check_receiver_receipt_status {
         address: String,
         receiver_receipt: ReceiverReceipt,
     },
+    verify_receipts {
+        account_id: String,
+        receiver_receipts: Vec<ReceiverReceipt>,
+    },
     create_receiver_receipts {
         tx_proposal: TxProposal,
     },
@@ -192,6 +426,11 @@ pub enum JsonCommandRequest {
         gift_code_b58: String,
         tx_proposal: TxProposal,
     },
+    create_gift_codes_batch {
+        account_id: String,
+        values_pmob: Vec<String>,
+        memo: Option<String>,
+    },
     get_gift_code {
         gift_code_b58: String,
     },
@@ -204,7 +443,15 @@ pub enum JsonCommandRequest {
         account_id: String,
         address: Option<String>,
     },
+    send_from_gift_code {
+        gift_code_b58: String,
+        recipient_public_address: String,
+        value: String,
+    },
     remove_gift_code {
         gift_code_b58: String,
     },
+    cancel_gift_code {
+        gift_code_b58: String,
+    },
 }
@@ -9,14 +9,16 @@ use crate::{
         account::Account,
         account_secrets::AccountSecrets,
         address::Address,
-        balance::Balance,
+        balance::{Balance, TxoBalanceChange},
         block::{Block, BlockContents},
         confirmation_number::Confirmation,
         gift_code::GiftCode,
+        network_status::NetworkStatus,
         receiver_receipt::ReceiverReceipt,
         transaction_log::TransactionLog,
         tx_proposal::TxProposal,
-        txo::Txo,
+        txo::{Txo, TxoStatusChange},
+        view_only_credentials::ViewOnlyCredentials,
         wallet_status::WalletStatus,
     },
     service::{gift_code::GiftCodeStatus, receipt::ReceiptTransactionStatus},
@@ -139,9 +141,15 @@ pub enum JsonCommandResponse {
     import_account_from_legacy_root_entropy {
         account: Account,
     },
+    import_account_from_account_key {
+        account: Account,
+    },
     export_account_secrets {
         account_secrets: AccountSecrets,
     },
+    export_view_only_credentials {
+        view_only_credentials: ViewOnlyCredentials,
+    },
     get_all_accounts {
         account_ids: Vec<String>,
         account_map: Map<String, serde_json::Value>,
@@ -152,28 +160,103 @@ pub enum JsonCommandResponse {
     update_account_name {
         account: Account,
     },
+    set_change_subaddress {
+        account: Account,
+    },
+    set_default_subaddress {
+        account: Account,
+    },
+    set_auto_consolidate_threshold {
+        account: Account,
+    },
+    set_finality_depth {
+        account: Account,
+    },
+    set_webhook_url {
+        account: Account,
+    },
+    archive_account {
+        account: Account,
+    },
+    unarchive_account {
+        account: Account,
+    },
     remove_account {
         removed: bool,
     },
+    get_import_summary {
+        new_txo_count: String,
+        shared_txo_count: String,
+    },
+    get_account_fingerprint {
+        fingerprint: String,
+    },
     get_balance_for_account {
         balance: Balance,
     },
+    get_balances_by_token {
+        token_ids: Vec<String>,
+        balance_map: Map<String, serde_json::Value>,
+    },
+    get_projected_balance {
+        balance: Balance,
+        projected_pmob: String,
+    },
+    get_balance_delta {
+        txo_changes: Vec<TxoBalanceChange>,
+        next_event_id: String,
+    },
     build_and_submit_transaction {
         transaction_log: TransactionLog,
     },
     build_transaction {
         tx_proposal: TxProposal,
         transaction_log_id: String,
+        build_id: String,
+        input_txo_ids: Vec<String>,
+        input_value_pmob: String,
+        change_value_pmob: String,
+        fee_pmob: String,
+    },
+    reserve_txos {
+        reservation_id: String,
+        input_txo_ids: Vec<String>,
+    },
+    build_with_reservation {
+        tx_proposal: TxProposal,
+        transaction_log_id: String,
     },
     submit_transaction {
         transaction_log: Option<TransactionLog>,
     },
+    bump_fee {
+        transaction_log: TransactionLog,
+        replaced_transaction_log_id: String,
+    },
+    sweep_account {
+        transaction_log: TransactionLog,
+    },
+    estimate_consolidation {
+        num_txos: String,
+        num_transactions: String,
+        total_fee_pmob: String,
+    },
+    serialize_tx_proposal {
+        tx_proposal_bytes: String,
+    },
+    deserialize_tx_proposal {
+        tx_proposal: TxProposal,
+    },
     get_all_transaction_logs_for_account {
         transaction_log_ids: Vec<String>,
         transaction_log_map: Map<String, serde_json::Value>,
     },
     get_transaction_log {
         transaction_log: TransactionLog,
+        child_transaction_log_ids: Vec<String>,
+    },
+    get_transaction_status {
+        status: String,
     },
     get_all_transaction_logs_for_block {
         transaction_log_ids: Vec<String>,
@@ -182,9 +265,33 @@ pub enum JsonCommandResponse {
     get_all_transaction_logs_ordered_by_block {
         transaction_log_map: Map<String, serde_json::Value>,
     },
+    get_all_transaction_logs {
+        transaction_log_ids: Vec<String>,
+        transaction_log_map: Map<String, serde_json::Value>,
+    },
+    get_transactions_to_recipient {
+        transaction_log_ids: Vec<String>,
+        transaction_log_map: Map<String, serde_json::Value>,
+    },
+    remove_transaction_log {
+        removed: bool,
+    },
+    update_transaction_comment {
+        transaction_log: TransactionLog,
+    },
     get_wallet_status {
         wallet_status: WalletStatus,
     },
+    get_network_status {
+        network_status: NetworkStatus,
+    },
+    refresh_network_status {
+        network_status: NetworkStatus,
+    },
+    compact_db {
+        size_before: String,
+        size_after: String,
+    },
     get_account_status {
         account: Account,
         balance: Balance,
@@ -192,13 +299,38 @@ pub enum JsonCommandResponse {
     assign_address_for_account {
         address: Address,
     },
+    create_addresses {
+        addresses: Vec<Address>,
+    },
     get_all_addresses_for_account {
         public_addresses: Vec<String>,
         address_map: Map<String, serde_json::Value>,
     },
+    get_address {
+        address: Address,
+    },
+    get_account_for_address {
+        account_id: String,
+        subaddress_index: String,
+    },
+    get_addresses_with_balances {
+        public_addresses: Vec<String>,
+        address_map: Map<String, serde_json::Value>,
+        balance_map: Map<String, serde_json::Value>,
+    },
+    retire_address {
+        address: Address,
+    },
+    unretire_address {
+        address: Address,
+    },
     verify_address {
         verified: bool,
     },
+    get_subaddress_keys {
+        subaddress_spend_public_key: String,
+        subaddress_view_public_key: String,
+    },
     get_balance_for_address {
         balance: Balance,
     },
@@ -206,33 +338,94 @@ pub enum JsonCommandResponse {
         txo_ids: Vec<String>,
         txo_map: Map<String, serde_json::Value>,
     },
+    get_secreted_txos_for_account {
+        txo_ids: Vec<String>,
+        txo_map: Map<String, serde_json::Value>,
+    },
+    get_spent_txos_in_block {
+        txo_ids: Vec<String>,
+        txo_map: Map<String, serde_json::Value>,
+    },
     get_txo {
         txo: Txo,
     },
+    get_txo_global {
+        txo: Txo,
+        account_status_map: Map<String, serde_json::Value>,
+    },
     get_all_txos_for_address {
         txo_ids: Vec<String>,
         txo_map: Map<String, serde_json::Value>,
     },
+    get_all_txos {
+        txo_ids: Vec<String>,
+        txo_map: Map<String, serde_json::Value>,
+    },
+    set_txo_label {
+        txo: Txo,
+    },
+    get_txo_status_history {
+        status_history: Vec<TxoStatusChange>,
+    },
+    select_unspent_txos_for_value_from_address {
+        txo_ids: Vec<String>,
+        txo_map: Map<String, serde_json::Value>,
+    },
+    preview_selection {
+        txo_ids: Vec<String>,
+        value_pmob_map: Map<String, serde_json::Value>,
+        total_value_pmob: String,
+    },
+    can_afford {
+        can_afford: bool,
+        shortfall_pmob: String,
+    },
+    get_txo_counts {
+        txo_counts: Map<String, serde_json::Value>,
+    },
+    purge_unspendable_txos {
+        txo_ids: Vec<String>,
+    },
+    release_dust {
+        txo_ids: Vec<String>,
+    },
     get_confirmations {
         confirmations: Vec<Confirmation>,
     },
+    get_txo_confirmation {
+        confirmation: Confirmation,
+    },
     validate_confirmation {
         validated: bool,
     },
     get_mc_protocol_transaction {
         transaction: JsonTx,
     },
+    get_mc_protocol_transaction_for_txo {
+        transaction: JsonTx,
+    },
     get_mc_protocol_txo {
         txo: JsonTxOut,
     },
+    get_txo_id {
+        txo_id: String,
+    },
     get_block {
         block: Block,
         block_contents: BlockContents,
     },
+    get_block_contents_since {
+        blocks: Vec<Block>,
+        block_contents: Vec<BlockContents>,
+    },
     check_receiver_receipt_status {
         receipt_transaction_status: ReceiptTransactionStatus,
         txo: Option<Txo>,
     },
+    verify_receipts {
+        receipt_transaction_statuses: Vec<ReceiptTransactionStatus>,
+        txos: Vec<Option<Txo>>,
+    },
     create_receiver_receipts {
         receiver_receipts: Vec<ReceiverReceipt>,
     },
@@ -243,6 +436,9 @@ pub enum JsonCommandResponse {
     submit_gift_code {
         gift_code: GiftCode,
     },
+    create_gift_codes_batch {
+        gift_codes: Vec<GiftCode>,
+    },
     get_gift_code {
         gift_code: GiftCode,
     },
@@ -257,7 +453,14 @@ pub enum JsonCommandResponse {
     claim_gift_code {
         txo_id: String,
     },
+    send_from_gift_code {
+        txo_id: String,
+        remaining_gift_code_b58: Option<String>,
+    },
     remove_gift_code {
         removed: bool,
     },
+    cancel_gift_code {
+        transaction_log_id: String,
+    },
 }
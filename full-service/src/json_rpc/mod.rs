@@ -13,11 +13,13 @@ mod confirmation_number;
 mod gift_code;
 pub mod json_rpc_request;
 pub mod json_rpc_response;
+mod network_status;
 mod receiver_receipt;
 mod transaction_log;
 mod tx_proposal;
 mod txo;
 mod unspent_tx_out;
+mod view_only_credentials;
 pub mod wallet;
 mod wallet_status;
 
@@ -0,0 +1,42 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! API definition for the Network Status object.
+
+use crate::service;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The status of the MobileCoin network, as seen by this wallet.
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct NetworkStatus {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The block count of MobileCoin's distributed ledger, as reported by the
+    /// consensus peers.
+    pub network_block_index: String,
+
+    /// The local block count downloaded from the ledger.
+    pub local_block_index: String,
+
+    /// The block version of the highest block in the local ledger.
+    pub block_version: String,
+
+    /// The fee, in pico MOB, the wallet pays when a transaction does not name
+    /// one. This is the minimum fee built into the wallet, as the consensus
+    /// peers do not report theirs.
+    pub default_fee_pmob: String,
+}
+
+impl From<&service::ledger::NetworkStatus> for NetworkStatus {
+    fn from(src: &service::ledger::NetworkStatus) -> NetworkStatus {
+        NetworkStatus {
+            object: "network_status".to_string(),
+            network_block_index: src.network_block_index.to_string(),
+            local_block_index: src.local_block_index.to_string(),
+            block_version: src.block_version.to_string(),
+            default_fee_pmob: src.default_fee.to_string(),
+        }
+    }
+}
@@ -50,6 +50,15 @@ pub struct TransactionLog {
     /// transaction logs. Only available if direction is "sent".
     pub fee_pmob: Option<String>,
 
+    /// The change in the account's balance, in pico MOB, from this transaction
+    /// log. For "sent" transaction logs this is the value plus the fee, and is
+    /// deducted from the balance. For "received" it is the value.
+    pub net_pmob: String,
+
+    /// Value in pico MOB returned to the account as change. This is zero for
+    /// "received" transaction logs.
+    pub change_pmob: String,
+
     /// The block index of the highest block on the network at the time the
     /// transaction was submitted.
     pub submitted_block_index: Option<String>,
@@ -79,6 +88,10 @@ pub struct TransactionLog {
     /// An arbitrary string attached to the object.
     pub comment: String,
 
+    /// Unique identifier for the transaction log this one was derived from,
+    /// such as the pending transaction it replaced with a higher fee.
+    pub parent_transaction_log_id: Option<String>,
+
     /// Code representing the cause of "failed" status.
     pub failure_code: Option<i32>,
 
@@ -113,8 +126,12 @@ impl TransactionLog {
             } else {
                 Some(assigned_address_id)
             },
-            value_pmob: transaction_log.value.to_string(),
-            fee_pmob: transaction_log.fee.map(|x| x.to_string()),
+            value_pmob: (transaction_log.value as u64).to_string(),
+            fee_pmob: transaction_log.fee.map(|x| (x as u64).to_string()),
+            net_pmob: (transaction_log.value as u64 as u128
+                + transaction_log.fee.unwrap_or(0) as u64 as u128)
+                .to_string(),
+            change_pmob: associated_txos.change_value.to_string(),
             submitted_block_index: transaction_log.submitted_block_index.map(|b| b.to_string()),
             finalized_block_index: transaction_log.finalized_block_index.map(|b| b.to_string()),
             status: transaction_log.status.clone(),
@@ -125,6 +142,7 @@ impl TransactionLog {
                 .sent_time
                 .map(|t| Utc.timestamp(t, 0).to_string()),
             comment: transaction_log.comment.clone(),
+            parent_transaction_log_id: transaction_log.parent_transaction_id_hex.clone(),
             failure_code: None,    // FIXME: WS-17 Failiure code
             failure_message: None, // FIXME: WS-17 Failure message
             offset_count: transaction_log.id,
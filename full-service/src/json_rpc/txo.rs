@@ -2,7 +2,8 @@
 
 //! API definition for the Txo object.
 
-use crate::db::txo::TxoDetails;
+use crate::db::{models::AccountTxoStatus, txo::TxoDetails};
+use chrono::{offset::TimeZone, Utc};
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Map;
 
@@ -27,6 +28,11 @@ pub struct Txo {
     /// Block index in which the txo was received by an account.
     pub received_block_index: Option<String>,
 
+    /// The time at which the block the txo was received in was signed by
+    /// consensus. Only returned by get_txo, and null if the local ledger has
+    /// no signature for the block.
+    pub received_block_timestamp: Option<String>,
+
     /// Block index in which the txo was spent by an account.
     pub spent_block_index: Option<String>,
 
@@ -44,7 +50,7 @@ pub struct Txo {
     pub minted_account_id: Option<String>,
 
     /// A normalized hash mapping account_id to account objects. Keys include
-    /// "type" and "status".
+    /// "type", "status" and "label".
     ///
     /// * `txo_type`: With respect to this account, the Txo may be
     /// "minted" or "received".
@@ -60,6 +66,9 @@ pub struct Txo {
     ///   calculated -- in this case, there are manual ways to discover the
     ///   missing assigned address for orphaned Txos or to recover an entire
     ///   account.
+    ///
+    /// * `label`: An optional annotation for the Txo, set by the user with
+    ///   respect to this account.
     pub account_status_map: Map<String, serde_json::Value>,
 
     /// A cryptographic key for this Txo.
@@ -88,6 +97,20 @@ pub struct Txo {
     /// that they participated in the construction of this Txo.
     pub confirmation: Option<String>,
 
+    /// Flag that indicates whether a confirmation proof is stored for this
+    /// Txo. Only Txos minted by this wallet carry one.
+    pub has_proof: bool,
+
+    /// Flag that indicates whether a verifiable receipt can be provided to the
+    /// recipient of this Txo, i.e. it was minted by an account in this wallet
+    /// and its confirmation proof is available.
+    pub can_generate_receipt: bool,
+
+    /// Where this Txo came from, with respect to this wallet: "external" if it
+    /// was not minted by an account in this wallet, "change" if it was minted
+    /// as change, or "self_send" if it was minted as a payment output.
+    pub origin: String,
+
     /// The value to offset pagination requests. Requests will exclude all list
     /// items up to and including this object.
     pub offset_count: i32,
@@ -100,22 +123,33 @@ impl From<&TxoDetails> for Txo {
         if let Some(received) = txo_details.received_to_account.clone() {
             account_status_map.insert(
                 received.account_id_hex,
-                json!({"txo_type": received.txo_type, "txo_status": received.txo_status}).into(),
+                json!({
+                    "txo_type": received.txo_type,
+                    "txo_status": received.txo_status,
+                    "label": received.label,
+                })
+                .into(),
             );
         }
 
         if let Some(spent) = txo_details.minted_from_account.clone() {
             account_status_map.insert(
                 spent.account_id_hex,
-                json!({"txo_type": spent.txo_type, "txo_status": spent.txo_status}).into(),
+                json!({
+                    "txo_type": spent.txo_type,
+                    "txo_status": spent.txo_status,
+                    "label": spent.label,
+                })
+                .into(),
             );
         }
 
         Txo {
             object: "txo".to_string(),
             txo_id_hex: txo_details.txo.txo_id_hex.clone(),
-            value_pmob: txo_details.txo.value.to_string(),
+            value_pmob: (txo_details.txo.value as u64).to_string(),
             received_block_index: txo_details.txo.received_block_index.map(|x| x.to_string()),
+            received_block_timestamp: None,
             spent_block_index: txo_details.txo.spent_block_index.map(|x| x.to_string()),
             is_spent_recovered: false,
             received_account_id: txo_details
@@ -138,7 +172,300 @@ impl From<&TxoDetails> for Txo {
                 .map(|a| a.assigned_subaddress_b58),
             key_image: txo_details.txo.key_image.as_ref().map(|k| hex::encode(&k)),
             confirmation: txo_details.txo.confirmation.as_ref().map(hex::encode),
+            has_proof: txo_details.txo.confirmation.is_some(),
+            can_generate_receipt: txo_details.txo.confirmation.is_some()
+                && txo_details.minted_from_account.is_some(),
+            origin: if txo_details.is_change {
+                "change"
+            } else if txo_details.minted_from_account.is_some() {
+                "self_send"
+            } else {
+                "external"
+            }
+            .to_string(),
             offset_count: txo_details.txo.id,
         }
     }
 }
+
+/// A change to a Txo's status with respect to an account.
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct TxoStatusChange {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The status the Txo moved to.
+    pub txo_status: String,
+
+    /// Time at which the Txo moved to this status.
+    pub changed_at: String,
+}
+
+impl From<&crate::db::models::TxoStatusChange> for TxoStatusChange {
+    fn from(src: &crate::db::models::TxoStatusChange) -> Self {
+        Self {
+            object: "txo_status_change".to_string(),
+            txo_status: src.txo_status.clone(),
+            changed_at: Utc.timestamp(src.changed_at, 0).to_string(),
+        }
+    }
+}
+
+/// Map each account_id to the Txo's type, status and label with respect to
+/// that account, in the same form as Txo::account_status_map.
+pub fn account_status_map(statuses: &[AccountTxoStatus]) -> Map<String, serde_json::Value> {
+    statuses
+        .iter()
+        .map(|status| {
+            (
+                status.account_id_hex.clone(),
+                json!({
+                    "txo_type": status.txo_type,
+                    "txo_status": status.txo_status,
+                    "label": status.label,
+                }),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{account::AccountID, b58_encode, txo::TxoID},
+        json_rpc::balance::Balance,
+        service::{
+            account::{AccountService, CreateAccountOptions},
+            balance::BalanceService,
+            transaction::{TransactionBuildOptions, TransactionService},
+            txo::TxoService,
+        },
+        test_utils::{
+            add_block_to_ledger_db, create_test_received_txo, get_test_ledger,
+            setup_wallet_service, wait_for_sync, MOB,
+        },
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_txo_proof_flags(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        // A purely received Txo has no proof.
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 1);
+        let received = Txo::from(&txos[0]);
+        assert!(!received.has_proof);
+        assert!(!received.can_generate_receipt);
+
+        // Send to Bob, minting Txos which carry a proof.
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap(),
+                "42000000000000".to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+            )
+            .unwrap();
+        service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap();
+
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 3);
+        for txo_details in txos.iter() {
+            let txo = Txo::from(txo_details);
+            if txo_details.minted_from_account.is_some() {
+                assert!(txo.has_proof);
+                assert!(txo.can_generate_receipt);
+            } else {
+                assert!(!txo.has_proof);
+                assert!(!txo.can_generate_receipt);
+            }
+        }
+    }
+
+    #[test_with_logger]
+    fn test_txo_origin(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        // The Txo from the faucet was not minted by this wallet.
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 1);
+        let faucet_txo_id = txos[0].txo.txo_id_hex.clone();
+        assert_eq!(Txo::from(&txos[0]).origin, "external");
+
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &b58_encode(&recipient).unwrap(),
+                "42000000000000".to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+            )
+            .unwrap();
+        let (_transaction_log, associated_txos) = service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap()
+            .unwrap();
+
+        let origin_of =
+            |txo_id: &str| Txo::from(&service.get_txo(&TxoID(txo_id.to_string())).unwrap()).origin;
+        assert_eq!(origin_of(&faucet_txo_id), "external");
+        assert_eq!(origin_of(&associated_txos.outputs[0]), "self_send");
+        assert_eq!(origin_of(&associated_txos.change[0]), "change");
+    }
+
+    #[test_with_logger]
+    fn test_large_values_round_trip_as_strings(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+
+        // Above 2^53, which a JSON number cannot represent exactly, and above
+        // i64::MAX, which the database stores as a negative number.
+        let value = u64::MAX - 7;
+        create_test_received_txo(
+            &alice_account_key,
+            0,
+            value,
+            12,
+            &mut rng,
+            &service.wallet_db,
+        );
+
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 1);
+        let json_txo = serde_json::to_value(Txo::from(&txos[0])).unwrap();
+        assert_eq!(json_txo["value_pmob"], json!("18446744073709551608"));
+        let txo: Txo = serde_json::from_str(&json_txo.to_string()).unwrap();
+        assert_eq!(txo.value_pmob.parse::<u64>().unwrap(), value);
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        let json_balance = serde_json::to_value(Balance::from(&balance)).unwrap();
+        assert_eq!(json_balance["unspent_pmob"], json!("18446744073709551608"));
+        let balance: Balance = serde_json::from_str(&json_balance.to_string()).unwrap();
+        assert_eq!(balance.unspent_pmob.parse::<u64>().unwrap(), value);
+    }
+
+    // The Txo carries the TxOut's keys and fog hint, hex-encoded from the bytes
+    // stored for it.
+    #[test_with_logger]
+    fn test_txo_crypto_fields(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let (txo_id_hex, tx_out, _key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            100 * MOB as u64,
+            12,
+            &mut rng,
+            &service.wallet_db,
+        );
+
+        let txo_details = service.get_txo(&TxoID(txo_id_hex)).unwrap();
+        let txo = Txo::from(&txo_details);
+        assert_eq!(txo.target_key, hex::encode(&txo_details.txo.target_key));
+        assert_eq!(txo.public_key, hex::encode(&txo_details.txo.public_key));
+        assert_eq!(txo.e_fog_hint, hex::encode(&txo_details.txo.e_fog_hint));
+
+        // The stored bytes decode to the fields of the TxOut in the ledger.
+        assert_eq!(
+            txo.target_key,
+            hex::encode(mc_util_serial::encode(&tx_out.target_key))
+        );
+        assert_eq!(
+            txo.public_key,
+            hex::encode(mc_util_serial::encode(&tx_out.public_key))
+        );
+        assert_eq!(
+            txo.e_fog_hint,
+            hex::encode(mc_util_serial::encode(&tx_out.e_fog_hint))
+        );
+    }
+}
@@ -0,0 +1,50 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! API definition for the View Only Credentials object.
+
+use crate::service;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// The keys needed to watch an account's received Txos without being able to
+/// spend them.
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct ViewOnlyCredentials {
+    /// String representing the object's type. Objects of the same type share
+    /// the same value.
+    pub object: String,
+
+    /// The account ID of the account these credentials were exported from.
+    pub account_id: String,
+
+    /// Private key used for view-key matching, hex-encoded Ristretto bytes.
+    pub view_private_key: String,
+
+    /// Map of subaddress index to the spend public key of that subaddress,
+    /// hex-encoded Ristretto bytes.
+    pub subaddress_spend_public_keys: Map<String, serde_json::Value>,
+
+    /// The first block in which the account could have received Txos.
+    pub first_block_index: String,
+}
+
+impl From<&service::account::ViewOnlyCredentials> for ViewOnlyCredentials {
+    fn from(src: &service::account::ViewOnlyCredentials) -> ViewOnlyCredentials {
+        ViewOnlyCredentials {
+            object: "view_only_credentials".to_string(),
+            account_id: src.account_id.to_string(),
+            view_private_key: hex::encode(mc_util_serial::encode(&src.view_private_key)),
+            subaddress_spend_public_keys: src
+                .subaddress_spend_public_keys
+                .iter()
+                .map(|(index, spend_public_key)| {
+                    (
+                        index.to_string(),
+                        serde_json::json!(hex::encode(mc_util_serial::encode(spend_public_key))),
+                    )
+                })
+                .collect(),
+            first_block_index: src.first_block_index.to_string(),
+        }
+    }
+}
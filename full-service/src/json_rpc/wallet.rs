@@ -3,37 +3,47 @@
 //! Entrypoint for Wallet API.
 
 use crate::{
-    db::{self, account::AccountID, transaction_log::TransactionID, txo::TxoID},
+    db::{
+        self, account::AccountID, transaction_log::TransactionID, txo::TxoID,
+        txo_selection::SelectionStrategy,
+    },
     json_rpc,
     json_rpc::{
         account_secrets::AccountSecrets,
         address::Address,
-        balance::Balance,
+        balance::{Balance, TxoBalanceChange},
         block::{Block, BlockContents},
         confirmation_number::Confirmation,
         gift_code::GiftCode,
         json_rpc_request::{help_str, JsonCommandRequest, JsonRPCRequest},
         json_rpc_response::{format_error, JsonCommandResponse, JsonRPCResponse},
+        network_status::NetworkStatus,
         receiver_receipt::ReceiverReceipt,
         tx_proposal::TxProposal,
-        txo::Txo,
+        txo::{account_status_map, Txo, TxoStatusChange},
+        view_only_credentials::ViewOnlyCredentials,
         wallet_status::WalletStatus,
     },
     service,
     service::{
-        account::AccountService,
+        account::{AccountService, CreateAccountOptions},
         address::AddressService,
         balance::BalanceService,
         confirmation_number::ConfirmationService,
+        database::DatabaseService,
         gift_code::{EncodedGiftCode, GiftCodeService},
         ledger::LedgerService,
         receipt::ReceiptService,
-        transaction::TransactionService,
+        transaction::{
+            TransactionBuildOptions, TransactionService, TransactionServiceError,
+            DEFAULT_LANDING_TIMEOUT, MAX_LANDING_TIMEOUT,
+        },
         transaction_log::TransactionLogService,
         txo::TxoService,
         WalletService,
     },
 };
+use chrono::{offset::TimeZone, Utc};
 use mc_common::logger::global_log;
 use mc_connection::{
     BlockchainConnection, HardcodedCredentialsProvider, ThickClient, UserTxConnection,
@@ -43,7 +53,7 @@ use mc_mobilecoind_json::data_types::{JsonTx, JsonTxOut};
 use rocket::{get, post, routes};
 use rocket_contrib::json::Json;
 use serde_json::Map;
-use std::{convert::TryFrom, iter::FromIterator};
+use std::{convert::TryFrom, iter::FromIterator, time::Duration};
 
 /// State managed by rocket.
 pub struct WalletState<
@@ -93,9 +103,28 @@ where
     global_log::trace!("Running command {:?}", command);
 
     let result: JsonCommandResponse = match command.0 {
-        JsonCommandRequest::create_account { name } => {
-            let account: db::models::Account =
-                service.create_account(name).map_err(format_error)?;
+        JsonCommandRequest::create_account {
+            name,
+            first_block_index,
+            fog_report_url,
+            fog_report_id,
+            fog_authority_spki,
+        } => {
+            let fb = first_block_index
+                .map(|fb| fb.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let account: db::models::Account = service
+                .create_account(
+                    name,
+                    CreateAccountOptions {
+                        first_block_index: fb,
+                        fog_report_url,
+                        fog_report_id,
+                        fog_authority_spki,
+                    },
+                )
+                .map_err(format_error)?;
 
             JsonCommandResponse::create_account {
                 account: json_rpc::account::Account::try_from(&account)
@@ -111,6 +140,7 @@ where
             fog_report_url,
             fog_report_id,
             fog_authority_spki,
+            recovery_mode,
         } => {
             let fb = first_block_index
                 .map(|fb| fb.parse::<u64>())
@@ -134,6 +164,7 @@ where
                             fog_report_url,
                             fog_report_id,
                             fog_authority_spki,
+                            recovery_mode.unwrap_or(false),
                         )
                         .map_err(format_error)?,
                 )
@@ -148,6 +179,7 @@ where
             fog_report_url,
             fog_report_id,
             fog_authority_spki,
+            recovery_mode,
         } => {
             let fb = first_block_index
                 .map(|fb| fb.parse::<u64>())
@@ -169,6 +201,32 @@ where
                             fog_report_url,
                             fog_report_id,
                             fog_authority_spki,
+                            recovery_mode.unwrap_or(false),
+                        )
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::import_account_from_account_key {
+            view_private_key,
+            spend_private_key,
+            name,
+            first_block_index,
+        } => {
+            let fb = first_block_index
+                .map(|fb| fb.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+
+            JsonCommandResponse::import_account_from_account_key {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .import_account_from_account_key(
+                            view_private_key,
+                            spend_private_key,
+                            name,
+                            fb,
                         )
                         .map_err(format_error)?,
                 )
@@ -183,8 +241,18 @@ where
                 account_secrets: AccountSecrets::try_from(&account).map_err(format_error)?,
             }
         }
-        JsonCommandRequest::get_all_accounts => {
-            let accounts = service.list_accounts().map_err(format_error)?;
+        JsonCommandRequest::export_view_only_credentials { account_id } => {
+            let credentials = service
+                .export_view_only_credentials(&AccountID(account_id))
+                .map_err(format_error)?;
+            JsonCommandResponse::export_view_only_credentials {
+                view_only_credentials: ViewOnlyCredentials::from(&credentials),
+            }
+        }
+        JsonCommandRequest::get_all_accounts { include_archived } => {
+            let accounts = service
+                .list_accounts(include_archived.unwrap_or(false))
+                .map_err(format_error)?;
             let json_accounts: Vec<(String, serde_json::Value)> = accounts
                 .iter()
                 .map(|a| {
@@ -219,18 +287,196 @@ where
                 .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::set_change_subaddress {
+            account_id,
+            subaddress_index,
+        } => {
+            let subaddress_index = subaddress_index.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::set_change_subaddress {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .set_change_subaddress(&AccountID(account_id), subaddress_index)
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::set_default_subaddress {
+            account_id,
+            subaddress_index,
+        } => {
+            let subaddress_index = subaddress_index.parse::<u64>().map_err(format_error)?;
+            JsonCommandResponse::set_default_subaddress {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .set_default_subaddress(&AccountID(account_id), subaddress_index)
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::set_auto_consolidate_threshold {
+            account_id,
+            threshold,
+        } => {
+            let threshold = threshold
+                .map(|t| t.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            JsonCommandResponse::set_auto_consolidate_threshold {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .set_auto_consolidate_threshold(&AccountID(account_id), threshold)
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::set_finality_depth {
+            account_id,
+            finality_depth,
+        } => {
+            let finality_depth = finality_depth
+                .map(|d| d.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            JsonCommandResponse::set_finality_depth {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .set_finality_depth(&AccountID(account_id), finality_depth)
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::set_webhook_url {
+            account_id,
+            webhook_url,
+        } => JsonCommandResponse::set_webhook_url {
+            account: json_rpc::account::Account::try_from(
+                &service
+                    .set_webhook_url(&AccountID(account_id), webhook_url)
+                    .map_err(format_error)?,
+            )
+            .map_err(format_error)?,
+        },
+        JsonCommandRequest::archive_account { account_id } => {
+            JsonCommandResponse::archive_account {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .archive_account(&AccountID(account_id))
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::unarchive_account { account_id } => {
+            JsonCommandResponse::unarchive_account {
+                account: json_rpc::account::Account::try_from(
+                    &service
+                        .unarchive_account(&AccountID(account_id))
+                        .map_err(format_error)?,
+                )
+                .map_err(format_error)?,
+            }
+        }
         JsonCommandRequest::remove_account { account_id } => JsonCommandResponse::remove_account {
             removed: service
                 .remove_account(&AccountID(account_id))
                 .map_err(format_error)?,
         },
-        JsonCommandRequest::get_balance_for_account { account_id } => {
-            JsonCommandResponse::get_balance_for_account {
-                balance: Balance::from(
-                    &service
-                        .get_balance_for_account(&AccountID(account_id))
+        JsonCommandRequest::get_import_summary { account_id } => {
+            let summary = service
+                .get_import_summary(&AccountID(account_id))
+                .map_err(format_error)?;
+            JsonCommandResponse::get_import_summary {
+                new_txo_count: summary.new_txos.to_string(),
+                shared_txo_count: summary.shared_txos.to_string(),
+            }
+        }
+        JsonCommandRequest::get_account_fingerprint { account_id } => {
+            JsonCommandResponse::get_account_fingerprint {
+                fingerprint: service
+                    .get_account_fingerprint(&AccountID(account_id))
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::get_balance_for_account {
+            account_id,
+            include_dust,
+        } => {
+            let mut balance = Balance::from(
+                &service
+                    .get_balance_for_account(&AccountID(account_id))
+                    .map_err(format_error)?,
+            );
+            // Dust is left out of the unspent balance unless requested.
+            if include_dust.unwrap_or(false) {
+                let unspent = balance.unspent_pmob.parse::<u64>().map_err(format_error)?;
+                let dust = balance.dust_pmob.parse::<u64>().map_err(format_error)?;
+                balance.unspent_pmob = (unspent + dust).to_string();
+            }
+            JsonCommandResponse::get_balance_for_account { balance }
+        }
+        JsonCommandRequest::get_balances_by_token { account_id } => {
+            let balances = service
+                .get_balances_by_token_for_account(&AccountID(account_id))
+                .map_err(format_error)?;
+            let balance_map: Map<String, serde_json::Value> = Map::from_iter(
+                balances
+                    .iter()
+                    .map(|(token_id, balance)| {
+                        (
+                            token_id.to_string(),
+                            serde_json::to_value(&(Balance::from(balance)))
+                                .expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+            JsonCommandResponse::get_balances_by_token {
+                token_ids: balances
+                    .iter()
+                    .map(|(token_id, _balance)| token_id.to_string())
+                    .collect(),
+                balance_map,
+            }
+        }
+        JsonCommandRequest::get_balance_delta {
+            account_id,
+            since_event_id,
+        } => {
+            let delta = service
+                .get_balance_delta(
+                    &AccountID(account_id),
+                    since_event_id
+                        .map(|e| e.parse::<i32>())
+                        .transpose()
                         .map_err(format_error)?,
-                ),
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::get_balance_delta {
+                txo_changes: delta
+                    .txo_changes
+                    .iter()
+                    .map(TxoBalanceChange::from)
+                    .collect(),
+                next_event_id: delta.last_event_id.to_string(),
+            }
+        }
+        JsonCommandRequest::get_projected_balance { account_id } => {
+            let account_id = AccountID(account_id);
+            let balance = Balance::from(
+                &service
+                    .get_balance_for_account(&account_id)
+                    .map_err(format_error)?,
+            );
+            let projected = service
+                .get_projected_balance_for_account(&account_id)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_projected_balance {
+                balance,
+                projected_pmob: projected.to_string(),
             }
         }
         JsonCommandRequest::get_wallet_status => JsonCommandResponse::get_wallet_status {
@@ -239,6 +485,23 @@ where
             )
             .map_err(format_error)?,
         },
+        JsonCommandRequest::get_network_status => JsonCommandResponse::get_network_status {
+            network_status: NetworkStatus::from(
+                &service.get_network_status().map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::refresh_network_status => JsonCommandResponse::refresh_network_status {
+            network_status: NetworkStatus::from(
+                &service.refresh_network_status().map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::compact_db => {
+            let (size_before, size_after) = service.compact_db().map_err(format_error)?;
+            JsonCommandResponse::compact_db {
+                size_before: size_before.to_string(),
+                size_after: size_after.to_string(),
+            }
+        }
         JsonCommandRequest::get_account_status { account_id } => {
             let account = json_rpc::account::Account::try_from(
                 &service
@@ -263,9 +526,31 @@ where
                     .map_err(format_error)?,
             ),
         },
-        JsonCommandRequest::get_all_addresses_for_account { account_id } => {
+        JsonCommandRequest::create_addresses {
+            account_id,
+            count,
+            comment_prefix,
+        } => JsonCommandResponse::create_addresses {
+            addresses: service
+                .assign_addresses_for_account(
+                    &AccountID(account_id),
+                    count.parse::<u64>().map_err(format_error)?,
+                    comment_prefix.as_deref(),
+                )
+                .map_err(format_error)?
+                .iter()
+                .map(Address::from)
+                .collect(),
+        },
+        JsonCommandRequest::get_all_addresses_for_account {
+            account_id,
+            include_retired,
+        } => {
             let addresses = service
-                .get_all_addresses_for_account(&AccountID(account_id))
+                .get_all_addresses_for_account(
+                    &AccountID(account_id),
+                    include_retired.unwrap_or(false),
+                )
                 .map_err(format_error)?;
             let address_map: Map<String, serde_json::Value> = Map::from_iter(
                 addresses
@@ -288,6 +573,76 @@ where
                 address_map,
             }
         }
+        JsonCommandRequest::get_address { address } => JsonCommandResponse::get_address {
+            address: Address::from(&service.get_address(&address).map_err(format_error)?),
+        },
+        JsonCommandRequest::get_account_for_address { address } => {
+            let (account_id, subaddress_index) = service
+                .get_account_for_address(&address)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_account_for_address {
+                account_id: account_id.to_string(),
+                subaddress_index: subaddress_index.to_string(),
+            }
+        }
+        JsonCommandRequest::get_addresses_with_balances {
+            account_id,
+            include_retired,
+        } => {
+            let addresses_and_balances = service
+                .get_addresses_with_balances(
+                    &AccountID(account_id),
+                    include_retired.unwrap_or(false),
+                )
+                .map_err(format_error)?;
+            let address_map: Map<String, serde_json::Value> = Map::from_iter(
+                addresses_and_balances
+                    .iter()
+                    .map(|(a, _b)| {
+                        (
+                            a.assigned_subaddress_b58.clone(),
+                            serde_json::to_value(&(Address::from(a)))
+                                .expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+            let balance_map: Map<String, serde_json::Value> = Map::from_iter(
+                addresses_and_balances
+                    .iter()
+                    .map(|(a, b)| {
+                        (
+                            a.assigned_subaddress_b58.clone(),
+                            serde_json::to_value(&(Balance::from(b)))
+                                .expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::get_addresses_with_balances {
+                public_addresses: addresses_and_balances
+                    .iter()
+                    .map(|(a, _b)| a.assigned_subaddress_b58.clone())
+                    .collect(),
+                address_map,
+                balance_map,
+            }
+        }
+        JsonCommandRequest::retire_address { address } => JsonCommandResponse::retire_address {
+            address: Address::from(
+                &service
+                    .set_address_retired(&address, true)
+                    .map_err(format_error)?,
+            ),
+        },
+        JsonCommandRequest::unretire_address { address } => JsonCommandResponse::unretire_address {
+            address: Address::from(
+                &service
+                    .set_address_retired(&address, false)
+                    .map_err(format_error)?,
+            ),
+        },
         JsonCommandRequest::build_and_submit_transaction {
             account_id,
             recipient_public_address,
@@ -296,6 +651,13 @@ where
             fee,
             tombstone_block,
             max_spendable_value,
+            min_confirmations,
+            strict_confirmations,
+            send_all,
+            num_outputs,
+            num_change_outputs,
+            prefer_single_subaddress,
+            preferred_denominations,
             comment,
         } => {
             let (transaction_log, associated_txos) = service
@@ -307,6 +669,15 @@ where
                     fee,
                     tombstone_block,
                     max_spendable_value,
+                    TransactionBuildOptions {
+                        min_confirmations,
+                        strict_confirmations,
+                        send_all,
+                        num_outputs,
+                        num_change_outputs,
+                        prefer_single_subaddress,
+                        preferred_denominations,
+                    },
                     comment,
                 )
                 .map_err(format_error)?;
@@ -325,6 +696,13 @@ where
             fee,
             tombstone_block,
             max_spendable_value,
+            min_confirmations,
+            strict_confirmations,
+            send_all,
+            num_outputs,
+            num_change_outputs,
+            prefer_single_subaddress,
+            preferred_denominations,
         } => {
             let tx_proposal = service
                 .build_transaction(
@@ -335,27 +713,135 @@ where
                     fee,
                     tombstone_block,
                     max_spendable_value,
+                    TransactionBuildOptions {
+                        min_confirmations,
+                        strict_confirmations,
+                        send_all,
+                        num_outputs,
+                        num_change_outputs,
+                        prefer_single_subaddress,
+                        preferred_denominations,
+                    },
                 )
                 .map_err(format_error)?;
+            // The change is whatever the inputs hold beyond the outlays and the fee.
+            let input_value = tx_proposal
+                .utxos
+                .iter()
+                .map(|utxo| utxo.value as u128)
+                .sum::<u128>();
+            let outlay_value = tx_proposal
+                .outlays
+                .iter()
+                .map(|outlay| outlay.value as u128)
+                .sum::<u128>();
+            let change_value = input_value - outlay_value - tx_proposal.fee as u128;
+            let build_id = service
+                .record_build(&account_id, &tx_proposal)
+                .map_err(format_error)?;
             JsonCommandResponse::build_transaction {
                 tx_proposal: TxProposal::from(&tx_proposal),
                 transaction_log_id: TransactionID::from(&tx_proposal.tx).to_string(),
+                build_id,
+                input_txo_ids: tx_proposal
+                    .utxos
+                    .iter()
+                    .map(|utxo| TxoID::from(&utxo.tx_out).to_string())
+                    .collect(),
+                input_value_pmob: input_value.to_string(),
+                change_value_pmob: change_value.to_string(),
+                fee_pmob: tx_proposal.fee.to_string(),
+            }
+        }
+        JsonCommandRequest::reserve_txos {
+            account_id,
+            value_pmob,
+            expiry_seconds,
+        } => {
+            let reservation = service
+                .reserve_txos(&account_id, value_pmob, expiry_seconds)
+                .map_err(format_error)?;
+            JsonCommandResponse::reserve_txos {
+                reservation_id: reservation.reservation_id,
+                input_txo_ids: reservation.txo_ids,
+            }
+        }
+        JsonCommandRequest::build_with_reservation {
+            reservation_id,
+            recipient_public_address,
+            value_pmob,
+            fee,
+            tombstone_block,
+        } => {
+            let tx_proposal = service
+                .build_with_reservation(
+                    &reservation_id,
+                    &recipient_public_address,
+                    value_pmob,
+                    fee,
+                    tombstone_block,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::build_with_reservation {
+                tx_proposal: TxProposal::from(&tx_proposal),
+                transaction_log_id: TransactionID::from(&tx_proposal.tx).to_string(),
             }
         }
         JsonCommandRequest::submit_transaction {
             tx_proposal,
             comment,
             account_id,
+            wait_for_landing,
+            landing_timeout_seconds,
+            build_id,
         } => {
-            let result: Option<json_rpc::transaction_log::TransactionLog> = service
-                .submit_transaction(
-                    mc_mobilecoind::payments::TxProposal::try_from(&tx_proposal)
-                        .map_err(format_error)?,
-                    comment,
-                    account_id,
-                )
+            let landing_timeout = landing_timeout_seconds
+                .map(|t| t.parse::<u64>())
+                .transpose()
                 .map_err(format_error)?
-                .map(|(transaction_log, associated_txos)| {
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_LANDING_TIMEOUT);
+            // Reject the timeout before submitting, rather than after.
+            if landing_timeout > MAX_LANDING_TIMEOUT {
+                return Err(format_error(
+                    TransactionServiceError::LandingTimeoutTooLong(MAX_LANDING_TIMEOUT.as_secs()),
+                ));
+            }
+            let wait_for_landing = wait_for_landing.unwrap_or(false);
+            // Only a logged transaction can be followed until it lands.
+            if wait_for_landing && account_id.is_none() {
+                return Err(format_error(
+                    TransactionServiceError::MissingAccountOnSubmit,
+                ));
+            }
+            let tx_proposal = mc_mobilecoind::payments::TxProposal::try_from(&tx_proposal)
+                .map_err(format_error)?;
+            if let Some(build_id) = &build_id {
+                // A build is only verified for the account it was recorded for.
+                let account_id = account_id
+                    .as_ref()
+                    .ok_or_else(|| format_error(TransactionServiceError::MissingAccountOnSubmit))?;
+                service
+                    .verify_build(account_id, build_id, &tx_proposal)
+                    .map_err(format_error)?;
+            }
+            let mut submitted = service
+                .submit_transaction(tx_proposal, comment, account_id)
+                .map_err(format_error)?;
+            // The build has served its purpose once the proposal is submitted.
+            if let Some(build_id) = &build_id {
+                service.remove_build(build_id).map_err(format_error)?;
+            }
+            if let (true, Some((transaction_log, _associated_txos))) =
+                (wait_for_landing, &submitted)
+            {
+                let landed = service
+                    .wait_for_landing(&transaction_log.transaction_id_hex, landing_timeout)
+                    .map_err(format_error)?;
+                submitted = Some(landed);
+            }
+            let result: Option<json_rpc::transaction_log::TransactionLog> =
+                submitted.map(|(transaction_log, associated_txos)| {
                     json_rpc::transaction_log::TransactionLog::new(
                         &transaction_log,
                         &associated_txos,
@@ -365,21 +851,95 @@ where
                 transaction_log: result,
             }
         }
-        JsonCommandRequest::get_all_transaction_logs_for_account { account_id } => {
-            let transaction_logs_and_txos = service
-                .list_transaction_logs(&AccountID(account_id))
+        JsonCommandRequest::bump_fee {
+            transaction_log_id,
+            new_fee,
+        } => {
+            let (transaction_log, associated_txos) = service
+                .bump_fee(
+                    &transaction_log_id,
+                    new_fee.parse::<u64>().map_err(format_error)?,
+                )
                 .map_err(format_error)?;
-            let transaction_log_map: Map<String, serde_json::Value> = Map::from_iter(
-                transaction_logs_and_txos
-                    .iter()
-                    .map(|(t, a)| {
-                        (
-                            t.transaction_id_hex.clone(),
-                            serde_json::json!(json_rpc::transaction_log::TransactionLog::new(t, a)),
-                        )
-                    })
-                    .collect::<Vec<(String, serde_json::Value)>>(),
-            );
+            JsonCommandResponse::bump_fee {
+                transaction_log: json_rpc::transaction_log::TransactionLog::new(
+                    &transaction_log,
+                    &associated_txos,
+                ),
+                replaced_transaction_log_id: transaction_log_id,
+            }
+        }
+        JsonCommandRequest::sweep_account {
+            account_id,
+            destination_address,
+            fee,
+            tombstone_block,
+            comment,
+        } => {
+            let (transaction_log, associated_txos) = service
+                .sweep_account(
+                    &account_id,
+                    destination_address,
+                    fee,
+                    tombstone_block,
+                    comment,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::sweep_account {
+                transaction_log: json_rpc::transaction_log::TransactionLog::new(
+                    &transaction_log,
+                    &associated_txos,
+                ),
+            }
+        }
+        JsonCommandRequest::estimate_consolidation { account_id, fee } => {
+            let estimate = service
+                .estimate_consolidation(&account_id, fee)
+                .map_err(format_error)?;
+            JsonCommandResponse::estimate_consolidation {
+                num_txos: estimate.num_txos.to_string(),
+                num_transactions: estimate.num_transactions.to_string(),
+                total_fee_pmob: estimate.total_fee.to_string(),
+            }
+        }
+        JsonCommandRequest::serialize_tx_proposal { tx_proposal } => {
+            JsonCommandResponse::serialize_tx_proposal {
+                tx_proposal_bytes: hex::encode(
+                    service
+                        .serialize_tx_proposal(
+                            &mc_mobilecoind::payments::TxProposal::try_from(&tx_proposal)
+                                .map_err(format_error)?,
+                        )
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::deserialize_tx_proposal { tx_proposal_bytes } => {
+            JsonCommandResponse::deserialize_tx_proposal {
+                tx_proposal: TxProposal::from(
+                    &service
+                        .deserialize_tx_proposal(
+                            &hex::decode(tx_proposal_bytes).map_err(format_error)?,
+                        )
+                        .map_err(format_error)?,
+                ),
+            }
+        }
+        JsonCommandRequest::get_all_transaction_logs_for_account { account_id } => {
+            let transaction_logs_and_txos = service
+                .list_transaction_logs(&AccountID(account_id))
+                .map_err(format_error)?;
+            let transaction_log_map: Map<String, serde_json::Value> = Map::from_iter(
+                transaction_logs_and_txos
+                    .iter()
+                    .map(|(t, a)| {
+                        (
+                            t.transaction_id_hex.clone(),
+                            serde_json::json!(json_rpc::transaction_log::TransactionLog::new(t, a)),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
 
             JsonCommandResponse::get_all_transaction_logs_for_account {
                 transaction_log_ids: transaction_logs_and_txos
@@ -393,11 +953,25 @@ where
             let (transaction_log, associated_txos) = service
                 .get_transaction_log(&transaction_log_id)
                 .map_err(format_error)?;
+            let children = service
+                .list_child_transaction_logs(&transaction_log_id)
+                .map_err(format_error)?;
             JsonCommandResponse::get_transaction_log {
                 transaction_log: json_rpc::transaction_log::TransactionLog::new(
                     &transaction_log,
                     &associated_txos,
                 ),
+                child_transaction_log_ids: children
+                    .iter()
+                    .map(|t| t.transaction_id_hex.clone())
+                    .collect(),
+            }
+        }
+        JsonCommandRequest::get_transaction_status { transaction_log_id } => {
+            JsonCommandResponse::get_transaction_status {
+                status: service
+                    .get_transaction_status(&transaction_log_id)
+                    .map_err(format_error)?,
             }
         }
         JsonCommandRequest::get_all_transaction_logs_for_block { block_index } => {
@@ -446,9 +1020,105 @@ where
                 transaction_log_map,
             }
         }
+        JsonCommandRequest::get_all_transaction_logs { offset, limit } => {
+            let offset = offset
+                .map(|o| o.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let limit = limit
+                .map(|l| l.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let transaction_logs_and_txos = service
+                .get_all_transaction_logs(offset, limit)
+                .map_err(format_error)?;
+            let transaction_log_map: Map<String, serde_json::Value> = Map::from_iter(
+                transaction_logs_and_txos
+                    .iter()
+                    .map(|(t, a)| {
+                        (
+                            t.transaction_id_hex.clone(),
+                            serde_json::json!(json_rpc::transaction_log::TransactionLog::new(t, a)),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::get_all_transaction_logs {
+                transaction_log_ids: transaction_logs_and_txos
+                    .iter()
+                    .map(|(t, _a)| t.transaction_id_hex.to_string())
+                    .collect(),
+                transaction_log_map,
+            }
+        }
+        JsonCommandRequest::get_transactions_to_recipient {
+            account_id,
+            recipient_public_address,
+        } => {
+            let transaction_logs_and_txos = service
+                .list_transaction_logs_to_recipient(
+                    &AccountID(account_id),
+                    &recipient_public_address,
+                )
+                .map_err(format_error)?;
+            let transaction_log_map: Map<String, serde_json::Value> = Map::from_iter(
+                transaction_logs_and_txos
+                    .iter()
+                    .map(|(t, a)| {
+                        (
+                            t.transaction_id_hex.clone(),
+                            serde_json::json!(json_rpc::transaction_log::TransactionLog::new(t, a)),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::get_transactions_to_recipient {
+                transaction_log_ids: transaction_logs_and_txos
+                    .iter()
+                    .map(|(t, _a)| t.transaction_id_hex.to_string())
+                    .collect(),
+                transaction_log_map,
+            }
+        }
+        JsonCommandRequest::remove_transaction_log { transaction_log_id } => {
+            JsonCommandResponse::remove_transaction_log {
+                removed: service
+                    .remove_transaction_log(&transaction_log_id)
+                    .map_err(format_error)?,
+            }
+        }
+        JsonCommandRequest::update_transaction_comment {
+            transaction_log_id,
+            comment,
+        } => {
+            let (transaction_log, associated_txos) = service
+                .update_transaction_comment(&transaction_log_id, comment)
+                .map_err(format_error)?;
+            JsonCommandResponse::update_transaction_comment {
+                transaction_log: json_rpc::transaction_log::TransactionLog::new(
+                    &transaction_log,
+                    &associated_txos,
+                ),
+            }
+        }
         JsonCommandRequest::verify_address { address } => JsonCommandResponse::verify_address {
             verified: service.verify_address(&address).map_err(format_error)?,
         },
+        JsonCommandRequest::get_subaddress_keys {
+            account_id,
+            subaddress_index,
+        } => {
+            let subaddress_index = subaddress_index.parse::<u64>().map_err(format_error)?;
+            let (spend_public_key, view_public_key) = service
+                .get_subaddress_keys(&AccountID(account_id), subaddress_index)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_subaddress_keys {
+                subaddress_spend_public_key: hex::encode(mc_util_serial::encode(&spend_public_key)),
+                subaddress_view_public_key: hex::encode(mc_util_serial::encode(&view_public_key)),
+            }
+        }
         JsonCommandRequest::get_balance_for_address { address } => {
             JsonCommandResponse::get_balance_for_address {
                 balance: Balance::from(
@@ -458,9 +1128,12 @@ where
                 ),
             }
         }
-        JsonCommandRequest::get_all_txos_for_account { account_id } => {
+        JsonCommandRequest::get_all_txos_for_account {
+            account_id,
+            exclude_spent,
+        } => {
             let txos = service
-                .list_txos(&AccountID(account_id))
+                .list_txos(&AccountID(account_id), exclude_spent)
                 .map_err(format_error)?;
             let txo_map: Map<String, serde_json::Value> = Map::from_iter(
                 txos.iter()
@@ -478,10 +1151,103 @@ where
                 txo_map,
             }
         }
+        JsonCommandRequest::get_secreted_txos_for_account {
+            account_id,
+            resolved,
+        } => {
+            let txos = service
+                .list_secreted_txos(&AccountID(account_id), resolved)
+                .map_err(format_error)?;
+            let txo_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|t| {
+                        (
+                            t.txo.txo_id_hex.clone(),
+                            serde_json::to_value(Txo::from(t)).expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::get_secreted_txos_for_account {
+                txo_ids: txos.iter().map(|t| t.txo.txo_id_hex.clone()).collect(),
+                txo_map,
+            }
+        }
+        JsonCommandRequest::get_spent_txos_in_block {
+            account_id,
+            block_index,
+        } => {
+            let txos = service
+                .get_spent_txos_in_block(
+                    &AccountID(account_id),
+                    block_index.parse::<u64>().map_err(format_error)?,
+                )
+                .map_err(format_error)?;
+            let txo_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|t| {
+                        (
+                            t.txo.txo_id_hex.clone(),
+                            serde_json::to_value(Txo::from(t)).expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::get_spent_txos_in_block {
+                txo_ids: txos.iter().map(|t| t.txo.txo_id_hex.clone()).collect(),
+                txo_map,
+            }
+        }
         JsonCommandRequest::get_txo { txo_id } => {
             let result = service.get_txo(&TxoID(txo_id)).map_err(format_error)?;
-            JsonCommandResponse::get_txo {
-                txo: Txo::from(&result),
+            let mut txo = Txo::from(&result);
+            if let Some(received_block_index) = result.txo.received_block_index {
+                txo.received_block_timestamp = service
+                    .get_block_timestamp(received_block_index as u64)
+                    .map_err(format_error)?
+                    .map(|t| Utc.timestamp(t as i64, 0).to_string());
+            }
+            JsonCommandResponse::get_txo { txo }
+        }
+        JsonCommandRequest::get_txo_global { txo_id } => {
+            let (txo_details, statuses) = service
+                .get_txo_global(&TxoID(txo_id))
+                .map_err(format_error)?;
+            JsonCommandResponse::get_txo_global {
+                txo: Txo::from(&txo_details),
+                account_status_map: account_status_map(&statuses),
+            }
+        }
+        JsonCommandRequest::get_all_txos { offset, limit } => {
+            let offset = offset
+                .map(|o| o.parse::<i32>())
+                .transpose()
+                .map_err(format_error)?;
+            let limit = limit
+                .map(|l| l.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let txos = service.get_all_txos(offset, limit).map_err(format_error)?;
+            let txo_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|(details, statuses)| {
+                        // Report every associated account, not only the receiving and
+                        // minting ones.
+                        let mut txo = Txo::from(details);
+                        txo.account_status_map = account_status_map(statuses);
+                        (
+                            details.txo.txo_id_hex.clone(),
+                            serde_json::to_value(txo).expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::get_all_txos {
+                txo_ids: txos.iter().map(|(t, _)| t.txo.txo_id_hex.clone()).collect(),
+                txo_map,
             }
         }
         JsonCommandRequest::get_all_txos_for_address { address } => {
@@ -504,6 +1270,136 @@ where
                 txo_map,
             }
         }
+        JsonCommandRequest::set_txo_label {
+            account_id,
+            txo_id,
+            label,
+        } => {
+            let result = service
+                .set_txo_label(&AccountID(account_id), &TxoID(txo_id), label)
+                .map_err(format_error)?;
+            JsonCommandResponse::set_txo_label {
+                txo: Txo::from(&result),
+            }
+        }
+        JsonCommandRequest::get_txo_status_history { account_id, txo_id } => {
+            JsonCommandResponse::get_txo_status_history {
+                status_history: service
+                    .get_txo_status_history(&AccountID(account_id), &TxoID(txo_id))
+                    .map_err(format_error)?
+                    .iter()
+                    .map(TxoStatusChange::from)
+                    .collect(),
+            }
+        }
+        JsonCommandRequest::select_unspent_txos_for_value_from_address {
+            account_id,
+            address,
+            target_value,
+        } => {
+            let target_value = target_value.parse::<u64>().map_err(format_error)?;
+            let txos = service
+                .select_unspent_txos_for_value_from_address(
+                    &AccountID(account_id),
+                    &address,
+                    target_value,
+                )
+                .map_err(format_error)?;
+            let txo_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|t| {
+                        (
+                            t.txo.txo_id_hex.clone(),
+                            serde_json::to_value(Txo::from(t)).expect("Could not get json value"),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+
+            JsonCommandResponse::select_unspent_txos_for_value_from_address {
+                txo_ids: txos.iter().map(|t| t.txo.txo_id_hex.clone()).collect(),
+                txo_map,
+            }
+        }
+        JsonCommandRequest::preview_selection {
+            account_id,
+            value,
+            max_spendable_value,
+            strategy,
+        } => {
+            let value = value.parse::<u64>().map_err(format_error)?;
+            let max_spendable_value = max_spendable_value
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let strategy = strategy
+                .map(|s| s.parse::<SelectionStrategy>())
+                .transpose()
+                .map_err(format_error)?
+                .unwrap_or_default();
+            let txos = service
+                .preview_selection(&AccountID(account_id), value, max_spendable_value, strategy)
+                .map_err(format_error)?;
+            let value_pmob_map: Map<String, serde_json::Value> = Map::from_iter(
+                txos.iter()
+                    .map(|t| {
+                        (
+                            t.txo_id_hex.clone(),
+                            serde_json::Value::String((t.value as u64).to_string()),
+                        )
+                    })
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+            let total_value_pmob = txos.iter().map(|t| t.value as u64 as u128).sum::<u128>();
+
+            JsonCommandResponse::preview_selection {
+                txo_ids: txos.iter().map(|t| t.txo_id_hex.clone()).collect(),
+                value_pmob_map,
+                total_value_pmob: total_value_pmob.to_string(),
+            }
+        }
+        JsonCommandRequest::can_afford {
+            account_id,
+            value,
+            max_spendable_value,
+        } => {
+            let value = value.parse::<u64>().map_err(format_error)?;
+            let max_spendable_value = max_spendable_value
+                .map(|v| v.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let (can_afford, shortfall) = service
+                .can_afford(&AccountID(account_id), value, max_spendable_value)
+                .map_err(format_error)?;
+            JsonCommandResponse::can_afford {
+                can_afford,
+                shortfall_pmob: shortfall.to_string(),
+            }
+        }
+        JsonCommandRequest::get_txo_counts { account_id } => {
+            let txo_counts: Map<String, serde_json::Value> = Map::from_iter(
+                service
+                    .get_txo_counts(&AccountID(account_id))
+                    .map_err(format_error)?
+                    .into_iter()
+                    .map(|(status, count)| (status, serde_json::Value::String(count.to_string())))
+                    .collect::<Vec<(String, serde_json::Value)>>(),
+            );
+            JsonCommandResponse::get_txo_counts { txo_counts }
+        }
+        JsonCommandRequest::purge_unspendable_txos {
+            account_id,
+            dry_run,
+        } => JsonCommandResponse::purge_unspendable_txos {
+            txo_ids: service
+                .purge_unspendable_txos(&AccountID(account_id), dry_run.unwrap_or(false))
+                .map_err(format_error)?,
+        },
+        JsonCommandRequest::release_dust { account_id } => JsonCommandResponse::release_dust {
+            txo_ids: service
+                .release_dust(&AccountID(account_id))
+                .map_err(format_error)?,
+        },
         JsonCommandRequest::get_confirmations { transaction_log_id } => {
             JsonCommandResponse::get_confirmations {
                 confirmations: service
@@ -514,6 +1410,15 @@ where
                     .collect(),
             }
         }
+        JsonCommandRequest::get_txo_confirmation { account_id, txo_id } => {
+            JsonCommandResponse::get_txo_confirmation {
+                confirmation: Confirmation::from(
+                    &service
+                        .get_txo_confirmation(&AccountID(account_id), &TxoID(txo_id))
+                        .map_err(format_error)?,
+                ),
+            }
+        }
         JsonCommandRequest::validate_confirmation {
             account_id,
             txo_id,
@@ -534,12 +1439,29 @@ where
                 transaction: json_tx,
             }
         }
+        JsonCommandRequest::get_mc_protocol_transaction_for_txo { txo_id } => {
+            let tx = service
+                .get_transaction_object_for_txo(&txo_id)
+                .map_err(format_error)?;
+            let proto_tx = mc_api::external::Tx::from(&tx);
+            let json_tx = JsonTx::from(&proto_tx);
+            JsonCommandResponse::get_mc_protocol_transaction_for_txo {
+                transaction: json_tx,
+            }
+        }
         JsonCommandRequest::get_mc_protocol_txo { txo_id } => {
             let tx_out = service.get_txo_object(&txo_id).map_err(format_error)?;
             let proto_txo = mc_api::external::TxOut::from(&tx_out);
             let json_txo = JsonTxOut::from(&proto_txo);
             JsonCommandResponse::get_mc_protocol_txo { txo: json_txo }
         }
+        JsonCommandRequest::get_txo_id { tx_out } => {
+            let proto_txo = mc_api::external::TxOut::try_from(&tx_out).map_err(format_error)?;
+            let txo = mc_transaction_core::tx::TxOut::try_from(&proto_txo).map_err(format_error)?;
+            JsonCommandResponse::get_txo_id {
+                txo_id: TxoID::from(&txo).to_string(),
+            }
+        }
         JsonCommandRequest::get_block { block_index } => {
             let (block, block_contents) = service
                 .get_block_object(block_index.parse::<u64>().map_err(format_error)?)
@@ -549,6 +1471,25 @@ where
                 block_contents: BlockContents::new(&block_contents),
             }
         }
+        JsonCommandRequest::get_block_contents_since { block_index, limit } => {
+            let limit = limit
+                .map(|l| l.parse::<u64>())
+                .transpose()
+                .map_err(format_error)?;
+            let block_objects = service
+                .get_block_objects_since(block_index.parse::<u64>().map_err(format_error)?, limit)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_block_contents_since {
+                blocks: block_objects
+                    .iter()
+                    .map(|(block, _contents)| Block::new(block))
+                    .collect(),
+                block_contents: block_objects
+                    .iter()
+                    .map(|(_block, contents)| BlockContents::new(contents))
+                    .collect(),
+            }
+        }
         JsonCommandRequest::check_receiver_receipt_status {
             address,
             receiver_receipt,
@@ -563,6 +1504,26 @@ where
                 txo: txo.as_ref().map(Txo::from),
             }
         }
+        JsonCommandRequest::verify_receipts {
+            account_id,
+            receiver_receipts,
+        } => {
+            let receipts = receiver_receipts
+                .iter()
+                .map(service::receipt::ReceiverReceipt::try_from)
+                .collect::<Result<Vec<service::receipt::ReceiverReceipt>, _>>()
+                .map_err(format_error)?;
+            let (statuses, txos): (Vec<_>, Vec<_>) = service
+                .verify_receipts(&AccountID(account_id), &receipts)
+                .map_err(format_error)?
+                .into_iter()
+                .map(|(status, txo)| (status, txo.as_ref().map(Txo::from)))
+                .unzip();
+            JsonCommandResponse::verify_receipts {
+                receipt_transaction_statuses: statuses,
+                txos,
+            }
+        }
         JsonCommandRequest::create_receiver_receipts { tx_proposal } => {
             let receipts = service
                 .create_receiver_receipts(
@@ -629,21 +1590,52 @@ where
                 gift_code: GiftCode::from(&gift_code),
             }
         }
-        JsonCommandRequest::get_gift_code { gift_code_b58 } => JsonCommandResponse::get_gift_code {
-            gift_code: GiftCode::from(
-                &service
-                    .get_gift_code(&EncodedGiftCode(gift_code_b58))
-                    .map_err(format_error)?,
-            ),
-        },
-        JsonCommandRequest::get_all_gift_codes {} => JsonCommandResponse::get_all_gift_codes {
-            gift_codes: service
+        JsonCommandRequest::create_gift_codes_batch {
+            account_id,
+            values_pmob,
+            memo,
+        } => {
+            let values = values_pmob
+                .iter()
+                .map(|v| v.parse::<u64>())
+                .collect::<Result<Vec<u64>, _>>()
+                .map_err(format_error)?;
+            let gift_codes = service
+                .create_gift_codes_batch(&AccountID(account_id), &values, memo)
+                .map_err(format_error)?;
+            JsonCommandResponse::create_gift_codes_batch {
+                gift_codes: gift_codes.iter().map(GiftCode::from).collect(),
+            }
+        }
+        JsonCommandRequest::get_gift_code { gift_code_b58 } => {
+            let gift_code_b58 = EncodedGiftCode(gift_code_b58);
+            let gift_code = service
+                .get_gift_code(&gift_code_b58)
+                .map_err(format_error)?;
+            let (status, _value, _memo) = service
+                .check_gift_code_status(&gift_code_b58)
+                .map_err(format_error)?;
+            JsonCommandResponse::get_gift_code {
+                gift_code: GiftCode {
+                    status: Some(status),
+                    ..GiftCode::from(&gift_code)
+                },
+            }
+        }
+        JsonCommandRequest::get_all_gift_codes {} => {
+            let gift_codes = service
                 .list_gift_codes()
                 .map_err(format_error)?
                 .iter()
-                .map(GiftCode::from)
-                .collect(),
-        },
+                .map(|gift_code| {
+                    let (status, _value, _memo) = service
+                        .check_gift_code_status(&EncodedGiftCode(gift_code.gift_code_b58.clone()))
+                        .map_err(format_error)?;
+                    Ok(GiftCode::redacted(gift_code, status))
+                })
+                .collect::<Result<Vec<GiftCode>, String>>()?;
+            JsonCommandResponse::get_all_gift_codes { gift_codes }
+        }
         JsonCommandRequest::check_gift_code_status { gift_code_b58 } => {
             let (status, value, memo) = service
                 .check_gift_code_status(&EncodedGiftCode(gift_code_b58))
@@ -670,6 +1662,23 @@ where
                 txo_id: TxoID::from(&tx.prefix.outputs[0]).to_string(),
             }
         }
+        JsonCommandRequest::send_from_gift_code {
+            gift_code_b58,
+            recipient_public_address,
+            value,
+        } => {
+            let (_tx, tx_out, remaining_gift_code) = service
+                .send_from_gift_code(
+                    &EncodedGiftCode(gift_code_b58),
+                    &recipient_public_address,
+                    value.parse::<u64>().map_err(format_error)?,
+                )
+                .map_err(format_error)?;
+            JsonCommandResponse::send_from_gift_code {
+                txo_id: TxoID::from(&tx_out).to_string(),
+                remaining_gift_code_b58: remaining_gift_code.map(|g| g.to_string()),
+            }
+        }
 
         JsonCommandRequest::remove_gift_code { gift_code_b58 } => {
             JsonCommandResponse::remove_gift_code {
@@ -678,6 +1687,14 @@ where
                     .map_err(format_error)?,
             }
         }
+        JsonCommandRequest::cancel_gift_code { gift_code_b58 } => {
+            let tx = service
+                .cancel_gift_code(&EncodedGiftCode(gift_code_b58))
+                .map_err(format_error)?;
+            JsonCommandResponse::cancel_gift_code {
+                transaction_log_id: TransactionID::from(&tx).to_string(),
+            }
+        }
     };
     let response = Json(JsonRPCResponse::from(result));
     Ok(response)
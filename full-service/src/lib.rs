@@ -12,9 +12,9 @@ mod error;
 mod json_rpc;
 mod service;
 
-pub use db::WalletDb;
+pub use db::{ConnectionOptions, Network, SynchronousMode, WalletDb};
 pub use json_rpc::wallet;
-pub use service::WalletService;
+pub use service::{sync::SyncThreadConfig, WalletService};
 
 extern crate alloc;
 #[macro_use]
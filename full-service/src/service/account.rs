@@ -4,15 +4,25 @@
 
 use crate::{
     db::{
-        account::{AccountID, AccountModel, MNEMONIC_KEY_DERIVATION_VERSION},
-        models::Account,
+        account::{
+            AccountID, AccountModel, MNEMONIC_KEY_DERIVATION_VERSION,
+            ROOT_ENTROPY_KEY_DERIVATION_VERSION,
+        },
+        account_txo_status::AccountTxoStatusModel,
+        assigned_subaddress::AssignedSubaddressModel,
+        models::{
+            Account, AccountTxoStatus, AssignedSubaddress, WebhookNotification, TXO_TYPE_RECEIVED,
+        },
+        webhook_notification::WebhookNotificationModel,
         WalletDbError,
     },
     service::{ledger::LedgerService, WalletService},
 };
-use mc_account_keys::RootEntropy;
+use mc_account_keys::{AccountKey, RootEntropy};
 use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_digestible::{Digestible, MerlinTranscript};
+use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
 
@@ -21,6 +31,18 @@ use bip39::{Language, Mnemonic, MnemonicType};
 use diesel::Connection;
 use displaydoc::Display;
 
+/// The number of bytes of the spend public key's digest kept in an account
+/// fingerprint.
+pub const ACCOUNT_FINGERPRINT_LEN: usize = 8;
+
+/// A short, hex-encoded hash of an account's spend public key, for verifying
+/// out of band that an account is the expected one.
+pub fn account_fingerprint(account_key: &AccountKey) -> String {
+    let spend_public_key = RistrettoPublic::from(account_key.spend_private_key());
+    let digest: [u8; 32] = spend_public_key.digest32::<MerlinTranscript>(b"account_fingerprint");
+    hex::encode(&digest[..ACCOUNT_FINGERPRINT_LEN])
+}
+
 #[derive(Display, Debug)]
 pub enum AccountServiceError {
     /// Error interacting with the database: {0}
@@ -40,6 +62,21 @@ pub enum AccountServiceError {
 
     /// Unknown key version version: {0}
     UnknownKeyDerivation(u8),
+
+    /// Key derivation version {0} requires a 24 word mnemonic
+    InvalidMnemonicForKeyDerivation(u8),
+
+    /// Error decoding private key: {0}
+    ProstDecode(prost::DecodeError),
+
+    /// Subaddress {0} is not assigned to this account
+    SubaddressNotAssigned(u64),
+
+    /// First block index {0} is beyond the current tip {1}
+    FirstBlockBeyondTip(u64, u64),
+
+    /// Invalid webhook URL {0}: {1}
+    InvalidWebhookUrl(String, String),
 }
 
 impl From<WalletDbError> for AccountServiceError {
@@ -48,6 +85,12 @@ impl From<WalletDbError> for AccountServiceError {
     }
 }
 
+impl From<prost::DecodeError> for AccountServiceError {
+    fn from(src: prost::DecodeError) -> Self {
+        Self::ProstDecode(src)
+    }
+}
+
 impl From<mc_ledger_db::Error> for AccountServiceError {
     fn from(src: mc_ledger_db::Error) -> Self {
         Self::LedgerDB(src)
@@ -72,13 +115,73 @@ impl From<LedgerServiceError> for AccountServiceError {
     }
 }
 
+/// A summary of how the Txos found for an account overlap with the other
+/// accounts in the wallet.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    /// Txos received only by this account.
+    pub new_txos: u64,
+
+    /// Txos that were also received by another account in the wallet, for
+    /// example because the two accounts share keys.
+    pub shared_txos: u64,
+}
+
+/// The keys needed to watch an account without being able to spend from it.
+#[derive(Debug, Clone)]
+pub struct ViewOnlyCredentials {
+    /// The account these credentials were exported from.
+    pub account_id: AccountID,
+
+    /// The private key used for view-key matching.
+    pub view_private_key: RistrettoPrivate,
+
+    /// The spend public key of each assigned subaddress, by subaddress index,
+    /// including retired subaddresses, which are still matched on receive.
+    pub subaddress_spend_public_keys: Vec<(u64, RistrettoPublic)>,
+
+    /// The first block in which the account could have received Txos.
+    pub first_block_index: u64,
+}
+
+/// Options for creating an account, beyond its name.
+#[derive(Clone, Debug, Default)]
+pub struct CreateAccountOptions {
+    /// The block at which scanning starts, which may not be beyond the current
+    /// tip. Defaults to the tip.
+    pub first_block_index: Option<u64>,
+
+    /// If provided, the account's addresses are fog-enabled, using this
+    /// report url.
+    pub fog_report_url: Option<String>,
+
+    /// The fog report id.
+    pub fog_report_id: Option<String>,
+
+    /// The hex-encoded fog authority SPKI.
+    pub fog_authority_spki: Option<String>,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// accounts.
 pub trait AccountService {
-    /// Creates a new account with default values.
-    fn create_account(&self, name: Option<String>) -> Result<Account, AccountServiceError>;
+    /// Creates a new account with default values, and the given
+    /// CreateAccountOptions.
+    fn create_account(
+        &self,
+        name: Option<String>,
+        options: CreateAccountOptions,
+    ) -> Result<Account, AccountServiceError>;
 
-    /// Import an existing account to the wallet using the entropy.
+    /// Import an existing account to the wallet using the mnemonic.
+    ///
+    /// The account key is derived from the mnemonic with SLIP-0010 for
+    /// MNEMONIC_KEY_DERIVATION_VERSION. For
+    /// ROOT_ENTROPY_KEY_DERIVATION_VERSION, the mnemonic encodes the root
+    /// entropy of a legacy account, as in wallets created before SLIP-0010.
+    ///
+    /// If recovery_mode is set, the account is marked as being imported to
+    /// recover its Txos.
     #[allow(clippy::too_many_arguments)]
     fn import_account(
         &self,
@@ -90,6 +193,7 @@ pub trait AccountService {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
+        recovery_mode: bool,
     ) -> Result<Account, AccountServiceError>;
 
     /// Import an existing account to the wallet using the entropy.
@@ -103,10 +207,23 @@ pub trait AccountService {
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
+        recovery_mode: bool,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Import an existing account to the wallet using its hex-encoded private
+    /// keys, for users without the entropy. The account id is the same as
+    /// if it were imported from the entropy behind the keys.
+    fn import_account_from_account_key(
+        &self,
+        view_private_key: String,
+        spend_private_key: String,
+        name: Option<String>,
+        first_block_index: Option<u64>,
     ) -> Result<Account, AccountServiceError>;
 
-    /// List accounts in the wallet.
-    fn list_accounts(&self) -> Result<Vec<Account>, AccountServiceError>;
+    /// List accounts in the wallet. Archived accounts are only included if
+    /// `include_archived` is true.
+    fn list_accounts(&self, include_archived: bool) -> Result<Vec<Account>, AccountServiceError>;
 
     /// Get an account in the wallet.
     fn get_account(&self, account_id: &AccountID) -> Result<Account, AccountServiceError>;
@@ -118,8 +235,82 @@ pub trait AccountService {
         name: String,
     ) -> Result<Account, AccountServiceError>;
 
+    /// Designate an assigned subaddress as the account's change subaddress.
+    ///
+    /// Change from every transaction built for the account is sent to this
+    /// subaddress.
+    fn set_change_subaddress(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Designate an assigned subaddress as the account's default subaddress
+    /// for receiving, which is reported as its main address.
+    fn set_default_subaddress(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Set the unspent Txo count above which sends from the account
+    /// opportunistically consolidate Txos, or None to disable consolidation.
+    fn set_auto_consolidate_threshold(
+        &self,
+        account_id: &AccountID,
+        threshold: Option<u64>,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Set the number of blocks which must follow the block a transaction
+    /// sent from the account landed in before it is reported as succeeded,
+    /// or None to report it as soon as it lands.
+    fn set_finality_depth(
+        &self,
+        account_id: &AccountID,
+        finality_depth: Option<u64>,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Set the URL notified of each Txo the account receives, or None to stop
+    /// notifications and drop any not yet delivered. Setting the URL
+    /// re-enables a webhook disabled after repeated failures.
+    fn set_webhook_url(
+        &self,
+        account_id: &AccountID,
+        webhook_url: Option<String>,
+    ) -> Result<Account, AccountServiceError>;
+
+    /// Archive an account, hiding it from account listings by default.
+    fn archive_account(&self, account_id: &AccountID) -> Result<Account, AccountServiceError>;
+
+    /// Unarchive an account.
+    fn unarchive_account(&self, account_id: &AccountID) -> Result<Account, AccountServiceError>;
+
     /// Remove an account from the wallet.
     fn remove_account(&self, account_id: &AccountID) -> Result<bool, AccountServiceError>;
+
+    /// Summarize which of the Txos received by an account are new to the
+    /// wallet, and which were also received by another account.
+    ///
+    /// Imported accounts are scanned in the background, so this reflects the
+    /// Txos found so far.
+    fn get_import_summary(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<ImportSummary, AccountServiceError>;
+
+    /// Export the view private key and subaddress spend public keys of an
+    /// account, so that another party can watch it without being able to
+    /// spend from it.
+    fn export_view_only_credentials(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<ViewOnlyCredentials, AccountServiceError>;
+
+    /// Get the fingerprint of an account's spend public key.
+    fn get_account_fingerprint(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<String, AccountServiceError>;
 }
 
 impl<T, FPR> AccountService for WalletService<T, FPR>
@@ -127,21 +318,53 @@ where
     T: BlockchainConnection + UserTxConnection + 'static,
     FPR: FogPubkeyResolver + Send + Sync + 'static,
 {
-    fn create_account(&self, name: Option<String>) -> Result<Account, AccountServiceError> {
-        log::info!(self.logger, "Creating account {:?}", name,);
+    fn create_account(
+        &self,
+        name: Option<String>,
+        options: CreateAccountOptions,
+    ) -> Result<Account, AccountServiceError> {
+        let CreateAccountOptions {
+            first_block_index,
+            fog_report_url,
+            fog_report_id,
+            fog_authority_spki,
+        } = options;
+        log::info!(
+            self.logger,
+            "Creating account {:?} with fog report url {:?}",
+            name,
+            fog_report_url,
+        );
+
+        // Validate the SPKI up front, since it is otherwise only decoded when deriving
+        // the account key.
+        if let Some(spki) = &fog_authority_spki {
+            hex::decode(spki)?;
+        }
 
         // Generate entropy for the account
         let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
 
-        // Since we are creating the account from randomness, it is highly unlikely that
-        // it would have collided with another account that already received funds. For
-        // this reason, start scanning at the current network block index.
-        let first_block_index = self.get_network_block_index()?;
-
         // The earliest we could start scanning is the current highest block index of
         // the local ledger.
         let import_block_index = self.ledger_db.num_blocks()? - 1;
 
+        // Since we are creating the account from randomness, it is highly unlikely that
+        // it would have collided with another account that already received funds. For
+        // this reason, start scanning at the current tip by default. A first block
+        // beyond the tip would silently skip any funds received before it.
+        let tip = std::cmp::max(self.get_network_block_index()?, import_block_index);
+        let first_block_index = match first_block_index {
+            Some(first_block_index) if first_block_index > tip => {
+                return Err(AccountServiceError::FirstBlockBeyondTip(
+                    first_block_index,
+                    tip,
+                ));
+            }
+            Some(first_block_index) => first_block_index,
+            None => tip,
+        };
+
         let conn = self.wallet_db.get_conn()?;
         let (account_id, _public_address_b58) = Account::create_from_mnemonic(
             &mnemonic,
@@ -149,9 +372,9 @@ where
             Some(import_block_index),
             None,
             &name.unwrap_or_else(|| "".to_string()),
-            None,
-            None,
-            None,
+            fog_report_url,
+            fog_report_id,
+            fog_authority_spki,
             &conn,
         )?;
 
@@ -169,6 +392,7 @@ where
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
+        recovery_mode: bool,
     ) -> Result<Account, AccountServiceError> {
         log::info!(
             self.logger,
@@ -177,7 +401,9 @@ where
             first_block_index,
         );
 
-        if key_derivation_version != MNEMONIC_KEY_DERIVATION_VERSION {
+        if key_derivation_version != MNEMONIC_KEY_DERIVATION_VERSION
+            && key_derivation_version != ROOT_ENTROPY_KEY_DERIVATION_VERSION
+        {
             return Err(AccountServiceError::UnknownKeyDerivation(
                 key_derivation_version,
             ));
@@ -191,6 +417,28 @@ where
         let import_block = self.ledger_db.num_blocks()? - 1;
 
         let conn = self.wallet_db.get_conn()?;
+        if key_derivation_version == ROOT_ENTROPY_KEY_DERIVATION_VERSION {
+            let mut entropy_bytes = [0u8; 32];
+            if mnemonic.entropy().len() != entropy_bytes.len() {
+                return Err(AccountServiceError::InvalidMnemonicForKeyDerivation(
+                    key_derivation_version,
+                ));
+            }
+            entropy_bytes.copy_from_slice(mnemonic.entropy());
+            return Ok(Account::import_legacy(
+                &RootEntropy::from(&entropy_bytes),
+                name,
+                import_block,
+                first_block_index,
+                next_subaddress_index,
+                fog_report_url,
+                fog_report_id,
+                fog_authority_spki,
+                recovery_mode,
+                &conn,
+            )?);
+        }
+
         Ok(Account::import(
             &mnemonic,
             name,
@@ -200,6 +448,7 @@ where
             fog_report_url,
             fog_report_id,
             fog_authority_spki,
+            recovery_mode,
             &conn,
         )?)
     }
@@ -213,6 +462,7 @@ where
         fog_report_url: Option<String>,
         fog_report_id: Option<String>,
         fog_authority_spki: Option<String>,
+        recovery_mode: bool,
     ) -> Result<Account, AccountServiceError> {
         log::info!(
             self.logger,
@@ -238,13 +488,50 @@ where
             fog_report_url,
             fog_report_id,
             fog_authority_spki,
+            recovery_mode,
             &conn,
         )?)
     }
 
-    fn list_accounts(&self) -> Result<Vec<Account>, AccountServiceError> {
+    fn import_account_from_account_key(
+        &self,
+        view_private_key: String,
+        spend_private_key: String,
+        name: Option<String>,
+        first_block_index: Option<u64>,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Importing account {:?} from account key with first block: {:?}",
+            name,
+            first_block_index,
+        );
+        let view_private_key: RistrettoPrivate =
+            mc_util_serial::decode(&hex::decode(view_private_key)?)?;
+        let spend_private_key: RistrettoPrivate =
+            mc_util_serial::decode(&hex::decode(spend_private_key)?)?;
+        let account_key = AccountKey::new(&spend_private_key, &view_private_key);
+
+        // We record the local highest block index because that is the earliest we could
+        // start scanning.
+        let import_block = self.ledger_db.num_blocks()? - 1;
+
+        let conn = self.wallet_db.get_conn()?;
+        Ok(Account::import_from_account_key(
+            &account_key,
+            name,
+            import_block,
+            first_block_index,
+            &conn,
+        )?)
+    }
+
+    fn list_accounts(&self, include_archived: bool) -> Result<Vec<Account>, AccountServiceError> {
         let conn = self.wallet_db.get_conn()?;
-        Ok(Account::list_all(&conn)?)
+        Ok(Account::list_all(&conn)?
+            .into_iter()
+            .filter(|a| include_archived || !a.archived)
+            .collect())
     }
 
     fn get_account(&self, account_id: &AccountID) -> Result<Account, AccountServiceError> {
@@ -265,27 +552,287 @@ where
         })?)
     }
 
+    fn set_change_subaddress(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Setting change subaddress for account {} to {}",
+            account_id,
+            subaddress_index
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            let account = Account::get(&account_id, &conn)?;
+            if !AssignedSubaddress::list_all(&account_id.to_string(), &conn)?
+                .iter()
+                .any(|s| s.subaddress_index == subaddress_index as i64)
+            {
+                return Err(AccountServiceError::SubaddressNotAssigned(subaddress_index));
+            }
+            account.update_change_subaddress_index(subaddress_index as i64, &conn)?;
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn set_default_subaddress(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Setting default subaddress for account {} to {}",
+            account_id,
+            subaddress_index
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            let account = Account::get(&account_id, &conn)?;
+            if !AssignedSubaddress::list_all(&account_id.to_string(), &conn)?
+                .iter()
+                .any(|s| s.subaddress_index == subaddress_index as i64)
+            {
+                return Err(AccountServiceError::SubaddressNotAssigned(subaddress_index));
+            }
+            account.update_main_subaddress_index(subaddress_index as i64, &conn)?;
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn set_auto_consolidate_threshold(
+        &self,
+        account_id: &AccountID,
+        threshold: Option<u64>,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Setting auto consolidate threshold for account {} to {:?}",
+            account_id,
+            threshold
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            Account::get(&account_id, &conn)?
+                .update_auto_consolidate_threshold(threshold.map(|t| t as i64), &conn)?;
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn set_finality_depth(
+        &self,
+        account_id: &AccountID,
+        finality_depth: Option<u64>,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Setting finality depth for account {} to {:?}",
+            account_id,
+            finality_depth
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            Account::get(&account_id, &conn)?
+                .update_finality_depth(finality_depth.map(|d| d as i64), &conn)?;
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn set_webhook_url(
+        &self,
+        account_id: &AccountID,
+        webhook_url: Option<String>,
+    ) -> Result<Account, AccountServiceError> {
+        log::info!(
+            self.logger,
+            "Setting webhook URL for account {} to {:?}",
+            account_id,
+            webhook_url
+        );
+        if let Some(url) = &webhook_url {
+            if let Err(e) = reqwest::Url::parse(url) {
+                return Err(AccountServiceError::InvalidWebhookUrl(
+                    url.clone(),
+                    e.to_string(),
+                ));
+            }
+        }
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            Account::get(&account_id, &conn)?.update_webhook_url(webhook_url.as_deref(), &conn)?;
+            if webhook_url.is_none() {
+                WebhookNotification::delete_all_for_account(&account_id.to_string(), &conn)?;
+            }
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn archive_account(&self, account_id: &AccountID) -> Result<Account, AccountServiceError> {
+        log::info!(self.logger, "Archiving account {}", account_id);
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            Account::get(&account_id, &conn)?.update_archived(true, &conn)?;
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
+    fn unarchive_account(&self, account_id: &AccountID) -> Result<Account, AccountServiceError> {
+        log::info!(self.logger, "Unarchiving account {}", account_id);
+
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<Account, AccountServiceError, _>(|| {
+            Account::get(&account_id, &conn)?.update_archived(false, &conn)?;
+            Ok(Account::get(&account_id, &conn)?)
+        })?)
+    }
+
     fn remove_account(&self, account_id: &AccountID) -> Result<bool, AccountServiceError> {
         log::info!(self.logger, "Deleting account {}", account_id,);
 
         let conn = self.wallet_db.get_conn()?;
         let account = Account::get(account_id, &conn)?;
         account.delete(&conn)?;
+        self.wallet_db
+            .spendable_txos_cache()
+            .invalidate(&account_id.to_string());
 
         Ok(true)
     }
+
+    fn get_import_summary(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<ImportSummary, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        // Make sure the account exists, so that we don't report an empty summary for
+        // an unknown account.
+        Account::get(account_id, &conn)?;
+
+        let total = AccountTxoStatus::get_all_for_account(&account_id.to_string(), &conn)?
+            .iter()
+            .filter(|status| status.txo_type == TXO_TYPE_RECEIVED)
+            .count();
+        let shared =
+            AccountTxoStatus::get_shared_txo_ids_for_account(&account_id.to_string(), &conn)?.len();
+
+        Ok(ImportSummary {
+            new_txos: (total - shared) as u64,
+            shared_txos: shared as u64,
+        })
+    }
+
+    fn export_view_only_credentials(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<ViewOnlyCredentials, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let account = Account::get(account_id, &conn)?;
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+
+        let mut subaddress_spend_public_keys =
+            AssignedSubaddress::list_all(&account_id.to_string(), &conn)?
+                .iter()
+                .map(|assigned_subaddress| {
+                    let spend_public_key: RistrettoPublic =
+                        mc_util_serial::decode(&assigned_subaddress.subaddress_spend_key)?;
+                    Ok((
+                        assigned_subaddress.subaddress_index as u64,
+                        spend_public_key,
+                    ))
+                })
+                .collect::<Result<Vec<(u64, RistrettoPublic)>, AccountServiceError>>()?;
+        subaddress_spend_public_keys.sort_by_key(|(index, _key)| *index);
+
+        Ok(ViewOnlyCredentials {
+            account_id: account_id.clone(),
+            view_private_key: account_key.view_private_key().clone(),
+            subaddress_spend_public_keys,
+            first_block_index: account.first_block_index as u64,
+        })
+    }
+
+    fn get_account_fingerprint(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<String, AccountServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account = Account::get(account_id, &conn)?;
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        Ok(account_fingerprint(&account_key))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        db::{account_txo_status::AccountTxoStatusModel, models::AccountTxoStatus},
+        db::{
+            b58_decode,
+            models::{Txo, TXO_STATUS_SECRETED, TXO_TYPE_MINTED},
+            txo::TxoModel,
+        },
+        service::address::AddressService,
         test_utils::{create_test_received_txo, get_test_ledger, setup_wallet_service, MOB},
     };
-    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_account_keys::{AccountKey, PublicAddress, RootIdentity};
+    use mc_account_keys_slip10::Slip10Key;
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_transaction_core::{onetime_keys::recover_public_subaddress_spend_key, tx::TxOut};
+    use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
+    use std::convert::TryFrom;
+
+    #[test_with_logger]
+    fn test_archive_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let bob = service
+            .create_account(Some("Bob".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        assert!(!alice.archived);
+
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        let alice = service.archive_account(&alice_account_id).unwrap();
+        assert!(alice.archived);
+
+        // Archived accounts are hidden unless requested.
+        let accounts = service.list_accounts(false).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].account_id_hex, bob.account_id_hex);
+        assert_eq!(service.list_accounts(true).unwrap().len(), 2);
+
+        // The account can still be retrieved directly.
+        assert!(service.get_account(&alice_account_id).unwrap().archived);
+
+        let alice = service.unarchive_account(&alice_account_id).unwrap();
+        assert!(!alice.archived);
+        assert_eq!(service.list_accounts(false).unwrap().len(), 2);
+    }
 
     #[test_with_logger]
     fn test_remove_account_txo_status(logger: Logger) {
@@ -298,7 +845,9 @@ mod tests {
         let wallet_db = &service.wallet_db;
 
         // Create an account.
-        let account = service.create_account(Some("A".to_string())).unwrap();
+        let account = service
+            .create_account(Some("A".to_string()), CreateAccountOptions::default())
+            .unwrap();
 
         let statuses = AccountTxoStatus::get_all_for_account(
             &account.account_id_hex,
@@ -338,4 +887,561 @@ mod tests {
         .unwrap();
         assert_eq!(statuses.len(), 0);
     }
+
+    #[test_with_logger]
+    fn test_import_summary_reports_shared_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let wallet_db = &service.wallet_db;
+
+        let alice = service
+            .import_account(
+                Mnemonic::new(MnemonicType::Words24, Language::English).into_phrase(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                Some("Alice".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+
+        let bob = service
+            .import_account(
+                Mnemonic::new(MnemonicType::Words24, Language::English).into_phrase(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                Some("Bob".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID(bob.account_id_hex.clone());
+
+        // One Txo only Alice has, and one only Bob has.
+        create_test_received_txo(
+            &alice_account_key,
+            0,
+            (10 * MOB) as u64,
+            13 as u64,
+            &mut rng,
+            &wallet_db,
+        );
+        let (bob_txo_id_hex, _txo, _key_image) = create_test_received_txo(
+            &bob_account_key,
+            0,
+            (20 * MOB) as u64,
+            13 as u64,
+            &mut rng,
+            &wallet_db,
+        );
+
+        // Alice minted the Txo Bob received, which does not make it shared.
+        AccountTxoStatus::create(
+            &alice.account_id_hex,
+            &bob_txo_id_hex,
+            TXO_STATUS_SECRETED,
+            TXO_TYPE_MINTED,
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        // A Txo received by Alice which is also found for Bob.
+        let (_txo_id_hex, txo, key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            (30 * MOB) as u64,
+            14 as u64,
+            &mut rng,
+            &wallet_db,
+        );
+        Txo::create_received(
+            txo,
+            Some(0),
+            Some(key_image),
+            (30 * MOB) as u64,
+            14,
+            &bob.account_id_hex,
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        let alice_summary = service.get_import_summary(&alice_account_id).unwrap();
+        assert_eq!(
+            alice_summary,
+            ImportSummary {
+                new_txos: 1,
+                shared_txos: 1,
+            }
+        );
+
+        let bob_summary = service.get_import_summary(&bob_account_id).unwrap();
+        assert_eq!(
+            bob_summary,
+            ImportSummary {
+                new_txos: 1,
+                shared_txos: 1,
+            }
+        );
+    }
+
+    #[test_with_logger]
+    fn test_import_account_with_key_derivation_version(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words24, Language::English);
+        let slip10_account = service
+            .import_account(
+                mnemonic.phrase().to_string(),
+                MNEMONIC_KEY_DERIVATION_VERSION,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        let legacy_account = service
+            .import_account(
+                mnemonic.phrase().to_string(),
+                ROOT_ENTROPY_KEY_DERIVATION_VERSION,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            slip10_account.key_derivation_version,
+            MNEMONIC_KEY_DERIVATION_VERSION as i32
+        );
+        assert_eq!(
+            legacy_account.key_derivation_version,
+            ROOT_ENTROPY_KEY_DERIVATION_VERSION as i32
+        );
+
+        // Each version derives the key its own way, so the main addresses
+        // differ.
+        let slip10_account_key: AccountKey =
+            mc_util_serial::decode(&slip10_account.account_key).unwrap();
+        let legacy_account_key: AccountKey =
+            mc_util_serial::decode(&legacy_account.account_key).unwrap();
+        assert_ne!(
+            slip10_account_key.default_subaddress(),
+            legacy_account_key.default_subaddress()
+        );
+        assert_ne!(slip10_account.account_id_hex, legacy_account.account_id_hex);
+
+        let expected_slip10_account_key = Slip10Key::from(mnemonic.clone())
+            .try_into_account_key("", "", &[])
+            .unwrap();
+        assert_eq!(
+            slip10_account_key.default_subaddress(),
+            expected_slip10_account_key.default_subaddress()
+        );
+
+        let mut entropy_bytes = [0u8; 32];
+        entropy_bytes.copy_from_slice(mnemonic.entropy());
+        let expected_legacy_account_key = AccountKey::from(&RootIdentity {
+            root_entropy: RootEntropy::from(&entropy_bytes),
+            fog_report_url: "".to_string(),
+            fog_report_id: "".to_string(),
+            fog_authority_spki: vec![],
+        });
+        assert_eq!(
+            legacy_account_key.default_subaddress(),
+            expected_legacy_account_key.default_subaddress()
+        );
+
+        // Legacy root entropy is 32 bytes, which needs a 24 word mnemonic.
+        match service.import_account(
+            Mnemonic::new(MnemonicType::Words12, Language::English).into_phrase(),
+            ROOT_ENTROPY_KEY_DERIVATION_VERSION,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            false,
+        ) {
+            Err(AccountServiceError::InvalidMnemonicForKeyDerivation(version)) => {
+                assert_eq!(version, ROOT_ENTROPY_KEY_DERIVATION_VERSION)
+            }
+            Ok(_) => panic!("Should not import a 12 word mnemonic as root entropy"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_create_account_with_fog(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let fog_report_url = "fog://fog.example.com".to_string();
+        let fog_report_id = "1".to_string();
+        let fog_authority_spki = hex::encode(b"fog authority spki");
+        let account = service
+            .create_account(
+                Some("Fog".to_string()),
+                CreateAccountOptions {
+                    fog_report_url: Some(fog_report_url.clone()),
+                    fog_report_id: Some(fog_report_id.clone()),
+                    fog_authority_spki: Some(fog_authority_spki),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key).unwrap();
+        assert_eq!(account_key.fog_report_url(), Some(fog_report_url.as_str()));
+
+        // The main address handed out for this account is fog-enabled.
+        let main_address = service
+            .get_all_addresses_for_account(&AccountID(account.account_id_hex.clone()), false)
+            .unwrap()
+            .into_iter()
+            .find(|a| a.subaddress_index == account.main_subaddress_index)
+            .unwrap();
+        let public_address = b58_decode(&main_address.assigned_subaddress_b58).unwrap();
+        assert_eq!(
+            public_address.fog_report_url(),
+            Some(fog_report_url.as_str())
+        );
+        assert_eq!(public_address.fog_report_id(), Some(fog_report_id.as_str()));
+        assert!(public_address.fog_authority_sig().is_some());
+
+        // An invalid SPKI is rejected rather than creating the account.
+        match service.create_account(
+            Some("Bad Fog".to_string()),
+            CreateAccountOptions {
+                fog_report_url: Some(fog_report_url),
+                fog_report_id: Some(fog_report_id),
+                fog_authority_spki: Some("not hex".to_string()),
+                ..Default::default()
+            },
+        ) {
+            Err(AccountServiceError::HexDecode(_)) => {}
+            Ok(_) => panic!("Should not create an account with an invalid SPKI"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_create_account_first_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let tip = ledger_db.num_blocks().unwrap() - 1;
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        // A fresh account starts scanning at the tip.
+        let account = service
+            .create_account(Some("Fresh".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        assert_eq!(account.first_block_index as u64, tip);
+        assert_eq!(account.next_block_index as u64, tip);
+
+        // An earlier first block is honored.
+        let account = service
+            .create_account(
+                Some("Earlier".to_string()),
+                CreateAccountOptions {
+                    first_block_index: Some(3),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(account.first_block_index, 3);
+        assert_eq!(account.next_block_index, 3);
+
+        // A first block beyond the tip is rejected.
+        match service.create_account(
+            Some("Later".to_string()),
+            CreateAccountOptions {
+                first_block_index: Some(tip + 1),
+                ..Default::default()
+            },
+        ) {
+            Err(AccountServiceError::FirstBlockBeyondTip(first_block_index, max)) => {
+                assert_eq!(first_block_index, tip + 1);
+                assert_eq!(max, tip);
+            }
+            Ok(_) => panic!("Should not create an account beyond the tip"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        assert_eq!(service.list_accounts(true).unwrap().len(), 2);
+    }
+
+    #[test_with_logger]
+    fn test_import_account_recovery_mode(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let import = |recovery_mode: bool| {
+            service
+                .import_account(
+                    Mnemonic::new(MnemonicType::Words24, Language::English).into_phrase(),
+                    MNEMONIC_KEY_DERIVATION_VERSION,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    recovery_mode,
+                )
+                .unwrap()
+        };
+
+        let recovering = import(true);
+        let account = service
+            .get_account(&AccountID(recovering.account_id_hex.clone()))
+            .unwrap();
+        assert!(account.recovery_mode);
+        let json_account = crate::json_rpc::account::Account::try_from(&account).unwrap();
+        assert!(json_account.recovery_mode);
+
+        let imported = import(false);
+        let account = service
+            .get_account(&AccountID(imported.account_id_hex.clone()))
+            .unwrap();
+        assert!(!account.recovery_mode);
+
+        // Newly created accounts are never in recovery mode.
+        let created = service
+            .create_account(None, CreateAccountOptions::default())
+            .unwrap();
+        assert!(!created.recovery_mode);
+    }
+
+    #[test_with_logger]
+    fn test_export_view_only_credentials(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let wallet_db = &service.wallet_db;
+
+        let alice = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        service
+            .assign_address_for_account(&alice_account_id, None)
+            .unwrap();
+
+        let bob = service
+            .create_account(Some("Bob".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+
+        // Alice receives at her main, change and newly assigned subaddresses.
+        let alice_txos: Vec<(u64, TxOut)> = (0..3)
+            .map(|subaddress_index| {
+                let (_txo_id_hex, txo, _key_image) = create_test_received_txo(
+                    &alice_account_key,
+                    subaddress_index,
+                    (10 * MOB) as u64,
+                    13 as u64,
+                    &mut rng,
+                    &wallet_db,
+                );
+                (subaddress_index, txo)
+            })
+            .collect();
+        let (_txo_id_hex, bob_txo, _key_image) = create_test_received_txo(
+            &bob_account_key,
+            0,
+            (10 * MOB) as u64,
+            13 as u64,
+            &mut rng,
+            &wallet_db,
+        );
+
+        let credentials = service
+            .export_view_only_credentials(&alice_account_id)
+            .unwrap();
+        assert_eq!(credentials.account_id, alice_account_id);
+        assert_eq!(
+            mc_util_serial::encode(&credentials.view_private_key),
+            mc_util_serial::encode(alice_account_key.view_private_key())
+        );
+        assert_eq!(
+            credentials.first_block_index,
+            alice.first_block_index as u64
+        );
+        assert_eq!(credentials.subaddress_spend_public_keys.len(), 3);
+
+        // Matching with only the exported keys finds the subaddress of each of
+        // Alice's Txos, and none of Bob's.
+        let find_subaddress = |txo: &TxOut| {
+            let subaddress_spk = recover_public_subaddress_spend_key(
+                &credentials.view_private_key,
+                &RistrettoPublic::try_from(&txo.target_key).unwrap(),
+                &RistrettoPublic::try_from(&txo.public_key).unwrap(),
+            );
+            credentials
+                .subaddress_spend_public_keys
+                .iter()
+                .find(|(_index, spend_public_key)| *spend_public_key == subaddress_spk)
+                .map(|(index, _spend_public_key)| *index)
+        };
+        for (subaddress_index, txo) in alice_txos.iter() {
+            assert_eq!(find_subaddress(txo), Some(*subaddress_index));
+        }
+        assert_eq!(find_subaddress(&bob_txo), None);
+
+        // These are the Txos the wallet found for Alice.
+        assert_eq!(
+            Txo::list_for_account(&alice.account_id_hex, false, &wallet_db.get_conn().unwrap())
+                .unwrap()
+                .len(),
+            alice_txos.len()
+        );
+    }
+
+    #[test_with_logger]
+    fn test_set_default_subaddress(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key).unwrap();
+        assert_eq!(account.main_subaddress_index, 0);
+
+        // Only an assigned subaddress can be the default.
+        match service.set_default_subaddress(&account_id, 2) {
+            Err(AccountServiceError::SubaddressNotAssigned(2)) => {}
+            Ok(_) => panic!("Should not be able to use an unassigned subaddress"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        let address = service
+            .assign_address_for_account(&account_id, Some("Donations"))
+            .unwrap();
+        assert_eq!(address.subaddress_index, 2);
+
+        let account = service.set_default_subaddress(&account_id, 2).unwrap();
+        assert_eq!(account.main_subaddress_index, 2);
+        assert_eq!(account.change_subaddress_index, 1);
+
+        let json_account = crate::json_rpc::account::Account::try_from(&account).unwrap();
+        assert_eq!(json_account.main_address, address.assigned_subaddress_b58);
+        assert_eq!(
+            b58_decode(&json_account.main_address).unwrap(),
+            account_key.subaddress(2)
+        );
+    }
+
+    #[test_with_logger]
+    fn test_account_fingerprint_is_stable(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let entropy = hex::encode(&root_id.root_entropy.bytes);
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                entropy.clone(),
+                Some("Alice".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+
+        let fingerprint = service.get_account_fingerprint(&account_id).unwrap();
+        assert_eq!(fingerprint.len(), 2 * ACCOUNT_FINGERPRINT_LEN);
+        assert!(!account.account_id_hex.starts_with(&fingerprint));
+        assert_eq!(
+            fingerprint,
+            account_fingerprint(&AccountKey::from(&root_id))
+        );
+
+        // Importing the same key again gives the same fingerprint.
+        service.remove_account(&account_id).unwrap();
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                entropy,
+                Some("Alice".to_string()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        assert_eq!(
+            service
+                .get_account_fingerprint(&AccountID(account.account_id_hex))
+                .unwrap(),
+            fingerprint
+        );
+
+        // A different key has a different fingerprint.
+        let other = service
+            .create_account(Some("Bob".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        assert_ne!(
+            service
+                .get_account_fingerprint(&AccountID(other.account_id_hex))
+                .unwrap(),
+            fingerprint
+        );
+    }
 }
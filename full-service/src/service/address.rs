@@ -4,18 +4,27 @@
 
 use crate::{
     db::{
-        account::AccountID, assigned_subaddress::AssignedSubaddressModel, b58_decode,
-        models::AssignedSubaddress, WalletDbError,
+        account::{AccountID, AccountModel},
+        assigned_subaddress::AssignedSubaddressModel,
+        b58_decode,
+        models::{Account, AssignedSubaddress},
+        WalletDbError,
     },
     service::WalletService,
 };
+use mc_account_keys::AccountKey;
 use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::RistrettoPublic;
 use mc_fog_report_validation::FogPubkeyResolver;
 
 use diesel::Connection;
 use displaydoc::Display;
 
+/// The maximum number of addresses created by a single call to
+/// assign_addresses_for_account.
+pub const MAX_ADDRESSES_PER_REQUEST: u64 = 1000;
+
 /// Errors for the Address Service.
 #[derive(Display, Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -25,6 +34,12 @@ pub enum AddressServiceError {
 
     /// Diesel Error: {0}
     Diesel(diesel::result::Error),
+
+    /// Error decoding prost: {0}
+    ProstDecode(prost::DecodeError),
+
+    /// At most {0} addresses can be created at once
+    TooManyAddresses(u64),
 }
 
 impl From<WalletDbError> for AddressServiceError {
@@ -39,6 +54,12 @@ impl From<diesel::result::Error> for AddressServiceError {
     }
 }
 
+impl From<prost::DecodeError> for AddressServiceError {
+    fn from(src: prost::DecodeError) -> Self {
+        Self::ProstDecode(src)
+    }
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// addresses.
 pub trait AddressService {
@@ -50,14 +71,64 @@ pub trait AddressService {
         // FIXME: FS-32 - add "sync from block"
     ) -> Result<AssignedSubaddress, AddressServiceError>;
 
+    /// Creates `count` new addresses with contiguous subaddress indices. At
+    /// most MAX_ADDRESSES_PER_REQUEST can be created at once.
+    ///
+    /// If a metadata prefix is provided, each address is annotated with the
+    /// prefix followed by its subaddress index.
+    fn assign_addresses_for_account(
+        &self,
+        account_id: &AccountID,
+        count: u64,
+        metadata_prefix: Option<&str>,
+    ) -> Result<Vec<AssignedSubaddress>, AddressServiceError>;
+
     /// Gets all the addresses for the given account.
+    ///
+    /// Retired addresses are only included if `include_retired` is true.
     fn get_all_addresses_for_account(
         &self,
         account_id: &AccountID,
+        include_retired: bool,
     ) -> Result<Vec<AssignedSubaddress>, AddressServiceError>;
 
+    /// Gets an assigned address by its b58-encoded public address.
+    fn get_address(&self, public_address: &str) -> Result<AssignedSubaddress, AddressServiceError>;
+
+    /// Finds the account, across all accounts in the wallet, to which an
+    /// assigned address belongs.
+    ///
+    /// Returns:
+    /// * (account ID, subaddress index)
+    fn get_account_for_address(
+        &self,
+        public_address: &str,
+    ) -> Result<(AccountID, u64), AddressServiceError>;
+
+    /// Retires or restores an address.
+    ///
+    /// Retired addresses are hidden from listings, but Txos received at them
+    /// are still matched to the account.
+    fn set_address_retired(
+        &self,
+        public_address: &str,
+        retired: bool,
+    ) -> Result<AssignedSubaddress, AddressServiceError>;
+
     /// Verifies whether an address can be decoded from b58.
     fn verify_address(&self, public_address: &str) -> Result<bool, AddressServiceError>;
+
+    /// Gets the public keys of a subaddress of the account, derived from the
+    /// account key, so that external scanners can match Txos to it. The
+    /// subaddress does not need to be assigned.
+    ///
+    /// Returns:
+    /// * (subaddress spend public key, subaddress view public key)
+    fn get_subaddress_keys(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+    ) -> Result<(RistrettoPublic, RistrettoPublic), AddressServiceError>;
 }
 
 impl<T, FPR> AddressService for WalletService<T, FPR>
@@ -87,12 +158,90 @@ where
         )
     }
 
+    fn assign_addresses_for_account(
+        &self,
+        account_id: &AccountID,
+        count: u64,
+        metadata_prefix: Option<&str>,
+    ) -> Result<Vec<AssignedSubaddress>, AddressServiceError> {
+        if count > MAX_ADDRESSES_PER_REQUEST {
+            return Err(AddressServiceError::TooManyAddresses(
+                MAX_ADDRESSES_PER_REQUEST,
+            ));
+        }
+
+        let conn = &self.wallet_db.get_conn()?;
+
+        Ok(
+            conn.transaction::<Vec<AssignedSubaddress>, AddressServiceError, _>(|| {
+                let account = Account::get(account_id, &conn)?;
+
+                let mut addresses = Vec::new();
+                for i in 0..count as i64 {
+                    let metadata = match metadata_prefix {
+                        Some(prefix) => format!("{}{}", prefix, account.next_subaddress_index + i),
+                        None => "".to_string(),
+                    };
+                    let (public_address_b58, _subaddress_index) =
+                        AssignedSubaddress::create_next_for_account(
+                            &account_id.to_string(),
+                            &metadata,
+                            &conn,
+                        )?;
+                    addresses.push(AssignedSubaddress::get(&public_address_b58, &conn)?);
+                }
+
+                Ok(addresses)
+            })?,
+        )
+    }
+
     fn get_all_addresses_for_account(
         &self,
         account_id: &AccountID,
+        include_retired: bool,
     ) -> Result<Vec<AssignedSubaddress>, AddressServiceError> {
-        Ok(AssignedSubaddress::list_all(
-            &account_id.to_string(),
+        Ok(
+            AssignedSubaddress::list_all(&account_id.to_string(), &self.wallet_db.get_conn()?)?
+                .into_iter()
+                .filter(|a| include_retired || !a.retired)
+                .collect(),
+        )
+    }
+
+    fn get_address(&self, public_address: &str) -> Result<AssignedSubaddress, AddressServiceError> {
+        Ok(AssignedSubaddress::get(
+            public_address,
+            &self.wallet_db.get_conn()?,
+        )?)
+    }
+
+    fn get_account_for_address(
+        &self,
+        public_address: &str,
+    ) -> Result<(AccountID, u64), AddressServiceError> {
+        let assigned_subaddress =
+            AssignedSubaddress::get(public_address, &self.wallet_db.get_conn()?)?;
+        Ok((
+            AccountID(assigned_subaddress.account_id_hex),
+            assigned_subaddress.subaddress_index as u64,
+        ))
+    }
+
+    fn set_address_retired(
+        &self,
+        public_address: &str,
+        retired: bool,
+    ) -> Result<AssignedSubaddress, AddressServiceError> {
+        log::info!(
+            self.logger,
+            "Setting retired to {} for address {}",
+            retired,
+            public_address
+        );
+        Ok(AssignedSubaddress::set_retired(
+            public_address,
+            retired,
             &self.wallet_db.get_conn()?,
         )?)
     }
@@ -114,18 +263,40 @@ where
             }
         }
     }
+
+    fn get_subaddress_keys(
+        &self,
+        account_id: &AccountID,
+        subaddress_index: u64,
+    ) -> Result<(RistrettoPublic, RistrettoPublic), AddressServiceError> {
+        let account = Account::get(account_id, &self.wallet_db.get_conn()?)?;
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        let subaddress = account_key.subaddress(subaddress_index);
+
+        Ok((
+            *subaddress.spend_public_key(),
+            *subaddress.view_public_key(),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        db::b58_encode,
-        test_utils::{get_test_ledger, setup_wallet_service},
+        db::{b58_encode, models::TXO_STATUS_UNSPENT},
+        service::{
+            account::{AccountService, CreateAccountOptions},
+            txo::TxoService,
+        },
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, setup_wallet_service, wait_for_sync, MOB,
+        },
     };
-    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_account_keys::PublicAddress;
     use mc_common::logger::{test_with_logger, Logger};
     use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
     use rand::{rngs::StdRng, SeedableRng};
 
     // A properly encoded address should verify.
@@ -173,4 +344,242 @@ mod tests {
             .verify_address(&public_address_b58)
             .expect("Could not verify address"));
     }
+
+    // Creating addresses in bulk should assign contiguous subaddress indices.
+    #[test_with_logger]
+    fn test_assign_addresses_for_account(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .create_account(
+                Some("Merchant".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let first_index = account.next_subaddress_index;
+
+        let addresses = service
+            .assign_addresses_for_account(&account_id, 50, Some("Invoice "))
+            .unwrap();
+        assert_eq!(addresses.len(), 50);
+        for (i, address) in addresses.iter().enumerate() {
+            let subaddress_index = first_index + i as i64;
+            assert_eq!(address.subaddress_index, subaddress_index);
+            assert_eq!(address.comment, format!("Invoice {}", subaddress_index));
+        }
+
+        // The next address continues after the bulk-created ones.
+        let next = service
+            .assign_address_for_account(&account_id, None)
+            .unwrap();
+        assert_eq!(next.subaddress_index, first_index + 50);
+        assert_eq!(
+            service
+                .get_all_addresses_for_account(&account_id, false)
+                .unwrap()
+                .len() as i64,
+            first_index + 51
+        );
+
+        // Requests above the cap are rejected without creating any addresses.
+        match service.assign_addresses_for_account(&account_id, MAX_ADDRESSES_PER_REQUEST + 1, None)
+        {
+            Err(AddressServiceError::TooManyAddresses(1000)) => {}
+            Ok(_) => panic!("Should not create more addresses than the cap"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        assert_eq!(
+            service
+                .get_all_addresses_for_account(&account_id, false)
+                .unwrap()
+                .len() as i64,
+            first_index + 51
+        );
+    }
+
+    // Retired addresses should be hidden from listings, but still match received
+    // Txos.
+    #[test_with_logger]
+    fn test_retire_address(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .create_account(
+                Some("Merchant".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let address = service
+            .assign_address_for_account(&account_id, Some("Invoice 1"))
+            .unwrap();
+        let address_b58 = address.assigned_subaddress_b58.clone();
+
+        let retired = service.set_address_retired(&address_b58, true).unwrap();
+        assert!(retired.retired);
+
+        // Main and change addresses remain listed, the retired address does not.
+        let listed = service
+            .get_all_addresses_for_account(&account_id, false)
+            .unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(!listed
+            .iter()
+            .any(|a| a.assigned_subaddress_b58 == address_b58));
+        let all = service
+            .get_all_addresses_for_account(&account_id, true)
+            .unwrap();
+        assert_eq!(all.len(), 3);
+
+        // A Txo received at the retired address is still matched to it.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![b58_decode(&address_b58).unwrap()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &account_id, 13);
+
+        let txos = service.get_all_txos_for_address(&address_b58).unwrap();
+        assert_eq!(txos.len(), 1);
+        assert_eq!(
+            txos[0].received_to_account.as_ref().unwrap().txo_status,
+            TXO_STATUS_UNSPENT
+        );
+        assert_eq!(
+            txos[0]
+                .received_to_assigned_subaddress
+                .as_ref()
+                .unwrap()
+                .assigned_subaddress_b58,
+            address_b58
+        );
+
+        // Restoring the address lists it again.
+        let restored = service.set_address_retired(&address_b58, false).unwrap();
+        assert!(!restored.retired);
+        let listed = service
+            .get_all_addresses_for_account(&account_id, false)
+            .unwrap();
+        assert_eq!(listed.len(), 3);
+    }
+
+    #[test_with_logger]
+    fn test_get_address(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let account = service
+            .create_account(
+                Some("Merchant".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let address = service
+            .assign_address_for_account(&account_id, Some("Invoice 1"))
+            .unwrap();
+
+        let fetched = service
+            .get_address(&address.assigned_subaddress_b58)
+            .unwrap();
+        assert_eq!(fetched, address);
+        assert_eq!(fetched.comment, "Invoice 1");
+        assert_eq!(fetched.subaddress_index, 2);
+        assert_eq!(fetched.account_id_hex, account.account_id_hex);
+
+        // An address the wallet has not assigned is not found.
+        let unknown_address =
+            b58_encode(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+        match service.get_address(&unknown_address) {
+            Err(AddressServiceError::Database(WalletDbError::AssignedSubaddressNotFound(_))) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Should not find an unassigned address"),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_account_for_address(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let alice = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let bob = service
+            .create_account(Some("Bob".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let alice_address = service
+            .assign_address_for_account(&AccountID(alice.account_id_hex.clone()), None)
+            .unwrap();
+        let bob_address = service
+            .assign_address_for_account(&AccountID(bob.account_id_hex.clone()), None)
+            .unwrap();
+
+        let (account_id, subaddress_index) = service
+            .get_account_for_address(&bob_address.assigned_subaddress_b58)
+            .unwrap();
+        assert_eq!(account_id, AccountID(bob.account_id_hex));
+        assert_eq!(subaddress_index, 2);
+
+        let (account_id, _subaddress_index) = service
+            .get_account_for_address(&alice_address.assigned_subaddress_b58)
+            .unwrap();
+        assert_eq!(account_id, AccountID(alice.account_id_hex));
+
+        let unknown_address =
+            b58_encode(&AccountKey::random(&mut rng).default_subaddress()).unwrap();
+        match service.get_account_for_address(&unknown_address) {
+            Err(AddressServiceError::Database(WalletDbError::AssignedSubaddressNotFound(_))) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Should not find an unassigned address"),
+        }
+    }
+
+    // The subaddress keys should be those of the account key's subaddress,
+    // whether or not the subaddress is assigned.
+    #[test_with_logger]
+    fn test_get_subaddress_keys(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+
+        let account = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let account_id = AccountID(account.account_id_hex.clone());
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key).unwrap();
+
+        for subaddress_index in [0, 1, 7].iter() {
+            let (spend_public_key, view_public_key) = service
+                .get_subaddress_keys(&account_id, *subaddress_index)
+                .unwrap();
+            let subaddress = account_key.subaddress(*subaddress_index);
+            assert_eq!(&spend_public_key, subaddress.spend_public_key());
+            assert_eq!(&view_public_key, subaddress.view_public_key());
+        }
+    }
 }
@@ -8,11 +8,15 @@ use crate::{
         account_txo_status::AccountTxoStatusModel,
         assigned_subaddress::AssignedSubaddressModel,
         models::{
-            Account, AccountTxoStatus, AssignedSubaddress, Txo, TXO_STATUS_ORPHANED,
+            Account, AccountTxoStatus, AssignedSubaddress, TransactionLog, Txo, TxoStatusChange,
+            MOB_TOKEN_ID, TXO_STATUS_DUST, TXO_STATUS_INVALIDATED, TXO_STATUS_ORPHANED,
             TXO_STATUS_PENDING, TXO_STATUS_SECRETED, TXO_STATUS_SPENT, TXO_STATUS_UNSPENT,
+            TX_DIRECTION_SENT, TX_STATUS_PENDING,
         },
+        transaction_log::TransactionLogModel,
         txo::TxoModel,
-        WalletDbError,
+        txo_status_history::TxoStatusChangeModel,
+        WalletDbConnection, WalletDbError,
     },
     service::{
         ledger::{LedgerService, LedgerServiceError},
@@ -26,10 +30,18 @@ use diesel::{
     Connection,
 };
 use displaydoc::Display;
+use mc_account_keys::AccountKey;
 use mc_common::HashMap;
 use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::RistrettoPublic;
 use mc_fog_report_validation::FogPubkeyResolver;
 use mc_ledger_db::Ledger;
+use mc_transaction_core::{
+    constants::{MAX_INPUTS, MINIMUM_FEE},
+    onetime_keys::recover_public_subaddress_spend_key,
+    tx::TxOut,
+};
+use std::convert::TryFrom;
 
 /// Errors for the Address Service.
 #[derive(Display, Debug)]
@@ -49,6 +61,12 @@ pub enum BalanceServiceError {
 
     /// Unexpected Account Txo Status: {0}
     UnexpectedAccountTxoStatus(String),
+
+    /// Error decoding prost: {0}
+    ProstDecode(prost::DecodeError),
+
+    /// Error with crypto keys: {0}
+    CryptoKey(mc_crypto_keys::KeyError),
 }
 
 impl From<WalletDbError> for BalanceServiceError {
@@ -75,16 +93,34 @@ impl From<LedgerServiceError> for BalanceServiceError {
     }
 }
 
+impl From<prost::DecodeError> for BalanceServiceError {
+    fn from(src: prost::DecodeError) -> Self {
+        Self::ProstDecode(src)
+    }
+}
+
+impl From<mc_crypto_keys::KeyError> for BalanceServiceError {
+    fn from(src: mc_crypto_keys::KeyError) -> Self {
+        Self::CryptoKey(src)
+    }
+}
+
 /// The balance object returned by balance services.
 ///
 /// This must be a service object because there is no "Balance" table in our
 /// data model.
 pub struct Balance {
     pub unspent: u64,
+    /// The most that can be sent in a single transaction, which may use at
+    /// most MAX_INPUTS inputs and must pay the minimum fee.
+    pub spendable: u64,
     pub pending: u64,
     pub spent: u64,
     pub secreted: u64,
     pub orphaned: u64,
+    /// Received Txos below the wallet's dust threshold, which are not included
+    /// in unspent.
+    pub dust: u64,
     pub network_block_index: u64,
     pub local_block_index: u64,
     pub synced_blocks: u64,
@@ -110,6 +146,20 @@ pub struct WalletStatus {
     pub account_map: HashMap<AccountID, Account>,
 }
 
+/// The changes to an account's Txos since a cursor, returned by balance
+/// services.
+///
+/// The cursor is the id of a Txo status change, so the delta is derived from
+/// the Txo status history rather than from a stored balance.
+pub struct BalanceDelta {
+    /// Each Txo whose status changed after the cursor, with its latest status
+    /// change, in the order of those changes.
+    pub txo_changes: Vec<(Txo, TxoStatusChange)>,
+
+    /// The id of the latest status change, to pass as the cursor next time.
+    pub last_event_id: i32,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// balances.
 pub trait BalanceService {
@@ -121,9 +171,52 @@ pub trait BalanceService {
         account_id: &AccountID,
     ) -> Result<Balance, BalanceServiceError>;
 
+    /// Gets the balance for a given account in each token it holds, in
+    /// ascending order of token id.
+    ///
+    /// The MOB balance is always included, under MOB_TOKEN_ID, even if the
+    /// account holds no MOB. Transactions only spend MOB, so other tokens
+    /// have no spendable value.
+    fn get_balances_by_token_for_account(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<(u64, Balance)>, BalanceServiceError>;
+
     fn get_balance_for_address(&self, address: &str) -> Result<Balance, BalanceServiceError>;
 
+    /// Gets all the addresses for the given account, each with its balance.
+    ///
+    /// Retired addresses are only included if `include_retired` is true.
+    fn get_addresses_with_balances(
+        &self,
+        account_id: &AccountID,
+        include_retired: bool,
+    ) -> Result<Vec<(AssignedSubaddress, Balance)>, BalanceServiceError>;
+
+    /// Gets the balance an account will have once the pending transactions in
+    /// the wallet land.
+    ///
+    /// The pending inputs of each sent transaction are assumed to be spent,
+    /// and only the change from them is returned to the unspent balance. The
+    /// outputs of pending transactions which pay the account, whether sent by
+    /// the account itself or by another account in the wallet, are added.
+    fn get_projected_balance_for_account(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<u64, BalanceServiceError>;
+
     fn get_wallet_status(&self) -> Result<WalletStatus, BalanceServiceError>;
+
+    /// Gets the Txos of an account whose status changed since the status
+    /// change with id `since_event_id`, or all of them if it is None.
+    ///
+    /// This lets a client which polls frequently keep its balance up to date
+    /// without fetching the whole balance each time.
+    fn get_balance_delta(
+        &self,
+        account_id: &AccountID,
+        since_event_id: Option<i32>,
+    ) -> Result<BalanceDelta, BalanceServiceError>;
 }
 
 impl<T, FPR> BalanceService for WalletService<T, FPR>
@@ -138,8 +231,9 @@ where
         let conn = self.wallet_db.get_conn()?;
         let account_id_hex = &account_id.to_string();
 
-        let (unspent, pending, spent, secreted, orphaned) =
-            Self::get_balance_inner(account_id_hex, &conn)?;
+        let (unspent, pending, spent, secreted, orphaned, dust) =
+            Self::get_balance_inner(account_id_hex, MOB_TOKEN_ID, &conn)?;
+        let spendable = Self::get_spendable_inner(account_id_hex, &conn)?;
 
         let network_block_index = self.get_network_block_index()? + 1;
         let local_block_index = self.ledger_db.num_blocks()?;
@@ -147,16 +241,65 @@ where
 
         Ok(Balance {
             unspent,
+            spendable,
             pending,
             spent,
             secreted,
             orphaned,
+            dust,
             network_block_index,
             local_block_index,
             synced_blocks: account.next_block_index as u64,
         })
     }
 
+    fn get_balances_by_token_for_account(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<(u64, Balance)>, BalanceServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account_id_hex = &account_id.to_string();
+
+        let network_block_index = self.get_network_block_index()? + 1;
+        let local_block_index = self.ledger_db.num_blocks()?;
+        let account = Account::get(account_id, &conn)?;
+
+        let mut token_ids = Txo::list_token_ids(account_id_hex, &conn)?;
+        if !token_ids.contains(&MOB_TOKEN_ID) {
+            token_ids.push(MOB_TOKEN_ID);
+            token_ids.sort_unstable();
+        }
+
+        token_ids
+            .into_iter()
+            .map(|token_id| {
+                let (unspent, pending, spent, secreted, orphaned, dust) =
+                    Self::get_balance_inner(account_id_hex, token_id, &conn)?;
+                let spendable = if token_id == MOB_TOKEN_ID {
+                    Self::get_spendable_inner(account_id_hex, &conn)?
+                } else {
+                    0
+                };
+
+                Ok((
+                    token_id,
+                    Balance {
+                        unspent,
+                        spendable,
+                        pending,
+                        spent,
+                        secreted,
+                        orphaned,
+                        dust,
+                        network_block_index,
+                        local_block_index,
+                        synced_blocks: account.next_block_index as u64,
+                    },
+                ))
+            })
+            .collect()
+    }
+
     fn get_balance_for_address(&self, address: &str) -> Result<Balance, BalanceServiceError> {
         let conn = self.wallet_db.get_conn()?;
 
@@ -164,47 +307,115 @@ where
         let local_block_index = self.ledger_db.num_blocks()?;
 
         Ok(conn.transaction::<Balance, BalanceServiceError, _>(|| {
-            let txos = Txo::list_for_address(&address.to_string(), &conn)?;
             let assigned_address = AssignedSubaddress::get(address, &conn)?;
+            let account = Account::get(&AccountID(assigned_address.account_id_hex.clone()), &conn)?;
+
+            Self::get_address_balance_inner(
+                &assigned_address,
+                network_block_index,
+                local_block_index,
+                account.next_block_index as u64,
+                &conn,
+            )
+        })?)
+    }
+
+    fn get_addresses_with_balances(
+        &self,
+        account_id: &AccountID,
+        include_retired: bool,
+    ) -> Result<Vec<(AssignedSubaddress, Balance)>, BalanceServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let network_block_index = self.get_network_block_index()? + 1;
+        let local_block_index = self.ledger_db.num_blocks()?;
+
+        Ok(
+            conn.transaction::<Vec<(AssignedSubaddress, Balance)>, BalanceServiceError, _>(|| {
+                let account = Account::get(account_id, &conn)?;
+
+                AssignedSubaddress::list_all(&account_id.to_string(), &conn)?
+                    .into_iter()
+                    .filter(|a| include_retired || !a.retired)
+                    .map(|assigned_address| {
+                        Self::get_address_balance_inner(
+                            &assigned_address,
+                            network_block_index,
+                            local_block_index,
+                            account.next_block_index as u64,
+                            &conn,
+                        )
+                        .map(|balance| (assigned_address, balance))
+                    })
+                    .collect()
+            })?,
+        )
+    }
+
+    fn get_projected_balance_for_account(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<u64, BalanceServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let account_id_hex = &account_id.to_string();
+
+        Ok(conn.transaction::<u64, BalanceServiceError, _>(|| {
+            let account = Account::get(account_id, &conn)?;
+            let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+
+            let (unspent, pending, _spent, _secreted, _orphaned, _dust) =
+                Self::get_balance_inner(account_id_hex, MOB_TOKEN_ID, &conn)?;
 
-            let mut unspent = 0;
-            let mut pending = 0;
-            let mut spent = 0;
-            let mut secreted = 0;
-            let mut orphaned = 0;
-
-            for txo in txos {
-                let status = AccountTxoStatus::get(
-                    &assigned_address.account_id_hex,
-                    &txo.txo.txo_id_hex,
-                    &conn,
-                )?;
-                match status.txo_status.as_str() {
-                    TXO_STATUS_UNSPENT => unspent += txo.txo.value,
-                    TXO_STATUS_PENDING => pending += txo.txo.value,
-                    TXO_STATUS_SPENT => spent += txo.txo.value,
-                    TXO_STATUS_SECRETED => secreted += txo.txo.value,
-                    TXO_STATUS_ORPHANED => orphaned += txo.txo.value,
-                    _ => {
-                        return Err(BalanceServiceError::UnexpectedAccountTxoStatus(
-                            status.txo_status,
-                        ))
+            let mut pending_outlays: u128 = 0;
+            let mut pending_receives: u128 = 0;
+            // Another account in the wallet may be paying this one, so check the
+            // pending transactions of every account.
+            for sender in Account::list_all(&conn)? {
+                for (log, associated) in TransactionLog::list_all(&sender.account_id_hex, &conn)? {
+                    if log.direction != TX_DIRECTION_SENT || log.status != TX_STATUS_PENDING {
+                        continue;
+                    }
+
+                    // The value and fee of the account's own transactions leave it, and the
+                    // rest of their pending inputs come back as change.
+                    if sender.account_id_hex == *account_id_hex {
+                        pending_outlays +=
+                            log.value as u64 as u128 + log.fee.unwrap_or(0) as u64 as u128;
+                    }
+
+                    for txo_id_hex in &associated.outputs {
+                        // An output the account has already received is counted as unspent.
+                        match AccountTxoStatus::get(account_id_hex, txo_id_hex, &conn) {
+                            Ok(status) if status.txo_status != TXO_STATUS_SECRETED => continue,
+                            Ok(_) | Err(WalletDbError::AccountTxoStatusNotFound(_)) => {}
+                            Err(e) => return Err(e.into()),
+                        }
+
+                        let txo = Txo::get(txo_id_hex, &conn)?.txo;
+                        let tx_out: TxOut = mc_util_serial::decode(&txo.txo)?;
+                        let subaddress_spend_key = recover_public_subaddress_spend_key(
+                            account_key.view_private_key(),
+                            &RistrettoPublic::try_from(&tx_out.target_key)?,
+                            &RistrettoPublic::try_from(&tx_out.public_key)?,
+                        );
+                        match AssignedSubaddress::find_by_subaddress_spend_public_key(
+                            &subaddress_spend_key,
+                            &conn,
+                        ) {
+                            Ok((_index, recipient_account_id_hex))
+                                if recipient_account_id_hex == *account_id_hex =>
+                            {
+                                pending_receives += txo.value as u64 as u128;
+                            }
+                            Ok(_) | Err(WalletDbError::AssignedSubaddressNotFound(_)) => {}
+                            Err(e) => return Err(e.into()),
+                        }
                     }
                 }
             }
 
-            let account = Account::get(&AccountID(assigned_address.account_id_hex), &conn)?;
-
-            Ok(Balance {
-                unspent: unspent as u64,
-                pending: pending as u64,
-                spent: spent as u64,
-                secreted: secreted as u64,
-                orphaned: orphaned as u64,
-                network_block_index,
-                local_block_index,
-                synced_blocks: account.next_block_index as u64,
-            })
+            Ok((unspent as u128 + pending as u128 + pending_receives)
+                .saturating_sub(pending_outlays) as u64)
         })?)
     }
 
@@ -229,7 +440,8 @@ where
                 let mut account_ids = Vec::new();
                 for account in accounts {
                     let account_id = AccountID(account.account_id_hex.clone());
-                    let balance = Self::get_balance_inner(&account_id.to_string(), &conn)?;
+                    let balance =
+                        Self::get_balance_inner(&account_id.to_string(), MOB_TOKEN_ID, &conn)?;
                     account_map.insert(account_id.clone(), account.clone());
                     unspent += balance.0;
                     pending += balance.1;
@@ -260,6 +472,42 @@ where
             })?,
         )
     }
+
+    fn get_balance_delta(
+        &self,
+        account_id: &AccountID,
+        since_event_id: Option<i32>,
+    ) -> Result<BalanceDelta, BalanceServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let since_event_id = since_event_id.unwrap_or(0);
+
+        Ok(
+            conn.transaction::<BalanceDelta, BalanceServiceError, _>(|| {
+                let changes =
+                    TxoStatusChange::list_since(&account_id.to_string(), since_event_id, &conn)?;
+                let last_event_id = changes.last().map_or(since_event_id, |c| c.id);
+
+                // Only the latest change to each Txo matters to the balance.
+                let mut latest: HashMap<String, TxoStatusChange> = HashMap::default();
+                for change in changes {
+                    latest.insert(change.txo_id_hex.clone(), change);
+                }
+                let mut latest: Vec<TxoStatusChange> = latest.into_iter().map(|(_, c)| c).collect();
+                latest.sort_by_key(|c| c.id);
+
+                let mut txo_changes = Vec::new();
+                for change in latest {
+                    let txo = Txo::get(&change.txo_id_hex, &conn)?.txo;
+                    txo_changes.push((txo, change));
+                }
+
+                Ok(BalanceDelta {
+                    txo_changes,
+                    last_event_id,
+                })
+            })?,
+        )
+    }
 }
 
 impl<T, FPR> WalletService<T, FPR>
@@ -267,29 +515,44 @@ where
     T: BlockchainConnection + UserTxConnection + 'static,
     FPR: FogPubkeyResolver + Send + Sync + 'static,
 {
+    /// The sums of the values of an account's Txos of a token in each status.
+    ///
+    /// Returns:
+    /// * (unspent, pending, spent, secreted, orphaned, dust)
     fn get_balance_inner(
         account_id_hex: &str,
-        conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
-    ) -> Result<(u64, u64, u64, u64, u64), BalanceServiceError> {
+        token_id: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<(u64, u64, u64, u64, u64, u64), BalanceServiceError> {
         let unspent = Txo::list_by_status(account_id_hex, TXO_STATUS_UNSPENT, &conn)?
             .iter()
-            .map(|t| t.value as u128)
+            .filter(|t| t.token_id as u64 == token_id)
+            .map(|t| t.value as u64 as u128)
             .sum::<u128>();
         let spent = Txo::list_by_status(account_id_hex, TXO_STATUS_SPENT, &conn)?
             .iter()
-            .map(|t| t.value as u128)
+            .filter(|t| t.token_id as u64 == token_id)
+            .map(|t| t.value as u64 as u128)
             .sum::<u128>();
         let secreted = Txo::list_by_status(account_id_hex, TXO_STATUS_SECRETED, &conn)?
             .iter()
-            .map(|t| t.value as u128)
+            .filter(|t| t.token_id as u64 == token_id)
+            .map(|t| t.value as u64 as u128)
             .sum::<u128>();
         let orphaned = Txo::list_by_status(account_id_hex, TXO_STATUS_ORPHANED, &conn)?
             .iter()
-            .map(|t| t.value as u128)
+            .filter(|t| t.token_id as u64 == token_id)
+            .map(|t| t.value as u64 as u128)
             .sum::<u128>();
         let pending = Txo::list_by_status(account_id_hex, TXO_STATUS_PENDING, &conn)?
             .iter()
-            .map(|t| t.value as u128)
+            .filter(|t| t.token_id as u64 == token_id)
+            .map(|t| t.value as u64 as u128)
+            .sum::<u128>();
+        let dust = Txo::list_by_status(account_id_hex, TXO_STATUS_DUST, &conn)?
+            .iter()
+            .filter(|t| t.token_id as u64 == token_id)
+            .map(|t| t.value as u64 as u128)
             .sum::<u128>();
 
         let result = (
@@ -298,10 +561,99 @@ where
             spent as u64,
             secreted as u64,
             orphaned as u64,
+            dust as u64,
         );
 
         Ok(result)
     }
+
+    /// The most MOB an account can send in a single transaction.
+    fn get_spendable_inner(
+        account_id_hex: &str,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<u64, BalanceServiceError> {
+        match Txo::select_max_spendable_txos(account_id_hex, None, &[], conn) {
+            Ok(txos) => Ok(spendable_in_one_transaction(&txos)),
+            Err(WalletDbError::NoSpendableTxos) => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The balance of the Txos received at a single assigned address.
+    fn get_address_balance_inner(
+        assigned_address: &AssignedSubaddress,
+        network_block_index: u64,
+        local_block_index: u64,
+        synced_blocks: u64,
+        conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ) -> Result<Balance, BalanceServiceError> {
+        let txos = Txo::list_for_address(&assigned_address.assigned_subaddress_b58, &conn)?;
+
+        let mut unspent: u128 = 0;
+        let mut spendable_txos = Vec::new();
+        let mut pending: u128 = 0;
+        let mut spent: u128 = 0;
+        let mut secreted: u128 = 0;
+        let mut orphaned: u128 = 0;
+        let mut dust: u128 = 0;
+
+        for txo in txos {
+            let status = AccountTxoStatus::get(
+                &assigned_address.account_id_hex,
+                &txo.txo.txo_id_hex,
+                &conn,
+            )?;
+            // Only MOB is reported, as in get_balance_for_account.
+            if txo.txo.token_id as u64 != MOB_TOKEN_ID {
+                continue;
+            }
+            let value = txo.txo.value as u64 as u128;
+            match status.txo_status.as_str() {
+                TXO_STATUS_UNSPENT => {
+                    unspent += value;
+                    if txo.txo.subaddress_index.is_some() && txo.txo.key_image.is_some() {
+                        spendable_txos.push(txo.txo);
+                    }
+                }
+                TXO_STATUS_PENDING => pending += value,
+                TXO_STATUS_SPENT => spent += value,
+                TXO_STATUS_SECRETED => secreted += value,
+                TXO_STATUS_ORPHANED => orphaned += value,
+                TXO_STATUS_DUST => dust += value,
+                TXO_STATUS_INVALIDATED => {}
+                _ => {
+                    return Err(BalanceServiceError::UnexpectedAccountTxoStatus(
+                        status.txo_status,
+                    ))
+                }
+            }
+        }
+
+        Ok(Balance {
+            unspent: unspent as u64,
+            spendable: spendable_in_one_transaction(&spendable_txos),
+            pending: pending as u64,
+            spent: spent as u64,
+            secreted: secreted as u64,
+            orphaned: orphaned as u64,
+            dust: dust as u64,
+            network_block_index,
+            local_block_index,
+            synced_blocks,
+        })
+    }
+}
+
+/// The most that can be sent in a single transaction from the given Txos,
+/// using the most valuable MAX_INPUTS of them and paying the minimum fee.
+fn spendable_in_one_transaction(txos: &[Txo]) -> u64 {
+    let mut values: Vec<u64> = txos.iter().map(|t| t.value as u64).collect();
+    values.sort_unstable_by(|a, b| b.cmp(a));
+    values
+        .iter()
+        .take(MAX_INPUTS as usize)
+        .fold(0u64, |total, value| total.saturating_add(*value))
+        .saturating_sub(MINIMUM_FEE)
 }
 
 #[cfg(test)]
@@ -309,11 +661,20 @@ mod tests {
     use super::*;
     use crate::{
         db::b58_encode,
-        service::{account::AccountService, address::AddressService},
-        test_utils::{get_test_ledger, manually_sync_account, setup_wallet_service, MOB},
+        service::{
+            account::{AccountService, CreateAccountOptions},
+            address::AddressService,
+            transaction::{TransactionBuildOptions, TransactionService},
+        },
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
+            manually_sync_account, setup_wallet_service, wait_for_sync, MOB,
+        },
     };
     use mc_account_keys::{AccountKey, PublicAddress, RootEntropy, RootIdentity};
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
     use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
 
@@ -350,6 +711,7 @@ mod tests {
                 None,
                 None,
                 None,
+                false,
             )
             .expect("Could not import account entropy");
 
@@ -411,4 +773,398 @@ mod tests {
             Err(e) => panic!("Unexpected error {:?}", e),
         }
     }
+    // Each address should be listed with the balance received at it.
+    #[test_with_logger]
+    fn test_addresses_with_balances(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let entropy = RootEntropy::from_random(&mut rng);
+        let account_key = AccountKey::from(&RootIdentity::from(&entropy));
+
+        // Fund the main address and the first assigned address, but not the change
+        // address.
+        let known_recipients: Vec<PublicAddress> =
+            vec![account_key.subaddress(0), account_key.subaddress(2)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let account = service
+            .import_account_from_legacy_root_entropy(
+                hex::encode(&entropy.bytes),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )
+            .expect("Could not import account entropy");
+        let account_id = AccountID(account.account_id_hex.clone());
+
+        let address = service
+            .assign_address_for_account(&account_id, None)
+            .expect("Could not assign address");
+        assert_eq!(address.subaddress_index, 2);
+
+        manually_sync_account(&ledger_db, &service.wallet_db, &account_id, 12, &logger);
+
+        let addresses_with_balances = service
+            .get_addresses_with_balances(&account_id, false)
+            .expect("Could not get addresses with balances");
+        assert_eq!(addresses_with_balances.len(), 3);
+
+        for (address, balance) in addresses_with_balances.iter() {
+            let expected = match address.subaddress_index {
+                0 | 2 => 60_000 * MOB as u64,
+                1 => 0,
+                i => panic!("Unexpected subaddress index {}", i),
+            };
+            assert_eq!(balance.unspent, expected);
+
+            // The listing agrees with querying each address on its own.
+            let address_balance = service
+                .get_balance_for_address(&address.assigned_subaddress_b58)
+                .expect("Could not get balance for address");
+            assert_eq!(balance.unspent, address_balance.unspent);
+            assert_eq!(balance.spendable, address_balance.spendable);
+        }
+
+        let total: u64 = addresses_with_balances
+            .iter()
+            .map(|(_address, balance)| balance.unspent)
+            .sum();
+        let account_balance = service
+            .get_balance_for_account(&account_id)
+            .expect("Could not get balance for account");
+        assert_eq!(total, account_balance.unspent);
+    }
+
+    // The projected balance should exclude the value and fee of a pending send.
+    #[test_with_logger]
+    fn test_projected_balance(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        // With nothing pending, the projection is the unspent balance.
+        let projected = service
+            .get_projected_balance_for_account(&alice_account_id)
+            .unwrap();
+        assert_eq!(projected, 100 * MOB as u64);
+
+        let bob_account_key = AccountKey::random(&mut rng);
+        let (transaction_log, _associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_account_key.default_subaddress()).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 0);
+        assert_eq!(balance.pending, 100 * MOB as u64);
+
+        let projected = service
+            .get_projected_balance_for_account(&alice_account_id)
+            .unwrap();
+        assert_eq!(projected, 100 * MOB as u64 - 42 * MOB as u64 - MINIMUM_FEE);
+
+        // Once the transaction lands, the projection matches the unspent balance.
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, projected);
+        assert_eq!(balance.pending, 0);
+        assert_eq!(
+            service
+                .get_projected_balance_for_account(&alice_account_id)
+                .unwrap(),
+            projected
+        );
+    }
+
+    // The projected balance should include the outputs of pending transactions
+    // which pay the account, from itself or from another account in the wallet.
+    #[test_with_logger]
+    fn test_projected_balance_pending_receives(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone(), alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID::from(&bob_account_key);
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 13);
+
+        let send = |recipient: &PublicAddress, value: i64| {
+            service
+                .build_and_submit(
+                    &alice.account_id_hex,
+                    &b58_encode(recipient).unwrap(),
+                    value.to_string(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    TransactionBuildOptions::default(),
+                    None,
+                )
+                .unwrap()
+        };
+
+        // Bob's projection includes what Alice is sending him.
+        send(
+            &bob_account_key.subaddress(bob.main_subaddress_index as u64),
+            42 * MOB,
+        );
+        assert_eq!(
+            service
+                .get_projected_balance_for_account(&bob_account_id)
+                .unwrap(),
+            42 * MOB as u64
+        );
+        assert_eq!(
+            service
+                .get_projected_balance_for_account(&alice_account_id)
+                .unwrap(),
+            200 * MOB as u64 - 42 * MOB as u64 - MINIMUM_FEE
+        );
+
+        // What Alice sends to herself comes back, so only the fee leaves.
+        send(&alice_public_address, 10 * MOB);
+        assert_eq!(
+            service
+                .get_projected_balance_for_account(&alice_account_id)
+                .unwrap(),
+            200 * MOB as u64 - 42 * MOB as u64 - 2 * MINIMUM_FEE
+        );
+    }
+
+    // An account with more unspent Txos than fit in one transaction can spend
+    // less than its unspent balance.
+    #[test_with_logger]
+    fn test_spendable_balance_fragmented(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // Before receiving anything, nothing is spendable.
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.spendable, 0);
+
+        let num_txos = MAX_INPUTS as usize + 4;
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone(); num_txos],
+            MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, num_txos as u64 * MOB as u64);
+        assert_eq!(balance.spendable, MAX_INPUTS * MOB as u64 - MINIMUM_FEE);
+        assert!(balance.spendable < balance.unspent);
+
+        let address_balance = service
+            .get_balance_for_address(&b58_encode(&alice_public_address).unwrap())
+            .unwrap();
+        assert_eq!(address_balance.spendable, balance.spendable);
+    }
+
+    // A wallet holding only MOB reports its whole balance under the MOB token
+    // id, matching the account balance.
+    #[test_with_logger]
+    fn test_balances_by_token(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // Before receiving anything, the MOB balance is still reported.
+        let balances = service
+            .get_balances_by_token_for_account(&alice_account_id)
+            .unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].0, MOB_TOKEN_ID);
+        assert_eq!(balances[0].1.unspent, 0);
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone(); 2],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        let balances = service
+            .get_balances_by_token_for_account(&alice_account_id)
+            .unwrap();
+        assert_eq!(balances.len(), 1);
+        let (token_id, mob_balance) = &balances[0];
+        assert_eq!(*token_id, MOB_TOKEN_ID);
+        assert_eq!(mob_balance.unspent, 200 * MOB as u64);
+        assert_eq!(mob_balance.unspent, balance.unspent);
+        assert_eq!(mob_balance.spendable, balance.spendable);
+        assert_eq!(mob_balance.pending, balance.pending);
+        assert_eq!(mob_balance.spent, balance.spent);
+    }
+
+    // A balance delta reports only the Txos whose status changed after the
+    // cursor.
+    #[test_with_logger]
+    fn test_balance_delta(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // Nothing has happened yet.
+        let delta = service.get_balance_delta(&alice_account_id, None).unwrap();
+        assert!(delta.txo_changes.is_empty());
+        assert_eq!(delta.last_event_id, 0);
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let delta = service.get_balance_delta(&alice_account_id, None).unwrap();
+        assert_eq!(delta.txo_changes.len(), 1);
+        assert_eq!(delta.txo_changes[0].0.value, 100 * MOB);
+        let cursor = delta.last_event_id;
+
+        // Only the newly received Txo is reported after the cursor.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            42 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let delta = service
+            .get_balance_delta(&alice_account_id, Some(cursor))
+            .unwrap();
+        assert_eq!(delta.txo_changes.len(), 1);
+        let (txo, change) = &delta.txo_changes[0];
+        assert_eq!(txo.value, 42 * MOB);
+        assert_eq!(change.txo_status, TXO_STATUS_UNSPENT);
+        assert!(delta.last_event_id > cursor);
+
+        // Nothing changed since the latest cursor.
+        let delta = service
+            .get_balance_delta(&alice_account_id, Some(delta.last_event_id))
+            .unwrap();
+        assert!(delta.txo_changes.is_empty());
+    }
 }
@@ -5,7 +5,9 @@
 use crate::{
     db::{
         account::AccountID,
-        models::Txo,
+        account_txo_status::AccountTxoStatusModel,
+        models::{AccountTxoStatus, TransactionLog, Txo},
+        transaction_log::TransactionLogModel,
         txo::{TxoID, TxoModel},
         WalletDbError,
     },
@@ -49,6 +51,9 @@ pub enum ConfirmationServiceError {
 
     /// Error with the TxoService: {0}
     TransactionLogService(TransactionLogServiceError),
+
+    /// Txo has no confirmation number: {0}
+    TxoHasNoConfirmation(String),
 }
 
 impl From<WalletDbError> for ConfirmationServiceError {
@@ -98,17 +103,36 @@ pub struct Confirmation {
     pub txo_id: TxoID,
     pub txo_index: u64,
     pub confirmation: TxOutConfirmationNumber,
+    pub value: u64,
+    /// The b58 address the Txo was sent to, if it was sent by this wallet.
+    pub recipient_address: Option<String>,
 }
 
 /// Trait defining the ways in which the wallet can interact with and manage
 /// tonfirmation numbers.
 pub trait ConfirmationService {
     /// Get the confirmations from the outputs in a transaction log.
+    ///
+    /// There is one confirmation per output, so a payment split across
+    /// several outputs returns a confirmation for each, along with the value
+    /// and recipient of that output.
     fn get_confirmations(
         &self,
         transaction_log_id: &str,
     ) -> Result<Vec<Confirmation>, ConfirmationServiceError>;
 
+    /// Get the confirmation number stored for a Txo associated with the given
+    /// account.
+    ///
+    /// Txos minted by this wallet carry a confirmation number, including Txos
+    /// sent from one account in the wallet to another, so the receiving
+    /// account can also re-share it.
+    fn get_txo_confirmation(
+        &self,
+        account_id: &AccountID,
+        txo_id: &TxoID,
+    ) -> Result<Confirmation, ConfirmationServiceError>;
+
     /// Validate the confirmation number with a given Txo.
     fn validate_confirmation(
         &self,
@@ -127,7 +151,9 @@ where
         &self,
         transaction_log_id: &str,
     ) -> Result<Vec<Confirmation>, ConfirmationServiceError> {
-        let (_transaction_log, associated_txos) = self.get_transaction_log(&transaction_log_id)?;
+        let (transaction_log, associated_txos) = self.get_transaction_log(&transaction_log_id)?;
+        let recipient_address = Some(transaction_log.recipient_public_address_b58)
+            .filter(|recipient| !recipient.is_empty());
 
         let mut results = Vec::new();
         for associated_txo in associated_txos.outputs {
@@ -141,6 +167,8 @@ where
                     txo_id: TxoID(txo.txo.txo_id_hex),
                     txo_index,
                     confirmation,
+                    value: txo.txo.value as u64,
+                    recipient_address: recipient_address.clone(),
                 });
             } else {
                 return Err(ConfirmationServiceError::MissingConfirmation(
@@ -151,6 +179,43 @@ where
         Ok(results)
     }
 
+    fn get_txo_confirmation(
+        &self,
+        account_id: &AccountID,
+        txo_id: &TxoID,
+    ) -> Result<Confirmation, ConfirmationServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        // Only accounts associated with the Txo may retrieve its confirmation.
+        AccountTxoStatus::get(&account_id.to_string(), &txo_id.to_string(), &conn)?;
+
+        let txo = self.get_txo(txo_id)?;
+        let confirmation: TxOutConfirmationNumber = match txo.txo.confirmation {
+            Some(confirmation) => mc_util_serial::decode(&confirmation)?,
+            None => {
+                return Err(ConfirmationServiceError::TxoHasNoConfirmation(
+                    txo_id.to_string(),
+                ))
+            }
+        };
+        let pubkey: CompressedRistrettoPublic = mc_util_serial::decode(&txo.txo.public_key)?;
+        let txo_index = self.ledger_db.get_tx_out_index_by_public_key(&pubkey)?;
+
+        // The recipient is recorded on the log of the transaction that sent the Txo.
+        let recipient_address = TransactionLog::select_for_txo(&txo_id.to_string(), &conn)?
+            .into_iter()
+            .map(|transaction_log| transaction_log.recipient_public_address_b58)
+            .find(|recipient| !recipient.is_empty());
+
+        Ok(Confirmation {
+            txo_id: TxoID(txo.txo.txo_id_hex),
+            txo_index,
+            confirmation,
+            value: txo.txo.value as u64,
+            recipient_address,
+        })
+    }
+
     fn validate_confirmation(
         &self,
         account_id: &AccountID,
@@ -168,3 +233,240 @@ where
         )?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::{
+            account::{AccountService, CreateAccountOptions},
+            address::AddressService,
+            transaction::{TransactionBuildOptions, TransactionService},
+        },
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
+            setup_wallet_service, wait_for_sync, MOB,
+        },
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // The recipient of a Txo sent within the wallet should be able to get its
+    // confirmation number.
+    #[test_with_logger]
+    fn test_get_txo_confirmation(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_id = AccountID(bob.account_id_hex.clone());
+        let bob_address = service
+            .assign_address_for_account(&bob_account_id, Some("From Alice"))
+            .unwrap();
+
+        // Alice's received Txo was not minted by this wallet, so has no confirmation.
+        let alice_txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(alice_txos.len(), 1);
+        let alice_txo_id = TxoID(alice_txos[0].txo.txo_id_hex.clone());
+        match service.get_txo_confirmation(&alice_account_id, &alice_txo_id) {
+            Err(ConfirmationServiceError::TxoHasNoConfirmation(_)) => {}
+            Ok(_) => panic!("Should not get a confirmation for a Txo without one"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &bob_address.assigned_subaddress_b58,
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 14);
+
+        // The output was minted by Alice and received by Bob.
+        let sent_confirmations = service
+            .get_confirmations(&transaction_log.transaction_id_hex)
+            .unwrap();
+        assert_eq!(sent_confirmations.len(), 1);
+        let txo_id = TxoID(associated_txos.outputs[0].clone());
+
+        let confirmation = service
+            .get_txo_confirmation(&bob_account_id, &txo_id)
+            .unwrap();
+        assert_eq!(confirmation.txo_id.to_string(), txo_id.to_string());
+        assert_eq!(confirmation.txo_index, sent_confirmations[0].txo_index);
+        assert_eq!(
+            confirmation.confirmation,
+            sent_confirmations[0].confirmation
+        );
+        assert!(service
+            .validate_confirmation(
+                &bob_account_id,
+                &txo_id,
+                &hex::encode(mc_util_serial::encode(&confirmation.confirmation)),
+            )
+            .unwrap());
+
+        // An account which is not associated with the Txo cannot get its confirmation.
+        let carol = service
+            .create_account(
+                Some("Carol's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        match service.get_txo_confirmation(&AccountID(carol.account_id_hex), &txo_id) {
+            Err(ConfirmationServiceError::Database(WalletDbError::AccountTxoStatusNotFound(_))) => {
+            }
+            Ok(_) => panic!("Should not get a confirmation for an unassociated account"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    // A payment split across several outputs should have a confirmation for
+    // each output, which the recipient can validate independently.
+    #[test_with_logger]
+    fn test_get_confirmations_per_output(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_id = AccountID(bob.account_id_hex.clone());
+        let bob_address = service
+            .assign_address_for_account(&bob_account_id, Some("From Alice"))
+            .unwrap();
+
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &bob_address.assigned_subaddress_b58,
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions {
+                    num_outputs: Some("3".to_string()),
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 14);
+
+        let confirmations = service
+            .get_confirmations(&transaction_log.transaction_id_hex)
+            .unwrap();
+        assert_eq!(confirmations.len(), 3);
+        assert_eq!(associated_txos.outputs.len(), 3);
+        assert_eq!(
+            confirmations.iter().map(|c| c.value).sum::<u64>(),
+            42 * MOB as u64
+        );
+
+        for confirmation in confirmations.iter() {
+            assert_eq!(
+                confirmation.recipient_address,
+                Some(bob_address.assigned_subaddress_b58.clone())
+            );
+            assert!(associated_txos
+                .outputs
+                .contains(&confirmation.txo_id.to_string()));
+
+            // Each confirmation validates against its own Txo only.
+            let confirmation_hex = hex::encode(mc_util_serial::encode(&confirmation.confirmation));
+            for other in confirmations.iter() {
+                let valid = service
+                    .validate_confirmation(&bob_account_id, &other.txo_id, &confirmation_hex)
+                    .unwrap();
+                assert_eq!(
+                    valid,
+                    other.txo_id.to_string() == confirmation.txo_id.to_string()
+                );
+            }
+
+            // The recipient sees the same value and recipient for the Txo.
+            let received = service
+                .get_txo_confirmation(&bob_account_id, &confirmation.txo_id)
+                .unwrap();
+            assert_eq!(received.value, confirmation.value);
+            assert_eq!(received.recipient_address, confirmation.recipient_address);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! Service for maintaining the wallet database.
+
+use crate::{error::WalletServiceError, WalletService};
+use mc_common::logger::log;
+use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_fog_report_validation::FogPubkeyResolver;
+
+/// Trait defining maintenance operations on the wallet database.
+pub trait DatabaseService {
+    /// Reclaim the space left in the database file by deleted accounts, Txos
+    /// and transaction logs, and refresh the query planner's statistics.
+    ///
+    /// Returns:
+    /// * (size in bytes before compaction, size in bytes after compaction)
+    fn compact_db(&self) -> Result<(u64, u64), WalletServiceError>;
+}
+
+impl<T, FPR> DatabaseService for WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    fn compact_db(&self) -> Result<(u64, u64), WalletServiceError> {
+        log::info!(self.logger, "Compacting wallet database");
+
+        Ok(self.wallet_db.compact()?)
+    }
+}
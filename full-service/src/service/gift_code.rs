@@ -12,19 +12,28 @@ use crate::{
         account::{AccountID, AccountModel},
         b58_decode, b58_encode,
         gift_code::GiftCodeModel,
-        models::{Account, GiftCode},
+        models::{
+            Account, GiftCode, TransactionLog, TX_DIRECTION_SENT, TX_STATUS_BUILT,
+            TX_STATUS_PENDING,
+        },
+        transaction_log::TransactionLogModel,
         txo::TxoID,
         WalletDbError,
     },
+    error::WalletTransactionBuilderError,
     service::{
         account::AccountServiceError,
         address::{AddressService, AddressServiceError},
-        transaction::{TransactionService, TransactionServiceError},
+        transaction::{TransactionBuildOptions, TransactionService, TransactionServiceError},
+        transaction_builder::WalletTransactionBuilder,
         WalletService,
     },
 };
+use diesel::Connection;
 use displaydoc::Display;
-use mc_account_keys::{AccountKey, RootEntropy, RootIdentity, DEFAULT_SUBADDRESS_INDEX};
+use mc_account_keys::{
+    AccountKey, PublicAddress, RootEntropy, RootIdentity, DEFAULT_SUBADDRESS_INDEX,
+};
 use mc_common::{logger::log, HashSet};
 use mc_connection::{BlockchainConnection, RetryableUserTxConnection, UserTxConnection};
 use mc_crypto_keys::{CompressedRistrettoPublic, RistrettoPublic};
@@ -44,6 +53,11 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, fmt, iter::empty, sync::atomic::Ordering};
 
+/// The maximum number of gift codes funded by a single transaction. Together
+/// with MAX_CHANGE_OUTPUTS, this keeps the transaction within the protocol's
+/// limit of 16 outputs.
+pub const MAX_GIFT_CODES_PER_BATCH: usize = 8;
+
 #[derive(Display, Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum GiftCodeServiceError {
@@ -71,6 +85,9 @@ pub enum GiftCodeServiceError {
     /// Gift Code does not contain enough value to cover the fee: {0}
     InsufficientValueForFee(u64),
 
+    /// Gift Code value {0} minus the fee cannot cover sending {1}
+    InsufficientValueForSend(u64, u64),
+
     /// Unexpected number of Txos in the Gift Code Account: {0}
     UnexpectedNumTxosInGiftCodeAccount(usize),
 
@@ -114,6 +131,13 @@ pub enum GiftCodeServiceError {
     /// Gift Code was removed from the DB prior to claiming
     GiftCodeRemoved,
 
+    /// The transaction funding the Gift Code is no longer pending: {0}
+    GiftCodeTransactionNotPending(String),
+
+    /// A batch must contain between 1 and MAX_GIFT_CODES_PER_BATCH gift codes:
+    /// {0}
+    InvalidGiftCodeBatchSize(usize),
+
     /// Node Not Found
     NodeNotFound,
 
@@ -128,6 +152,9 @@ pub enum GiftCodeServiceError {
 
     /// Error with Account Service
     AddressService(AddressServiceError),
+
+    /// Error building the transaction: {0}
+    TransactionBuilder(WalletTransactionBuilderError),
 }
 
 impl From<WalletDbError> for GiftCodeServiceError {
@@ -208,6 +235,12 @@ impl From<AddressServiceError> for GiftCodeServiceError {
     }
 }
 
+impl From<WalletTransactionBuilderError> for GiftCodeServiceError {
+    fn from(src: WalletTransactionBuilderError) -> Self {
+        Self::TransactionBuilder(src)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct EncodedGiftCode(pub String);
 
@@ -234,7 +267,7 @@ impl fmt::Display for GiftCodeEntropy {
 }
 
 /// Possible states for a Gift Code in relation to accounts in this wallet.
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum GiftCodeStatus {
     /// The Gift Code has been submitted, but has not yet hit the ledger.
     GiftCodeSubmittedPending,
@@ -280,6 +313,19 @@ pub trait GiftCodeService {
         tx_proposal: &TxProposal,
     ) -> Result<GiftCode, GiftCodeServiceError>;
 
+    /// Builds and submits a single transaction funding a gift code for each
+    /// of the given values, all with the same memo, and records the gift
+    /// codes together.
+    ///
+    /// This is cheaper than building each gift code on its own, since the
+    /// gift codes share one fee.
+    fn create_gift_codes_batch(
+        &self,
+        from_account_id: &AccountID,
+        values: &[u64],
+        memo: Option<String>,
+    ) -> Result<Vec<GiftCode>, GiftCodeServiceError>;
+
     /// Get the details for a specific gift code.
     fn get_gift_code(
         &self,
@@ -307,6 +353,19 @@ pub trait GiftCodeService {
         assigned_subaddress_b58: Option<String>,
     ) -> Result<Tx, GiftCodeServiceError>;
 
+    /// Send value from a gift code directly to a public address, without
+    /// claiming it to an account in this wallet first.
+    ///
+    /// Whatever the gift code holds beyond the value and the fee is sent back
+    /// to the gift code account in the same transaction, and returned as a new
+    /// gift code with the same memo.
+    fn send_from_gift_code(
+        &self,
+        gift_code_b58: &EncodedGiftCode,
+        recipient_public_address: &str,
+        value: u64,
+    ) -> Result<(Tx, TxOut, Option<EncodedGiftCode>), GiftCodeServiceError>;
+
     /// Decode the gift code from b58 to its component parts.
     fn decode_gift_code(
         &self,
@@ -317,6 +376,20 @@ pub trait GiftCodeService {
         &self,
         gift_code_b58: &EncodedGiftCode,
     ) -> Result<bool, GiftCodeServiceError>;
+
+    /// Recover the funds of a gift code submitted by this wallet which has not
+    /// been claimed yet, returning them to the account it was sent from.
+    ///
+    /// If the gift code is available, it is claimed to the originating account
+    /// and removed. If its transaction is still pending, that transaction is
+    /// replaced with one spending the same inputs back to the originating
+    /// account, so that at most one of the two lands. The gift code is kept in
+    /// that case, so that it can still be cancelled if the original
+    /// transaction lands first.
+    ///
+    /// A gift code which has already been claimed cannot be cancelled.
+    fn cancel_gift_code(&self, gift_code_b58: &EncodedGiftCode)
+        -> Result<Tx, GiftCodeServiceError>;
 }
 
 impl<T, FPR> GiftCodeService for WalletService<T, FPR>
@@ -366,6 +439,7 @@ where
             fee.map(|f| f.to_string()),
             tombstone_block.map(|t| t.to_string()),
             max_spendable_value.map(|f| f.to_string()),
+            TransactionBuildOptions::default(),
         )?;
 
         if tx_proposal.outlay_index_to_tx_out_index.len() != 1 {
@@ -374,20 +448,13 @@ where
 
         let outlay_index = tx_proposal.outlay_index_to_tx_out_index[&0];
         let tx_out = tx_proposal.tx.prefix.outputs[outlay_index].clone();
-        let txo_public_key = tx_out.public_key;
-
-        let proto_tx_pubkey: mc_api::external::CompressedRistretto = (&txo_public_key).into();
-
-        let mut gift_code_payload = mc_mobilecoind_api::printable::TransferPayload::new();
-        gift_code_payload.set_entropy(gift_code_root_entropy.bytes.to_vec());
-        gift_code_payload.set_tx_out_public_key(proto_tx_pubkey);
-        gift_code_payload.set_memo(memo.unwrap_or_else(|| "".to_string()));
-
-        let mut gift_code_wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
-        gift_code_wrapper.set_transfer_payload(gift_code_payload);
-        let gift_code_b58 = gift_code_wrapper.b58_encode()?;
+        let gift_code_b58 = encode_gift_code(
+            &gift_code_root_entropy,
+            &tx_out.public_key,
+            &memo.unwrap_or_else(|| "".to_string()),
+        )?;
 
-        Ok((tx_proposal, EncodedGiftCode(gift_code_b58)))
+        Ok((tx_proposal, gift_code_b58))
     }
 
     fn submit_gift_code(
@@ -423,6 +490,83 @@ where
         )?)
     }
 
+    fn create_gift_codes_batch(
+        &self,
+        from_account_id: &AccountID,
+        values: &[u64],
+        memo: Option<String>,
+    ) -> Result<Vec<GiftCode>, GiftCodeServiceError> {
+        if values.is_empty() || values.len() > MAX_GIFT_CODES_PER_BATCH {
+            return Err(GiftCodeServiceError::InvalidGiftCodeBatchSize(values.len()));
+        }
+        let memo = memo.unwrap_or_else(|| "".to_string());
+
+        // Each gift code is its own onetime account, as in build_gift_code, so
+        // that handing out one gift code does not give away the others.
+        let mut rng = rand::thread_rng();
+        let gift_code_root_entropies: Vec<RootEntropy> = values
+            .iter()
+            .map(|_| RootEntropy::from_random(&mut rng))
+            .collect();
+
+        let mut builder = WalletTransactionBuilder::new(
+            from_account_id.to_string(),
+            self.wallet_db.clone(),
+            self.ledger_db.clone(),
+            self.fog_resolver_factory.clone(),
+            self.logger.clone(),
+        );
+        builder.set_allow_multiple_recipients(true)?;
+        for (root_entropy, value) in gift_code_root_entropies.iter().zip(values) {
+            let gift_code_account_key = AccountKey::from(&RootIdentity::from(root_entropy));
+            builder.add_recipient(gift_code_account_key.default_subaddress(), *value)?;
+        }
+        builder.select_txos(None)?;
+        builder.set_tombstone(0)?;
+        let tx_proposal = builder.build()?;
+
+        let mut gift_code_outputs = Vec::new();
+        for (i, root_entropy) in gift_code_root_entropies.iter().enumerate() {
+            let tx_out =
+                tx_proposal.tx.prefix.outputs[tx_proposal.outlay_index_to_tx_out_index[&i]].clone();
+            let gift_code_b58 = encode_gift_code(root_entropy, &tx_out.public_key, &memo)?;
+            gift_code_outputs.push((gift_code_b58, root_entropy, tx_out));
+        }
+
+        log::info!(
+            self.logger,
+            "submitting transaction for {} gift codes...",
+            values.len()
+        );
+        self.submit_transaction(
+            tx_proposal,
+            Some(json!({ "gift_code_memo": memo }).to_string()),
+            Some(from_account_id.to_string()),
+        )?;
+
+        let conn = self.wallet_db.get_conn()?;
+        Ok(
+            conn.transaction::<Vec<GiftCode>, GiftCodeServiceError, _>(|| {
+                let mut gift_codes = Vec::new();
+                for ((gift_code_b58, root_entropy, tx_out), value) in
+                    gift_code_outputs.iter().zip(values)
+                {
+                    gift_codes.push(GiftCode::create(
+                        gift_code_b58,
+                        root_entropy,
+                        &tx_out.public_key,
+                        *value as i64,
+                        memo.clone(),
+                        from_account_id,
+                        &TxoID::from(tx_out),
+                        &conn,
+                    )?);
+                }
+                Ok(gift_codes)
+            })?,
+        )
+    }
+
     fn get_gift_code(
         &self,
         gift_code_b58: &EncodedGiftCode,
@@ -522,8 +666,6 @@ where
         let gift_value = gift_value.unwrap();
 
         let decoded_gift_code = self.decode_gift_code(&gift_code_b58)?;
-        let gift_account_key =
-            AccountKey::from(&RootIdentity::from(&decoded_gift_code.root_entropy));
 
         let default_subaddress = if assigned_subaddress_b58.is_some() {
             assigned_subaddress_b58.ok_or(GiftCodeServiceError::AccountNotFound)
@@ -546,6 +688,224 @@ where
             ));
         }
 
+        let (tx, _tx_outs) = self.spend_gift_code_txo(
+            &decoded_gift_code,
+            &[(recipient_public_address, gift_value as u64 - MINIMUM_FEE)],
+        )?;
+
+        Ok(tx)
+    }
+
+    fn send_from_gift_code(
+        &self,
+        gift_code_b58: &EncodedGiftCode,
+        recipient_public_address: &str,
+        value: u64,
+    ) -> Result<(Tx, TxOut, Option<EncodedGiftCode>), GiftCodeServiceError> {
+        let (status, gift_value, _memo) = self.check_gift_code_status(gift_code_b58)?;
+
+        match status {
+            GiftCodeStatus::GiftCodeClaimed => return Err(GiftCodeServiceError::GiftCodeClaimed),
+            GiftCodeStatus::GiftCodeSubmittedPending => {
+                return Err(GiftCodeServiceError::GiftCodeNotYetAvailable)
+            }
+            GiftCodeStatus::GiftCodeAvailable => {}
+        }
+
+        let gift_value = gift_value.unwrap() as u64;
+        let remainder = gift_value
+            .checked_sub(MINIMUM_FEE)
+            .and_then(|v| v.checked_sub(value))
+            .ok_or(GiftCodeServiceError::InsufficientValueForSend(
+                gift_value, value,
+            ))?;
+
+        let decoded_gift_code = self.decode_gift_code(&gift_code_b58)?;
+        let gift_account_key =
+            AccountKey::from(&RootIdentity::from(&decoded_gift_code.root_entropy));
+
+        let mut outputs = vec![(b58_decode(recipient_public_address)?, value)];
+        if remainder > 0 {
+            outputs.push((gift_account_key.default_subaddress(), remainder));
+        }
+        let (tx, mut tx_outs) = self.spend_gift_code_txo(&decoded_gift_code, &outputs)?;
+
+        let remaining_gift_code = if remainder > 0 {
+            Some(encode_gift_code(
+                &decoded_gift_code.root_entropy,
+                &tx_outs[1].public_key,
+                &decoded_gift_code.memo,
+            )?)
+        } else {
+            None
+        };
+
+        Ok((tx, tx_outs.swap_remove(0), remaining_gift_code))
+    }
+
+    fn decode_gift_code(
+        &self,
+        gift_code_b58: &EncodedGiftCode,
+    ) -> Result<DecodedGiftCode, GiftCodeServiceError> {
+        let wrapper =
+            mc_mobilecoind_api::printable::PrintableWrapper::b58_decode(gift_code_b58.to_string())?;
+        let transfer_payload = wrapper.get_transfer_payload();
+
+        let mut entropy = [0u8; 32];
+        entropy.copy_from_slice(transfer_payload.get_entropy());
+        let root_entropy = RootEntropy::from(&entropy);
+
+        let txo_public_key =
+            CompressedRistrettoPublic::try_from(transfer_payload.get_tx_out_public_key())?;
+
+        Ok(DecodedGiftCode {
+            root_entropy,
+            txo_public_key,
+            memo: transfer_payload.get_memo().to_string(),
+        })
+    }
+
+    fn remove_gift_code(
+        &self,
+        gift_code_b58: &EncodedGiftCode,
+    ) -> Result<bool, GiftCodeServiceError> {
+        log::info!(self.logger, "Deleting gift code {}", gift_code_b58,);
+
+        let conn = self.wallet_db.get_conn()?;
+        GiftCode::get(gift_code_b58, &conn)?.delete(&conn)?;
+        Ok(true)
+    }
+
+    fn cancel_gift_code(
+        &self,
+        gift_code_b58: &EncodedGiftCode,
+    ) -> Result<Tx, GiftCodeServiceError> {
+        let gift_code = self.get_gift_code(gift_code_b58)?;
+        let from_account_id = AccountID(gift_code.account_id_hex.clone());
+
+        let (status, _value, _memo) = self.check_gift_code_status(gift_code_b58)?;
+        match status {
+            GiftCodeStatus::GiftCodeClaimed => Err(GiftCodeServiceError::GiftCodeClaimed),
+            GiftCodeStatus::GiftCodeAvailable => {
+                let tx = self.claim_gift_code(gift_code_b58, &from_account_id, None)?;
+                self.remove_gift_code(gift_code_b58)?;
+                log::info!(
+                    self.logger,
+                    "Cancelled gift code {}, claiming it back to {}",
+                    gift_code_b58,
+                    from_account_id
+                );
+                Ok(tx)
+            }
+            GiftCodeStatus::GiftCodeSubmittedPending => {
+                let tx = self.replace_gift_code_transaction(&gift_code)?;
+                log::info!(
+                    self.logger,
+                    "Cancelled pending gift code {}, returning its inputs to {}",
+                    gift_code_b58,
+                    from_account_id
+                );
+                Ok(tx)
+            }
+        }
+    }
+}
+
+/// Encode a gift code for the Txo with the given public key, which belongs to
+/// the main subaddress of the account with the given entropy.
+fn encode_gift_code(
+    root_entropy: &RootEntropy,
+    txo_public_key: &CompressedRistrettoPublic,
+    memo: &str,
+) -> Result<EncodedGiftCode, GiftCodeServiceError> {
+    let proto_tx_pubkey: mc_api::external::CompressedRistretto = txo_public_key.into();
+
+    let mut gift_code_payload = mc_mobilecoind_api::printable::TransferPayload::new();
+    gift_code_payload.set_entropy(root_entropy.bytes.to_vec());
+    gift_code_payload.set_tx_out_public_key(proto_tx_pubkey);
+    gift_code_payload.set_memo(memo.to_string());
+
+    let mut gift_code_wrapper = mc_mobilecoind_api::printable::PrintableWrapper::new();
+    gift_code_wrapper.set_transfer_payload(gift_code_payload);
+    Ok(EncodedGiftCode(gift_code_wrapper.b58_encode()?))
+}
+
+impl<T, FPR> WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    /// Replace the pending transaction which funds a gift code with one
+    /// spending the same inputs back to the main subaddress of the account it
+    /// was sent from.
+    fn replace_gift_code_transaction(
+        &self,
+        gift_code: &GiftCode,
+    ) -> Result<Tx, GiftCodeServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let original = TransactionLog::select_for_txo(&gift_code.txo_id_hex, &conn)?
+            .into_iter()
+            .find(|log| {
+                log.direction == TX_DIRECTION_SENT
+                    && (log.status == TX_STATUS_BUILT || log.status == TX_STATUS_PENDING)
+            })
+            .ok_or_else(|| {
+                GiftCodeServiceError::GiftCodeTransactionNotPending(gift_code.gift_code_b58.clone())
+            })?;
+        // The original stays pending until it or its replacement lands, so it
+        // may already have been replaced.
+        if original
+            .list_children(&conn)?
+            .iter()
+            .any(|child| child.status == TX_STATUS_BUILT || child.status == TX_STATUS_PENDING)
+        {
+            return Err(GiftCodeServiceError::GiftCodeTransactionNotPending(
+                gift_code.gift_code_b58.clone(),
+            ));
+        }
+
+        let account = Account::get(&AccountID(gift_code.account_id_hex.clone()), &conn)?;
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+        let main_address = account_key.subaddress(account.main_subaddress_index as u64);
+
+        let associated = original.get_associated_txos(&conn)?;
+        let mut builder = WalletTransactionBuilder::new(
+            account.account_id_hex.clone(),
+            self.wallet_db.clone(),
+            self.ledger_db.clone(),
+            self.fog_resolver_factory.clone(),
+            self.logger.clone(),
+        );
+        builder.set_replacement_txos(&associated.inputs)?;
+        builder.add_recipient(main_address, original.value as u64)?;
+        builder.set_fee(original.fee.map_or(MINIMUM_FEE, |f| f as u64))?;
+        builder.set_tombstone(0)?;
+        let tx_proposal = builder.build()?;
+        let tx = tx_proposal.tx.clone();
+
+        let (transaction_log, _associated_txos) = self
+            .submit_transaction(
+                tx_proposal,
+                Some(json!({ "cancelled_gift_code_memo": gift_code.memo }).to_string()),
+                Some(account.account_id_hex.clone()),
+            )?
+            .ok_or(TransactionServiceError::MissingAccountOnSubmit)?;
+        transaction_log.set_parent(&original.transaction_id_hex, &conn)?;
+        Ok(tx)
+    }
+
+    /// Build and submit a transaction spending the gift code Txo to the given
+    /// outputs, paying the minimum fee.
+    ///
+    /// Returns the transaction and its outputs, in the order given.
+    fn spend_gift_code_txo(
+        &self,
+        decoded_gift_code: &DecodedGiftCode,
+        outputs: &[(PublicAddress, u64)],
+    ) -> Result<(Tx, Vec<TxOut>), GiftCodeServiceError> {
+        let gift_account_key =
+            AccountKey::from(&RootIdentity::from(&decoded_gift_code.root_entropy));
+
         let gift_txo_index = self
             .ledger_db
             .get_tx_out_index_by_public_key(&decoded_gift_code.txo_public_key)?;
@@ -596,11 +956,12 @@ where
 
         let mut transaction_builder = TransactionBuilder::new(fog_resolver);
         transaction_builder.add_input(input_credentials);
-        let (_tx_out, _confirmation) = transaction_builder.add_output(
-            gift_value as u64 - MINIMUM_FEE,
-            &recipient_public_address,
-            &mut rng,
-        )?;
+        let mut tx_outs = Vec::new();
+        for (recipient, value) in outputs {
+            let (tx_out, _confirmation) =
+                transaction_builder.add_output(*value, recipient, &mut rng)?;
+            tx_outs.push(tx_out);
+        }
 
         transaction_builder.set_fee(MINIMUM_FEE);
 
@@ -630,40 +991,7 @@ where
             block_index
         );
 
-        Ok(tx)
-    }
-
-    fn decode_gift_code(
-        &self,
-        gift_code_b58: &EncodedGiftCode,
-    ) -> Result<DecodedGiftCode, GiftCodeServiceError> {
-        let wrapper =
-            mc_mobilecoind_api::printable::PrintableWrapper::b58_decode(gift_code_b58.to_string())?;
-        let transfer_payload = wrapper.get_transfer_payload();
-
-        let mut entropy = [0u8; 32];
-        entropy.copy_from_slice(transfer_payload.get_entropy());
-        let root_entropy = RootEntropy::from(&entropy);
-
-        let txo_public_key =
-            CompressedRistrettoPublic::try_from(transfer_payload.get_tx_out_public_key())?;
-
-        Ok(DecodedGiftCode {
-            root_entropy,
-            txo_public_key,
-            memo: transfer_payload.get_memo().to_string(),
-        })
-    }
-
-    fn remove_gift_code(
-        &self,
-        gift_code_b58: &EncodedGiftCode,
-    ) -> Result<bool, GiftCodeServiceError> {
-        log::info!(self.logger, "Deleting gift code {}", gift_code_b58,);
-
-        let conn = self.wallet_db.get_conn()?;
-        GiftCode::get(gift_code_b58, &conn)?.delete(&conn)?;
-        Ok(true)
+        Ok((tx, tx_outs))
     }
 }
 
@@ -671,13 +999,16 @@ where
 mod tests {
     use super::*;
     use crate::{
-        service::{account::AccountService, balance::BalanceService},
+        db::models::TX_STATUS_FAILED,
+        service::{
+            account::{AccountService, CreateAccountOptions},
+            balance::BalanceService,
+        },
         test_utils::{
             add_block_to_ledger_db, add_block_with_tx, add_block_with_tx_proposal, get_test_ledger,
             manually_sync_account, setup_wallet_service, MOB,
         },
     };
-    use mc_account_keys::PublicAddress;
     use mc_common::logger::{test_with_logger, Logger};
     use mc_crypto_rand::rand_core::RngCore;
     use mc_transaction_core::ring_signature::KeyImage;
@@ -694,7 +1025,10 @@ mod tests {
 
         // Create our main account for the wallet
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Add a block with a transaction for Alice
@@ -808,7 +1142,10 @@ mod tests {
         // Claim the gift code to another account
         log::info!(logger, "Creating new account to receive gift code");
         let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
         manually_sync_account(
             &ledger_db,
@@ -869,7 +1206,10 @@ mod tests {
 
         // Create our main account for the wallet
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Add a block with a transaction for Alice
@@ -960,4 +1300,313 @@ mod tests {
             .expect("Could not list gift codes");
         assert_eq!(gift_codes.len(), 0);
     }
+
+    #[test_with_logger]
+    fn test_send_from_gift_code(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.to_string());
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &alice_account_id,
+            13,
+            &logger,
+        );
+
+        // Alice makes a 2 MOB gift code.
+        let (tx_proposal, gift_code_b58) = service
+            .build_gift_code(
+                &alice_account_id,
+                2 * MOB as u64,
+                Some("Pass it on".to_string()),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        service
+            .submit_gift_code(&alice_account_id, &gift_code_b58, &tx_proposal)
+            .unwrap();
+        add_block_with_tx_proposal(&mut ledger_db, tx_proposal);
+
+        // Carol is a third party, so that her balance shows what was forwarded.
+        let carol = service
+            .create_account(
+                Some("Carol's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let carol_account_key: AccountKey = mc_util_serial::decode(&carol.account_key).unwrap();
+        let carol_account_id = AccountID(carol.account_id_hex.to_string());
+        let carol_b58 =
+            b58_encode(&carol_account_key.subaddress(carol.main_subaddress_index as u64)).unwrap();
+
+        // Forward 1 MOB to Carol. The rest, less the fee, stays in a new gift code.
+        let (tx, tx_out, remaining_gift_code) = service
+            .send_from_gift_code(&gift_code_b58, &carol_b58, MOB as u64)
+            .unwrap();
+        assert!(tx.prefix.outputs.contains(&tx_out));
+        let remaining_gift_code = remaining_gift_code.unwrap();
+        add_block_with_tx(&mut ledger_db, tx);
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &carol_account_id,
+            15,
+            &logger,
+        );
+
+        let carol_balance = service.get_balance_for_account(&carol_account_id).unwrap();
+        assert_eq!(carol_balance.unspent, MOB as u64);
+
+        let (status, _value, _memo) = service.check_gift_code_status(&gift_code_b58).unwrap();
+        assert_eq!(status, GiftCodeStatus::GiftCodeClaimed);
+        let (status, value, memo) = service
+            .check_gift_code_status(&remaining_gift_code)
+            .unwrap();
+        assert_eq!(status, GiftCodeStatus::GiftCodeAvailable);
+        assert_eq!(value, Some(MOB - MINIMUM_FEE as i64));
+        assert_eq!(memo, "Pass it on");
+
+        // The remaining gift code cannot cover its whole value plus the fee.
+        match service.send_from_gift_code(&remaining_gift_code, &carol_b58, MOB as u64) {
+            Err(GiftCodeServiceError::InsufficientValueForSend(_, _)) => {}
+            Ok(_) => panic!("Should not send more than the gift code holds"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Sending exactly what remains after the fee leaves no gift code behind.
+        let (tx, _tx_out, remaining) = service
+            .send_from_gift_code(
+                &remaining_gift_code,
+                &carol_b58,
+                (MOB - 2 * MINIMUM_FEE as i64) as u64,
+            )
+            .unwrap();
+        assert!(remaining.is_none());
+        add_block_with_tx(&mut ledger_db, tx);
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &carol_account_id,
+            16,
+            &logger,
+        );
+        let carol_balance = service.get_balance_for_account(&carol_account_id).unwrap();
+        assert_eq!(carol_balance.unspent, 2 * MOB as u64 - 2 * MINIMUM_FEE);
+    }
+
+    #[test_with_logger]
+    fn test_cancel_pending_gift_code(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_public_address =
+            &alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        let alice_account_id = AccountID(alice.account_id_hex.to_string());
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &alice_account_id,
+            13,
+            &logger,
+        );
+
+        // Build and submit a gift code, but do not hand it out.
+        let (tx_proposal, gift_code_b58) = service
+            .build_gift_code(
+                &alice_account_id,
+                2 * MOB as u64,
+                Some("Gift code for Bob".to_string()),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let gift_code = service
+            .submit_gift_code(&alice_account_id, &gift_code_b58, &tx_proposal)
+            .unwrap();
+
+        // Cancelling the pending gift code replaces its transaction. Either could
+        // still land, so the original stays pending, and cannot be replaced again.
+        let tx = service.cancel_gift_code(&gift_code_b58).unwrap();
+        let conn = service.wallet_db.get_conn().unwrap();
+        let logs = TransactionLog::select_for_txo(&gift_code.txo_id_hex, &conn).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].status, TX_STATUS_PENDING);
+        match service.cancel_gift_code(&gift_code_b58) {
+            Err(GiftCodeServiceError::GiftCodeTransactionNotPending(_)) => {}
+            Ok(_) => panic!("Should not be able to replace the transaction twice"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Once the replacement lands, the funds are back, less its fee.
+        add_block_with_tx(&mut ledger_db, tx);
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &alice_account_id,
+            14,
+            &logger,
+        );
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 100 * MOB as u64 - MINIMUM_FEE);
+
+        // The gift code never landed, and its transaction failed once the
+        // replacement landed.
+        let logs = TransactionLog::select_for_txo(&gift_code.txo_id_hex, &conn).unwrap();
+        assert_eq!(logs[0].status, TX_STATUS_FAILED);
+        let (status, _value, _memo) = service.check_gift_code_status(&gift_code_b58).unwrap();
+        assert_eq!(status, GiftCodeStatus::GiftCodeSubmittedPending);
+        match service.cancel_gift_code(&gift_code_b58) {
+            Err(GiftCodeServiceError::GiftCodeTransactionNotPending(_)) => {}
+            Ok(_) => panic!("Should not be able to cancel the gift code twice"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_create_gift_codes_batch(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_public_address =
+            &alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        let alice_account_id = AccountID(alice.account_id_hex.to_string());
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &alice_account_id,
+            13,
+            &logger,
+        );
+
+        // Fund five gift codes in a single transaction.
+        let values: Vec<u64> = (1..=5).map(|i| i * MOB as u64).collect();
+        let gift_codes = service
+            .create_gift_codes_batch(&alice_account_id, &values, Some("Party favors".to_string()))
+            .unwrap();
+        assert_eq!(gift_codes.len(), 5);
+        assert_eq!(service.list_gift_codes().unwrap().len(), 5);
+
+        let conn = service.wallet_db.get_conn().unwrap();
+        let transaction_logs =
+            TransactionLog::select_for_txo(&gift_codes[0].txo_id_hex, &conn).unwrap();
+        assert_eq!(transaction_logs.len(), 1);
+        let tx: Tx = mc_util_serial::decode(&transaction_logs[0].tx.clone().unwrap()).unwrap();
+        for gift_code in gift_codes.iter().skip(1) {
+            assert_eq!(
+                TransactionLog::select_for_txo(&gift_code.txo_id_hex, &conn).unwrap(),
+                transaction_logs
+            );
+        }
+
+        add_block_with_tx(&mut ledger_db, tx);
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &alice_account_id,
+            14,
+            &logger,
+        );
+
+        // The gift codes share one fee.
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 85 * MOB as u64 - MINIMUM_FEE);
+
+        // Each gift code can be claimed on its own.
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_id = AccountID(bob.account_id_hex.clone());
+        for (i, (gift_code, value)) in gift_codes.iter().zip(&values).enumerate() {
+            let gift_code_b58 = EncodedGiftCode(gift_code.gift_code_b58.clone());
+            let (status, gift_code_value_opt, memo) =
+                service.check_gift_code_status(&gift_code_b58).unwrap();
+            assert_eq!(status, GiftCodeStatus::GiftCodeAvailable);
+            assert_eq!(gift_code_value_opt, Some(*value as i64));
+            assert_eq!(memo, "Party favors");
+
+            let tx = service
+                .claim_gift_code(&gift_code_b58, &bob_account_id, None)
+                .unwrap();
+            add_block_with_tx(&mut ledger_db, tx);
+            manually_sync_account(
+                &ledger_db,
+                &service.wallet_db,
+                &bob_account_id,
+                15 + i as u64,
+                &logger,
+            );
+
+            let (status, _value, _memo) = service.check_gift_code_status(&gift_code_b58).unwrap();
+            assert_eq!(status, GiftCodeStatus::GiftCodeClaimed);
+        }
+
+        let bob_balance = service.get_balance_for_account(&bob_account_id).unwrap();
+        assert_eq!(bob_balance.unspent, 15 * MOB as u64 - 5 * MINIMUM_FEE);
+    }
 }
@@ -10,17 +10,27 @@ use crate::{
     },
     WalletService,
 };
+use mc_common::{logger::log, HashMap};
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
-use mc_ledger_db::Ledger;
+use mc_ledger_db::{Ledger, LedgerDB};
 use mc_ledger_sync::NetworkState;
 use mc_transaction_core::{
+    constants::MINIMUM_FEE,
     tx::{Tx, TxOut},
     Block, BlockContents,
 };
 
 use crate::db::WalletDbError;
 use displaydoc::Display;
+use std::sync::{Arc, Mutex};
+
+/// The maximum number of blocks returned by a single call to
+/// get_block_objects_since.
+pub const MAX_BLOCKS_PER_REQUEST: u64 = 100;
+
+/// The maximal number of block timestamps kept in the BlockTimestampCache.
+const MAX_CACHED_BLOCK_TIMESTAMPS: usize = 10_000;
 
 /// Errors for the Address Service.
 #[derive(Display, Debug)]
@@ -38,6 +48,12 @@ pub enum LedgerServiceError {
     /// No transaction object associated with this transaction. Note, received
     /// transactions do not have transaction objects.
     NoTxInTransaction,
+
+    /// Txo {0} was not created by a transaction built by this wallet
+    TxoNotBuiltByWallet(String),
+
+    /// Cannot query the network while running offline
+    Offline,
 }
 
 impl From<mc_ledger_db::Error> for LedgerServiceError {
@@ -58,6 +74,86 @@ impl From<WalletDbError> for LedgerServiceError {
     }
 }
 
+/// Block timestamps read from the ledger, so that listing many Txos received
+/// in the same blocks does not read each block's signature repeatedly.
+#[derive(Clone, Default)]
+pub struct BlockTimestampCache {
+    inner: Arc<Mutex<BlockTimestampCacheInner>>,
+}
+
+#[derive(Default)]
+struct BlockTimestampCacheInner {
+    /// Cached timestamps, by block index. None if the block is not signed.
+    timestamps: HashMap<u64, Option<u64>>,
+
+    /// The number of block signatures read from the ledger.
+    num_ledger_reads: u64,
+}
+
+impl BlockTimestampCache {
+    /// Get the time, in seconds since the Unix epoch, at which a block was
+    /// signed by consensus, reading it from the ledger if it is not cached.
+    ///
+    /// Returns None if the ledger has no signature for the block, as for blocks
+    /// which were not downloaded with their signatures.
+    pub fn get_block_timestamp(
+        &self,
+        ledger_db: &LedgerDB,
+        block_index: u64,
+    ) -> Result<Option<u64>, mc_ledger_db::Error> {
+        if let Some(timestamp) = self
+            .inner
+            .lock()
+            .expect("mutex poisoned")
+            .timestamps
+            .get(&block_index)
+        {
+            return Ok(*timestamp);
+        }
+
+        // A block beyond the tip has no timestamp yet, so is not cached.
+        if block_index >= ledger_db.num_blocks()? {
+            return Err(mc_ledger_db::Error::NotFound);
+        }
+        let timestamp = match ledger_db.get_block_signature(block_index) {
+            Ok(signature) => Some(signature.signed_at()),
+            Err(mc_ledger_db::Error::NotFound) => None,
+            Err(err) => return Err(err),
+        };
+
+        let mut inner = self.inner.lock().expect("mutex poisoned");
+        inner.num_ledger_reads += 1;
+        if inner.timestamps.len() >= MAX_CACHED_BLOCK_TIMESTAMPS {
+            inner.timestamps.clear();
+        }
+        inner.timestamps.insert(block_index, timestamp);
+        Ok(timestamp)
+    }
+
+    /// The number of block signatures read from the ledger so far.
+    pub fn num_ledger_reads(&self) -> u64 {
+        self.inner.lock().expect("mutex poisoned").num_ledger_reads
+    }
+}
+
+/// The state of the MobileCoin network, as seen by this wallet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkStatus {
+    /// The block count reported by the consensus peers, as in WalletStatus.
+    pub network_block_index: u64,
+
+    /// The block count of the local ledger, as in WalletStatus.
+    pub local_block_index: u64,
+
+    /// The block version of the highest block in the local ledger.
+    pub block_version: u32,
+
+    /// The fee, in pico MOB, the wallet pays when a transaction does not name
+    /// one. This is the minimum fee built into the wallet, not one reported by
+    /// the network.
+    pub default_fee: u64,
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// ledger objects and interfaces.
 pub trait LedgerService {
@@ -65,14 +161,44 @@ pub trait LedgerService {
     /// network.
     fn get_network_block_index(&self) -> Result<u64, LedgerServiceError>;
 
+    /// Gets the current network tip, block version, and the wallet's default
+    /// fee.
+    ///
+    /// Consensus peers at this protocol version do not report their fee, so
+    /// the fee is the compiled-in MINIMUM_FEE, which a network with a higher
+    /// minimum would reject.
+    fn get_network_status(&self) -> Result<NetworkStatus, LedgerServiceError>;
+
+    /// Polls the consensus peers for the network tip, rather than using the
+    /// tip from the last background poll, and then gets the network status.
+    fn refresh_network_status(&self) -> Result<NetworkStatus, LedgerServiceError>;
+
     fn get_transaction_object(&self, transaction_id_hex: &str) -> Result<Tx, LedgerServiceError>;
 
+    /// Gets the transaction which created a Txo, if this wallet built it.
+    fn get_transaction_object_for_txo(&self, txo_id_hex: &str) -> Result<Tx, LedgerServiceError>;
+
     fn get_txo_object(&self, txo_id_hex: &str) -> Result<TxOut, LedgerServiceError>;
 
     fn get_block_object(
         &self,
         block_index: u64,
     ) -> Result<(Block, BlockContents), LedgerServiceError>;
+
+    /// Gets the blocks and their contents from block_index up to the tip of
+    /// the local ledger, returning at most `limit` blocks (capped at
+    /// MAX_BLOCKS_PER_REQUEST). Callers can resume from the index after the
+    /// last block returned.
+    fn get_block_objects_since(
+        &self,
+        block_index: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<(Block, BlockContents)>, LedgerServiceError>;
+
+    /// Gets the time, in seconds since the Unix epoch, at which a block was
+    /// signed by consensus, or None if the local ledger has no signature for
+    /// it.
+    fn get_block_timestamp(&self, block_index: u64) -> Result<Option<u64>, LedgerServiceError>;
 }
 
 impl<T, FPR> LedgerService for WalletService<T, FPR>
@@ -85,6 +211,33 @@ where
         Ok(network_state.highest_block_index_on_network().unwrap_or(0))
     }
 
+    fn get_network_status(&self) -> Result<NetworkStatus, LedgerServiceError> {
+        let local_block_index = self.ledger_db.num_blocks()?;
+        let block = self.ledger_db.get_block(local_block_index - 1)?;
+        Ok(NetworkStatus {
+            network_block_index: self.get_network_block_index()? + 1,
+            local_block_index,
+            block_version: block.version,
+            default_fee: MINIMUM_FEE,
+        })
+    }
+
+    fn refresh_network_status(&self) -> Result<NetworkStatus, LedgerServiceError> {
+        if self.offline {
+            return Err(LedgerServiceError::Offline);
+        }
+        {
+            let mut network_state = self.network_state.write().expect("lock poisoned");
+            if !network_state.poll() {
+                log::warn!(
+                    self.logger,
+                    "Not all peers responded when refreshing the network status"
+                );
+            }
+        }
+        self.get_network_status()
+    }
+
     fn get_transaction_object(&self, transaction_id_hex: &str) -> Result<Tx, LedgerServiceError> {
         let conn = self.wallet_db.get_conn()?;
         let transaction = TransactionLog::get(transaction_id_hex, &conn)?;
@@ -97,6 +250,32 @@ where
         }
     }
 
+    fn get_transaction_object_for_txo(&self, txo_id_hex: &str) -> Result<Tx, LedgerServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        // The Txo may also be an input to a later transaction, so find the
+        // transaction in which it is an output or change.
+        for transaction in TransactionLog::select_for_txo(txo_id_hex, &conn)? {
+            let tx_bytes = match &transaction.tx {
+                Some(tx_bytes) => tx_bytes,
+                None => continue,
+            };
+            let associated_txos = transaction.get_associated_txos(&conn)?;
+            if associated_txos
+                .outputs
+                .iter()
+                .chain(associated_txos.change.iter())
+                .any(|t| t == txo_id_hex)
+            {
+                let tx: Tx = mc_util_serial::decode(tx_bytes)?;
+                return Ok(tx);
+            }
+        }
+        Err(LedgerServiceError::TxoNotBuiltByWallet(
+            txo_id_hex.to_string(),
+        ))
+    }
+
     fn get_txo_object(&self, txo_id_hex: &str) -> Result<TxOut, LedgerServiceError> {
         let conn = self.wallet_db.get_conn()?;
         let txo_details = Txo::get(txo_id_hex, &conn)?;
@@ -113,4 +292,227 @@ where
         let block_contents = self.ledger_db.get_block_contents(block_index)?;
         Ok((block, block_contents))
     }
+
+    fn get_block_objects_since(
+        &self,
+        block_index: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<(Block, BlockContents)>, LedgerServiceError> {
+        let limit = limit
+            .unwrap_or(MAX_BLOCKS_PER_REQUEST)
+            .min(MAX_BLOCKS_PER_REQUEST);
+        let end = self
+            .ledger_db
+            .num_blocks()?
+            .min(block_index.saturating_add(limit));
+        (block_index..end)
+            .map(|i| self.get_block_object(i))
+            .collect()
+    }
+
+    fn get_block_timestamp(&self, block_index: u64) -> Result<Option<u64>, LedgerServiceError> {
+        Ok(self
+            .block_timestamp_cache
+            .get_block_timestamp(&self.ledger_db, block_index)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{account::AccountID, b58_encode, txo::TxoID},
+        service::{
+            account::{AccountService, CreateAccountOptions},
+            transaction::{TransactionBuildOptions, TransactionService},
+            txo::TxoService,
+        },
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, add_signed_block_to_ledger_db,
+            get_test_ledger, setup_wallet_service, wait_for_sync, MOB,
+        },
+    };
+    use mc_account_keys::{AccountKey, PublicAddress};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test_with_logger]
+    fn test_get_transaction_object_for_txo(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        // The Txo received from the ledger was not built by the wallet.
+        let received = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(received.len(), 1);
+        match service.get_transaction_object_for_txo(&received[0].txo.txo_id_hex) {
+            Err(LedgerServiceError::TxoNotBuiltByWallet(_)) => {}
+            Ok(_) => panic!("Should not find a transaction for a received Txo"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Send to ourselves, and receive the output once it lands.
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&alice_public_address).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let output_txo_id = associated_txos.outputs[0].clone();
+        let output = service.get_txo(&TxoID(output_txo_id.clone())).unwrap();
+        assert!(output.received_to_account.is_some());
+
+        let tx = service
+            .get_transaction_object_for_txo(&output_txo_id)
+            .unwrap();
+        let expected_tx = service
+            .get_transaction_object(&transaction_log.transaction_id_hex)
+            .unwrap();
+        assert_eq!(tx, expected_tx);
+        let output_tx_out = service.get_txo_object(&output_txo_id).unwrap();
+        assert!(tx.prefix.outputs.contains(&output_tx_out));
+
+        // The spent input resolves to the transaction that created it, which the
+        // wallet did not build.
+        match service.get_transaction_object_for_txo(&associated_txos.inputs[0]) {
+            Err(LedgerServiceError::TxoNotBuiltByWallet(_)) => {}
+            Ok(_) => panic!("Should not resolve an input to the transaction spending it"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    // Refreshing should pick up a block added since the network state was last
+    // polled.
+    #[test_with_logger]
+    fn test_refresh_network_status(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        assert_eq!(
+            service.get_network_status().unwrap().network_block_index,
+            12
+        );
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![AccountKey::random(&mut rng).default_subaddress()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        let network_status = service.refresh_network_status().unwrap();
+        assert_eq!(network_status.network_block_index, 13);
+        assert_eq!(network_status.local_block_index, 13);
+        assert_eq!(
+            service.get_network_status().unwrap().network_block_index,
+            13
+        );
+    }
+
+    #[test_with_logger]
+    fn test_get_block_timestamp(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+
+        // The test ledger's blocks are not signed, so have no timestamp.
+        assert_eq!(service.get_block_timestamp(0).unwrap(), None);
+
+        let signed_at = 1_600_000_000;
+        let num_blocks = add_signed_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            signed_at,
+            &mut rng,
+        );
+        wait_for_sync(
+            &ledger_db,
+            &service.wallet_db,
+            &alice_account_id,
+            num_blocks,
+        );
+
+        let received = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(received.len(), 1);
+        let received_block_index = received[0].txo.received_block_index.unwrap() as u64;
+        assert_eq!(received_block_index, num_blocks - 1);
+
+        let timestamp = service.get_block_timestamp(received_block_index).unwrap();
+        assert_eq!(timestamp, Some(signed_at));
+        assert_eq!(
+            timestamp,
+            Some(
+                ledger_db
+                    .get_block_signature(received_block_index)
+                    .unwrap()
+                    .signed_at()
+            )
+        );
+
+        // Timestamps are read from the ledger once.
+        let num_ledger_reads = service.block_timestamp_cache.num_ledger_reads();
+        service.get_block_timestamp(received_block_index).unwrap();
+        assert_eq!(
+            service.block_timestamp_cache.num_ledger_reads(),
+            num_ledger_reads
+        );
+
+        // A block beyond the tip has no timestamp yet.
+        match service.get_block_timestamp(num_blocks) {
+            Err(LedgerServiceError::LedgerDB(mc_ledger_db::Error::NotFound)) => {}
+            Ok(_) => panic!("Should not get a timestamp for a block beyond the tip"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
 }
@@ -6,6 +6,7 @@ pub mod account;
 pub mod address;
 pub mod balance;
 pub mod confirmation_number;
+pub mod database;
 pub mod gift_code;
 pub mod ledger;
 pub mod receipt;
@@ -14,6 +15,7 @@ pub mod transaction;
 pub mod transaction_builder;
 pub mod transaction_log;
 pub mod txo;
+pub mod webhook;
 mod wallet_service;
 
 pub use wallet_service::WalletService;
@@ -14,11 +14,14 @@ use crate::{
         assigned_subaddress::AssignedSubaddressModel,
         models::{Account, AssignedSubaddress, Txo, TXO_STATUS_SECRETED, TXO_TYPE_MINTED},
         txo::{TxoDetails, TxoModel},
-        WalletDbError,
+        WalletDbConnection, WalletDbError,
     },
     WalletService,
 };
-use diesel::Connection;
+use diesel::{
+    r2d2::{ConnectionManager, PooledConnection},
+    Connection,
+};
 use displaydoc::Display;
 use mc_account_keys::AccountKey;
 use mc_connection::{BlockchainConnection, UserTxConnection};
@@ -169,6 +172,16 @@ pub trait ReceiptService {
         receiver_receipt: &ReceiverReceipt,
     ) -> Result<(ReceiptTransactionStatus, Option<TxoDetails>), ReceiptServiceError>;
 
+    /// Check the status of the Txos in several receipts for Txos received by
+    /// an account, for example all of the outlays of a single payment.
+    ///
+    /// Returns the status of each receipt, in the order given.
+    fn verify_receipts(
+        &self,
+        account_id: &AccountID,
+        receiver_receipts: &[ReceiverReceipt],
+    ) -> Result<Vec<(ReceiptTransactionStatus, Option<TxoDetails>)>, ReceiptServiceError>;
+
     /// Create a receipt from a given TxProposal
     fn create_receiver_receipts(
         &self,
@@ -193,75 +206,26 @@ where
                 || {
                     let assigned_address = AssignedSubaddress::get(address, &conn)?;
                     let account_id = AccountID(assigned_address.account_id_hex);
-                    let account = Account::get(&account_id, &conn)?;
-                    // Get the transaction from the database, with status.
-                    let txos_and_statuses = Txo::select_by_public_key(
-                        &account_id,
-                        &[&receiver_receipt.public_key],
-                        &conn,
-                    )?;
-
-                    // Return if the Txo from the receipt is not in this wallet yet.
-                    if txos_and_statuses.is_empty() {
-                        return Ok((ReceiptTransactionStatus::TransactionPending, None));
-                    }
-                    let (txo, status) = &txos_and_statuses[0];
-
-                    // Figure out whether the Txo was minted by us, and has not yet been received by
-                    // us. (For to-self transactions). If the Txo was minted by us, this
-                    // transaction is pending.
-                    if status.txo_type == TXO_TYPE_MINTED
-                        && status.txo_status == TXO_STATUS_SECRETED
-                    {
-                        return Ok((ReceiptTransactionStatus::TransactionPending, None));
-                    }
-                    let details = Txo::get(&txo.txo_id_hex, &conn)?;
-
-                    // Decrypt the amount to get the expected value
-                    let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
-                    let public_key: RistrettoPublic =
-                        RistrettoPublic::try_from(&receiver_receipt.public_key)?;
-                    let shared_secret =
-                        get_tx_out_shared_secret(account_key.view_private_key(), &public_key);
-                    let expected_value = match receiver_receipt.amount.get_value(&shared_secret) {
-                        Ok((v, _blinding)) => v,
-                        Err(AmountError::InconsistentCommitment) => {
-                            return Ok((
-                                ReceiptTransactionStatus::FailedAmountDecryption,
-                                Some(details),
-                            ))
-                        }
-                    };
-                    // Check that the value of the received Txo matches the expected value.
-                    if (txo.value as u64) != expected_value {
-                        return Ok((
-                            ReceiptTransactionStatus::AmountMismatch(format!(
-                                "Expected: {}, Got: {}",
-                                expected_value, txo.value
-                            )),
-                            Some(details),
-                        ));
-                    }
-
-                    // Validate the confirmation number.
-                    let confirmation_hex =
-                        hex::encode(mc_util_serial::encode(&receiver_receipt.confirmation));
-                    let confirmation: TxOutConfirmationNumber =
-                        mc_util_serial::decode(&hex::decode(confirmation_hex)?)?;
-                    if !Txo::validate_confirmation(
-                        &account_id,
-                        &txo.txo_id_hex.clone(),
-                        &confirmation,
-                        &conn,
-                    )? {
-                        return Ok((ReceiptTransactionStatus::InvalidConfirmation, Some(details)));
-                    }
-
-                    Ok((ReceiptTransactionStatus::TransactionSuccess, Some(details)))
+                    check_receipt_status_for_account(&account_id, receiver_receipt, &conn)
                 },
             )?)
     }
 
+    fn verify_receipts(
+        &self,
+        account_id: &AccountID,
+        receiver_receipts: &[ReceiverReceipt],
+    ) -> Result<Vec<(ReceiptTransactionStatus, Option<TxoDetails>)>, ReceiptServiceError> {
+        let conn = &self.wallet_db.get_conn()?;
+
+        conn.transaction(|| {
+            receiver_receipts
+                .iter()
+                .map(|receipt| check_receipt_status_for_account(account_id, receipt, &conn))
+                .collect()
+        })
+    }
+
     fn create_receiver_receipts(
         &self,
         tx_proposal: &TxProposal,
@@ -285,6 +249,69 @@ where
     }
 }
 
+/// Check the status of the Txo in a receipt, for the account which received it.
+///
+/// The value in the receipt must match the value of the received Txo, and the
+/// confirmation number must prove that the sender created it.
+fn check_receipt_status_for_account(
+    account_id: &AccountID,
+    receiver_receipt: &ReceiverReceipt,
+    conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+) -> Result<(ReceiptTransactionStatus, Option<TxoDetails>), ReceiptServiceError> {
+    let account = Account::get(account_id, conn)?;
+    // Get the transaction from the database, with status.
+    let txos_and_statuses =
+        Txo::select_by_public_key(account_id, &[&receiver_receipt.public_key], conn)?;
+
+    // Return if the Txo from the receipt is not in this wallet yet.
+    if txos_and_statuses.is_empty() {
+        return Ok((ReceiptTransactionStatus::TransactionPending, None));
+    }
+    let (txo, status) = &txos_and_statuses[0];
+
+    // Figure out whether the Txo was minted by us, and has not yet been received by
+    // us. (For to-self transactions). If the Txo was minted by us, this
+    // transaction is pending.
+    if status.txo_type == TXO_TYPE_MINTED && status.txo_status == TXO_STATUS_SECRETED {
+        return Ok((ReceiptTransactionStatus::TransactionPending, None));
+    }
+    let details = Txo::get(&txo.txo_id_hex, conn)?;
+
+    // Decrypt the amount to get the expected value
+    let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+    let public_key: RistrettoPublic = RistrettoPublic::try_from(&receiver_receipt.public_key)?;
+    let shared_secret = get_tx_out_shared_secret(account_key.view_private_key(), &public_key);
+    let expected_value = match receiver_receipt.amount.get_value(&shared_secret) {
+        Ok((v, _blinding)) => v,
+        Err(AmountError::InconsistentCommitment) => {
+            return Ok((
+                ReceiptTransactionStatus::FailedAmountDecryption,
+                Some(details),
+            ))
+        }
+    };
+    // Check that the value of the received Txo matches the expected value.
+    if (txo.value as u64) != expected_value {
+        return Ok((
+            ReceiptTransactionStatus::AmountMismatch(format!(
+                "Expected: {}, Got: {}",
+                expected_value, txo.value
+            )),
+            Some(details),
+        ));
+    }
+
+    // Validate the confirmation number.
+    let confirmation_hex = hex::encode(mc_util_serial::encode(&receiver_receipt.confirmation));
+    let confirmation: TxOutConfirmationNumber =
+        mc_util_serial::decode(&hex::decode(confirmation_hex)?)?;
+    if !Txo::validate_confirmation(account_id, &txo.txo_id_hex.clone(), &confirmation, conn)? {
+        return Ok((ReceiptTransactionStatus::InvalidConfirmation, Some(details)));
+    }
+
+    Ok((ReceiptTransactionStatus::TransactionSuccess, Some(details)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,9 +323,12 @@ mod tests {
             transaction_log::{AssociatedTxos, TransactionLogModel},
         },
         service::{
-            account::AccountService, address::AddressService,
-            confirmation_number::ConfirmationService, transaction::TransactionService,
-            transaction_log::TransactionLogService, txo::TxoService,
+            account::{AccountService, CreateAccountOptions},
+            address::AddressService,
+            confirmation_number::ConfirmationService,
+            transaction::{TransactionBuildOptions, TransactionService},
+            transaction_log::TransactionLogService,
+            txo::TxoService,
         },
         test_utils::{
             add_block_to_ledger_db, add_block_with_tx_proposal, get_test_ledger,
@@ -362,7 +392,10 @@ mod tests {
 
         let service = setup_wallet_service(ledger_db.clone(), logger.clone());
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Fund Alice
@@ -384,10 +417,13 @@ mod tests {
         );
 
         let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
         let bob_addresses = service
-            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()))
+            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()), false)
             .expect("Could not get addresses for Bob");
         let bob_address = bob_addresses[0].assigned_subaddress_b58.clone();
 
@@ -401,6 +437,7 @@ mod tests {
                 None,
                 None,
                 None,
+                TransactionBuildOptions::default(),
             )
             .expect("Could not build transaction");
 
@@ -441,7 +478,7 @@ mod tests {
 
         // Get corresponding Txo for Bob
         let txos = service
-            .list_txos(&AccountID(bob.account_id_hex))
+            .list_txos(&AccountID(bob.account_id_hex), None)
             .expect("Could not get Bob Txos");
         assert_eq!(txos.len(), 1);
 
@@ -487,7 +524,10 @@ mod tests {
 
         let service = setup_wallet_service(ledger_db.clone(), logger.clone());
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Fund Alice
@@ -509,10 +549,13 @@ mod tests {
         );
 
         let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
         let bob_addresses = service
-            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()))
+            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()), false)
             .expect("Could not get addresses for Bob");
         let bob_address = &bob_addresses[0].assigned_subaddress_b58.clone();
 
@@ -526,6 +569,7 @@ mod tests {
                 None,
                 None,
                 None,
+                TransactionBuildOptions::default(),
             )
             .expect("Could not build transaction");
 
@@ -601,7 +645,10 @@ mod tests {
 
         let service = setup_wallet_service(ledger_db.clone(), logger.clone());
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Fund Alice
@@ -623,10 +670,13 @@ mod tests {
         );
 
         let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
         let bob_addresses = service
-            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()))
+            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()), false)
             .expect("Could not get addresses for Bob");
         let bob_address = &bob_addresses[0].assigned_subaddress_b58.clone();
         let bob_account_id = AccountID(bob.account_id_hex.to_string());
@@ -641,6 +691,7 @@ mod tests {
                 None,
                 None,
                 None,
+                TransactionBuildOptions::default(),
             )
             .expect("Could not build transaction");
 
@@ -719,7 +770,10 @@ mod tests {
 
         let service = setup_wallet_service(ledger_db.clone(), logger.clone());
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Fund Alice
@@ -741,10 +795,13 @@ mod tests {
         );
 
         let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
         let bob_addresses = service
-            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()))
+            .get_all_addresses_for_account(&AccountID(bob.account_id_hex.clone()), false)
             .expect("Could not get addresses for Bob");
         let bob_address = &bob_addresses[0].assigned_subaddress_b58.clone();
         let bob_account_id = AccountID(bob.account_id_hex.to_string());
@@ -759,6 +816,7 @@ mod tests {
                 None,
                 None,
                 None,
+                TransactionBuildOptions::default(),
             )
             .expect("Could not build transaction");
 
@@ -808,4 +866,125 @@ mod tests {
             .expect("Could not check status of receipt");
         assert_eq!(status, ReceiptTransactionStatus::TransactionPending);
     }
+
+    #[test_with_logger]
+    fn test_verify_receipts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        // Fund Alice
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        manually_sync_account(
+            &ledger_db,
+            &service.wallet_db,
+            &AccountID(alice.account_id_hex.to_string()),
+            13,
+            &logger,
+        );
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_address = b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64))
+            .expect("Could not encode Bob address");
+        let bob_account_id = AccountID(bob.account_id_hex.to_string());
+
+        // Pay Bob in three outputs, each with its own receipt.
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &bob_address,
+                (24 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions {
+                    num_outputs: Some("3".to_string()),
+                    ..Default::default()
+                },
+            )
+            .expect("Could not build transaction");
+        let receipts = service
+            .create_receiver_receipts(&tx_proposal)
+            .expect("Could not create receiver receipts");
+        assert_eq!(receipts.len(), 3);
+
+        // Before the payment lands, every receipt is pending.
+        let statuses = service
+            .verify_receipts(&bob_account_id, &receipts)
+            .expect("Could not verify receipts");
+        assert_eq!(statuses.len(), 3);
+        assert!(statuses.iter().all(|(status, txo)| *status
+            == ReceiptTransactionStatus::TransactionPending
+            && txo.is_none()));
+
+        TransactionLog::log_submitted(
+            tx_proposal.clone(),
+            14,
+            "".to_string(),
+            Some(&alice.account_id_hex),
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .expect("Could not log submitted");
+        add_block_with_tx_proposal(&mut ledger_db, tx_proposal);
+        manually_sync_account(&ledger_db, &service.wallet_db, &bob_account_id, 14, &logger);
+
+        // One receipt claims the wrong value, and another has a bad confirmation
+        // number.
+        let mut receipts = receipts;
+        let public_key: RistrettoPublic = RistrettoPublic::try_from(&receipts[1].public_key)
+            .expect("Could not get ristretto public from compressed");
+        let shared_secret =
+            get_tx_out_shared_secret(bob_account_key.view_private_key(), &public_key);
+        receipts[1].amount =
+            Amount::new(10 * MOB as u64, &shared_secret).expect("Could not create Amount");
+        let mut bad_confirmation_bytes = [0u8; 32];
+        rng.fill_bytes(&mut bad_confirmation_bytes);
+        receipts[2].confirmation = TxOutConfirmationNumber::from(bad_confirmation_bytes);
+
+        let statuses = service
+            .verify_receipts(&bob_account_id, &receipts)
+            .expect("Could not verify receipts");
+        assert_eq!(statuses.len(), 3);
+        assert_eq!(statuses[0].0, ReceiptTransactionStatus::TransactionSuccess);
+        assert_eq!(
+            statuses[1].0,
+            ReceiptTransactionStatus::AmountMismatch(
+                "Expected: 10000000000000, Got: 8000000000000".to_string()
+            )
+        );
+        assert_eq!(statuses[2].0, ReceiptTransactionStatus::InvalidConfirmation);
+        for ((_status, txo), receipt) in statuses.iter().zip(receipts.iter()) {
+            assert_eq!(
+                txo.as_ref()
+                    .expect("Expected a received Txo")
+                    .txo
+                    .public_key,
+                mc_util_serial::encode(&receipt.public_key)
+            );
+        }
+    }
 }
@@ -23,17 +23,35 @@
 //! available blocks gets processed at once. When that happens, instead of
 //! removing the account id from the hashset, it would be placed back into the
 //! queue to be picked up by the next available worker thread.
+//!
+//! Accounts scanning the same blocks share their ledger reads through a
+//! BlockContentsCache, so that catching up many accounts reads each block from
+//! the ledger once, while the output matching for each account is done in
+//! parallel by the worker threads.
+//!
+//! Blocks are processed in batches of scan_batch_size blocks per database
+//! transaction. Larger batches reduce the per-block overhead of committing
+//! during initial catch-up, at the cost of holding the database write lock,
+//! and the batch's uncommitted writes, for longer.
 
 use crate::{
     db::{
         account::{AccountID, AccountModel},
+        account_txo_status::AccountTxoStatusModel,
         assigned_subaddress::AssignedSubaddressModel,
-        models::{Account, AssignedSubaddress, TransactionLog, Txo},
+        models::{
+            Account, AccountTxoStatus, AssignedSubaddress, TransactionBuild, TransactionLog, Txo,
+            WebhookNotification, TXO_STATUS_DUST, TXO_STATUS_ORPHANED, TXO_STATUS_PENDING,
+            TXO_STATUS_UNSPENT, TXO_TYPE_MINTED,
+        },
+        transaction_build::TransactionBuildModel,
         transaction_log::TransactionLogModel,
         txo::TxoModel,
-        WalletDb, WalletDbError,
+        webhook_notification::WebhookNotificationModel,
+        WalletDb, WalletDbConnection, WalletDbError,
     },
     error::SyncError,
+    service::webhook::is_webhook_enabled,
 };
 use mc_account_keys::AccountKey;
 use mc_common::{
@@ -47,7 +65,7 @@ use mc_transaction_core::{
     onetime_keys::{recover_onetime_private_key, recover_public_subaddress_spend_key},
     ring_signature::KeyImage,
     tx::TxOut,
-    AmountError,
+    AmountError, BlockContents, BlockID,
 };
 
 use diesel::{
@@ -64,12 +82,37 @@ use std::{
     time::Duration,
 };
 
-///  The maximal number of blocks a worker thread would process at once.
+///  The maximal number of batches a worker thread would process at once.
 const MAX_BLOCKS_PROCESSING_CHUNK_SIZE: usize = 5;
 
+/// The default number of blocks processed in one database transaction.
+pub const DEFAULT_SCAN_BATCH_SIZE: usize = 1;
+
+/// The maximal number of blocks kept in the shared block contents cache.
+const MAX_CACHED_BLOCKS: usize = 100;
+
 /// The AccountId corresponds to the Account's primary key: account_key_hex.
 pub type AccountId = String;
 
+/// Settings for how the sync thread scans accounts.
+#[derive(Clone, Debug)]
+pub struct SyncThreadConfig {
+    /// Whether archived accounts are left unsynced until they are unarchived.
+    pub skip_archived_accounts: bool,
+
+    /// The number of blocks processed in one database transaction.
+    pub scan_batch_size: usize,
+}
+
+impl Default for SyncThreadConfig {
+    fn default() -> Self {
+        SyncThreadConfig {
+            skip_archived_accounts: false,
+            scan_batch_size: DEFAULT_SCAN_BATCH_SIZE,
+        }
+    }
+}
+
 /// Message type our crossbeam channel uses to communicate with the worker
 /// thread pull.
 enum SyncMsg {
@@ -87,6 +130,70 @@ pub enum SyncAccountOk {
     MoreBlocksPotentiallyAvailable,
 }
 
+/// Block contents read from the ledger, shared between the sync worker threads
+/// so that each block is read once, rather than once per account.
+#[derive(Clone, Default)]
+pub struct BlockContentsCache {
+    inner: Arc<Mutex<BlockContentsCacheInner>>,
+}
+
+#[derive(Default)]
+struct BlockContentsCacheInner {
+    /// Cached block contents, by block index and block id.
+    blocks: HashMap<(u64, BlockID), Arc<BlockContents>>,
+
+    /// The number of blocks read from the ledger.
+    num_ledger_reads: u64,
+}
+
+impl BlockContentsCache {
+    /// Get the contents of a block, reading them from the ledger if they are
+    /// not cached.
+    pub fn get_block_contents(
+        &self,
+        ledger_db: &LedgerDB,
+        block_index: u64,
+    ) -> Result<Arc<BlockContents>, mc_ledger_db::Error> {
+        // The block id is part of the key, so that a block which replaced the
+        // cached one at the same index, as after a rollback, is read afresh.
+        let key = (block_index, ledger_db.get_block(block_index)?.id);
+        if let Some(block_contents) = self.inner.lock().expect("mutex poisoned").blocks.get(&key) {
+            return Ok(block_contents.clone());
+        }
+
+        // Read outside of the lock, so that workers scanning other blocks are not
+        // held up. Two workers may occasionally read the same block.
+        let block_contents = Arc::new(ledger_db.get_block_contents(block_index)?);
+
+        let mut inner = self.inner.lock().expect("mutex poisoned");
+        inner.num_ledger_reads += 1;
+        if inner.blocks.len() >= MAX_CACHED_BLOCKS {
+            // Accounts scan upwards, so the lowest block is the least likely to be
+            // needed again.
+            if let Some(lowest_key) = inner
+                .blocks
+                .keys()
+                .min_by_key(|(cached_block_index, _)| *cached_block_index)
+                .cloned()
+            {
+                inner.blocks.remove(&lowest_key);
+            }
+        }
+        inner.blocks.insert(key, block_contents.clone());
+        Ok(block_contents)
+    }
+
+    /// Drop all cached blocks, for example because the ledger was rolled back.
+    pub fn clear(&self) {
+        self.inner.lock().expect("mutex poisoned").blocks.clear();
+    }
+
+    /// The number of blocks read from the ledger so far.
+    pub fn num_ledger_reads(&self) -> u64 {
+        self.inner.lock().expect("mutex poisoned").num_ledger_reads
+    }
+}
+
 /// Sync thread - holds objects needed to cleanly terminate the sync thread.
 pub struct SyncThread {
     /// The main sync thread handle.
@@ -94,6 +201,9 @@ pub struct SyncThread {
 
     /// Stop trigger, used to signal the thread to reminate.
     stop_requested: Arc<AtomicBool>,
+
+    /// Block contents shared by the worker threads.
+    block_contents_cache: BlockContentsCache,
 }
 
 impl SyncThread {
@@ -101,8 +211,14 @@ impl SyncThread {
         ledger_db: LedgerDB,
         wallet_db: WalletDb,
         num_workers: Option<usize>,
+        config: SyncThreadConfig,
         logger: Logger,
     ) -> Self {
+        let SyncThreadConfig {
+            skip_archived_accounts,
+            scan_batch_size,
+        } = config;
+
         // Queue for sending jobs to our worker threads.
         let (sender, receiver) = crossbeam_channel::unbounded::<SyncMsg>();
 
@@ -110,6 +226,9 @@ impl SyncThread {
         // preventing them from being sent again until they are processed.
         let queued_account_ids = Arc::new(Mutex::new(HashSet::<AccountId>::default()));
 
+        // Blocks read by one worker are reused by the others.
+        let block_contents_cache = BlockContentsCache::default();
+
         // Create worker threads.
         let mut worker_join_handles = Vec::new();
 
@@ -119,6 +238,7 @@ impl SyncThread {
             let thread_sender = sender.clone();
             let thread_receiver = receiver.clone();
             let thread_queued_account_ids = queued_account_ids.clone();
+            let thread_block_contents_cache = block_contents_cache.clone();
             let thread_logger = logger.clone();
             let join_handle = thread::Builder::new()
                 .name(format!("sync_worker_{}", idx))
@@ -129,6 +249,8 @@ impl SyncThread {
                         thread_sender,
                         thread_receiver,
                         thread_queued_account_ids,
+                        thread_block_contents_cache,
+                        scan_batch_size,
                         thread_logger,
                     );
                 })
@@ -142,6 +264,7 @@ impl SyncThread {
         // and adds new one into our cyclic queue.
         let stop_requested = Arc::new(AtomicBool::new(false));
         let thread_stop_requested = stop_requested.clone();
+        let thread_block_contents_cache = block_contents_cache.clone();
 
         let join_handle = Some(
             thread::Builder::new()
@@ -149,6 +272,7 @@ impl SyncThread {
                 .spawn(move || {
                     log::debug!(logger, "Syncthread started.");
 
+                    let mut last_num_blocks = 0;
                     loop {
                         if thread_stop_requested.load(Ordering::SeqCst) {
                             log::debug!(logger, "SyncThread stop requested.");
@@ -179,12 +303,38 @@ impl SyncThread {
                             })
                             .expect("Failed executing database transaction")
                         };
+
+                        // If the ledger was rolled back, cached blocks may no longer match it,
+                        // and received Txos may no longer be in it. An account which synced
+                        // past the tip when we start up was rolled back while we were stopped.
+                        let rolled_back = num_blocks < last_num_blocks
+                            || (last_num_blocks == 0
+                                && accounts
+                                    .iter()
+                                    .any(|account| account.next_block_index > num_blocks as i64));
+                        if rolled_back {
+                            if let Err(err) = handle_ledger_rollback(
+                                &ledger_db,
+                                &wallet_db,
+                                &thread_block_contents_cache,
+                                &logger,
+                            ) {
+                                log::error!(logger, "error handling ledger rollback: {:?}", err);
+                            }
+                        }
+                        last_num_blocks = num_blocks;
+
                         for account in accounts {
                             // If there are no new blocks for this account, don't do anything.
                             if account.next_block_index >= num_blocks as i64 {
                                 continue;
                             }
 
+                            // Archived accounts catch up once they are unarchived.
+                            if skip_archived_accounts && account.archived {
+                                continue;
+                            }
+
                             let mut queued_account_ids =
                                 queued_account_ids.lock().expect("mutex poisoned");
                             if !queued_account_ids.insert(account.account_id_hex.clone()) {
@@ -255,9 +405,15 @@ impl SyncThread {
         Self {
             join_handle,
             stop_requested,
+            block_contents_cache,
         }
     }
 
+    /// The number of blocks the worker threads read from the ledger so far.
+    pub fn num_ledger_reads(&self) -> u64 {
+        self.block_contents_cache.num_ledger_reads()
+    }
+
     pub fn stop(&mut self) {
         self.stop_requested.store(true, Ordering::SeqCst);
         if let Some(join_handle) = self.join_handle.take() {
@@ -278,17 +434,31 @@ fn sync_thread_entry_point(
     sender: crossbeam_channel::Sender<SyncMsg>,
     receiver: crossbeam_channel::Receiver<SyncMsg>,
     queued_account_ids: Arc<Mutex<HashSet<AccountId>>>,
+    block_contents_cache: BlockContentsCache,
+    scan_batch_size: usize,
     logger: Logger,
 ) {
     for msg in receiver.iter() {
         match msg {
             SyncMsg::SyncAccount(account_id) => {
-                match sync_account(&ledger_db, &wallet_db, &account_id, &logger) {
+                match sync_account_with_cache(
+                    &ledger_db,
+                    &wallet_db,
+                    &block_contents_cache,
+                    &account_id,
+                    scan_batch_size,
+                    &logger,
+                ) {
                     // Success - No more blocks are currently available.
                     Ok(SyncAccountOk::NoMoreBlocks) => {
                         // Remove the account id from the list of queued ones so that the main
                         // thread could queue it again if necessary.
-                        log::trace!(logger, "{}: sync_account returned NoMoreBlocks", account_id);
+                        log::trace!(
+                            logger,
+                            "{}: sync_account returned NoMoreBlocks, {} blocks read from ledger",
+                            account_id,
+                            block_contents_cache.num_ledger_reads(),
+                        );
 
                         let mut queued_account_ids =
                             queued_account_ids.lock().expect("mutex poisoned");
@@ -357,60 +527,122 @@ pub fn sync_account(
     account_id: &str,
     logger: &Logger,
 ) -> Result<SyncAccountOk, SyncError> {
+    sync_account_with_cache(
+        ledger_db,
+        wallet_db,
+        &BlockContentsCache::default(),
+        account_id,
+        DEFAULT_SCAN_BATCH_SIZE,
+        logger,
+    )
+}
+
+/// Sync a single account, reading blocks through a cache shared with the sync
+/// of other accounts, and processing up to scan_batch_size blocks in each
+/// database transaction.
+pub fn sync_account_with_cache(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    block_contents_cache: &BlockContentsCache,
+    account_id: &str,
+    scan_batch_size: usize,
+    logger: &Logger,
+) -> Result<SyncAccountOk, SyncError> {
+    let _sync_guard = wallet_db.sync_guard();
+
+    // Subaddresses may have been assigned since the last pass, so check the
+    // orphaned Txos before scanning new blocks.
+    {
+        let conn = wallet_db.get_conn()?;
+        conn.transaction::<usize, SyncError, _>(|| {
+            let account = Account::get(&AccountID(account_id.to_string()), &conn)?;
+            recover_orphaned_txos(
+                &conn,
+                ledger_db,
+                &account,
+                wallet_db.dust_threshold(),
+                logger,
+            )
+        })?;
+    }
+
     for _ in 0..MAX_BLOCKS_PROCESSING_CHUNK_SIZE {
         let conn = wallet_db.get_conn()?;
+        // Each batch of blocks is processed in a single transaction, so that the Txos
+        // found in it and the advance of next_block_index are committed together. If
+        // the wallet stops partway through a batch, none of it is committed, and sync
+        // resumes at the batch's first block.
         let sync_status = conn.transaction::<SyncAccountOk, SyncError, _>(|| {
             // Get the account data. If it is no longer available, the account has been
             // removed and we can simply return.
             let account = Account::get(&AccountID(account_id.to_string()), &conn)?;
-            let block_contents = match ledger_db.get_block_contents(account.next_block_index as u64)
-            {
-                Ok(block_contents) => block_contents,
-                Err(mc_ledger_db::Error::NotFound) => {
-                    return Ok(SyncAccountOk::NoMoreBlocks);
-                }
-                Err(err) => {
-                    return Err(err.into());
-                }
-            };
+            let first_block_index = account.next_block_index;
 
-            log::trace!(
-                logger,
-                "processing {} outputs and {} key images from block {} for account {}",
-                block_contents.outputs.len(),
-                block_contents.key_images.len(),
-                account.next_block_index,
-                account_id,
-            );
+            // Builds whose proposals can no longer land are not needed to verify them.
+            TransactionBuild::delete_expired(&account.account_id_hex, first_block_index, &conn)?;
 
-            // Match tx outs into UTXOs.
-            let output_txo_ids = process_txos(
-                &conn,
-                &block_contents.outputs,
-                &account,
-                account.next_block_index,
-                logger,
-            )?;
+            for block_index in first_block_index..first_block_index + scan_batch_size as i64 {
+                let block_contents =
+                    match block_contents_cache.get_block_contents(ledger_db, block_index as u64) {
+                        Ok(block_contents) => block_contents,
+                        Err(mc_ledger_db::Error::NotFound) => {
+                            // Commit the blocks processed so far.
+                            return Ok(SyncAccountOk::NoMoreBlocks);
+                        }
+                        Err(err) => {
+                            return Err(err.into());
+                        }
+                    };
 
-            // Note: Doing this here means we are updating key images multiple times, once
-            // per account. We do actually want to do it this way, because each account may
-            // need to process the same block at a different time, depending on when we add
-            // it to the DB.
-            account.update_spent_and_increment_next_block(
-                account.next_block_index,
-                block_contents.key_images,
-                &conn,
-            )?;
+                log::trace!(
+                    logger,
+                    "processing {} outputs and {} key images from block {} for account {}",
+                    block_contents.outputs.len(),
+                    block_contents.key_images.len(),
+                    block_index,
+                    account_id,
+                );
 
-            // Add a transaction for the received TXOs
-            TransactionLog::log_received(
-                &output_txo_ids,
-                &account,
-                account.next_block_index as u64,
-                &conn,
-            )?;
+                // Match tx outs into UTXOs.
+                let output_txo_ids = process_txos(
+                    &conn,
+                    &block_contents.outputs,
+                    &account,
+                    block_index,
+                    wallet_db.dust_threshold(),
+                    logger,
+                )?;
+
+                // Note: Doing this here means we are updating key images multiple times, once
+                // per account. We do actually want to do it this way, because each account
+                // may need to process the same block at a different time, depending on when
+                // we add it to the DB.
+                account.update_spent_and_increment_next_block(
+                    block_index,
+                    block_contents.key_images.clone(),
+                    &conn,
+                )?;
+                TransactionLog::resolve_replacements(
+                    &account.account_id_hex,
+                    block_index,
+                    &block_contents.outputs,
+                    &conn,
+                )?;
+
+                // Add a transaction for the received TXOs
+                TransactionLog::log_received(&output_txo_ids, &account, block_index as u64, &conn)?;
+
+                // Queue the webhook notifications with the Txos, so that they are
+                // delivered by the WebhookThread even if the wallet restarts.
+                if is_webhook_enabled(&account) {
+                    for txo_id_hex in output_txo_ids.values().flatten() {
+                        WebhookNotification::queue(account_id, txo_id_hex, block_index, &conn)?;
+                    }
+                }
+            }
             Ok(SyncAccountOk::MoreBlocksPotentiallyAvailable)
         })?;
+
         // Early out of the loop if we hit NoMoreBlocks
         if let SyncAccountOk::NoMoreBlocks = sync_status {
             return Ok(SyncAccountOk::NoMoreBlocks);
@@ -419,12 +651,199 @@ pub fn sync_account(
     Ok(SyncAccountOk::MoreBlocksPotentiallyAvailable)
 }
 
+/// Handle a rollback of the ledger: drop the cached blocks, which may no longer
+/// match it, invalidate the received Txos of every account which it no longer
+/// contains, and move back to the new tip every account which synced past it,
+/// so that the blocks which replace the rolled back ones are scanned.
+///
+/// This reads the receive block of every received Txo in the wallet, so it is
+/// only run when the ledger is seen to roll back, rather than on every sync.
+pub fn handle_ledger_rollback(
+    ledger_db: &LedgerDB,
+    wallet_db: &WalletDb,
+    block_contents_cache: &BlockContentsCache,
+    logger: &Logger,
+) -> Result<(), SyncError> {
+    let _sync_guard = wallet_db.sync_guard();
+
+    log::warn!(logger, "Ledger rolled back, checking received Txos");
+    block_contents_cache.clear();
+
+    let num_blocks = ledger_db.num_blocks()?;
+    let conn = wallet_db.get_conn()?;
+    for account in Account::list_all(&conn)? {
+        conn.transaction::<(), SyncError, _>(|| {
+            invalidate_missing_txos(ledger_db, block_contents_cache, &conn, &account, logger)?;
+            if account.next_block_index > num_blocks as i64 {
+                log::info!(
+                    logger,
+                    "Moving account {} back from block {} to the ledger tip {}",
+                    account.account_id_hex,
+                    account.next_block_index,
+                    num_blocks,
+                );
+                account.update_next_block_index(num_blocks as i64, &conn)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+/// Invalidate an account's received Txos which the ledger no longer contains at
+/// the block they were received in, so they are excluded from balances and
+/// not selected for spending.
+pub fn invalidate_missing_txos(
+    ledger_db: &LedgerDB,
+    block_contents_cache: &BlockContentsCache,
+    conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    account: &Account,
+    logger: &Logger,
+) -> Result<(), SyncError> {
+    let num_blocks = ledger_db.num_blocks()?;
+    // Public keys of the outputs in each block checked so far.
+    let mut block_public_keys: HashMap<i64, HashSet<Vec<u8>>> = HashMap::default();
+
+    for status in &[
+        TXO_STATUS_UNSPENT,
+        TXO_STATUS_PENDING,
+        TXO_STATUS_ORPHANED,
+        TXO_STATUS_DUST,
+    ] {
+        for txo in Txo::list_by_status(&account.account_id_hex, status, conn)? {
+            // Minted Txos have no received block until they land.
+            let received_block_index = match txo.received_block_index {
+                Some(block_index) => block_index,
+                None => continue,
+            };
+
+            if !block_public_keys.contains_key(&received_block_index) {
+                let public_keys = if (received_block_index as u64) < num_blocks {
+                    block_contents_cache
+                        .get_block_contents(ledger_db, received_block_index as u64)?
+                        .outputs
+                        .iter()
+                        .map(|output| mc_util_serial::encode(&output.public_key))
+                        .collect()
+                } else {
+                    HashSet::default()
+                };
+                block_public_keys.insert(received_block_index, public_keys);
+            }
+            if block_public_keys[&received_block_index].contains(&txo.public_key) {
+                continue;
+            }
+
+            log::warn!(
+                logger,
+                "Txo {} is no longer in block {}, invalidating it for account {}",
+                txo.txo_id_hex,
+                received_block_index,
+                account.account_id_hex,
+            );
+            AccountTxoStatus::get(&account.account_id_hex, &txo.txo_id_hex, conn)?
+                .set_invalidated(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Match an account's orphaned Txos against its currently assigned
+/// subaddresses, and make any matches spendable, or dust if their value is
+/// below `dust_threshold` and the account did not mint them. Matches whose key
+/// image is already in the ledger are marked spent.
+///
+/// Returns the number of Txos recovered.
+pub fn recover_orphaned_txos(
+    conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+    ledger_db: &LedgerDB,
+    account: &Account,
+    dust_threshold: u64,
+    logger: &Logger,
+) -> Result<usize, SyncError> {
+    let orphaned_txos = Txo::list_by_status(&account.account_id_hex, TXO_STATUS_ORPHANED, conn)?;
+    if orphaned_txos.is_empty() {
+        return Ok(0);
+    }
+
+    let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+
+    let mut num_recovered = 0;
+    for txo in orphaned_txos {
+        let tx_out: TxOut = mc_util_serial::decode(&txo.txo)?;
+        let tx_out_target_key = RistrettoPublic::try_from(&tx_out.target_key)?;
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key)?;
+
+        let subaddress_spk: RistrettoPublic = recover_public_subaddress_spend_key(
+            account_key.view_private_key(),
+            &tx_out_target_key,
+            &tx_public_key,
+        );
+
+        let subaddress_index =
+            match AssignedSubaddress::find_by_subaddress_spend_public_key(&subaddress_spk, &conn) {
+                Ok((index, account_id)) if account_id == account.account_id_hex => index,
+                // Still orphaned.
+                Ok(_) | Err(WalletDbError::AssignedSubaddressNotFound(_)) => continue,
+                Err(err) => {
+                    return Err(err.into());
+                }
+            };
+
+        let received_block_index = match txo.received_block_index {
+            Some(block_index) => block_index,
+            None => continue,
+        };
+
+        let onetime_private_key = recover_onetime_private_key(
+            &tx_public_key,
+            account_key.view_private_key(),
+            &account_key.subaddress_spend_private(subaddress_index as u64),
+        );
+
+        log::info!(
+            logger,
+            "recovered orphaned txo {} at subaddress index {} for account {}",
+            txo.txo_id_hex,
+            subaddress_index,
+            account.account_id_hex,
+        );
+
+        let key_image = KeyImage::from(&onetime_private_key);
+        txo.update_to_spendable(
+            Some(subaddress_index),
+            Some(key_image),
+            received_block_index,
+            &conn,
+        )?;
+        let status = AccountTxoStatus::get(&account.account_id_hex, &txo.txo_id_hex, &conn)?;
+        // The blocks which spent the Txo were scanned while it had no key image, so
+        // check the ledger for it now.
+        if let Some(spent_block_index) = ledger_db.check_key_image(&key_image)? {
+            txo.update_spent_block_index(spent_block_index as i64, &conn)?;
+            status.set_spent(&conn)?;
+        } else if (txo.value as u64) < dust_threshold && status.txo_type != TXO_TYPE_MINTED {
+            status.set_dust(&conn)?;
+        } else {
+            status.set_unspent(&conn)?;
+        }
+        num_recovered += 1;
+    }
+
+    Ok(num_recovered)
+}
+
 /// Helper function for matching a list of TxOuts to a given account.
+///
+/// Received Txos with a value below `dust_threshold` are recorded as dust,
+/// unless the account minted them itself, such as its change.
 pub fn process_txos(
-    conn: &PooledConnection<ConnectionManager<SqliteConnection>>,
+    conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
     outputs: &[TxOut],
     account: &Account,
     received_block_index: i64,
+    dust_threshold: u64,
     logger: &Logger,
 ) -> Result<HashMap<i64, Vec<String>>, SyncError> {
     let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
@@ -504,6 +923,23 @@ pub fn process_txos(
             &conn,
         )?;
 
+        // A spendable Txo below the dust threshold is kept out of selection and the
+        // unspent balance. Txos the account sent itself, such as change, are not
+        // spam, so they stay spendable.
+        if value < dust_threshold {
+            let status = AccountTxoStatus::get(&account_id_hex, &txo_id, &conn)?;
+            if status.txo_status == TXO_STATUS_UNSPENT && status.txo_type != TXO_TYPE_MINTED {
+                log::debug!(
+                    logger,
+                    "categorizing txo {} of value {} as dust for account {}",
+                    txo_id,
+                    value,
+                    account_id_hex,
+                );
+                status.set_dust(&conn)?;
+            }
+        }
+
         // If we couldn't find an assigned subaddress for this value, store for -1
         let subaddress_key: i64 = subaddress_index.unwrap_or(-1) as i64;
         if output_txo_ids.get(&(subaddress_key)).is_none() {
@@ -519,5 +955,772 @@ pub fn process_txos(
     Ok(output_txo_ids)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::models::{TXO_STATUS_INVALIDATED, TXO_STATUS_SPENT},
+        test_utils::{
+            add_block_to_ledger_db, get_test_ledger, wait_for_sync, WalletDbTestContext, MOB,
+        },
+    };
+    use mc_account_keys::{PublicAddress, RootIdentity};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    // Archived accounts are not synced while skip_archived_accounts is set, and
+    // catch up once unarchived.
+    #[test_with_logger]
+    fn test_sync_skips_archived_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        let mut account_ids = Vec::new();
+        for _ in 0..2 {
+            let root_id = RootIdentity::from_random(&mut rng);
+            let (account_id, _b58) = Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                "",
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+            account_ids.push(account_id);
+        }
+        let archived_account = Account::get(&account_ids[0], &conn).unwrap();
+        archived_account.update_archived(true, &conn).unwrap();
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig {
+                skip_archived_accounts: true,
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+        wait_for_sync(
+            &ledger_db,
+            &wallet_db,
+            &account_ids[1],
+            ledger_db.num_blocks().unwrap(),
+        );
+        sync_thread.stop();
+
+        let archived_account = Account::get(&account_ids[0], &conn).unwrap();
+        assert_eq!(archived_account.next_block_index, 0);
+
+        archived_account.update_archived(false, &conn).unwrap();
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig {
+                skip_archived_accounts: true,
+                ..Default::default()
+            },
+            logger.clone(),
+        );
+        wait_for_sync(
+            &ledger_db,
+            &wallet_db,
+            &account_ids[0],
+            ledger_db.num_blocks().unwrap(),
+        );
+    }
+
+    fn create_accounts(
+        num_accounts: usize,
+        wallet_db: &WalletDb,
+        rng: &mut StdRng,
+    ) -> Vec<AccountID> {
+        let conn = wallet_db.get_conn().unwrap();
+        (0..num_accounts)
+            .map(|_| {
+                let root_id = RootIdentity::from_random(rng);
+                let (account_id, _b58) = Account::create_from_root_entropy(
+                    &root_id.root_entropy,
+                    Some(0),
+                    None,
+                    None,
+                    "",
+                    None,
+                    None,
+                    None,
+                    &conn,
+                )
+                .unwrap();
+                account_id
+            })
+            .collect()
+    }
+
+    // Sync an account to the tip of the ledger, reading blocks through the given
+    // cache.
+    fn sync_to_tip(
+        ledger_db: &LedgerDB,
+        wallet_db: &WalletDb,
+        cache: &BlockContentsCache,
+        account_id: &AccountID,
+        logger: &Logger,
+    ) {
+        while sync_account_with_cache(
+            ledger_db,
+            wallet_db,
+            cache,
+            &account_id.to_string(),
+            DEFAULT_SCAN_BATCH_SIZE,
+            logger,
+        )
+        .unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+        let account = Account::get(account_id, &wallet_db.get_conn().unwrap()).unwrap();
+        assert_eq!(
+            account.next_block_index as u64,
+            ledger_db.num_blocks().unwrap()
+        );
+    }
+
+    // Accounts scanning the same blocks through a shared cache read each block
+    // from the ledger once, instead of once per account.
+    #[test_with_logger]
+    fn test_sync_shares_block_reads_across_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        // Two wallets with the same accounts, so that each starts syncing from
+        // the origin block.
+        let shared_db_test_context = WalletDbTestContext::default();
+        let shared_wallet_db = shared_db_test_context.get_db_instance(logger.clone());
+        let account_ids = create_accounts(10, &shared_wallet_db, &mut rng.clone());
+        let unshared_db_test_context = WalletDbTestContext::default();
+        let unshared_wallet_db = unshared_db_test_context.get_db_instance(logger.clone());
+        assert_eq!(
+            create_accounts(10, &unshared_wallet_db, &mut rng),
+            account_ids
+        );
+
+        let shared_cache = BlockContentsCache::default();
+        let mut num_unshared_reads = 0;
+        for account_id in account_ids.iter() {
+            sync_to_tip(
+                &ledger_db,
+                &shared_wallet_db,
+                &shared_cache,
+                account_id,
+                &logger,
+            );
+
+            let unshared_cache = BlockContentsCache::default();
+            sync_to_tip(
+                &ledger_db,
+                &unshared_wallet_db,
+                &unshared_cache,
+                account_id,
+                &logger,
+            );
+            num_unshared_reads += unshared_cache.num_ledger_reads();
+        }
+
+        assert_eq!(shared_cache.num_ledger_reads(), num_blocks);
+        assert_eq!(num_unshared_reads, num_blocks * account_ids.len() as u64);
+    }
+
+    // Many accounts are caught up in parallel by the worker threads, which share
+    // their block reads. A block is read again only when several workers miss
+    // the cache for it at once.
+    #[test_with_logger]
+    fn test_sync_many_accounts_in_parallel(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let account_ids = create_accounts(20, &wallet_db, &mut rng);
+
+        let num_workers = 4;
+        let sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            Some(num_workers),
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+        for account_id in account_ids.iter() {
+            wait_for_sync(&ledger_db, &wallet_db, account_id, num_blocks);
+        }
+
+        assert!(sync_thread.num_ledger_reads() >= num_blocks);
+        assert!(sync_thread.num_ledger_reads() <= num_blocks * num_workers as u64);
+    }
+
+    // A block which replaced a cached block at the same index is read from the
+    // ledger, rather than served from the cache.
+    #[test_with_logger]
+    fn test_block_contents_cache_keyed_on_block_id(_logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let ledger_db = get_test_ledger(5, &[], 12, &mut rng);
+        let replaced_ledger_db = get_test_ledger(5, &[], 12, &mut rng);
+
+        let cache = BlockContentsCache::default();
+        let block_contents = cache.get_block_contents(&ledger_db, 5).unwrap();
+        assert_eq!(*block_contents, ledger_db.get_block_contents(5).unwrap());
+        cache.get_block_contents(&ledger_db, 5).unwrap();
+        assert_eq!(cache.num_ledger_reads(), 1);
+
+        let block_contents = cache.get_block_contents(&replaced_ledger_db, 5).unwrap();
+        assert_eq!(
+            *block_contents,
+            replaced_ledger_db.get_block_contents(5).unwrap()
+        );
+        assert_eq!(cache.num_ledger_reads(), 2);
+    }
+
+    // Txos received at a subaddress before it is assigned should become spendable
+    // once the subaddress is assigned and the account syncs again.
+    #[test_with_logger]
+    fn test_sync_recovers_orphaned_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        // Pay subaddress 3, which is not assigned when the account is created.
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(3)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap();
+
+        let orphaned = Txo::list_by_status(&account_id_hex, TXO_STATUS_ORPHANED, &conn).unwrap();
+        assert!(!orphaned.is_empty());
+        assert!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Assign subaddresses 2 and 3.
+        AssignedSubaddress::create_next_for_account(&account_id_hex, "", &conn).unwrap();
+        let (_b58, subaddress_index) =
+            AssignedSubaddress::create_next_for_account(&account_id_hex, "", &conn).unwrap();
+        assert_eq!(subaddress_index, 3);
+
+        // There are no new blocks, but syncing should still retry the orphans.
+        sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap();
+
+        assert!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_ORPHANED, &conn)
+                .unwrap()
+                .is_empty()
+        );
+        let unspent = Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn).unwrap();
+        assert_eq!(unspent.len(), orphaned.len());
+        for txo in unspent {
+            assert_eq!(txo.subaddress_index, Some(3));
+            assert!(txo.key_image.is_some());
+        }
+    }
+
+    // An orphaned Txo whose key image reached the ledger while it was orphaned
+    // should be recovered as spent.
+    #[test_with_logger]
+    fn test_sync_recovers_spent_orphaned_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        // Pay subaddress 3, which is not assigned when the account is created.
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(3)];
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap();
+        let orphaned = Txo::list_by_status(&account_id_hex, TXO_STATUS_ORPHANED, &conn).unwrap();
+        assert!(orphaned.len() > 1);
+
+        // Spend one of the orphans in a new block, and sync past it while the
+        // Txo is still orphaned, so that the block's key images match nothing.
+        let spent_txo = &orphaned[0];
+        let tx_out: TxOut = mc_util_serial::decode(&spent_txo.txo).unwrap();
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+            account_key.view_private_key(),
+            &account_key.subaddress_spend_private(3),
+        );
+        let spent_block_index = add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            100 * MOB as u64,
+            &[KeyImage::from(&onetime_private_key)],
+            &mut rng,
+        ) - 1;
+        while sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+        assert_eq!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_ORPHANED, &conn)
+                .unwrap()
+                .len(),
+            orphaned.len()
+        );
+
+        // Assign subaddresses 2 and 3, and recover the orphans.
+        AssignedSubaddress::create_next_for_account(&account_id_hex, "", &conn).unwrap();
+        AssignedSubaddress::create_next_for_account(&account_id_hex, "", &conn).unwrap();
+        sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap();
+
+        let spent = Txo::list_by_status(&account_id_hex, TXO_STATUS_SPENT, &conn).unwrap();
+        assert_eq!(spent.len(), 1);
+        assert_eq!(spent[0].txo_id_hex, spent_txo.txo_id_hex);
+        assert_eq!(spent[0].spent_block_index, Some(spent_block_index as i64));
+        assert_eq!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn)
+                .unwrap()
+                .len(),
+            orphaned.len() - 1
+        );
+    }
+
+    // Txos which are no longer in the ledger at their received block, as after a
+    // rollback, should be invalidated rather than counted as unspent.
+    #[test_with_logger]
+    fn test_sync_invalidates_txos_missing_from_ledger(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(0)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        while sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+        let unspent = Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn).unwrap();
+        assert!(unspent.len() > 1);
+
+        // Dust is checked as well.
+        AccountTxoStatus::get(&account_id_hex, &unspent[0].txo_id_hex, &conn)
+            .unwrap()
+            .set_dust(&conn)
+            .unwrap();
+
+        // Roll back to a ledger of the same height whose blocks do not pay the
+        // account. Syncing alone does not re-check the received Txos.
+        let rolled_back_ledger_db = get_test_ledger(5, &[], 12, &mut rng);
+        sync_account(&rolled_back_ledger_db, &wallet_db, &account_id_hex, &logger).unwrap();
+        assert_eq!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn)
+                .unwrap()
+                .len(),
+            unspent.len() - 1
+        );
+
+        let block_contents_cache = BlockContentsCache::default();
+        handle_ledger_rollback(
+            &rolled_back_ledger_db,
+            &wallet_db,
+            &block_contents_cache,
+            &logger,
+        )
+        .unwrap();
+        assert!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn)
+                .unwrap()
+                .is_empty()
+        );
+        let invalidated =
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_INVALIDATED, &conn).unwrap();
+        assert_eq!(invalidated.len(), unspent.len());
+    }
+
+    // After a rollback to a shorter ledger, accounts should sync the blocks which
+    // replace the rolled back ones, and find the Txos they hold.
+    #[test_with_logger]
+    fn test_sync_after_rollback_scans_regrown_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id = AccountID::from(&account_key);
+        let account_id_hex = account_id.to_string();
+
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(0)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 14, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        while sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index,
+            14
+        );
+
+        // Roll back to a shorter ledger whose blocks do not pay the account.
+        let mut rolled_back_ledger_db = get_test_ledger(5, &[], 12, &mut rng);
+        handle_ledger_rollback(
+            &rolled_back_ledger_db,
+            &wallet_db,
+            &BlockContentsCache::default(),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index,
+            12
+        );
+        assert!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn)
+                .unwrap()
+                .is_empty()
+        );
+
+        // The ledger regrows past its old height, with blocks paying the account.
+        for _ in 0..3 {
+            add_block_to_ledger_db(
+                &mut rolled_back_ledger_db,
+                &[account_key.subaddress(0)],
+                7 * MOB as u64,
+                &[KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        while sync_account(&rolled_back_ledger_db, &wallet_db, &account_id_hex, &logger).unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index,
+            15
+        );
+        let unspent = Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn).unwrap();
+        assert_eq!(unspent.len(), 3);
+        assert!(unspent.iter().all(|txo| txo.value == 7 * MOB));
+    }
+
+    // A received Txo below the dust threshold is recorded, but categorized as dust
+    // rather than unspent, so it is not spendable.
+    #[test_with_logger]
+    fn test_sync_categorizes_dust(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(0)];
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &known_recipients,
+            1000,
+            &[KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        wallet_db.set_dust_threshold(MOB as u64);
+        let conn = wallet_db.get_conn().unwrap();
+        Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        while sync_account(&ledger_db, &wallet_db, &account_id_hex, &logger).unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+
+        let dust = Txo::list_by_status(&account_id_hex, TXO_STATUS_DUST, &conn).unwrap();
+        assert_eq!(dust.len(), 1);
+        assert_eq!(dust[0].value, 1000);
+        assert_eq!(dust[0].received_block_index, Some(12));
+
+        let unspent = Txo::list_by_status(&account_id_hex, TXO_STATUS_UNSPENT, &conn).unwrap();
+        assert_eq!(unspent.len(), 12);
+        assert!(unspent.iter().all(|t| t.value as u64 >= MOB as u64));
+
+        let spendable = Txo::list_spendable(&account_id_hex, &conn).unwrap();
+        assert!(spendable.iter().all(|t| t.txo_id_hex != dust[0].txo_id_hex));
+
+        // Syncing the same block again leaves the Txo as dust.
+        let account = Account::get(&AccountID(account_id_hex.clone()), &conn).unwrap();
+        let block_contents = ledger_db.get_block_contents(12).unwrap();
+        process_txos(
+            &conn,
+            &block_contents.outputs,
+            &account,
+            12,
+            wallet_db.dust_threshold(),
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(
+            Txo::list_by_status(&account_id_hex, TXO_STATUS_DUST, &conn)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    // A larger scan batch finds the same Txos as scanning block by block, in
+    // fewer passes and database transactions.
+    #[test_with_logger]
+    fn test_sync_with_scan_batch_size(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id = AccountID::from(&account_key);
+
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(0)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        let mut synced_txo_ids = Vec::new();
+        let mut num_passes = Vec::new();
+        for scan_batch_size in [1, 4].iter() {
+            let db_test_context = WalletDbTestContext::default();
+            let wallet_db = db_test_context.get_db_instance(logger.clone());
+            let conn = wallet_db.get_conn().unwrap();
+            Account::create_from_root_entropy(
+                &root_id.root_entropy,
+                Some(0),
+                None,
+                None,
+                "",
+                None,
+                None,
+                None,
+                &conn,
+            )
+            .unwrap();
+
+            let cache = BlockContentsCache::default();
+            let start = std::time::Instant::now();
+            let mut passes = 1;
+            while sync_account_with_cache(
+                &ledger_db,
+                &wallet_db,
+                &cache,
+                &account_id.to_string(),
+                *scan_batch_size,
+                &logger,
+            )
+            .unwrap()
+                != SyncAccountOk::NoMoreBlocks
+            {
+                passes += 1;
+            }
+            log::info!(
+                logger,
+                "synced {} blocks with scan_batch_size {} in {} passes, {:?}",
+                num_blocks,
+                scan_batch_size,
+                passes,
+                start.elapsed()
+            );
+
+            let account = Account::get(&account_id, &conn).unwrap();
+            assert_eq!(account.next_block_index as u64, num_blocks);
+
+            let mut txo_ids: Vec<String> =
+                Txo::list_for_account(&account_id.to_string(), false, &conn)
+                    .unwrap()
+                    .into_iter()
+                    .map(|txo| txo.txo.txo_id_hex)
+                    .collect();
+            txo_ids.sort();
+            assert!(!txo_ids.is_empty());
+            synced_txo_ids.push(txo_ids);
+            num_passes.push(passes);
+        }
+
+        assert_eq!(synced_txo_ids[0], synced_txo_ids[1]);
+        // Block by block, each pass processes MAX_BLOCKS_PROCESSING_CHUNK_SIZE
+        // blocks. With batches of 4, one pass processes all 12 blocks.
+        assert_eq!(num_passes, vec![3, 1]);
+    }
+
+    // If the wallet stops after writing a block's Txos but before advancing
+    // next_block_index, the Txos are rolled back and found again on restart.
+    #[test_with_logger]
+    fn test_sync_resumes_after_crash_mid_block(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let account_key = AccountKey::from(&root_id);
+        let account_id = AccountID::from(&account_key);
+
+        let known_recipients: Vec<PublicAddress> = vec![account_key.subaddress(0)];
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+
+        // Write the first block's Txos, then stop before advancing next_block_index.
+        let mut written_txo_ids: Vec<String> = Vec::new();
+        let crashed = conn.transaction::<(), SyncError, _>(|| {
+            let account = Account::get(&account_id, &conn)?;
+            let block_contents = ledger_db.get_block_contents(account.next_block_index as u64)?;
+            let output_txo_ids = process_txos(
+                &conn,
+                &block_contents.outputs,
+                &account,
+                account.next_block_index,
+                0,
+                &logger,
+            )?;
+            written_txo_ids = output_txo_ids.values().flatten().cloned().collect();
+            Err(SyncError::Diesel(
+                diesel::result::Error::RollbackTransaction,
+            ))
+        });
+        assert!(crashed.is_err());
+        assert!(!written_txo_ids.is_empty());
+
+        // Nothing from the block was committed.
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index,
+            0
+        );
+        assert!(Txo::list_for_account(&account_id.to_string(), false, &conn)
+            .unwrap()
+            .is_empty());
+
+        // On restart, sync resumes at the same block and finds its Txos.
+        while sync_account(&ledger_db, &wallet_db, &account_id.to_string(), &logger).unwrap()
+            != SyncAccountOk::NoMoreBlocks
+        {}
+        assert_eq!(
+            Account::get(&account_id, &conn).unwrap().next_block_index as u64,
+            ledger_db.num_blocks().unwrap()
+        );
+        let synced_txo_ids: HashSet<String> =
+            Txo::list_for_account(&account_id.to_string(), false, &conn)
+                .unwrap()
+                .into_iter()
+                .map(|txo| txo.txo.txo_id_hex)
+                .collect();
+        for txo_id in written_txo_ids {
+            assert!(synced_txo_ids.contains(&txo_id));
+        }
+    }
+}
+
 // FIXME: test select received txo by value
 // FIXME: test syncing after removing account
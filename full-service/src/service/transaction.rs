@@ -4,22 +4,113 @@
 
 use crate::{
     db::{
-        b58_decode,
-        models::TransactionLog,
-        transaction_log::{AssociatedTxos, TransactionLogModel},
+        account::{AccountID, AccountModel},
+        b58_decode, b58_encode,
+        models::{
+            Account, TransactionBuild, TransactionLog, Txo, TXO_STATUS_PENDING, TXO_STATUS_UNSPENT,
+            TX_DIRECTION_SENT, TX_STATUS_BUILT, TX_STATUS_FAILED, TX_STATUS_PENDING,
+            TX_STATUS_SUCCEEDED,
+        },
+        transaction_build::TransactionBuildModel,
+        transaction_log::{AssociatedTxos, TransactionID, TransactionLogModel},
+        txo::TxoModel,
+        txo_reservations::{TxoReservation, DEFAULT_RESERVATION_EXPIRY},
+        txo_selection::SelectionOptions,
         WalletDbError,
     },
     error::WalletTransactionBuilderError,
-    service::{transaction_builder::WalletTransactionBuilder, WalletService},
+    service::{
+        transaction_builder::{ChangeStrategy, WalletTransactionBuilder},
+        WalletService,
+    },
 };
+use mc_account_keys::{AccountKey, PublicAddress};
 use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, RetryableUserTxConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
-use mc_mobilecoind::payments::TxProposal;
+use mc_ledger_db::Ledger;
+use mc_mobilecoind::{
+    payments::{Outlay, TxProposal},
+    UnspentTxOut,
+};
+use mc_transaction_core::{
+    constants::{MAX_INPUTS, MINIMUM_FEE},
+    tx::Tx,
+};
 
 use crate::service::address::{AddressService, AddressServiceError};
 use displaydoc::Display;
-use std::{convert::TryFrom, iter::empty, sync::atomic::Ordering};
+use prost::Message;
+use rand::RngCore;
+use std::{
+    convert::TryFrom,
+    iter::empty,
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The version of the binary TxProposal encoding produced by
+/// serialize_tx_proposal.
+pub const TX_PROPOSAL_ENCODING_VERSION: u32 = 1;
+
+/// How long wait_for_landing waits by default for a transaction to land.
+pub const DEFAULT_LANDING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The longest wait_for_landing will wait. By then a transaction with the
+/// default tombstone window has either landed or failed, so a longer wait
+/// would only tie up the caller.
+pub const MAX_LANDING_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often wait_for_landing checks whether a transaction has landed.
+const LANDING_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Options for building a transaction, beyond its recipient, value, inputs,
+/// fee and tombstone block.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionBuildOptions {
+    /// When selecting inputs, only Txos with at least this many blocks on top
+    /// of the block in which they were received are used.
+    pub min_confirmations: Option<String>,
+
+    /// If true and the inputs cannot be selected without Txos younger than
+    /// min_confirmations, an InsufficientConfirmations error lists them.
+    pub strict_confirmations: Option<bool>,
+
+    /// If true, the most valuable spendable Txos (up to MAX_INPUTS) are sent
+    /// to the recipient, less the fee, with no change. The value, input Txos
+    /// and max_spendable_value are then ignored.
+    pub send_all: Option<bool>,
+
+    /// The number of outputs of nearly equal value the payment is split into,
+    /// so that the amount sent is not revealed by a single output.
+    pub num_outputs: Option<String>,
+
+    /// The number of outputs of nearly equal value the change is split into.
+    pub num_change_outputs: Option<String>,
+
+    /// If true, the inputs are taken from a single subaddress when one holds
+    /// enough.
+    pub prefer_single_subaddress: Option<bool>,
+
+    /// Txo values in pMOB to spend first. The inputs are taken from only the
+    /// Txos with one of these values when they are enough.
+    pub preferred_denominations: Option<Vec<String>>,
+}
+
+/// The transactions needed to consolidate an account's spendable Txos into
+/// one, returned by estimate_consolidation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsolidationEstimate {
+    /// The number of spendable Txos the account holds.
+    pub num_txos: u64,
+
+    /// The number of sweep_account transactions needed to leave a single Txo.
+    pub num_transactions: u64,
+
+    /// The sum of the fees of those transactions.
+    pub total_fee: u64,
+}
 
 /// Errors for the Transaction Service.
 #[derive(Display, Debug)]
@@ -61,6 +152,54 @@ pub enum TransactionServiceError {
 
     /// Address Service Error: {0}
     AddressService(AddressServiceError),
+
+    /// Transaction is not pending, and cannot be replaced: {0}
+    TransactionNotPending(String),
+
+    /// Transaction has already been replaced: {0}
+    TransactionAlreadyReplaced(String),
+
+    /// Transaction {0} cannot be rebuilt, as its outlays were not recorded
+    OutlaysNotRecorded(String),
+
+    /// New fee must be greater than the original fee of {0}
+    FeeNotIncreased(u64),
+
+    /// Transaction {0} failed to land in the ledger
+    TransactionFailed(String),
+
+    /// Timed out after {0} seconds waiting for transaction {1} to land
+    LandingTimeout(u64, String),
+
+    /// Landing timeout must be at most {0} seconds
+    LandingTimeoutTooLong(u64),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Transaction {0} was rejected by consensus and will not land: {1}
+    SubmissionRejected(String, String),
+
+    /// Transaction {0} could not be submitted, and may be submitted again: {1}
+    SubmissionRetryable(String, String),
+
+    /// Error decoding prost: {0}
+    ProstDecode(prost::DecodeError),
+
+    /// Error encoding prost: {0}
+    ProstEncode(prost::EncodeError),
+
+    /// Unsupported TxProposal encoding version: {0}
+    UnsupportedTxProposalVersion(u32),
+
+    /// Txo reservation not found or expired: {0}
+    ReservationNotFound(String),
+
+    /// The TxProposal does not match the one recorded for build {0}
+    BuildMismatch(String),
+
+    /// Build {0} was recorded for another account
+    BuildForAnotherAccount(String),
 }
 
 impl From<WalletDbError> for TransactionServiceError {
@@ -99,10 +238,44 @@ impl From<AddressServiceError> for TransactionServiceError {
     }
 }
 
+impl From<prost::DecodeError> for TransactionServiceError {
+    fn from(src: prost::DecodeError) -> Self {
+        Self::ProstDecode(src)
+    }
+}
+
+impl From<prost::EncodeError> for TransactionServiceError {
+    fn from(src: prost::EncodeError) -> Self {
+        Self::ProstEncode(src)
+    }
+}
+
+impl From<mc_ledger_db::Error> for TransactionServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+/// Whether a failure to submit a transaction is transient, so that submitting
+/// the same transaction again may succeed.
+///
+/// Only a transaction which consensus validated and rejected is known never to
+/// land. Network, attestation and other failures are retryable.
+pub fn is_retryable_submission_error(error: &retry::Error<mc_connection::Error>) -> bool {
+    !matches!(
+        error,
+        retry::Error::Operation {
+            error: mc_connection::Error::TransactionValidation(_),
+            ..
+        }
+    )
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// transactions.
 pub trait TransactionService {
-    /// Builds a transaction from the given account to the specified recipient.
+    /// Builds a transaction from the given account to the specified recipient,
+    /// with the given TransactionBuildOptions.
     #[allow(clippy::too_many_arguments)]
     fn build_transaction(
         &self,
@@ -113,9 +286,17 @@ pub trait TransactionService {
         fee: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        options: TransactionBuildOptions,
     ) -> Result<TxProposal, TransactionServiceError>;
 
     /// Submits a pre-built TxProposal to the MobileCoin Consensus Network.
+    ///
+    /// If the submission fails, the transaction is still logged for the
+    /// account. A transaction rejected by consensus fails with
+    /// SubmissionRejected, and its log is marked as failed. Otherwise it fails
+    /// with SubmissionRetryable, and its log stays pending so that the same
+    /// proposal can be submitted again. A failed transaction which is accepted
+    /// when submitted again is pending again.
     fn submit_transaction(
         &self,
         tx_proposal: TxProposal,
@@ -134,8 +315,129 @@ pub trait TransactionService {
         fee: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        options: TransactionBuildOptions,
+        comment: Option<String>,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError>;
+
+    /// Replaces a pending transaction with one paying a higher fee.
+    ///
+    /// The replacement pays the same outlays as the original, from the same
+    /// inputs, with a fresh tombstone block. Because both transactions
+    /// spend the same key images, at most one of them can land. Both
+    /// TransactionLogs stay pending until one of them lands, when the other is
+    /// marked as failed. The original is the parent of the replacement's
+    /// TransactionLog.
+    fn bump_fee(
+        &self,
+        transaction_log_id: &str,
+        new_fee: u64,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError>;
+
+    /// Blocks until a submitted transaction lands in the ledger and the wallet
+    /// marks its TransactionLog as succeeded.
+    ///
+    /// Fails if the transaction fails, for example because its tombstone block
+    /// passed, or if it has not landed within `timeout`, which must be at most
+    /// MAX_LANDING_TIMEOUT.
+    fn wait_for_landing(
+        &self,
+        transaction_log_id: &str,
+        timeout: Duration,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError>;
+
+    /// Sends the most valuable spendable Txos of an account (up to MAX_INPUTS)
+    /// to a single destination, less the fee, with no change.
+    ///
+    /// If `destination_address` is None, the Txos are sent to the account's own
+    /// main address, consolidating them into one Txo. Otherwise they are swept
+    /// out of the account to the given address.
+    fn sweep_account(
+        &self,
+        account_id_hex: &str,
+        destination_address: Option<String>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
         comment: Option<String>,
     ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError>;
+
+    /// Estimates what it takes to consolidate an account's spendable Txos into
+    /// one by repeatedly sweeping the account to itself, paying `fee` (by
+    /// default MINIMUM_FEE) per transaction.
+    ///
+    /// Each sweep spends up to MAX_INPUTS Txos and leaves one in their place,
+    /// so n Txos take ceil((n - 1) / (MAX_INPUTS - 1)) transactions.
+    fn estimate_consolidation(
+        &self,
+        account_id_hex: &str,
+        fee: Option<String>,
+    ) -> Result<ConsolidationEstimate, TransactionServiceError>;
+
+    /// Encodes a TxProposal as versioned bytes, so that it can be handed
+    /// between an offline signer and a node which submits it.
+    ///
+    /// The encoding is canonical: the same proposal always produces the same
+    /// bytes.
+    fn serialize_tx_proposal(
+        &self,
+        tx_proposal: &TxProposal,
+    ) -> Result<Vec<u8>, TransactionServiceError>;
+
+    /// Decodes a TxProposal from bytes produced by serialize_tx_proposal.
+    fn deserialize_tx_proposal(
+        &self,
+        tx_proposal_bytes: &[u8],
+    ) -> Result<TxProposal, TransactionServiceError>;
+
+    /// Records a TxProposal built for an account, so that it can be verified
+    /// when it is submitted, for example after passing through an offline
+    /// signer.
+    ///
+    /// Returns the ID of the build.
+    fn record_build(
+        &self,
+        account_id_hex: &str,
+        tx_proposal: &TxProposal,
+    ) -> Result<String, TransactionServiceError>;
+
+    /// Checks that a TxProposal is exactly the one recorded for a build of the
+    /// account submitting it. Fails with BuildForAnotherAccount if the build
+    /// was recorded for another account, and with BuildMismatch if the
+    /// proposal was altered since.
+    fn verify_build(
+        &self,
+        account_id_hex: &str,
+        build_id: &str,
+        tx_proposal: &TxProposal,
+    ) -> Result<(), TransactionServiceError>;
+
+    /// Removes a recorded build, once its proposal has been submitted. Builds
+    /// which are never submitted are removed by the sync thread once their
+    /// tombstone block has passed.
+    fn remove_build(&self, build_id: &str) -> Result<(), TransactionServiceError>;
+
+    /// Reserves spendable Txos of an account covering `value` plus the minimum
+    /// fee, so that they are not selected by any other transaction.
+    ///
+    /// The reservation expires after `expiry_seconds` (by default
+    /// DEFAULT_RESERVATION_EXPIRY), releasing its Txos, unless a transaction
+    /// is built with it first.
+    fn reserve_txos(
+        &self,
+        account_id_hex: &str,
+        value: String,
+        expiry_seconds: Option<String>,
+    ) -> Result<TxoReservation, TransactionServiceError>;
+
+    /// Builds a transaction spending the Txos of a reservation, and releases
+    /// the reservation.
+    fn build_with_reservation(
+        &self,
+        reservation_id: &str,
+        recipient_public_address: &str,
+        value: String,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    ) -> Result<TxProposal, TransactionServiceError>;
 }
 
 impl<T, FPR> TransactionService for WalletService<T, FPR>
@@ -152,6 +454,7 @@ where
         fee: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        options: TransactionBuildOptions,
     ) -> Result<TxProposal, TransactionServiceError> {
         let mut builder = WalletTransactionBuilder::new(
             account_id_hex.to_string(),
@@ -166,25 +469,48 @@ where
             ));
         };
         let recipient = b58_decode(recipient_public_address)?;
-        builder.add_recipient(recipient, value.parse::<u64>()?)?;
-        if let Some(inputs) = input_txo_ids {
-            builder.set_txos(inputs)?;
+        if let Some(f) = fee {
+            builder.set_fee(f.parse::<u64>()?)?;
+        }
+        if let Some(mc) = options.min_confirmations {
+            builder.set_min_confirmations(mc.parse::<u64>()?)?;
+        }
+        builder.set_strict_confirmations(options.strict_confirmations.unwrap_or(false))?;
+        if let Some(n) = options.num_outputs {
+            builder.set_num_outputs_per_recipient(n.parse::<u64>()?)?;
+        }
+        if let Some(n) = options.num_change_outputs {
+            builder.set_change_strategy(ChangeStrategy::NumOutputs(n.parse::<u64>()?))?;
+        }
+        builder.set_prefer_single_subaddress(options.prefer_single_subaddress.unwrap_or(false))?;
+        if let Some(denominations) = options.preferred_denominations {
+            builder.set_preferred_denominations(
+                denominations
+                    .iter()
+                    .map(|d| Ok(d.parse::<u64>()? as i64))
+                    .collect::<Result<Vec<i64>, TransactionServiceError>>()?,
+            )?;
+        }
+        if options.send_all.unwrap_or(false) {
+            builder.send_all(recipient)?;
         } else {
-            let max_spendable = if let Some(msv) = max_spendable_value {
-                Some(msv.parse::<u64>()?)
+            builder.add_recipient(recipient, value.parse::<u64>()?)?;
+            if let Some(inputs) = input_txo_ids {
+                builder.set_txos(inputs)?;
             } else {
-                None
-            };
-            builder.select_txos(max_spendable)?;
+                let max_spendable = if let Some(msv) = max_spendable_value {
+                    Some(msv.parse::<u64>()?)
+                } else {
+                    None
+                };
+                builder.select_txos(max_spendable)?;
+            }
         }
         if let Some(tombstone) = tombstone_block {
             builder.set_tombstone(tombstone.parse::<u64>()?)?;
         } else {
             builder.set_tombstone(0)?;
         }
-        if let Some(f) = fee {
-            builder.set_fee(f.parse::<u64>()?)?;
-        }
         let tx_proposal = builder.build()?;
 
         // FIXME: WS-32 - Might be nice to have a tx_proposal table so that you don't
@@ -219,12 +545,17 @@ where
         let tx = mc_transaction_core::tx::Tx::try_from(tx_proposal_proto.get_tx())
             .map_err(|_| TransactionServiceError::ProtoConversionInfallible)?;
 
-        let block_index = self
+        let block_index = match self
             .peer_manager
             .conn(responder_id)
             .ok_or(TransactionServiceError::NodeNotFound)?
             .propose_tx(&tx, empty())
-            .map_err(TransactionServiceError::from)?;
+        {
+            Ok(block_index) => block_index,
+            Err(e) => {
+                return Err(self.log_submission_failure(tx_proposal, e, comment, account_id_hex)?)
+            }
+        };
 
         log::trace!(
             self.logger,
@@ -234,14 +565,8 @@ where
         );
 
         if let Some(a) = account_id_hex {
-            // FIXME: put in db transaction
-            let transaction_log = TransactionLog::log_submitted(
-                tx_proposal,
-                block_index,
-                comment.unwrap_or_else(|| "".to_string()),
-                Some(&a),
-                &self.wallet_db.get_conn()?,
-            )?;
+            let transaction_log =
+                self.get_or_log_submitted(tx_proposal, block_index, comment, &a)?;
             let associated_txos =
                 transaction_log.get_associated_txos(&self.wallet_db.get_conn()?)?;
             Ok(Some((transaction_log, associated_txos)))
@@ -259,6 +584,7 @@ where
         fee: Option<String>,
         tombstone_block: Option<String>,
         max_spendable_value: Option<String>,
+        options: TransactionBuildOptions,
         comment: Option<String>,
     ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError> {
         let tx_proposal = self.build_transaction(
@@ -269,6 +595,7 @@ where
             fee,
             tombstone_block,
             max_spendable_value,
+            options,
         )?;
         if let Some(transaction_log_and_associated_txos) =
             self.submit_transaction(tx_proposal, comment, Some(account_id_hex.to_string()))?
@@ -278,126 +605,711 @@ where
             Err(TransactionServiceError::MissingAccountOnSubmit)
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        db::{
-            account::AccountID,
-            b58_encode,
-            models::Txo,
-            txo::{TxoDetails, TxoModel},
-        },
-        service::{account::AccountService, address::AddressService, balance::BalanceService},
-        test_utils::{
-            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
-            setup_wallet_service, wait_for_sync, MOB,
-        },
-    };
-    use mc_account_keys::{AccountKey, PublicAddress};
-    use mc_common::logger::{test_with_logger, Logger};
-    use mc_crypto_rand::rand_core::RngCore;
-    use mc_transaction_core::ring_signature::KeyImage;
-    use rand::{rngs::StdRng, SeedableRng};
+    fn bump_fee(
+        &self,
+        transaction_log_id: &str,
+        new_fee: u64,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let original = TransactionLog::get(transaction_log_id, &conn)?;
+        if original.direction != TX_DIRECTION_SENT
+            || (original.status != TX_STATUS_BUILT && original.status != TX_STATUS_PENDING)
+        {
+            return Err(TransactionServiceError::TransactionNotPending(
+                transaction_log_id.to_string(),
+            ));
+        }
 
-    // Test sending a transaction from Alice -> Bob, and then from Bob -> Alice
-    #[test_with_logger]
-    fn test_send_transaction(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+        let original_fee = original.fee.unwrap_or(0) as u64;
+        if new_fee <= original_fee {
+            return Err(TransactionServiceError::FeeNotIncreased(original_fee));
+        }
 
-        let known_recipients: Vec<PublicAddress> = Vec::new();
-        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+        // A transaction is replaced at most once. The replacement can be bumped
+        // in turn.
+        if original
+            .list_children(&conn)?
+            .iter()
+            .any(|child| child.status == TX_STATUS_BUILT || child.status == TX_STATUS_PENDING)
+        {
+            return Err(TransactionServiceError::TransactionAlreadyReplaced(
+                transaction_log_id.to_string(),
+            ));
+        }
 
-        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        // If any input is no longer unspent or pending, the original transaction has
+        // already landed and there is nothing left to replace.
+        let associated = original.get_associated_txos(&conn)?;
+        if Txo::select_by_id(&associated.inputs, &conn)?
+            .iter()
+            .any(|(_txo, status)| {
+                status.txo_status != TXO_STATUS_PENDING && status.txo_status != TXO_STATUS_UNSPENT
+            })
+        {
+            return Err(TransactionServiceError::TransactionNotPending(
+                transaction_log_id.to_string(),
+            ));
+        }
 
-        // Create our main account for the wallet
-        let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
-            .unwrap();
+        let mut builder = WalletTransactionBuilder::new(
+            original.account_id_hex.clone(),
+            self.wallet_db.clone(),
+            self.ledger_db.clone(),
+            self.fog_resolver_factory.clone(),
+            self.logger.clone(),
+        );
+        builder.set_replacement_txos(&associated.inputs)?;
+
+        // Pay each of the original's outputs again, so that a payment to several
+        // recipients, or split into several outputs, is replaced as a whole. The
+        // outputs paying the logged recipient come first, so that the replacement
+        // is logged with the same recipient.
+        let mut outputs = Txo::select_by_id(&associated.outputs, &conn)?;
+        outputs.sort_by_key(|(txo, _status)| txo.id);
+        outputs.dedup_by_key(|(txo, _status)| txo.id);
+        outputs.sort_by_key(|(txo, _status)| {
+            txo.recipient_public_address_b58.as_ref()
+                != Some(&original.recipient_public_address_b58)
+        });
+        builder.set_allow_multiple_recipients(true)?;
+        for (txo, _status) in outputs.iter() {
+            match &txo.recipient_public_address_b58 {
+                Some(recipient) => {
+                    builder.add_recipient(b58_decode(recipient)?, txo.value as u64)?
+                }
+                // Txos minted before their recipient was recorded. A single output
+                // pays the whole value to the logged recipient.
+                None if outputs.len() == 1 => builder.add_recipient(
+                    b58_decode(&original.recipient_public_address_b58)?,
+                    original.value as u64,
+                )?,
+                None => {
+                    return Err(TransactionServiceError::OutlaysNotRecorded(
+                        transaction_log_id.to_string(),
+                    ))
+                }
+            }
+        }
+        builder.set_fee(new_fee)?;
+        builder.set_tombstone(0)?;
+        let tx_proposal = builder.build()?;
 
-        // Add a block with a transaction for Alice
-        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
-        let alice_account_id = AccountID::from(&alice_account_key);
-        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
-        add_block_to_ledger_db(
-            &mut ledger_db,
-            &vec![alice_public_address.clone()],
-            100 * MOB as u64,
-            &vec![KeyImage::from(rng.next_u64())],
-            &mut rng,
+        let (transaction_log, associated_txos) = self
+            .submit_transaction(
+                tx_proposal,
+                Some(original.comment.clone()),
+                Some(original.account_id_hex.clone()),
+            )?
+            .ok_or(TransactionServiceError::MissingAccountOnSubmit)?;
+        transaction_log.set_parent(&original.transaction_id_hex, &conn)?;
+        let transaction_log = TransactionLog::get(&transaction_log.transaction_id_hex, &conn)?;
+
+        log::info!(
+            self.logger,
+            "Replaced transaction {} with {}, raising fee from {} to {}",
+            original.transaction_id_hex,
+            transaction_log.transaction_id_hex,
+            original_fee,
+            new_fee
         );
+        Ok((transaction_log, associated_txos))
+    }
 
-        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+    fn wait_for_landing(
+        &self,
+        transaction_log_id: &str,
+        timeout: Duration,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError> {
+        if timeout > MAX_LANDING_TIMEOUT {
+            return Err(TransactionServiceError::LandingTimeoutTooLong(
+                MAX_LANDING_TIMEOUT.as_secs(),
+            ));
+        }
 
-        // Verify balance for Alice
-        let balance = service
-            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
-            .unwrap();
-        assert_eq!(balance.unspent, 100 * MOB as u64);
+        let start = Instant::now();
+        loop {
+            {
+                let conn = self.wallet_db.get_conn()?;
+                let transaction_log = TransactionLog::get(transaction_log_id, &conn)?;
+                match transaction_log.status.as_str() {
+                    TX_STATUS_SUCCEEDED => {
+                        let associated_txos = transaction_log.get_associated_txos(&conn)?;
+                        return Ok((transaction_log, associated_txos));
+                    }
+                    TX_STATUS_FAILED => {
+                        return Err(TransactionServiceError::TransactionFailed(
+                            transaction_log_id.to_string(),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
 
-        // Add an account for Bob
-        let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
-            .unwrap();
-        let bob_account_key: AccountKey =
-            mc_util_serial::decode(&bob.account_key).expect("Could not decode account key");
-        let bob_account_id = AccountID::from(&bob_account_key);
+            if start.elapsed() >= timeout {
+                return Err(TransactionServiceError::LandingTimeout(
+                    timeout.as_secs(),
+                    transaction_log_id.to_string(),
+                ));
+            }
+            thread::sleep(LANDING_POLL_INTERVAL);
+        }
+    }
 
-        // Create an assigned subaddress for Bob
-        let bob_address_from_alice = service
-            .assign_address_for_account(&AccountID(bob.account_id_hex.clone()), Some("From Alice"))
-            .unwrap();
+    fn sweep_account(
+        &self,
+        account_id_hex: &str,
+        destination_address: Option<String>,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+        comment: Option<String>,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionServiceError> {
+        let destination_address = match destination_address {
+            Some(address) => address,
+            None => {
+                let account = Account::get(
+                    &AccountID(account_id_hex.to_string()),
+                    &self.wallet_db.get_conn()?,
+                )?;
+                let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+                b58_encode(&account_key.subaddress(account.main_subaddress_index as u64))?
+            }
+        };
+        log::info!(
+            self.logger,
+            "Sweeping account {} to {}",
+            account_id_hex,
+            destination_address
+        );
 
-        // Send a transaction from Alice to Bob
-        let (transaction_log, _associated_txos) = service
-            .build_and_submit(
-                &alice.account_id_hex,
-                &bob_address_from_alice.assigned_subaddress_b58,
-                (42 * MOB).to_string(),
-                None,
-                None,
-                None,
-                None,
-                None,
-            )
-            .unwrap();
-        log::info!(logger, "Built and submitted transaction from Alice");
+        // The value is ignored when sending all.
+        self.build_and_submit(
+            account_id_hex,
+            &destination_address,
+            "0".to_string(),
+            None,
+            fee,
+            tombstone_block,
+            None,
+            TransactionBuildOptions {
+                send_all: Some(true),
+                ..Default::default()
+            },
+            comment,
+        )
+    }
 
-        // NOTE: Submitting to the test ledger via propose_tx doesn't actually add the
-        // block to the ledger, because no consensus is occurring, so this is the
-        // workaround.
-        {
-            log::info!(logger, "Adding block from transaction log");
-            let conn = service.wallet_db.get_conn().unwrap();
-            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
-        }
+    fn estimate_consolidation(
+        &self,
+        account_id_hex: &str,
+        fee: Option<String>,
+    ) -> Result<ConsolidationEstimate, TransactionServiceError> {
+        let fee = match fee {
+            Some(f) => f.parse::<u64>()?,
+            None => MINIMUM_FEE,
+        };
 
-        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
-        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 14);
+        let conn = self.wallet_db.get_conn()?;
+        // Ensure the account exists.
+        Account::get(&AccountID(account_id_hex.to_string()), &conn)?;
+        let num_txos = self
+            .wallet_db
+            .spendable_txos_cache()
+            .get_spendable_txos(account_id_hex, self.ledger_db.num_blocks()?, &conn)?
+            .len() as u64;
+
+        let max_inputs = MAX_INPUTS as u64;
+        let num_transactions = if num_txos <= 1 {
+            0
+        } else {
+            (num_txos - 1 + max_inputs - 2) / (max_inputs - 1)
+        };
 
-        // Get the Txos from the transaction log
-        let transaction_txos = transaction_log
-            .get_associated_txos(&service.wallet_db.get_conn().unwrap())
-            .unwrap();
-        let secreted = transaction_txos
-            .outputs
-            .iter()
-            .map(|t| Txo::get(t, &service.wallet_db.get_conn().unwrap()).unwrap())
-            .collect::<Vec<TxoDetails>>();
-        assert_eq!(secreted.len(), 1);
-        assert_eq!(secreted[0].txo.value, 42 * MOB);
+        Ok(ConsolidationEstimate {
+            num_txos,
+            num_transactions,
+            total_fee: num_transactions * fee,
+        })
+    }
 
-        let change = transaction_txos
-            .change
+    fn serialize_tx_proposal(
+        &self,
+        tx_proposal: &TxProposal,
+    ) -> Result<Vec<u8>, TransactionServiceError> {
+        // Order the outlay map by outlay index, so that the bytes do not depend on
+        // the iteration order of the HashMap.
+        let mut outlay_index_to_tx_out_index: Vec<OutlayIndexEntry> = tx_proposal
+            .outlay_index_to_tx_out_index
             .iter()
-            .map(|t| Txo::get(t, &service.wallet_db.get_conn().unwrap()).unwrap())
-            .collect::<Vec<TxoDetails>>();
-        assert_eq!(change.len(), 1);
-        assert_eq!(change[0].txo.value, (57.99 * MOB as f64) as i64);
+            .map(|(outlay_index, tx_out_index)| OutlayIndexEntry {
+                outlay_index: *outlay_index as u64,
+                tx_out_index: *tx_out_index as u64,
+            })
+            .collect();
+        outlay_index_to_tx_out_index.sort_by_key(|entry| entry.outlay_index);
+
+        let encoded = EncodedTxProposal {
+            version: TX_PROPOSAL_ENCODING_VERSION,
+            utxos: tx_proposal.utxos.clone(),
+            outlays: tx_proposal
+                .outlays
+                .iter()
+                .map(|outlay| EncodedOutlay {
+                    value: outlay.value,
+                    receiver: outlay.receiver.clone(),
+                })
+                .collect(),
+            tx: tx_proposal.tx.clone(),
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers: tx_proposal
+                .outlay_confirmation_numbers
+                .iter()
+                .map(mc_util_serial::encode)
+                .collect(),
+        };
 
-        let inputs = transaction_txos
+        let mut bytes = Vec::with_capacity(encoded.encoded_len());
+        encoded.encode(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn deserialize_tx_proposal(
+        &self,
+        tx_proposal_bytes: &[u8],
+    ) -> Result<TxProposal, TransactionServiceError> {
+        let encoded = EncodedTxProposal::decode(tx_proposal_bytes)?;
+        if encoded.version != TX_PROPOSAL_ENCODING_VERSION {
+            return Err(TransactionServiceError::UnsupportedTxProposalVersion(
+                encoded.version,
+            ));
+        }
+
+        Ok(TxProposal {
+            utxos: encoded.utxos,
+            outlays: encoded
+                .outlays
+                .into_iter()
+                .map(|outlay| Outlay {
+                    value: outlay.value,
+                    receiver: outlay.receiver,
+                })
+                .collect(),
+            tx: encoded.tx,
+            outlay_index_to_tx_out_index: encoded
+                .outlay_index_to_tx_out_index
+                .iter()
+                .map(|entry| (entry.outlay_index as usize, entry.tx_out_index as usize))
+                .collect(),
+            outlay_confirmation_numbers: encoded
+                .outlay_confirmation_numbers
+                .iter()
+                .map(|bytes| mc_util_serial::decode(bytes))
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+
+    fn record_build(
+        &self,
+        account_id_hex: &str,
+        tx_proposal: &TxProposal,
+    ) -> Result<String, TransactionServiceError> {
+        let tx_proposal_bytes = self.serialize_tx_proposal(tx_proposal)?;
+
+        let mut build_id_bytes = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut build_id_bytes);
+        let build_id = hex::encode(build_id_bytes);
+
+        let conn = self.wallet_db.get_conn()?;
+        TransactionBuild::record(
+            &build_id,
+            account_id_hex,
+            &tx_proposal_bytes,
+            tx_proposal.tx.prefix.tombstone_block,
+            &conn,
+        )?;
+        Ok(build_id)
+    }
+
+    fn verify_build(
+        &self,
+        account_id_hex: &str,
+        build_id: &str,
+        tx_proposal: &TxProposal,
+    ) -> Result<(), TransactionServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let build = TransactionBuild::get(build_id, &conn)?;
+
+        if build.account_id_hex != account_id_hex {
+            return Err(TransactionServiceError::BuildForAnotherAccount(
+                build_id.to_string(),
+            ));
+        }
+
+        // The encoding is canonical, so any change to the proposal changes
+        // its bytes.
+        if self.serialize_tx_proposal(tx_proposal)? != build.tx_proposal {
+            return Err(TransactionServiceError::BuildMismatch(build_id.to_string()));
+        }
+        Ok(())
+    }
+
+    fn remove_build(&self, build_id: &str) -> Result<(), TransactionServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        TransactionBuild::delete(build_id, &conn)?;
+        Ok(())
+    }
+
+    fn reserve_txos(
+        &self,
+        account_id_hex: &str,
+        value: String,
+        expiry_seconds: Option<String>,
+    ) -> Result<TxoReservation, TransactionServiceError> {
+        let value = value.parse::<u64>()?;
+        let expiry = match expiry_seconds {
+            Some(s) => Duration::from_secs(s.parse::<u64>()?),
+            None => DEFAULT_RESERVATION_EXPIRY,
+        };
+        let target_value = value
+            .checked_add(MINIMUM_FEE)
+            .ok_or(WalletTransactionBuilderError::OutboundValueTooLarge)?;
+
+        let conn = self.wallet_db.get_conn()?;
+        Account::get(&AccountID(account_id_hex.to_string()), &conn)?;
+        let spendable_txos = self.wallet_db.spendable_txos_cache().get_spendable_txos(
+            account_id_hex,
+            self.ledger_db.num_blocks()?,
+            &conn,
+        )?;
+
+        let reservation = self.wallet_db.txo_reservations().reserve(
+            account_id_hex,
+            value,
+            expiry,
+            |reserved_txo_ids| {
+                let unreserved_txos: Vec<Txo> = spendable_txos
+                    .iter()
+                    .filter(|txo| !reserved_txo_ids.contains(&txo.txo_id_hex))
+                    .cloned()
+                    .collect();
+                let selection = Txo::select_from_spendable(
+                    &unreserved_txos,
+                    target_value,
+                    None,
+                    &SelectionOptions::default(),
+                )?;
+                Ok::<_, TransactionServiceError>(
+                    selection.into_iter().map(|txo| txo.txo_id_hex).collect(),
+                )
+            },
+        )?;
+        log::info!(
+            self.logger,
+            "Reserved {} Txos of account {} for value {} as {}",
+            reservation.txo_ids.len(),
+            account_id_hex,
+            value,
+            reservation.reservation_id
+        );
+        Ok(reservation)
+    }
+
+    fn build_with_reservation(
+        &self,
+        reservation_id: &str,
+        recipient_public_address: &str,
+        value: String,
+        fee: Option<String>,
+        tombstone_block: Option<String>,
+    ) -> Result<TxProposal, TransactionServiceError> {
+        let reservation = self
+            .wallet_db
+            .txo_reservations()
+            .get(reservation_id)
+            .ok_or_else(|| {
+                TransactionServiceError::ReservationNotFound(reservation_id.to_string())
+            })?;
+
+        let mut builder = WalletTransactionBuilder::new(
+            reservation.account_id_hex.clone(),
+            self.wallet_db.clone(),
+            self.ledger_db.clone(),
+            self.fog_resolver_factory.clone(),
+            self.logger.clone(),
+        );
+        if !self.verify_address(recipient_public_address)? {
+            return Err(TransactionServiceError::InvalidPublicAddress(
+                recipient_public_address.to_string(),
+            ));
+        };
+        let recipient = b58_decode(recipient_public_address)?;
+        if let Some(f) = fee {
+            builder.set_fee(f.parse::<u64>()?)?;
+        }
+        builder.add_recipient(recipient, value.parse::<u64>()?)?;
+        builder.set_txos(&reservation.txo_ids)?;
+        if let Some(tombstone) = tombstone_block {
+            builder.set_tombstone(tombstone.parse::<u64>()?)?;
+        } else {
+            builder.set_tombstone(0)?;
+        }
+        let tx_proposal = builder.build()?;
+
+        // The Txos are now spent by the proposal, which is expected to be
+        // submitted, so they are left for it rather than held until expiry.
+        self.wallet_db.txo_reservations().release(reservation_id);
+        Ok(tx_proposal)
+    }
+}
+
+/// The binary encoding of a TxProposal produced by serialize_tx_proposal.
+#[derive(Clone, PartialEq, Message)]
+struct EncodedTxProposal {
+    #[prost(uint32, tag = "1")]
+    version: u32,
+    #[prost(message, repeated, tag = "2")]
+    utxos: Vec<UnspentTxOut>,
+    #[prost(message, repeated, tag = "3")]
+    outlays: Vec<EncodedOutlay>,
+    #[prost(message, required, tag = "4")]
+    tx: Tx,
+    /// Sorted by outlay index.
+    #[prost(message, repeated, tag = "5")]
+    outlay_index_to_tx_out_index: Vec<OutlayIndexEntry>,
+    /// Each confirmation number, encoded with mc_util_serial.
+    #[prost(bytes, repeated, tag = "6")]
+    outlay_confirmation_numbers: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct EncodedOutlay {
+    #[prost(uint64, tag = "1")]
+    value: u64,
+    #[prost(message, required, tag = "2")]
+    receiver: PublicAddress,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OutlayIndexEntry {
+    #[prost(uint64, tag = "1")]
+    outlay_index: u64,
+    #[prost(uint64, tag = "2")]
+    tx_out_index: u64,
+}
+
+impl<T, FPR> WalletService<T, FPR>
+where
+    T: BlockchainConnection + UserTxConnection + 'static,
+    FPR: FogPubkeyResolver + Send + Sync + 'static,
+{
+    /// Get the TransactionLog for a submitted transaction, logging it if this
+    /// is its first submission.
+    ///
+    /// A transaction whose earlier submission failed with a retryable error is
+    /// already logged as pending. One which was rejected is logged as failed,
+    /// and is made pending again, with its inputs, now that it was submitted
+    /// again.
+    fn get_or_log_submitted(
+        &self,
+        tx_proposal: TxProposal,
+        block_index: u64,
+        comment: Option<String>,
+        account_id_hex: &str,
+    ) -> Result<TransactionLog, TransactionServiceError> {
+        // FIXME: put in db transaction
+        let conn = self.wallet_db.get_conn()?;
+        let transaction_id = TransactionID::from(&tx_proposal.tx);
+        match TransactionLog::get(&transaction_id.to_string(), &conn) {
+            Ok(transaction_log) if transaction_log.status == TX_STATUS_FAILED => {
+                transaction_log.mark_resubmitted(block_index, &conn)?;
+                Ok(TransactionLog::get(&transaction_id.to_string(), &conn)?)
+            }
+            Ok(transaction_log) => Ok(transaction_log),
+            Err(WalletDbError::TransactionLogNotFound(_)) => Ok(TransactionLog::log_submitted(
+                tx_proposal,
+                block_index,
+                comment.unwrap_or_else(|| "".to_string()),
+                Some(account_id_hex),
+                &conn,
+            )?),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Log a transaction which failed to submit, and classify the failure.
+    ///
+    /// Returns the error to report for the submission.
+    fn log_submission_failure(
+        &self,
+        tx_proposal: TxProposal,
+        error: retry::Error<mc_connection::Error>,
+        comment: Option<String>,
+        account_id_hex: Option<String>,
+    ) -> Result<TransactionServiceError, TransactionServiceError> {
+        let transaction_id = TransactionID::from(&tx_proposal.tx).to_string();
+        let retryable = is_retryable_submission_error(&error);
+        log::warn!(
+            self.logger,
+            "Failed to submit transaction {} (retryable: {}): {}",
+            transaction_id,
+            retryable,
+            error
+        );
+
+        if let Some(a) = account_id_hex {
+            let block_index = self.ledger_db.num_blocks()?;
+            let transaction_log =
+                self.get_or_log_submitted(tx_proposal, block_index, comment, &a)?;
+            if !retryable {
+                transaction_log.mark_rejected(&self.wallet_db.get_conn()?)?;
+            }
+        }
+
+        if retryable {
+            Ok(TransactionServiceError::SubmissionRetryable(
+                transaction_id,
+                error.to_string(),
+            ))
+        } else {
+            Ok(TransactionServiceError::SubmissionRejected(
+                transaction_id,
+                error.to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{models::TX_STATUS_LANDING, txo::TxoDetails},
+        service::{
+            account::{AccountService, AccountServiceError, CreateAccountOptions},
+            address::AddressService,
+            balance::BalanceService,
+            transaction_log::TransactionLogService,
+        },
+        test_utils::{
+            add_block_from_transaction_log, add_block_to_ledger_db, get_test_ledger,
+            setup_wallet_service, setup_wallet_service_with_failing_peers, wait_for_sync, MOB,
+        },
+    };
+    use mc_account_keys::PublicAddress;
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_connection_test_utils::MockBlockchainConnection;
+    use mc_crypto_rand::rand_core::RngCore;
+    use mc_fog_report_validation::MockFogPubkeyResolver;
+    use mc_ledger_db::LedgerDB;
+    use mc_transaction_core::{
+        constants::MINIMUM_FEE, ring_signature::KeyImage, validation::TransactionValidationError,
+    };
+    use mc_util_from_random::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // Test sending a transaction from Alice -> Bob, and then from Bob -> Alice
+    #[test_with_logger]
+    fn test_send_transaction(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        // Create our main account for the wallet
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        // Add a block with a transaction for Alice
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        // Verify balance for Alice
+        let balance = service
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .unwrap();
+        assert_eq!(balance.unspent, 100 * MOB as u64);
+
+        // Add an account for Bob
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey =
+            mc_util_serial::decode(&bob.account_key).expect("Could not decode account key");
+        let bob_account_id = AccountID::from(&bob_account_key);
+
+        // Create an assigned subaddress for Bob
+        let bob_address_from_alice = service
+            .assign_address_for_account(&AccountID(bob.account_id_hex.clone()), Some("From Alice"))
+            .unwrap();
+
+        // Send a transaction from Alice to Bob
+        let (transaction_log, _associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &bob_address_from_alice.assigned_subaddress_b58,
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        log::info!(logger, "Built and submitted transaction from Alice");
+
+        // NOTE: Submitting to the test ledger via propose_tx doesn't actually add the
+        // block to the ledger, because no consensus is occurring, so this is the
+        // workaround.
+        {
+            log::info!(logger, "Adding block from transaction log");
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 14);
+
+        // Get the Txos from the transaction log
+        let transaction_txos = transaction_log
+            .get_associated_txos(&service.wallet_db.get_conn().unwrap())
+            .unwrap();
+        let secreted = transaction_txos
+            .outputs
+            .iter()
+            .map(|t| Txo::get(t, &service.wallet_db.get_conn().unwrap()).unwrap())
+            .collect::<Vec<TxoDetails>>();
+        assert_eq!(secreted.len(), 1);
+        assert_eq!(secreted[0].txo.value, 42 * MOB);
+
+        let change = transaction_txos
+            .change
+            .iter()
+            .map(|t| Txo::get(t, &service.wallet_db.get_conn().unwrap()).unwrap())
+            .collect::<Vec<TxoDetails>>();
+        assert_eq!(change.len(), 1);
+        assert_eq!(change[0].txo.value, (57.99 * MOB as f64) as i64);
+
+        let inputs = transaction_txos
             .inputs
             .iter()
             .map(|t| Txo::get(t, &service.wallet_db.get_conn().unwrap()).unwrap())
@@ -427,6 +1339,7 @@ mod tests {
                 None,
                 None,
                 None,
+                TransactionBuildOptions::default(),
                 None,
             )
             .unwrap();
@@ -468,7 +1381,10 @@ mod tests {
 
         // Create our main account for the wallet
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Add a block with a transaction for Alice
@@ -493,6 +1409,7 @@ mod tests {
             None,
             None,
             None,
+            TransactionBuildOptions::default(),
         ) {
             Ok(_) => {
                 panic!("Should not be able to build transaction to invalid b58 public address")
@@ -502,7 +1419,1390 @@ mod tests {
         };
     }
 
-    // FIXME: Test with 0 change transactions
+    // Change split into several outputs should be recorded as change for each
+    // output.
+    #[test_with_logger]
+    fn test_build_with_num_change_outputs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let recipient = PublicAddress::from_random(&mut rng);
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &b58_encode(&recipient).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions {
+                    num_change_outputs: Some("2".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 3); // outlay + change
+
+        let (_transaction_log, associated_txos) = service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(associated_txos.outputs.len(), 1);
+        assert_eq!(associated_txos.change.len(), 2);
+
+        // The change is split evenly between the two outputs.
+        let conn = service.wallet_db.get_conn().unwrap();
+        let change_values: Vec<i64> = associated_txos
+            .change
+            .iter()
+            .map(|txo_id| Txo::get(txo_id, &conn).unwrap().txo.value)
+            .collect();
+        let change = 58 * MOB - MINIMUM_FEE as i64;
+        assert_eq!(change_values, vec![change / 2, change / 2]);
+    }
+
+    // A serialized TxProposal should have stable bytes and decode to the same
+    // proposal.
+    #[test_with_logger]
+    fn test_serialize_tx_proposal(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        // Split the payment, so that the proposal has several outlays.
+        let recipient = PublicAddress::from_random(&mut rng);
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &b58_encode(&recipient).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions {
+                    num_outputs: Some("3".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.outlays.len(), 3);
+
+        let bytes = service.serialize_tx_proposal(&tx_proposal).unwrap();
+        assert_eq!(bytes, service.serialize_tx_proposal(&tx_proposal).unwrap());
+
+        let decoded = service.deserialize_tx_proposal(&bytes).unwrap();
+        assert_eq!(decoded.utxos, tx_proposal.utxos);
+        assert_eq!(decoded.outlays, tx_proposal.outlays);
+        assert_eq!(decoded.tx, tx_proposal.tx);
+        assert_eq!(
+            decoded.outlay_index_to_tx_out_index,
+            tx_proposal.outlay_index_to_tx_out_index
+        );
+        assert_eq!(
+            decoded.outlay_confirmation_numbers,
+            tx_proposal.outlay_confirmation_numbers
+        );
+
+        // Round-tripping does not change the bytes.
+        assert_eq!(bytes, service.serialize_tx_proposal(&decoded).unwrap());
+
+        // An encoding from an unknown version is rejected.
+        let future_bytes = mc_util_serial::encode(&EncodedTxProposal {
+            version: TX_PROPOSAL_ENCODING_VERSION + 1,
+            ..EncodedTxProposal::decode(bytes.as_slice()).unwrap()
+        });
+        match service.deserialize_tx_proposal(&future_bytes) {
+            Err(TransactionServiceError::UnsupportedTxProposalVersion(v)) => {
+                assert_eq!(v, TX_PROPOSAL_ENCODING_VERSION + 1)
+            }
+            Ok(_) => panic!("Should not decode an unknown version"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    // A proposal altered after it was built should not pass as the recorded
+    // build.
+    #[test_with_logger]
+    fn test_verify_build_rejects_mismatch(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let recipient = PublicAddress::from_random(&mut rng);
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &b58_encode(&recipient).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+            )
+            .unwrap();
+        let build_id = service
+            .record_build(&alice.account_id_hex, &tx_proposal)
+            .unwrap();
+
+        // The proposal as built matches its build.
+        service
+            .verify_build(&alice.account_id_hex, &build_id, &tx_proposal)
+            .unwrap();
+
+        // A proposal claiming a different outlay value does not.
+        let mut tampered = tx_proposal.clone();
+        tampered.outlays[0].value += 1;
+        match service.verify_build(&alice.account_id_hex, &build_id, &tampered) {
+            Err(TransactionServiceError::BuildMismatch(id)) => assert_eq!(id, build_id),
+            Ok(_) => panic!("Should not verify a tampered proposal"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Nor does the proposal, submitted by another account.
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        match service.verify_build(&bob.account_id_hex, &build_id, &tx_proposal) {
+            Err(TransactionServiceError::BuildForAnotherAccount(id)) => assert_eq!(id, build_id),
+            Ok(_) => panic!("Should not verify a build for another account"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Neither does an unknown build.
+        match service.verify_build(&alice.account_id_hex, "deadbeef", &tx_proposal) {
+            Err(TransactionServiceError::Database(WalletDbError::TransactionBuildNotFound(id))) => {
+                assert_eq!(id, "deadbeef")
+            }
+            Ok(_) => panic!("Should not verify against an unknown build"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // A build is kept until its tombstone block has passed.
+        let conn = service.wallet_db.get_conn().unwrap();
+        let tombstone_block = tx_proposal.tx.prefix.tombstone_block as i64;
+        TransactionBuild::delete_expired(&alice.account_id_hex, tombstone_block, &conn).unwrap();
+        service
+            .verify_build(&alice.account_id_hex, &build_id, &tx_proposal)
+            .unwrap();
+        TransactionBuild::delete_expired(&alice.account_id_hex, tombstone_block + 1, &conn)
+            .unwrap();
+        match service.verify_build(&alice.account_id_hex, &build_id, &tx_proposal) {
+            Err(TransactionServiceError::Database(WalletDbError::TransactionBuildNotFound(_))) => {}
+            Ok(_) => panic!("Should not verify against an expired build"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // A build is also removed once its proposal is submitted.
+        let build_id = service
+            .record_build(&alice.account_id_hex, &tx_proposal)
+            .unwrap();
+        service.remove_build(&build_id).unwrap();
+        match service.verify_build(&alice.account_id_hex, &build_id, &tx_proposal) {
+            Err(TransactionServiceError::Database(WalletDbError::TransactionBuildNotFound(_))) => {}
+            Ok(_) => panic!("Should not verify against a removed build"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    // Sending all of Alice's Txos should empty her account with no change.
+    #[test_with_logger]
+    fn test_send_all(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        // Add two blocks with transactions for Alice
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            50 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let balance = service
+            .get_balance_for_account(&AccountID(alice.account_id_hex.clone()))
+            .unwrap();
+        assert_eq!(balance.unspent, 150 * MOB as u64);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID::from(&bob_account_key);
+        let bob_public_address = bob_account_key.subaddress(bob.main_subaddress_index as u64);
+
+        // The value is ignored when sending all.
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_public_address).unwrap(),
+                "0".to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions {
+                    send_all: Some(true),
+                    ..Default::default()
+                },
+                None,
+            )
+            .unwrap();
+        assert_eq!(associated_txos.inputs.len(), 2);
+        assert_eq!(associated_txos.outputs.len(), 1);
+        assert!(associated_txos.change.is_empty());
+        assert_eq!(transaction_log.value, 150 * MOB - MINIMUM_FEE as i64);
+
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 15);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 15);
+
+        let alice_balance = service
+            .get_balance_for_account(&AccountID(alice.account_id_hex))
+            .unwrap();
+        assert_eq!(alice_balance.unspent, 0);
+
+        let bob_balance = service
+            .get_balance_for_account(&AccountID(bob.account_id_hex))
+            .unwrap();
+        assert_eq!(bob_balance.unspent, 150 * MOB as u64 - MINIMUM_FEE);
+    }
+
+    // A sent transaction is reported as landing until the account's finality
+    // depth of blocks follow the block it landed in.
+    #[test_with_logger]
+    fn test_finality_depth(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let alice = service
+            .set_finality_depth(&alice_account_id, Some(2))
+            .unwrap();
+        assert_eq!(alice.finality_depth, Some(2));
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_public_address = bob_account_key.subaddress(bob.main_subaddress_index as u64);
+
+        let (transaction_log, _associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_public_address).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let reported_status = || {
+            let (reported, _associated_txos) = service
+                .get_transaction_log(&transaction_log.transaction_id_hex)
+                .unwrap();
+            reported.status
+        };
+
+        // The transaction has landed, but no blocks follow it yet.
+        assert_eq!(reported_status(), TX_STATUS_LANDING);
+        let logs = service.list_transaction_logs(&alice_account_id).unwrap();
+        let (listed, _associated_txos) = logs
+            .iter()
+            .find(|(log, _)| log.transaction_id_hex == transaction_log.transaction_id_hex)
+            .unwrap();
+        assert_eq!(listed.finalized_block_index, Some(13));
+        assert_eq!(listed.status, TX_STATUS_LANDING);
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![bob_public_address.clone()],
+            MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 15);
+        assert_eq!(reported_status(), TX_STATUS_LANDING);
+
+        // Two blocks now follow the block it landed in.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![bob_public_address.clone()],
+            MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 16);
+        assert_eq!(reported_status(), TX_STATUS_SUCCEEDED);
+    }
+
+    // Fund a new account with three Txos, and wait for it to sync.
+    fn create_account_with_three_txos(
+        service: &WalletService<MockBlockchainConnection<LedgerDB>, MockFogPubkeyResolver>,
+        ledger_db: &mut LedgerDB,
+        rng: &mut StdRng,
+    ) -> Account {
+        let account = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let account_key: AccountKey = mc_util_serial::decode(&account.account_key).unwrap();
+        let public_address = account_key.subaddress(account.main_subaddress_index as u64);
+        for value in [100 * MOB, 50 * MOB, 25 * MOB].iter() {
+            add_block_to_ledger_db(
+                ledger_db,
+                &vec![public_address.clone()],
+                *value as u64,
+                &vec![KeyImage::from(rng.next_u64())],
+                rng,
+            );
+        }
+        wait_for_sync(
+            ledger_db,
+            &service.wallet_db,
+            &AccountID(account.account_id_hex.clone()),
+            15,
+        );
+        account
+    }
+
+    // Reserved Txos are not selected again, are spent by the transaction built
+    // with the reservation, and are released when the reservation expires.
+    #[test_with_logger]
+    fn test_reserve_and_build(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = create_account_with_three_txos(&service, &mut ledger_db, &mut rng);
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_address =
+            b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap();
+
+        let first = service
+            .reserve_txos(&alice.account_id_hex, (20 * MOB).to_string(), None)
+            .unwrap();
+        let second = service
+            .reserve_txos(&alice.account_id_hex, (40 * MOB).to_string(), None)
+            .unwrap();
+        assert!(!first.txo_ids.is_empty());
+        assert!(!second.txo_ids.is_empty());
+        assert!(first
+            .txo_ids
+            .iter()
+            .all(|txo_id| !second.txo_ids.contains(txo_id)));
+
+        // Building consumes the reservation and spends exactly its Txos.
+        let tx_proposal = service
+            .build_with_reservation(
+                &first.reservation_id,
+                &bob_address,
+                (20 * MOB).to_string(),
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(tx_proposal.utxos.len(), first.txo_ids.len());
+        assert_eq!(tx_proposal.outlays[0].value, 20 * MOB as u64);
+        let reservations = service.wallet_db.txo_reservations();
+        assert!(reservations.get(&first.reservation_id).is_none());
+        match service.build_with_reservation(
+            &first.reservation_id,
+            &bob_address,
+            (20 * MOB).to_string(),
+            None,
+            None,
+        ) {
+            Err(TransactionServiceError::ReservationNotFound(id)) => {
+                assert_eq!(id, first.reservation_id)
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Should not build twice with a reservation"),
+        }
+
+        // A reservation which expires releases its Txos without being built.
+        let expired = service
+            .reserve_txos(
+                &alice.account_id_hex,
+                (10 * MOB).to_string(),
+                Some("0".to_string()),
+            )
+            .unwrap();
+        assert!(!expired.txo_ids.is_empty());
+        match service.build_with_reservation(
+            &expired.reservation_id,
+            &bob_address,
+            (10 * MOB).to_string(),
+            None,
+            None,
+        ) {
+            Err(TransactionServiceError::ReservationNotFound(_)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Should not build with an expired reservation"),
+        }
+        let reserved_txo_ids = reservations.reserved_txo_ids();
+        assert_eq!(reserved_txo_ids.len(), second.txo_ids.len());
+        assert!(expired
+            .txo_ids
+            .iter()
+            .all(|txo_id| !reserved_txo_ids.contains(txo_id)));
+
+        // An expiry longer than the maximum is rejected, and does not poison the
+        // reservations.
+        match service.reserve_txos(
+            &alice.account_id_hex,
+            (10 * MOB).to_string(),
+            Some(u64::MAX.to_string()),
+        ) {
+            Err(TransactionServiceError::Database(WalletDbError::ReservationExpiryTooLong(_))) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+            Ok(_) => panic!("Should not reserve with an expiry past the maximum"),
+        }
+        assert_eq!(reservations.reserved_txo_ids().len(), second.txo_ids.len());
+    }
+
+    // Sweeping an account without a destination consolidates its Txos into one,
+    // keeping the funds in the account.
+    #[test_with_logger]
+    fn test_sweep_account_consolidates(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = create_account_with_three_txos(&service, &mut ledger_db, &mut rng);
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        let alice_address =
+            b58_encode(&alice_account_key.subaddress(alice.main_subaddress_index as u64)).unwrap();
+
+        let (transaction_log, associated_txos) = service
+            .sweep_account(&alice.account_id_hex, None, None, None, None)
+            .unwrap();
+        assert_eq!(associated_txos.inputs.len(), 3);
+        assert_eq!(associated_txos.outputs.len(), 1);
+        assert!(associated_txos.change.is_empty());
+        assert_eq!(transaction_log.recipient_public_address_b58, alice_address);
+
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 16);
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 175 * MOB as u64 - MINIMUM_FEE);
+        let unspent_txos = Txo::select_max_spendable_txos(
+            &alice.account_id_hex,
+            None,
+            &[],
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(unspent_txos.len(), 1);
+    }
+
+    // The estimate for a fragmented account matches the sweeps it actually takes
+    // to consolidate it.
+    #[test_with_logger]
+    fn test_estimate_consolidation(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // Twenty Txos take one sweep of MAX_INPUTS, and one of the remaining five.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address; 20],
+            MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let estimate = service
+            .estimate_consolidation(&alice.account_id_hex, None)
+            .unwrap();
+        assert_eq!(
+            estimate,
+            ConsolidationEstimate {
+                num_txos: 20,
+                num_transactions: 2,
+                total_fee: 2 * MINIMUM_FEE,
+            }
+        );
+
+        // Consolidate by sweeping until a single Txo is left.
+        let mut num_transactions = 0;
+        let mut total_fee = 0;
+        let mut num_blocks = 13;
+        while service
+            .estimate_consolidation(&alice.account_id_hex, None)
+            .unwrap()
+            .num_txos
+            > 1
+        {
+            let (transaction_log, _associated_txos) = service
+                .sweep_account(&alice.account_id_hex, None, None, None, None)
+                .unwrap();
+            num_transactions += 1;
+            total_fee += transaction_log.fee.unwrap() as u64;
+            {
+                let conn = service.wallet_db.get_conn().unwrap();
+                add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+            }
+            num_blocks += 1;
+            wait_for_sync(
+                &ledger_db,
+                &service.wallet_db,
+                &alice_account_id,
+                num_blocks,
+            );
+        }
+        assert_eq!(num_transactions, estimate.num_transactions);
+        assert_eq!(total_fee, estimate.total_fee);
+
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 20 * MOB as u64 - estimate.total_fee);
+
+        // A consolidated account needs no more transactions.
+        let estimate = service
+            .estimate_consolidation(&alice.account_id_hex, None)
+            .unwrap();
+        assert_eq!(estimate.num_transactions, 0);
+        assert_eq!(estimate.total_fee, 0);
+    }
+
+    // Sweeping an account to a destination moves all of its funds out.
+    #[test_with_logger]
+    fn test_sweep_account_to_destination(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = create_account_with_three_txos(&service, &mut ledger_db, &mut rng);
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID(bob.account_id_hex.clone());
+        let bob_address =
+            b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap();
+
+        let (transaction_log, associated_txos) = service
+            .sweep_account(
+                &alice.account_id_hex,
+                Some(bob_address.clone()),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(associated_txos.inputs.len(), 3);
+        assert_eq!(associated_txos.outputs.len(), 1);
+        assert!(associated_txos.change.is_empty());
+        assert_eq!(transaction_log.recipient_public_address_b58, bob_address);
+
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 16);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 16);
+
+        let alice_balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(alice_balance.unspent, 0);
+        let bob_balance = service.get_balance_for_account(&bob_account_id).unwrap();
+        assert_eq!(bob_balance.unspent, 175 * MOB as u64 - MINIMUM_FEE);
+    }
+
+    #[test_with_logger]
+    fn test_bump_fee(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID::from(&bob_account_key);
+        let bob_public_address = bob_account_key.subaddress(bob.main_subaddress_index as u64);
+
+        let (original_log, original_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_public_address).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions {
+                    num_outputs: Some("2".to_string()),
+                    ..Default::default()
+                },
+                Some("Pay Bob".to_string()),
+            )
+            .unwrap();
+        assert_eq!(original_log.fee, Some(MINIMUM_FEE as i64));
+
+        // The new fee must be higher than the original.
+        match service.bump_fee(&original_log.transaction_id_hex, MINIMUM_FEE) {
+            Err(TransactionServiceError::FeeNotIncreased(fee)) => assert_eq!(fee, MINIMUM_FEE),
+            Ok(_) => panic!("Should not be able to bump fee without increasing it"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        let (bumped_log, bumped_txos) = service
+            .bump_fee(&original_log.transaction_id_hex, 2 * MINIMUM_FEE)
+            .unwrap();
+        assert_ne!(
+            bumped_log.transaction_id_hex,
+            original_log.transaction_id_hex
+        );
+        assert_eq!(bumped_log.fee, Some(2 * MINIMUM_FEE as i64));
+        assert_eq!(bumped_log.value, original_log.value);
+        assert_eq!(
+            bumped_log.recipient_public_address_b58,
+            original_log.recipient_public_address_b58
+        );
+        assert_eq!(bumped_log.comment, "Pay Bob");
+        assert_eq!(bumped_log.status, TX_STATUS_PENDING);
+        assert_eq!(bumped_txos.inputs, original_txos.inputs);
+
+        // The payment is rebuilt from the original's outlays, so it is still split
+        // into two outputs.
+        assert_eq!(original_txos.outputs.len(), 2);
+        assert_eq!(bumped_txos.outputs.len(), 2);
+
+        let original_log = TransactionLog::get(
+            &original_log.transaction_id_hex,
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(original_log.status, TX_STATUS_PENDING);
+
+        // The replacement is linked to the transaction it replaced.
+        assert_eq!(
+            bumped_log.parent_transaction_id_hex,
+            Some(original_log.transaction_id_hex.clone())
+        );
+        assert_eq!(original_log.parent_transaction_id_hex, None);
+        let children = service
+            .list_child_transaction_logs(&original_log.transaction_id_hex)
+            .unwrap();
+        assert_eq!(children, vec![bumped_log.clone()]);
+
+        // The replaced transaction can no longer be bumped.
+        match service.bump_fee(&original_log.transaction_id_hex, 3 * MINIMUM_FEE) {
+            Err(TransactionServiceError::TransactionAlreadyReplaced(_)) => {}
+            Ok(_) => panic!("Should not be able to bump a replaced transaction"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &bumped_log);
+        }
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 14);
+
+        let conn = service.wallet_db.get_conn().unwrap();
+        let bumped_log = TransactionLog::get(&bumped_log.transaction_id_hex, &conn).unwrap();
+        assert_eq!(bumped_log.status, TX_STATUS_SUCCEEDED);
+        let original_log = TransactionLog::get(&original_log.transaction_id_hex, &conn).unwrap();
+        assert_eq!(original_log.status, TX_STATUS_FAILED);
+
+        let alice_balance = service
+            .get_balance_for_account(&AccountID(alice.account_id_hex))
+            .unwrap();
+        assert_eq!(alice_balance.unspent, 58 * MOB as u64 - 2 * MINIMUM_FEE);
+
+        let bob_balance = service
+            .get_balance_for_account(&AccountID(bob.account_id_hex))
+            .unwrap();
+        assert_eq!(bob_balance.unspent, 42 * MOB as u64);
+    }
+
+    // If the original transaction lands rather than its replacement, the
+    // original succeeds and the replacement fails.
+    #[test_with_logger]
+    fn test_bump_fee_original_lands(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+        let alice = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(Some("Bob".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_public_address = bob_account_key.subaddress(bob.main_subaddress_index as u64);
+
+        let (original_log, _original_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_public_address).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        let (bumped_log, _bumped_txos) = service
+            .bump_fee(&original_log.transaction_id_hex, 2 * MINIMUM_FEE)
+            .unwrap();
+
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &original_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let conn = service.wallet_db.get_conn().unwrap();
+        let original_log = TransactionLog::get(&original_log.transaction_id_hex, &conn).unwrap();
+        assert_eq!(original_log.status, TX_STATUS_SUCCEEDED);
+        assert_eq!(original_log.finalized_block_index, Some(13));
+        let bumped_log = TransactionLog::get(&bumped_log.transaction_id_hex, &conn).unwrap();
+        assert_eq!(bumped_log.status, TX_STATUS_FAILED);
+
+        let alice_balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(alice_balance.unspent, 58 * MOB as u64 - MINIMUM_FEE);
+    }
+
+    #[test_with_logger]
+    fn test_wait_for_landing(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let (transaction_log, _associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&alice_public_address).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(transaction_log.status, TX_STATUS_PENDING);
+
+        // Waits longer than the maximum are rejected.
+        match service.wait_for_landing(
+            &transaction_log.transaction_id_hex,
+            MAX_LANDING_TIMEOUT + Duration::from_secs(1),
+        ) {
+            Err(TransactionServiceError::LandingTimeoutTooLong(600)) => {}
+            Ok(_) => panic!("Should not wait longer than the maximum"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Nothing lands the transaction yet.
+        match service.wait_for_landing(&transaction_log.transaction_id_hex, Duration::from_secs(1))
+        {
+            Err(TransactionServiceError::LandingTimeout(1, _)) => {}
+            Ok(_) => panic!("Transaction should not have landed"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Land the transaction while waiting for it.
+        let lander = {
+            let mut ledger_db = ledger_db.clone();
+            let wallet_db = service.wallet_db.clone();
+            let transaction_log = transaction_log.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(500));
+                let conn = wallet_db.get_conn().unwrap();
+                add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+            })
+        };
+        let (landed_log, associated_txos) = service
+            .wait_for_landing(&transaction_log.transaction_id_hex, DEFAULT_LANDING_TIMEOUT)
+            .unwrap();
+        lander.join().unwrap();
+
+        assert_eq!(landed_log.status, TX_STATUS_SUCCEEDED);
+        assert_eq!(landed_log.finalized_block_index, Some(13));
+        assert_eq!(associated_txos.outputs.len(), 1);
+    }
+
+    // Change should always land at the account's designated change subaddress.
+    #[test_with_logger]
+    fn test_change_lands_at_change_subaddress(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_public_address = bob_account_key.subaddress(bob.main_subaddress_index as u64);
+        let bob_b58 = b58_encode(&bob_public_address).unwrap();
+
+        // New accounts send change to the "Change" subaddress created at setup.
+        assert_eq!(alice.change_subaddress_index, 1);
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &bob_b58,
+                (10 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        assert_eq!(associated_txos.change.len(), 1);
+        let change = Txo::get(
+            &associated_txos.change[0],
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(change.txo.subaddress_index, Some(1));
+
+        // Only assigned subaddresses can receive change.
+        match service.set_change_subaddress(&alice_account_id, 2) {
+            Err(AccountServiceError::SubaddressNotAssigned(2)) => {}
+            Ok(_) => panic!("Should not be able to send change to an unassigned subaddress"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        let new_change_address = service
+            .assign_address_for_account(&alice_account_id, Some("New Change"))
+            .unwrap();
+        assert_eq!(new_change_address.subaddress_index, 2);
+        let alice = service.set_change_subaddress(&alice_account_id, 2).unwrap();
+        assert_eq!(alice.change_subaddress_index, 2);
+
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &bob_b58,
+                (10 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 15);
+
+        assert_eq!(associated_txos.change.len(), 1);
+        let change = Txo::get(
+            &associated_txos.change[0],
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(change.txo.subaddress_index, Some(2));
+
+        // Building fails if the change subaddress is not assigned.
+        alice
+            .update_change_subaddress_index(99, &service.wallet_db.get_conn().unwrap())
+            .unwrap();
+        match service.build_transaction(
+            &alice.account_id_hex,
+            &bob_b58,
+            (10 * MOB).to_string(),
+            None,
+            None,
+            None,
+            None,
+            TransactionBuildOptions::default(),
+        ) {
+            Err(TransactionServiceError::TransactionBuilder(
+                WalletTransactionBuilderError::NoChangeAddressConfigured(account_id),
+            )) => assert_eq!(account_id, alice.account_id_hex),
+            Ok(_) => panic!("Should not be able to build without a change subaddress"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    // Above the account's auto consolidate threshold, sends should sweep up
+    // extra inputs beyond those needed to cover the payment.
+    #[test_with_logger]
+    fn test_auto_consolidate_threshold(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // Fragment Alice's balance across 10 Txos.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone(); 10],
+            MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_b58 =
+            b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap();
+
+        let build = || {
+            service
+                .build_transaction(
+                    &alice.account_id_hex,
+                    &bob_b58,
+                    (MOB / 2).to_string(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    TransactionBuildOptions::default(),
+                )
+                .unwrap()
+        };
+
+        // Consolidation is opt-in, so only one input is needed by default.
+        assert_eq!(alice.auto_consolidate_threshold, None);
+        assert_eq!(build().utxos.len(), 1);
+
+        // At or below the threshold, nothing changes.
+        let alice = service
+            .set_auto_consolidate_threshold(&alice_account_id, Some(10))
+            .unwrap();
+        assert_eq!(alice.auto_consolidate_threshold, Some(10));
+        assert_eq!(build().utxos.len(), 1);
+
+        // Above the threshold, every spendable Txo is swept into the send.
+        service
+            .set_auto_consolidate_threshold(&alice_account_id, Some(5))
+            .unwrap();
+        let tx_proposal = build();
+        assert_eq!(tx_proposal.utxos.len(), 10);
+        assert_eq!(tx_proposal.tx.prefix.outputs.len(), 2);
+
+        // Disabling it again restores the minimal selection.
+        let alice = service
+            .set_auto_consolidate_threshold(&alice_account_id, None)
+            .unwrap();
+        assert_eq!(alice.auto_consolidate_threshold, None);
+        assert_eq!(build().utxos.len(), 1);
+    }
+
+    #[test_with_logger]
+    fn test_submission_failures(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let (service, next_propose_tx_error) =
+            setup_wallet_service_with_failing_peers(ledger_db.clone(), logger.clone());
+
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let build = || {
+            service
+                .build_transaction(
+                    &alice.account_id_hex,
+                    &b58_encode(&recipient).unwrap(),
+                    (42 * MOB).to_string(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    TransactionBuildOptions::default(),
+                )
+                .unwrap()
+        };
+
+        // A transaction rejected by consensus is logged as failed, and its inputs
+        // are released.
+        *next_propose_tx_error.lock().unwrap() = Some(mc_connection::Error::TransactionValidation(
+            TransactionValidationError::TombstoneBlockExceeded,
+        ));
+        let tx_proposal = build();
+        let transaction_id = TransactionID::from(&tx_proposal.tx).to_string();
+        match service.submit_transaction(
+            tx_proposal.clone(),
+            None,
+            Some(alice.account_id_hex.clone()),
+        ) {
+            Err(TransactionServiceError::SubmissionRejected(id, _)) => {
+                assert_eq!(id, transaction_id)
+            }
+            Ok(_) => panic!("Should not submit a rejected transaction"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        let conn = service.wallet_db.get_conn().unwrap();
+        let transaction_log = TransactionLog::get(&transaction_id, &conn).unwrap();
+        assert_eq!(transaction_log.status, TX_STATUS_FAILED);
+        let associated_txos = transaction_log.get_associated_txos(&conn).unwrap();
+        for (_txo, status) in Txo::select_by_id(&associated_txos.inputs, &conn).unwrap() {
+            assert_eq!(status.txo_status, TXO_STATUS_UNSPENT);
+        }
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 100 * MOB as u64);
+        assert_eq!(balance.pending, 0);
+
+        // Once the rejected proposal is accepted on a later submission, its log is
+        // pending again, and its inputs are reserved again.
+        let (transaction_log, _associated_txos) = service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction_log.transaction_id_hex, transaction_id);
+        assert_eq!(transaction_log.status, TX_STATUS_PENDING);
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 0);
+        assert_eq!(balance.pending, 100 * MOB as u64);
+        transaction_log.mark_rejected(&conn).unwrap();
+
+        // A transient failure leaves the transaction pending, with its inputs
+        // reserved.
+        *next_propose_tx_error.lock().unwrap() =
+            Some(mc_connection::Error::Other("connection reset".to_string()));
+        let tx_proposal = build();
+        let transaction_id = TransactionID::from(&tx_proposal.tx).to_string();
+        match service.submit_transaction(
+            tx_proposal.clone(),
+            None,
+            Some(alice.account_id_hex.clone()),
+        ) {
+            Err(TransactionServiceError::SubmissionRetryable(id, _)) => {
+                assert_eq!(id, transaction_id)
+            }
+            Ok(_) => panic!("Should not submit after a transient failure"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        let transaction_log = TransactionLog::get(&transaction_id, &conn).unwrap();
+        assert_eq!(transaction_log.status, TX_STATUS_PENDING);
+        let associated_txos = transaction_log.get_associated_txos(&conn).unwrap();
+        for (_txo, status) in Txo::select_by_id(&associated_txos.inputs, &conn).unwrap() {
+            assert_eq!(status.txo_status, TXO_STATUS_PENDING);
+        }
+        let balance = service.get_balance_for_account(&alice_account_id).unwrap();
+        assert_eq!(balance.unspent, 0);
+        assert_eq!(balance.pending, 100 * MOB as u64);
+
+        // Submitting the same proposal again reuses its transaction log.
+        let (transaction_log, _associated_txos) = service
+            .submit_transaction(tx_proposal, None, Some(alice.account_id_hex.clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(transaction_log.transaction_id_hex, transaction_id);
+        assert_eq!(transaction_log.status, TX_STATUS_PENDING);
+    }
+
     // FIXME: Test with balance > u64::max
     // FIXME: sending a transaction with value > u64::max
 }
@@ -11,9 +11,16 @@
 use crate::{
     db::{
         account::{AccountID, AccountModel},
-        models::{Account, Txo, TXO_STATUS_UNSPENT},
+        assigned_subaddress::AssignedSubaddressModel,
+        b58_encode,
+        models::{
+            Account, AssignedSubaddress, Txo, TXO_STATUS_ORPHANED, TXO_STATUS_PENDING,
+            TXO_STATUS_UNSPENT,
+        },
+        transaction_log::MAX_CHANGE_OUTPUTS,
         txo::TxoModel,
-        WalletDb,
+        txo_selection::SelectionOptions,
+        WalletDb, WalletDbError,
     },
     error::WalletTransactionBuilderError,
 };
@@ -33,20 +40,80 @@ use mc_transaction_core::{
     constants::{MINIMUM_FEE, RING_SIZE},
     onetime_keys::recover_onetime_private_key,
     ring_signature::KeyImage,
-    tx::{TxOut, TxOutMembershipProof},
+    tx::{TxOut, TxOutConfirmationNumber, TxOutMembershipProof},
 };
-use mc_transaction_std::{InputCredentials, TransactionBuilder};
+use mc_transaction_std::{InputCredentials, TransactionBuilder, TxBuilderError};
 use mc_util_uri::FogUri;
 
 use diesel::prelude::*;
-use rand::Rng;
-use std::{convert::TryFrom, iter::FromIterator, str::FromStr, sync::Arc};
+use rand::{CryptoRng, Rng, RngCore};
+use std::{collections::BTreeSet, convert::TryFrom, iter::FromIterator, str::FromStr, sync::Arc};
 
 /// Default number of blocks used for calculating transaction tombstone block
 /// number.
 // TODO support for making this configurable
 pub const DEFAULT_NEW_TX_BLOCK_ATTEMPTS: u64 = 50;
 
+/// The maximum number of outputs a payment to a recipient may be split into.
+/// Together with MAX_CHANGE_OUTPUTS, this keeps transactions within the
+/// protocol's limit of 16 outputs.
+pub const MAX_OUTPUTS_PER_RECIPIENT: u64 = 8;
+
+/// How change is distributed among outputs back to the sending account.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeStrategy {
+    /// All change goes to a single output.
+    Single,
+
+    /// Change is split into outputs of the target value, so that future
+    /// transactions have Txos of a useful denomination to select from. A
+    /// remainder of at least half the target gets its own output; a smaller
+    /// remainder is folded into the last output rather than left as dust.
+    TargetValue(u64),
+
+    /// Change is split into this many outputs of nearly equal value, trading
+    /// fewer Txos to manage for less linkable change.
+    NumOutputs(u64),
+}
+
+impl Default for ChangeStrategy {
+    fn default() -> Self {
+        ChangeStrategy::Single
+    }
+}
+
+impl ChangeStrategy {
+    /// The values of the change outputs for the given total change, which
+    /// always sum to the change.
+    pub fn split(&self, change: u64) -> Vec<u64> {
+        let target = match *self {
+            ChangeStrategy::TargetValue(target) if target > 0 && change > target => target,
+            ChangeStrategy::NumOutputs(num_outputs) if num_outputs > 1 && change >= num_outputs => {
+                let num_outputs = num_outputs.min(MAX_CHANGE_OUTPUTS as u64);
+                return (0..num_outputs)
+                    .map(|i| change / num_outputs + if i < change % num_outputs { 1 } else { 0 })
+                    .collect();
+            }
+            _ => return vec![change],
+        };
+
+        let mut values = vec![target; (change / target) as usize];
+        let remainder = change % target;
+        if remainder > 0 && remainder >= target / 2 {
+            values.push(remainder);
+        } else if let Some(last) = values.last_mut() {
+            *last += remainder;
+        }
+
+        if values.len() > MAX_CHANGE_OUTPUTS {
+            let overflow: u64 = values[MAX_CHANGE_OUTPUTS - 1..].iter().sum();
+            values.truncate(MAX_CHANGE_OUTPUTS - 1);
+            values.push(overflow);
+        }
+        values
+    }
+}
+
 /// A builder of transactions constructed from this wallet.
 pub struct WalletTransactionBuilder<FPR: FogPubkeyResolver + 'static> {
     /// Account ID (hex-encoded) from which to construct a transaction.
@@ -71,6 +138,31 @@ pub struct WalletTransactionBuilder<FPR: FogPubkeyResolver + 'static> {
     /// The fee for the transaction.
     fee: Option<u64>,
 
+    /// The number of blocks that must follow the block in which a Txo was
+    /// received before it can be selected as an input.
+    min_confirmations: u64,
+
+    /// If true, failing to select inputs because some unspent Txos do not yet
+    /// have min_confirmations is reported as InsufficientConfirmations.
+    strict_confirmations: bool,
+
+    /// How change is distributed among outputs back to the sending account.
+    change_strategy: ChangeStrategy,
+
+    /// Txo values that select_txos spends first, if they reach the value.
+    preferred_denominations: Vec<i64>,
+
+    /// Whether select_txos spends from a single subaddress when one holds
+    /// enough to cover the outlays and fee.
+    prefer_single_subaddress: bool,
+
+    /// The number of outputs each payment added with add_recipient is split
+    /// into.
+    num_outputs_per_recipient: u64,
+
+    /// Whether payments may be added for more than one recipient.
+    allow_multiple_recipients: bool,
+
     /// Fog resolver maker, used when constructing outputs to fog recipients.
     /// This is abstracted because in tests, we don't want to form grpc
     /// connections to fog.
@@ -96,6 +188,13 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             outlays: vec![],
             tombstone: 0,
             fee: None,
+            min_confirmations: 0,
+            strict_confirmations: false,
+            change_strategy: ChangeStrategy::default(),
+            preferred_denominations: vec![],
+            prefer_single_subaddress: false,
+            num_outputs_per_recipient: 1,
+            allow_multiple_recipients: false,
             fog_resolver_factory,
             logger,
         }
@@ -106,17 +205,62 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
     pub fn set_txos(
         &mut self,
         input_txo_ids: &[String],
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.set_txos_with_status(input_txo_ids, &[TXO_STATUS_UNSPENT])
+    }
+
+    /// Sets inputs to the txos associated with the given txo_ids, including
+    /// txos that are pending in a previous transaction. This is used to
+    /// replace a pending transaction, since the replacement spends the same
+    /// key images and at most one of the two can land.
+    pub fn set_replacement_txos(
+        &mut self,
+        input_txo_ids: &[String],
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.set_txos_with_status(input_txo_ids, &[TXO_STATUS_UNSPENT, TXO_STATUS_PENDING])
+    }
+
+    fn set_txos_with_status(
+        &mut self,
+        input_txo_ids: &[String],
+        statuses: &[&str],
     ) -> Result<(), WalletTransactionBuilderError> {
         let txos = Txo::select_by_id(&input_txo_ids.to_vec(), &self.wallet_db.get_conn()?)?;
-        let unspent: Vec<Txo> = txos
+        // Orphaned Txos, and Txos without a key image, cannot be spent until
+        // their subaddress is recovered, so say so rather than dropping them.
+        for (txo, status) in txos.iter() {
+            if status.account_id_hex != self.account_id_hex {
+                continue;
+            }
+            if status.txo_status == TXO_STATUS_ORPHANED {
+                return Err(WalletDbError::TxoNotSpendable(
+                    txo.txo_id_hex.clone(),
+                    "the Txo is orphaned".to_string(),
+                )
+                .into());
+            }
+            if txo.key_image.is_none() {
+                return Err(WalletDbError::TxoNotSpendable(
+                    txo.txo_id_hex.clone(),
+                    "the Txo has no key image".to_string(),
+                )
+                .into());
+            }
+        }
+        let spendable: Vec<Txo> = txos
             .iter()
-            .filter(|(_txo, status)| status.txo_status == TXO_STATUS_UNSPENT)
+            .filter(|(_txo, status)| statuses.contains(&status.txo_status.as_str()))
             .map(|(t, _s)| t.clone())
             .collect();
-        if unspent.iter().map(|t| t.value as u128).sum::<u128>() > u64::MAX as u128 {
+        if spendable
+            .iter()
+            .map(|t| t.value as u64 as u128)
+            .sum::<u128>()
+            > u64::MAX as u128
+        {
             return Err(WalletTransactionBuilderError::OutboundValueTooLarge);
         }
-        self.inputs = unspent;
+        self.inputs = spendable;
         Ok(())
     }
 
@@ -139,23 +283,158 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
             fee
         );
         let total_value = outlay_value_sum as u64 + fee;
-        self.inputs = Txo::select_unspent_txos_for_value(
+
+        let conn = self.wallet_db.get_conn()?;
+        let account = Account::get(&AccountID(self.account_id_hex.to_string()), &conn)?;
+        let max_received_block_index = self.max_received_block_index()?;
+        let spendable_txos = self.wallet_db.spendable_txos_cache().get_spendable_txos(
             &self.account_id_hex,
-            total_value,
-            max_spendable_value.map(|v| v as i64),
-            &self.wallet_db.get_conn()?,
+            self.ledger_db.num_blocks()?,
+            &conn,
         )?;
+        // Txos reserved for another transaction are left for it.
+        let reserved_txo_ids = self.wallet_db.txo_reservations().reserved_txo_ids();
+        let unreserved_txos: Vec<Txo> = spendable_txos
+            .iter()
+            .filter(|txo| !reserved_txo_ids.contains(&txo.txo_id_hex))
+            .cloned()
+            .collect();
+        let options = SelectionOptions {
+            max_received_block_index,
+            auto_consolidate_threshold: account.auto_consolidate_threshold,
+            preferred_denominations: self.preferred_denominations.clone(),
+            ..Default::default()
+        };
+        let select = |subaddress_index: Option<i64>| {
+            Txo::select_from_spendable(
+                &unreserved_txos,
+                total_value,
+                max_spendable_value.map(|v| v as i64),
+                &SelectionOptions {
+                    subaddress_index,
+                    ..options.clone()
+                },
+            )
+        };
+        let single_subaddress_selection = if self.prefer_single_subaddress {
+            // Of the subaddresses which can cover the value on their own, spend
+            // from the one whose selection holds the least value.
+            let subaddress_indices: BTreeSet<i64> = unreserved_txos
+                .iter()
+                .filter_map(|txo| txo.subaddress_index)
+                .collect();
+            subaddress_indices
+                .into_iter()
+                .filter_map(|subaddress_index| select(Some(subaddress_index)).ok())
+                .min_by_key(|inputs| inputs.iter().map(|txo| txo.value as u128).sum::<u128>())
+        } else {
+            None
+        };
+        let selection = match single_subaddress_selection {
+            Some(inputs) => Ok(inputs),
+            None => select(None),
+        };
+        self.inputs = match (selection, max_received_block_index) {
+            (Ok(inputs), _) => inputs,
+            (Err(_), Some(max_index)) if self.strict_confirmations => {
+                // The young Txos are only to blame if the value could be covered
+                // with them. Otherwise the funds are insufficient regardless.
+                Txo::select_from_spendable(
+                    &unreserved_txos,
+                    total_value,
+                    max_spendable_value.map(|v| v as i64),
+                    &SelectionOptions {
+                        max_received_block_index: None,
+                        ..options.clone()
+                    },
+                )?;
+                let young_txo_ids: Vec<String> = unreserved_txos
+                    .iter()
+                    .filter(|txo| txo.received_block_index.map_or(true, |i| i > max_index))
+                    .map(|txo| txo.txo_id_hex.clone())
+                    .collect();
+                return Err(WalletTransactionBuilderError::InsufficientConfirmations(
+                    young_txo_ids,
+                ));
+            }
+            (Err(e), _) => return Err(e.into()),
+        };
 
         Ok(())
     }
 
+    /// Selects the most valuable Txos from the account, up to MAX_INPUTS, and
+    /// sends their total value less the fee to the recipient, so that the
+    /// transaction produces no change.
+    ///
+    /// Returns the value sent to the recipient.
+    pub fn send_all(
+        &mut self,
+        recipient: PublicAddress,
+    ) -> Result<u64, WalletTransactionBuilderError> {
+        let fee = self.fee.unwrap_or(MINIMUM_FEE);
+        let reserved_txo_ids: Vec<String> = self
+            .wallet_db
+            .txo_reservations()
+            .reserved_txo_ids()
+            .into_iter()
+            .collect();
+        let inputs: Vec<Txo> = Txo::select_max_spendable_txos(
+            &self.account_id_hex,
+            self.max_received_block_index()?,
+            &reserved_txo_ids,
+            &self.wallet_db.get_conn()?,
+        )?;
+        let input_value = inputs.iter().map(|t| t.value as u64 as u128).sum::<u128>();
+        if input_value > u64::MAX as u128 {
+            return Err(WalletTransactionBuilderError::OutboundValueTooLarge);
+        }
+        if input_value <= fee as u128 {
+            return Err(WalletTransactionBuilderError::InsufficientInputFunds(
+                format!(
+                    "Total value of inputs {:?} does not cover the fee {:?}",
+                    input_value, fee
+                ),
+            ));
+        }
+        let value = input_value as u64 - fee;
+        log::info!(
+            self.logger,
+            "Sending all {:?} from {:?} inputs with fee {:?}",
+            value,
+            inputs.len(),
+            fee
+        );
+        self.add_recipient(recipient, value)?;
+        self.inputs = inputs;
+        Ok(value)
+    }
+
+    /// Only Txos received at or before this block have enough confirmations.
+    /// If the ledger is not yet long enough, no Txo qualifies.
+    fn max_received_block_index(&self) -> Result<Option<i64>, WalletTransactionBuilderError> {
+        if self.min_confirmations == 0 {
+            return Ok(None);
+        }
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
+        Ok(Some(
+            tip_block_index
+                .checked_sub(self.min_confirmations)
+                .map_or(-1, |i| i as i64),
+        ))
+    }
+
     pub fn add_recipient(
         &mut self,
         recipient: PublicAddress,
         value: u64,
     ) -> Result<(), WalletTransactionBuilderError> {
-        // This wallet does not support multiple outgoing recipients at this time.
-        if !self.outlays.is_empty() && recipient != self.outlays[0].0 {
+        // This wallet does not support multiple outgoing recipients, except where
+        // explicitly allowed.
+        if !self.allow_multiple_recipients
+            && !self.outlays.is_empty()
+            && recipient != self.outlays[0].0
+        {
             return Err(WalletTransactionBuilderError::MultipleOutgoingRecipients);
         }
         // Verify that the maximum output value of this transaction remains under
@@ -164,7 +443,41 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         if cur_sum > u64::MAX as u128 {
             return Err(WalletTransactionBuilderError::OutboundValueTooLarge);
         }
-        self.outlays.push((recipient, value));
+
+        // Split the payment into outputs of nearly equal value, so that no single
+        // output reveals the amount sent.
+        let num_outputs = self.num_outputs_per_recipient;
+        if num_outputs > 1 && value < num_outputs {
+            return Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                "Cannot split a payment of {} into {} outputs",
+                value, num_outputs
+            )));
+        }
+        for i in 0..num_outputs {
+            let output_value = value / num_outputs + if i < value % num_outputs { 1 } else { 0 };
+            self.outlays.push((recipient.clone(), output_value));
+        }
+        Ok(())
+    }
+
+    /// Split each payment added with add_recipient into this many outputs to
+    /// the recipient. Must be set before adding recipients.
+    pub fn set_num_outputs_per_recipient(
+        &mut self,
+        num_outputs: u64,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        if num_outputs == 0 || num_outputs > MAX_OUTPUTS_PER_RECIPIENT {
+            return Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                "Number of outputs per recipient must be between 1 and {}",
+                MAX_OUTPUTS_PER_RECIPIENT
+            )));
+        }
+        if !self.outlays.is_empty() {
+            return Err(WalletTransactionBuilderError::InvalidArgument(
+                "Number of outputs per recipient must be set before adding recipients".to_string(),
+            ));
+        }
+        self.num_outputs_per_recipient = num_outputs;
         Ok(())
     }
 
@@ -178,6 +491,81 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
         Ok(())
     }
 
+    pub fn set_min_confirmations(
+        &mut self,
+        min_confirmations: u64,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.min_confirmations = min_confirmations;
+        Ok(())
+    }
+
+    /// Allow payments to more than one recipient, such as the transaction
+    /// funding a batch of gift codes. The transaction is logged with the
+    /// recipient of its first payment.
+    pub fn set_allow_multiple_recipients(
+        &mut self,
+        allow_multiple_recipients: bool,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.allow_multiple_recipients = allow_multiple_recipients;
+        Ok(())
+    }
+
+    /// When set, select_txos fails with InsufficientConfirmations, listing the
+    /// Txos that are too young, if the inputs could only be selected by
+    /// including Txos with fewer than min_confirmations.
+    pub fn set_strict_confirmations(
+        &mut self,
+        strict_confirmations: bool,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.strict_confirmations = strict_confirmations;
+        Ok(())
+    }
+
+    pub fn set_change_strategy(
+        &mut self,
+        change_strategy: ChangeStrategy,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        match change_strategy {
+            ChangeStrategy::TargetValue(0) => {
+                return Err(WalletTransactionBuilderError::InvalidArgument(
+                    "Target change value must be greater than zero".to_string(),
+                ));
+            }
+            ChangeStrategy::NumOutputs(n) if n == 0 || n > MAX_CHANGE_OUTPUTS as u64 => {
+                return Err(WalletTransactionBuilderError::InvalidArgument(format!(
+                    "Number of change outputs must be between 1 and {}",
+                    MAX_CHANGE_OUTPUTS
+                )));
+            }
+            _ => {}
+        }
+        self.change_strategy = change_strategy;
+        Ok(())
+    }
+
+    /// When set, select_txos chooses from only the Txos with one of these
+    /// values if they are enough to cover the outlays and fee, and otherwise
+    /// from all spendable Txos.
+    pub fn set_preferred_denominations(
+        &mut self,
+        preferred_denominations: Vec<i64>,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.preferred_denominations = preferred_denominations;
+        Ok(())
+    }
+
+    /// When set, select_txos chooses only Txos received at a single
+    /// subaddress if that subaddress's Txos are enough to cover the outlays
+    /// and fee, keeping the funds of each address apart, and otherwise mixes
+    /// Txos from any subaddress.
+    pub fn set_prefer_single_subaddress(
+        &mut self,
+        prefer_single_subaddress: bool,
+    ) -> Result<(), WalletTransactionBuilderError> {
+        self.prefer_single_subaddress = prefer_single_subaddress;
+        Ok(())
+    }
+
     pub fn set_tombstone(&mut self, tombstone: u64) -> Result<(), WalletTransactionBuilderError> {
         let tombstone_block = if tombstone > 0 {
             tombstone
@@ -207,12 +595,24 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
                     Account::get(&AccountID(self.account_id_hex.to_string()), &conn)?;
                 let from_account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
 
+                // Change always goes to the account's designated change subaddress, which
+                // must be assigned so that we recognize the change when it lands.
+                let change_public_address =
+                    from_account_key.subaddress(account.change_subaddress_index as u64);
+                match AssignedSubaddress::get(&b58_encode(&change_public_address)?, &conn) {
+                    Ok(_) => {}
+                    Err(WalletDbError::AssignedSubaddressNotFound(_)) => {
+                        return Err(WalletTransactionBuilderError::NoChangeAddressConfigured(
+                            account.account_id_hex.clone(),
+                        ));
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+
                 // Collect all required FogUris from public addresses, then pass to resolver
                 // factory
                 let fog_resolver = {
-                    let change_address =
-                        from_account_key.subaddress(account.change_subaddress_index as u64);
-                    let fog_uris = core::slice::from_ref(&change_address)
+                    let fog_uris = core::slice::from_ref(&change_public_address)
                         .iter()
                         .chain(self.outlays.iter().map(|(receiver, _amount)| receiver))
                         .filter_map(|x| extract_fog_uri(x).transpose())
@@ -346,7 +746,8 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
 
                 // Add outputs to our destinations.
                 // Note that we make an assumption currently when logging submitted Txos that
-                // they were built  with only one recipient, and one change txo.
+                // they were built with only one recipient, unless multiple recipients were
+                // explicitly allowed.
                 let mut total_value = 0;
                 let mut tx_out_to_outlay_index: HashMap<TxOut, usize> = HashMap::default();
                 let mut outlay_confirmation_numbers = Vec::default();
@@ -354,12 +755,16 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
                 let recip_check = &self.outlays[0].0;
                 for (i, (recipient, out_value)) in self.outlays.iter().enumerate() {
                     // Note: Should not fail this check due to filtering on add_recipient
-                    if recipient != recip_check {
+                    if !self.allow_multiple_recipients && recipient != recip_check {
                         return Err(WalletTransactionBuilderError::MultipleRecipientsInTransaction);
                     }
 
-                    let (tx_out, confirmation_number) =
-                        transaction_builder.add_output(*out_value as u64, &recipient, &mut rng)?;
+                    let (tx_out, confirmation_number) = add_output_for_recipient(
+                        &mut transaction_builder,
+                        *out_value as u64,
+                        &recipient,
+                        &mut rng,
+                    )?;
 
                     tx_out_to_outlay_index.insert(tx_out, i);
                     outlay_confirmation_numbers.push(confirmation_number);
@@ -383,13 +788,18 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
 
                 let change = input_value as u64 - total_value - transaction_builder.fee;
 
-                // If we do, add an output for that as well.
+                // If we do, add outputs for that as well.
                 if change > 0 {
-                    let change_public_address =
-                        from_account_key.subaddress(account.change_subaddress_index as u64);
                     // FIXME: verify that fog resolver knows to send change with hint encrypted to
                     // the main public address
-                    transaction_builder.add_output(change, &change_public_address, &mut rng)?;
+                    for change_value in self.change_strategy.split(change) {
+                        add_output_for_recipient(
+                            &mut transaction_builder,
+                            change_value,
+                            &change_public_address,
+                            &mut rng,
+                        )?;
+                    }
                     // FIXME: CBB - map error to indicate error with change
                 }
 
@@ -526,6 +936,29 @@ impl<FPR: FogPubkeyResolver + 'static> WalletTransactionBuilder<FPR> {
     }
 }
 
+// Helper which adds an output to a recipient, resolving their fog pubkey if
+// they are a fog user. If the fog report cannot be resolved, the recipient
+// could not find the output, so fail with a clear error rather than build an
+// undeliverable Txo.
+fn add_output_for_recipient<FPR: FogPubkeyResolver, RNG: CryptoRng + RngCore>(
+    transaction_builder: &mut TransactionBuilder<FPR>,
+    value: u64,
+    recipient: &PublicAddress,
+    rng: &mut RNG,
+) -> Result<(TxOut, TxOutConfirmationNumber), WalletTransactionBuilderError> {
+    transaction_builder
+        .add_output(value, recipient, rng)
+        .map_err(|err| match err {
+            TxBuilderError::FogPublicKey(fog_err) => {
+                WalletTransactionBuilderError::FogRecipientUnresolved(
+                    recipient.fog_report_url().unwrap_or("").to_string(),
+                    format!("{:?}", fog_err),
+                )
+            }
+            err => err.into(),
+        })
+}
+
 // Helper which extracts FogUri from PublicAddress or returns None, or returns
 // an error
 fn extract_fog_uri(addr: &PublicAddress) -> Result<Option<FogUri>, WalletTransactionBuilderError> {
@@ -540,14 +973,26 @@ fn extract_fog_uri(addr: &PublicAddress) -> Result<Option<FogUri>, WalletTransac
 mod tests {
     use super::*;
     use crate::{
-        db::WalletDbError,
-        service::sync::SyncThread,
+        db::{
+            account_txo_status::AccountTxoStatusModel,
+            models::{AccountTxoStatus, TransactionLog},
+            transaction_log::TransactionLogModel,
+            WalletDbError,
+        },
+        service::sync::{SyncThread, SyncThreadConfig},
         test_utils::{
-            builder_for_random_recipient, get_test_ledger, random_account_with_seed_values,
-            WalletDbTestContext, MOB,
+            add_block_to_ledger_db, builder_for_random_recipient, get_test_ledger,
+            random_account_with_seed_values, wait_for_sync, WalletDbTestContext, MOB,
         },
     };
     use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_keys::RistrettoPrivate;
+    use mc_crypto_rand::RngCore;
+    use mc_fog_report_validation::{
+        FogPubkeyError, FullyValidatedFogPubkey, MockFogPubkeyResolver,
+    };
+    use mc_transaction_core::fog_hint::FogHint;
+    use mc_util_from_random::FromRandom;
     use rand::{rngs::StdRng, SeedableRng};
 
     #[test_with_logger]
@@ -560,8 +1005,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -597,6 +1047,135 @@ mod tests {
         assert_eq!(proposal.tx.prefix.outputs.len(), 2);
     }
 
+    // When one subaddress can cover a send on its own, preferring a single
+    // subaddress should spend only from it, rather than mix subaddresses.
+    #[test_with_logger]
+    fn test_prefer_single_subaddress(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        // 10 MOB at the main subaddress, and 8 and 30 MOB at the change
+        // subaddress.
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![10 * MOB as u64],
+            &mut rng,
+        );
+        for value in &[8 * MOB as u64, 30 * MOB as u64] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![account_key.subaddress(1)],
+                *value,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        wait_for_sync(&ledger_db, &wallet_db, &AccountID::from(&account_key), 15);
+
+        // By default, the 8 and 10 MOB Txos are the closest match for 17 MOB,
+        // mixing the two subaddresses.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder
+            .add_recipient(recipient.clone(), 17 * MOB as u64)
+            .unwrap();
+        builder.select_txos(None).unwrap();
+        let mut subaddress_indices: Vec<Option<i64>> = builder
+            .inputs
+            .iter()
+            .map(|txo| txo.subaddress_index)
+            .collect();
+        subaddress_indices.sort_unstable();
+        assert_eq!(subaddress_indices, vec![Some(0), Some(1)]);
+
+        // Only the change subaddress can cover 17 MOB on its own.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder.set_prefer_single_subaddress(true).unwrap();
+        builder
+            .add_recipient(recipient.clone(), 17 * MOB as u64)
+            .unwrap();
+        builder.select_txos(None).unwrap();
+        assert!(builder
+            .inputs
+            .iter()
+            .all(|txo| txo.subaddress_index == Some(1)));
+        builder.set_tombstone(0).unwrap();
+        let proposal = builder.build().unwrap();
+        assert_eq!(proposal.tx.prefix.inputs.len(), 1);
+
+        // A send no single subaddress can cover still mixes them.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder.set_prefer_single_subaddress(true).unwrap();
+        builder.add_recipient(recipient, 45 * MOB as u64).unwrap();
+        builder.select_txos(None).unwrap();
+        assert_eq!(builder.inputs.len(), 3);
+    }
+
+    // When the Txos of the preferred denominations can cover a send, only they
+    // should be spent.
+    #[test_with_logger]
+    fn test_preferred_denominations(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![100 * MOB as u64, 10 * MOB as u64, 10 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder.set_preferred_denominations(vec![10 * MOB]).unwrap();
+        builder
+            .add_recipient(recipient.clone(), 15 * MOB as u64)
+            .unwrap();
+        builder.select_txos(None).unwrap();
+        assert_eq!(builder.inputs.len(), 2);
+        assert!(builder.inputs.iter().all(|txo| txo.value == 10 * MOB));
+        builder.set_tombstone(0).unwrap();
+        let proposal = builder.build().unwrap();
+        assert_eq!(proposal.tx.prefix.inputs.len(), 2);
+
+        // A send the preferred denominations cannot cover spends other Txos.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder.set_preferred_denominations(vec![10 * MOB]).unwrap();
+        builder.add_recipient(recipient, 25 * MOB as u64).unwrap();
+        builder.select_txos(None).unwrap();
+        assert!(builder.inputs.iter().any(|txo| txo.value == 100 * MOB));
+    }
+
     // Test that large values are handled correctly.
     #[test_with_logger]
     fn test_big_values(logger: Logger) {
@@ -608,8 +1187,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         // Give ourselves enough MOB that we have more than u64::MAX, 18_446_745 MOB
         let account_key = random_account_with_seed_values(
@@ -659,8 +1243,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -672,6 +1261,7 @@ mod tests {
         // Get our TXO list
         let txos: Vec<Txo> = Txo::list_for_account(
             &AccountID::from(&account_key).to_string(),
+            false,
             &wallet_db.get_conn().unwrap(),
         )
         .unwrap()
@@ -722,6 +1312,200 @@ mod tests {
         assert_eq!(proposal.tx.prefix.outputs.len(), 2); // self and change
     }
 
+    // Test that listing an orphaned Txo as an input reports why it cannot be
+    // spent
+    #[test_with_logger]
+    fn test_set_txos_rejects_orphaned(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64, 80 * MOB as u64],
+            &mut rng,
+        );
+        let account_id_hex = AccountID::from(&account_key).to_string();
+
+        let conn = wallet_db.get_conn().unwrap();
+        let txos: Vec<Txo> = Txo::list_for_account(&account_id_hex, false, &conn)
+            .unwrap()
+            .iter()
+            .map(|t| t.txo.clone())
+            .collect();
+        AccountTxoStatus::get(&account_id_hex, &txos[0].txo_id_hex, &conn)
+            .unwrap()
+            .set_orphaned(&conn)
+            .unwrap();
+
+        let (_recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        match builder.set_txos(&vec![
+            txos[0].txo_id_hex.clone(),
+            txos[1].txo_id_hex.clone(),
+        ]) {
+            Err(WalletTransactionBuilderError::WalletDb(WalletDbError::TxoNotSpendable(
+                txo_id_hex,
+                _reason,
+            ))) => assert_eq!(txo_id_hex, txos[0].txo_id_hex),
+            Ok(_) => panic!("Should not be able to spend an orphaned Txo"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    // Test that recently received Txos are not selected until they have enough
+    // confirmations
+    #[test_with_logger]
+    fn test_min_confirmations(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        // The Txo is received in the latest block
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder
+            .add_recipient(recipient.clone(), 50 * MOB as u64)
+            .unwrap();
+        builder.set_min_confirmations(3).unwrap();
+
+        match builder.select_txos(None) {
+            Ok(_) => panic!("Should not select a Txo without enough confirmations"),
+            Err(WalletTransactionBuilderError::WalletDb(WalletDbError::NoSpendableTxos)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Add blocks on top of the one containing our Txo
+        for _ in 0..3 {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![AccountKey::random(&mut rng).default_subaddress()],
+                10 * MOB as u64,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        wait_for_sync(
+            &ledger_db,
+            &wallet_db,
+            &AccountID::from(&account_key),
+            ledger_db.num_blocks().unwrap(),
+        );
+
+        builder.select_txos(None).unwrap();
+        builder.set_tombstone(0).unwrap();
+        let proposal = builder.build().unwrap();
+        assert_eq!(proposal.tx.prefix.inputs.len(), 1);
+        assert_eq!(proposal.outlays[0].value, 50 * MOB as u64);
+    }
+
+    // Test that strict confirmations reports the Txos which are too young
+    #[test_with_logger]
+    fn test_strict_confirmations(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        // Both Txos are received in the latest blocks
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64, 30 * MOB as u64],
+            &mut rng,
+        );
+        let young_txo_ids: Vec<String> = Txo::list_by_status(
+            &AccountID::from(&account_key).to_string(),
+            TXO_STATUS_UNSPENT,
+            &wallet_db.get_conn().unwrap(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|txo| txo.txo_id_hex)
+        .collect();
+        assert_eq!(young_txo_ids.len(), 2);
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder
+            .add_recipient(recipient.clone(), 50 * MOB as u64)
+            .unwrap();
+        builder.set_min_confirmations(3).unwrap();
+        builder.set_strict_confirmations(true).unwrap();
+
+        match builder.select_txos(None) {
+            Ok(_) => panic!("Should not select a Txo without enough confirmations"),
+            Err(WalletTransactionBuilderError::InsufficientConfirmations(mut txo_ids)) => {
+                txo_ids.sort();
+                let mut expected = young_txo_ids.clone();
+                expected.sort();
+                assert_eq!(txo_ids, expected);
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // When the young Txos could not cover the value either, the funds are
+        // insufficient, whatever their confirmations.
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+        builder
+            .add_recipient(recipient.clone(), 150 * MOB as u64)
+            .unwrap();
+        builder.set_min_confirmations(3).unwrap();
+        builder.set_strict_confirmations(true).unwrap();
+
+        match builder.select_txos(None) {
+            Ok(_) => panic!("Should not select Txos worth less than the value"),
+            Err(WalletTransactionBuilderError::WalletDb(
+                WalletDbError::InsufficientFundsUnderMaxSpendable(_),
+            )) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
     // Test max_spendable correctly filters out txos above max_spendable
     #[test_with_logger]
     fn test_max_spendable(logger: Logger) {
@@ -733,8 +1517,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -792,8 +1581,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -865,8 +1659,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -947,8 +1746,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -977,6 +1781,160 @@ mod tests {
                                                          // self (no change)
     }
 
+    // Change should be split into outputs of the target value, with small
+    // remainders folded into the last output.
+    #[test]
+    fn test_change_strategy_split() {
+        assert_eq!(ChangeStrategy::Single.split(100), vec![100]);
+        assert_eq!(ChangeStrategy::TargetValue(100).split(60), vec![60]);
+        assert_eq!(ChangeStrategy::TargetValue(20).split(60), vec![20, 20, 20]);
+        assert_eq!(ChangeStrategy::TargetValue(20).split(65), vec![20, 20, 25]);
+        assert_eq!(
+            ChangeStrategy::TargetValue(20).split(75),
+            vec![20, 20, 20, 15]
+        );
+        let capped = ChangeStrategy::TargetValue(1).split(100);
+        assert_eq!(capped.len(), MAX_CHANGE_OUTPUTS);
+        assert_eq!(capped.iter().sum::<u64>(), 100);
+
+        assert_eq!(ChangeStrategy::NumOutputs(1).split(100), vec![100]);
+        assert_eq!(ChangeStrategy::NumOutputs(3).split(100), vec![34, 33, 33]);
+        assert_eq!(ChangeStrategy::NumOutputs(3).split(2), vec![2]);
+    }
+
+    // A transaction built with a target change value should split its change,
+    // and the wallet should record the value of each change output.
+    #[test_with_logger]
+    fn test_build_with_target_change_value(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+
+        match builder.set_change_strategy(ChangeStrategy::TargetValue(0)) {
+            Ok(_) => panic!("Should not be able to target a change value of 0"),
+            Err(WalletTransactionBuilderError::InvalidArgument(_)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        builder
+            .set_change_strategy(ChangeStrategy::TargetValue(20 * MOB as u64))
+            .unwrap();
+        builder.add_recipient(recipient, 10 * MOB as u64).unwrap();
+        builder.select_txos(None).unwrap();
+        builder.set_tombstone(0).unwrap();
+
+        // 60 MOB of change less the fee is two 20 MOB outputs, with the
+        // remainder in a third.
+        let proposal = builder.build().unwrap();
+        assert_eq!(proposal.tx.prefix.outputs.len(), 4);
+
+        let conn = wallet_db.get_conn().unwrap();
+        let transaction_log = TransactionLog::log_submitted(
+            proposal,
+            ledger_db.num_blocks().unwrap(),
+            "".to_string(),
+            Some(&AccountID::from(&account_key).to_string()),
+            &conn,
+        )
+        .unwrap();
+        let associated_txos = transaction_log.get_associated_txos(&conn).unwrap();
+        let mut change_values: Vec<i64> = associated_txos
+            .change
+            .iter()
+            .map(|txo_id| Txo::get(txo_id, &conn).unwrap().txo.value)
+            .collect();
+        change_values.sort_unstable();
+        assert_eq!(
+            change_values,
+            vec![20 * MOB - MINIMUM_FEE as i64, 20 * MOB, 20 * MOB]
+        );
+    }
+
+    // A payment should be split into the requested number of outputs to the
+    // recipient, of nearly equal value.
+    #[test_with_logger]
+    fn test_split_outputs_to_recipient(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) =
+            builder_for_random_recipient(&account_key, &wallet_db, &ledger_db, &mut rng, &logger);
+
+        match builder.set_num_outputs_per_recipient(0) {
+            Ok(_) => panic!("Should not be able to split a payment into 0 outputs"),
+            Err(WalletTransactionBuilderError::InvalidArgument(_)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+        match builder.set_num_outputs_per_recipient(MAX_OUTPUTS_PER_RECIPIENT + 1) {
+            Ok(_) => panic!("Should not be able to exceed MAX_OUTPUTS_PER_RECIPIENT"),
+            Err(WalletTransactionBuilderError::InvalidArgument(_)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        builder.set_num_outputs_per_recipient(3).unwrap();
+        let value = 10 * MOB as u64;
+        builder.add_recipient(recipient.clone(), value).unwrap();
+        builder.select_txos(None).unwrap();
+        builder.set_tombstone(0).unwrap();
+
+        // The split can no longer be changed once recipients are added.
+        match builder.set_num_outputs_per_recipient(2) {
+            Ok(_) => panic!("Should not be able to change the split after adding recipients"),
+            Err(WalletTransactionBuilderError::InvalidArgument(_)) => {}
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        let proposal = builder.build().unwrap();
+        assert_eq!(proposal.outlays.len(), 3);
+        assert_eq!(proposal.tx.prefix.outputs.len(), 4); // outlays + change
+        for outlay in proposal.outlays.iter() {
+            assert_eq!(outlay.receiver, recipient);
+            assert!(outlay.value == value / 3 || outlay.value == value / 3 + 1);
+        }
+        assert_eq!(proposal.outlays.iter().map(|o| o.value).sum::<u64>(), value);
+    }
+
     // We should be able to add multiple TxOuts to the same recipient, not to
     // multiple
     #[test_with_logger]
@@ -989,8 +1947,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -1045,8 +2008,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -1092,8 +2060,13 @@ mod tests {
         let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
 
         // Start sync thread
-        let _sync_thread =
-            SyncThread::start(ledger_db.clone(), wallet_db.clone(), None, logger.clone());
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
 
         let account_key = random_account_with_seed_values(
             &wallet_db,
@@ -1117,4 +2090,167 @@ mod tests {
             Err(e) => panic!("Unexpected error {:?}", e),
         }
     }
+
+    // A builder for a payment to a fog recipient, whose resolver expects to be
+    // asked for that recipient's fog pubkey exactly once.
+    fn builder_for_fog_recipient(
+        account_key: &AccountKey,
+        wallet_db: &WalletDb,
+        ledger_db: &LedgerDB,
+        fog_pubkey: Option<RistrettoPublic>,
+        rng: &mut StdRng,
+        logger: &Logger,
+    ) -> (
+        PublicAddress,
+        WalletTransactionBuilder<MockFogPubkeyResolver>,
+    ) {
+        let recipient_account_key = AccountKey::new_with_fog(
+            &RistrettoPrivate::from_random(rng),
+            &RistrettoPrivate::from_random(rng),
+            "fog://fog.example.com".to_string(),
+            "".to_string(),
+            vec![],
+        );
+        let recipient = recipient_account_key.default_subaddress();
+
+        let expected_recipient = recipient.clone();
+        let fog_resolver_factory: Arc<
+            dyn Fn(&[FogUri]) -> Result<MockFogPubkeyResolver, String> + Send + Sync,
+        > = Arc::new(move |fog_uris| -> Result<MockFogPubkeyResolver, String> {
+            assert_eq!(
+                fog_uris,
+                &[FogUri::from_str("fog://fog.example.com").unwrap()]
+            );
+            let mut fog_pubkey_resolver = MockFogPubkeyResolver::new();
+            let expected_recipient = expected_recipient.clone();
+            fog_pubkey_resolver
+                .expect_get_fog_pubkey()
+                .withf(move |recipient| *recipient == expected_recipient)
+                .times(1)
+                .return_once(move |recipient| match fog_pubkey {
+                    Some(pubkey) => Ok(FullyValidatedFogPubkey {
+                        pubkey,
+                        pubkey_expiry: 10000,
+                    }),
+                    None => Err(FogPubkeyError::NoMatchingReportResponse(
+                        recipient.fog_report_url().unwrap().to_string(),
+                    )),
+                });
+            Ok(fog_pubkey_resolver)
+        });
+
+        let builder = WalletTransactionBuilder::new(
+            AccountID::from(account_key).to_string(),
+            wallet_db.clone(),
+            ledger_db.clone(),
+            fog_resolver_factory,
+            logger.clone(),
+        );
+        (recipient, builder)
+    }
+
+    // Outputs to a fog recipient use the fog pubkey resolved for them.
+    #[test_with_logger]
+    fn test_build_to_fog_recipient(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64, 80 * MOB as u64, 90 * MOB as u64],
+            &mut rng,
+        );
+
+        let fog_private_key = RistrettoPrivate::from_random(&mut rng);
+        let fog_pubkey = RistrettoPublic::from(&fog_private_key);
+        let (recipient, mut builder) = builder_for_fog_recipient(
+            &account_key,
+            &wallet_db,
+            &ledger_db,
+            Some(fog_pubkey),
+            &mut rng,
+            &logger,
+        );
+
+        builder
+            .add_recipient(recipient.clone(), 10 * MOB as u64)
+            .unwrap();
+        builder.select_txos(None).unwrap();
+        builder.set_tombstone(0).unwrap();
+
+        // The mock resolver checks that it was asked for the recipient's fog pubkey.
+        let proposal = builder.build().unwrap();
+        assert_eq!(proposal.outlays.len(), 1);
+        assert_eq!(proposal.outlays[0].receiver, recipient);
+
+        // The fog hint of the recipient's output is encrypted to the resolved pubkey.
+        let tx_out_index = proposal.outlay_index_to_tx_out_index[&0];
+        let tx_out = &proposal.tx.prefix.outputs[tx_out_index];
+        assert!(FogHint::decrypt(&fog_private_key, &tx_out.e_fog_hint).is_ok());
+    }
+
+    // A fog recipient whose fog report cannot be resolved is an error, rather
+    // than an output they could never find.
+    #[test_with_logger]
+    fn test_build_to_unresolvable_fog_recipient(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        // Start sync thread
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            wallet_db.clone(),
+            None,
+            SyncThreadConfig::default(),
+            logger.clone(),
+        );
+
+        let account_key = random_account_with_seed_values(
+            &wallet_db,
+            &mut ledger_db,
+            &vec![70 * MOB as u64, 80 * MOB as u64, 90 * MOB as u64],
+            &mut rng,
+        );
+
+        let (recipient, mut builder) = builder_for_fog_recipient(
+            &account_key,
+            &wallet_db,
+            &ledger_db,
+            None,
+            &mut rng,
+            &logger,
+        );
+
+        builder
+            .add_recipient(recipient.clone(), 10 * MOB as u64)
+            .unwrap();
+        builder.select_txos(None).unwrap();
+        builder.set_tombstone(0).unwrap();
+
+        match builder.build() {
+            Ok(_) => panic!("Should not build a transaction to an unresolvable fog recipient"),
+            Err(WalletTransactionBuilderError::FogRecipientUnresolved(fog_report_url, _)) => {
+                assert_eq!(fog_report_url, "fog://fog.example.com");
+            }
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
 }
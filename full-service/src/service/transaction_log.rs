@@ -4,20 +4,35 @@
 
 use crate::{
     db::{
-        account::AccountID,
-        models::TransactionLog,
+        account::{AccountID, AccountModel},
+        b58_decode, b58_encode,
+        models::{
+            Account, TransactionLog, Txo, TX_DIRECTION_SENT, TX_STATUS_FAILED, TX_STATUS_LANDING,
+            TX_STATUS_PENDING, TX_STATUS_SUCCEEDED,
+        },
         transaction_log::{AssociatedTxos, TransactionLogModel},
+        txo::TxoModel,
+        WalletDbConnection,
     },
     error::WalletServiceError,
     WalletService,
 };
+use mc_common::logger::log;
 use mc_connection::{BlockchainConnection, UserTxConnection};
 use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
 
 use crate::db::WalletDbError;
-use diesel::connection::Connection;
+use diesel::{
+    connection::Connection,
+    r2d2::{ConnectionManager, PooledConnection},
+};
 use displaydoc::Display;
 
+/// The maximum number of transaction logs returned by a single call to
+/// get_all_transaction_logs.
+pub const MAX_TRANSACTION_LOGS_PER_REQUEST: u64 = 1000;
+
 /// Errors for the Transaction Log Service.
 #[derive(Display, Debug)]
 #[allow(clippy::large_enum_variant)]
@@ -27,6 +42,9 @@ pub enum TransactionLogServiceError {
 
     /// Diesel Error: {0}
     Diesel(diesel::result::Error),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
 }
 
 impl From<WalletDbError> for TransactionLogServiceError {
@@ -41,8 +59,17 @@ impl From<diesel::result::Error> for TransactionLogServiceError {
     }
 }
 
+impl From<mc_ledger_db::Error> for TransactionLogServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// transaction logs.
+///
+/// A sent transaction which has succeeded is reported as landing until its
+/// account's finality_depth of blocks follow the block it landed in.
 pub trait TransactionLogService {
     /// List all transactions associated with the given Account ID.
     fn list_transaction_logs(
@@ -66,6 +93,57 @@ pub trait TransactionLogService {
     fn get_all_transaction_logs_ordered_by_block(
         &self,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError>;
+
+    /// List the transaction logs of every account in the wallet, ordered by
+    /// finalized_block_index.
+    ///
+    /// Returns at most `limit` logs (capped at
+    /// MAX_TRANSACTION_LOGS_PER_REQUEST), skipping the first `offset`.
+    fn get_all_transaction_logs(
+        &self,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError>;
+
+    /// Remove a transaction log from the wallet's history.
+    ///
+    /// The Txos associated with the log are not affected.
+    fn remove_transaction_log(
+        &self,
+        transaction_id_hex: &str,
+    ) -> Result<bool, TransactionLogServiceError>;
+
+    /// Replace the comment on a transaction log.
+    fn update_transaction_comment(
+        &self,
+        transaction_id_hex: &str,
+        comment: String,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionLogServiceError>;
+
+    /// List the transaction logs derived from a transaction log, such as the
+    /// transactions replacing it, oldest first.
+    fn list_child_transaction_logs(
+        &self,
+        transaction_id_hex: &str,
+    ) -> Result<Vec<TransactionLog>, TransactionLogServiceError>;
+
+    /// List the transactions an account sent to the given recipient, oldest
+    /// first.
+    fn list_transaction_logs_to_recipient(
+        &self,
+        account_id: &AccountID,
+        recipient_public_address: &str,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, TransactionLogServiceError>;
+
+    /// Get the status of a transaction, computed from its input Txos.
+    ///
+    /// A pending sent transaction has succeeded once all of its inputs are
+    /// spent, and failed once any of them passes its tombstone block unspent,
+    /// even if the wallet has not synced the transaction log yet.
+    fn get_transaction_status(
+        &self,
+        transaction_id_hex: &str,
+    ) -> Result<String, TransactionLogServiceError>;
 }
 
 impl<T, FPR> TransactionLogService for WalletService<T, FPR>
@@ -77,10 +155,19 @@ where
         &self,
         account_id: &AccountID,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError> {
-        Ok(TransactionLog::list_all(
-            &account_id.to_string(),
-            &self.wallet_db.get_conn()?,
-        )?)
+        let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
+
+        let mut res: Vec<(TransactionLog, AssociatedTxos)> = Vec::new();
+        for (transaction_log, associated) in
+            TransactionLog::list_all(&account_id.to_string(), &conn)?
+        {
+            res.push((
+                with_reported_status(transaction_log, tip_block_index, &conn)?,
+                associated,
+            ));
+        }
+        Ok(res)
     }
 
     fn get_transaction_log(
@@ -88,6 +175,7 @@ where
         transaction_id_hex: &str,
     ) -> Result<(TransactionLog, AssociatedTxos), TransactionLogServiceError> {
         let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
 
         Ok(
             conn.transaction::<(TransactionLog, AssociatedTxos), TransactionLogServiceError, _>(
@@ -95,7 +183,10 @@ where
                     let transaction_log = TransactionLog::get(transaction_id_hex, &conn)?;
                     let associated = transaction_log.get_associated_txos(&conn)?;
 
-                    Ok((transaction_log, associated))
+                    Ok((
+                        with_reported_status(transaction_log, tip_block_index, &conn)?,
+                        associated,
+                    ))
                 },
             )?,
         )
@@ -106,6 +197,7 @@ where
         block_index: u64,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError> {
         let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
 
         Ok(
             conn.transaction::<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError, _>(
@@ -114,9 +206,10 @@ where
                         TransactionLog::get_all_for_block_index(block_index, &conn)?;
                     let mut res: Vec<(TransactionLog, AssociatedTxos)> = Vec::new();
                     for transaction_log in transaction_logs {
+                        let associated = transaction_log.get_associated_txos(&conn)?;
                         res.push((
-                            transaction_log.clone(),
-                            transaction_log.get_associated_txos(&conn)?,
+                            with_reported_status(transaction_log, tip_block_index, &conn)?,
+                            associated,
                         ));
                     }
                     Ok(res)
@@ -129,6 +222,7 @@ where
         &self,
     ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError> {
         let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
 
         Ok(
             conn.transaction::<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError, _>(
@@ -136,9 +230,43 @@ where
                     let transaction_logs = TransactionLog::get_all_ordered_by_block_index(&conn)?;
                     let mut res: Vec<(TransactionLog, AssociatedTxos)> = Vec::new();
                     for transaction_log in transaction_logs {
+                        let associated = transaction_log.get_associated_txos(&conn)?;
+                        res.push((
+                            with_reported_status(transaction_log, tip_block_index, &conn)?,
+                            associated,
+                        ));
+                    }
+                    Ok(res)
+                },
+            )?,
+        )
+    }
+
+    fn get_all_transaction_logs(
+        &self,
+        offset: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
+        let limit = limit
+            .unwrap_or(MAX_TRANSACTION_LOGS_PER_REQUEST)
+            .min(MAX_TRANSACTION_LOGS_PER_REQUEST);
+
+        Ok(
+            conn.transaction::<Vec<(TransactionLog, AssociatedTxos)>, WalletServiceError, _>(
+                || {
+                    let transaction_logs = TransactionLog::list_page_ordered_by_block_index(
+                        offset.unwrap_or(0) as i64,
+                        limit as i64,
+                        &conn,
+                    )?;
+                    let mut res: Vec<(TransactionLog, AssociatedTxos)> = Vec::new();
+                    for transaction_log in transaction_logs {
+                        let associated = transaction_log.get_associated_txos(&conn)?;
                         res.push((
-                            transaction_log.clone(),
-                            transaction_log.get_associated_txos(&conn)?,
+                            with_reported_status(transaction_log, tip_block_index, &conn)?,
+                            associated,
                         ));
                     }
                     Ok(res)
@@ -146,4 +274,138 @@ where
             )?,
         )
     }
+
+    fn remove_transaction_log(
+        &self,
+        transaction_id_hex: &str,
+    ) -> Result<bool, TransactionLogServiceError> {
+        log::info!(
+            self.logger,
+            "Deleting transaction log {}",
+            transaction_id_hex
+        );
+
+        let conn = self.wallet_db.get_conn()?;
+        TransactionLog::delete(transaction_id_hex, &conn)?;
+        Ok(true)
+    }
+
+    fn update_transaction_comment(
+        &self,
+        transaction_id_hex: &str,
+        comment: String,
+    ) -> Result<(TransactionLog, AssociatedTxos), TransactionLogServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
+
+        Ok(
+            conn.transaction::<(TransactionLog, AssociatedTxos), TransactionLogServiceError, _>(
+                || {
+                    TransactionLog::get(transaction_id_hex, &conn)?
+                        .update_comment(comment, &conn)?;
+                    let transaction_log = TransactionLog::get(transaction_id_hex, &conn)?;
+                    let associated = transaction_log.get_associated_txos(&conn)?;
+
+                    Ok((
+                        with_reported_status(transaction_log, tip_block_index, &conn)?,
+                        associated,
+                    ))
+                },
+            )?,
+        )
+    }
+
+    fn list_child_transaction_logs(
+        &self,
+        transaction_id_hex: &str,
+    ) -> Result<Vec<TransactionLog>, TransactionLogServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
+
+        let mut res: Vec<TransactionLog> = Vec::new();
+        for child in TransactionLog::get(transaction_id_hex, &conn)?.list_children(&conn)? {
+            res.push(with_reported_status(child, tip_block_index, &conn)?);
+        }
+        Ok(res)
+    }
+
+    fn list_transaction_logs_to_recipient(
+        &self,
+        account_id: &AccountID,
+        recipient_public_address: &str,
+    ) -> Result<Vec<(TransactionLog, AssociatedTxos)>, TransactionLogServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let tip_block_index = self.ledger_db.num_blocks()? - 1;
+
+        // Round trip the address, so that an invalid one is reported rather
+        // than matching nothing.
+        let recipient_public_address_b58 = b58_encode(&b58_decode(recipient_public_address)?)?;
+
+        let mut res: Vec<(TransactionLog, AssociatedTxos)> = Vec::new();
+        for transaction_log in TransactionLog::list_sent_to_recipient(
+            &account_id.to_string(),
+            &recipient_public_address_b58,
+            &conn,
+        )? {
+            let associated = transaction_log.get_associated_txos(&conn)?;
+            res.push((
+                with_reported_status(transaction_log, tip_block_index, &conn)?,
+                associated,
+            ));
+        }
+        Ok(res)
+    }
+
+    fn get_transaction_status(
+        &self,
+        transaction_id_hex: &str,
+    ) -> Result<String, TransactionLogServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let num_blocks = self.ledger_db.num_blocks()?;
+
+        Ok(
+            conn.transaction::<String, TransactionLogServiceError, _>(|| {
+                let mut transaction_log = TransactionLog::get(transaction_id_hex, &conn)?;
+                if transaction_log.direction == TX_DIRECTION_SENT
+                    && transaction_log.status == TX_STATUS_PENDING
+                {
+                    let inputs = transaction_log.get_associated_txos(&conn)?.inputs;
+                    if !inputs.is_empty() && Txo::are_all_spent(&inputs, &conn)? {
+                        transaction_log.status = TX_STATUS_SUCCEEDED.to_string();
+                    } else if Txo::any_failed(&inputs, num_blocks as i64, &conn)? {
+                        transaction_log.status = TX_STATUS_FAILED.to_string();
+                    }
+                }
+                Ok(with_reported_status(transaction_log, num_blocks - 1, &conn)?.status)
+            })?,
+        )
+    }
+}
+
+/// Report a sent transaction which has succeeded as landing if fewer than its
+/// account's finality_depth of blocks follow the block it landed in.
+fn with_reported_status(
+    mut transaction_log: TransactionLog,
+    tip_block_index: u64,
+    conn: &PooledConnection<ConnectionManager<WalletDbConnection>>,
+) -> Result<TransactionLog, WalletDbError> {
+    if transaction_log.status != TX_STATUS_SUCCEEDED
+        || transaction_log.direction != TX_DIRECTION_SENT
+    {
+        return Ok(transaction_log);
+    }
+    let finality_depth =
+        match Account::get(&AccountID(transaction_log.account_id_hex.clone()), conn) {
+            Ok(account) => account.finality_depth,
+            Err(WalletDbError::AccountNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+    if let (Some(depth), Some(finalized_block_index)) =
+        (finality_depth, transaction_log.finalized_block_index)
+    {
+        if (tip_block_index as i64) - finalized_block_index < depth {
+            transaction_log.status = TX_STATUS_LANDING.to_string();
+        }
+    }
+    Ok(transaction_log)
 }
@@ -4,16 +4,54 @@
 
 use crate::{
     db::{
-        account::AccountID,
-        models::Txo,
+        account::{AccountID, AccountModel},
+        account_txo_status::AccountTxoStatusModel,
+        assigned_subaddress::AssignedSubaddressModel,
+        models::{
+            Account, AccountTxoStatus, AssignedSubaddress, Txo, TxoStatusChange, TXO_STATUS_DUST,
+            TXO_STATUS_INVALIDATED, TXO_STATUS_ORPHANED, TXO_STATUS_PENDING, TXO_STATUS_SECRETED,
+            TXO_STATUS_SPENT, TXO_STATUS_UNSPENT, TXO_TYPE_MINTED, TXO_TYPE_RECEIVED,
+        },
         txo::{TxoDetails, TxoID, TxoModel},
+        txo_selection::{SelectionOptions, SelectionStrategy},
+        txo_status_history::TxoStatusChangeModel,
         WalletDbError,
     },
     WalletService,
 };
+use diesel::Connection;
 use displaydoc::Display;
+use mc_account_keys::AccountKey;
+use mc_common::{logger::log, HashSet};
 use mc_connection::{BlockchainConnection, UserTxConnection};
+use mc_crypto_keys::RistrettoPublic;
 use mc_fog_report_validation::FogPubkeyResolver;
+use mc_ledger_db::Ledger;
+use mc_transaction_core::{
+    constants::{MAX_INPUTS, MINIMUM_FEE},
+    onetime_keys::recover_public_subaddress_spend_key,
+    tx::TxOut,
+};
+use std::convert::TryFrom;
+
+/// The maximum number of Txos returned by a single call to get_all_txos.
+pub const MAX_TXOS_PER_REQUEST: u64 = 1000;
+
+/// How many subaddresses past an account's next subaddress index it may still
+/// assign. An orphaned Txo received at a later subaddress is treated as
+/// unrecoverable by purge_unspendable_txos.
+pub const PURGE_SUBADDRESS_LOOKAHEAD: u64 = 1000;
+
+/// The statuses reported by get_txo_counts, in the order they are returned.
+pub const TXO_STATUSES: [&str; 7] = [
+    TXO_STATUS_UNSPENT,
+    TXO_STATUS_PENDING,
+    TXO_STATUS_SPENT,
+    TXO_STATUS_SECRETED,
+    TXO_STATUS_ORPHANED,
+    TXO_STATUS_INVALIDATED,
+    TXO_STATUS_DUST,
+];
 
 /// Errors for the Txo Service.
 #[derive(Display, Debug)]
@@ -27,6 +65,21 @@ pub enum TxoServiceError {
 
     /// Minted Txo should contain confirmation: {0}
     MissingConfirmation(String),
+
+    /// Address {0} is not assigned to account {1}
+    AddressNotInAccount(String, String),
+
+    /// Value {0} plus the fee exceeds the maximum value
+    ValueTooLarge(u64),
+
+    /// Error with LedgerDB: {0}
+    LedgerDB(mc_ledger_db::Error),
+
+    /// Error decoding prost: {0}
+    ProstDecode(prost::DecodeError),
+
+    /// Error with Keys: {0}
+    CryptoKey(mc_crypto_keys::KeyError),
 }
 
 impl From<WalletDbError> for TxoServiceError {
@@ -41,17 +94,169 @@ impl From<diesel::result::Error> for TxoServiceError {
     }
 }
 
+impl From<mc_ledger_db::Error> for TxoServiceError {
+    fn from(src: mc_ledger_db::Error) -> Self {
+        Self::LedgerDB(src)
+    }
+}
+
+impl From<prost::DecodeError> for TxoServiceError {
+    fn from(src: prost::DecodeError) -> Self {
+        Self::ProstDecode(src)
+    }
+}
+
+impl From<mc_crypto_keys::KeyError> for TxoServiceError {
+    fn from(src: mc_crypto_keys::KeyError) -> Self {
+        Self::CryptoKey(src)
+    }
+}
+
 /// Trait defining the ways in which the wallet can interact with and manage
 /// Txos.
 pub trait TxoService {
     /// List the Txos for a given account in the wallet.
-    fn list_txos(&self, account_id: &AccountID) -> Result<Vec<TxoDetails>, TxoServiceError>;
+    ///
+    /// If exclude_spent is true, only the Txos which are not spent or secreted
+    /// for the account are listed.
+    fn list_txos(
+        &self,
+        account_id: &AccountID,
+        exclude_spent: Option<bool>,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError>;
+
+    /// List the Txos minted by a given account which are still secreted, or,
+    /// if resolved is true, which the wallet has since seen received or
+    /// spent.
+    fn list_secreted_txos(
+        &self,
+        account_id: &AccountID,
+        resolved: Option<bool>,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError>;
+
+    /// List the Txos a given account spent in a given block, for
+    /// reconciliation.
+    fn get_spent_txos_in_block(
+        &self,
+        account_id: &AccountID,
+        block_index: u64,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError>;
 
     /// Get a Txo from the wallet.
     fn get_txo(&self, txo_id: &TxoID) -> Result<TxoDetails, TxoServiceError>;
 
+    /// Get a Txo from the wallet, along with its status for every account it
+    /// is associated with.
+    ///
+    /// Unlike get_txo, this does not assume the Txo is associated with at most
+    /// one minting and one receiving account, which helps diagnose Txos
+    /// shared between overlapping accounts.
+    fn get_txo_global(
+        &self,
+        txo_id: &TxoID,
+    ) -> Result<(TxoDetails, Vec<AccountTxoStatus>), TxoServiceError>;
+
     /// List the Txos for a given address for an account in the wallet.
     fn get_all_txos_for_address(&self, address: &str) -> Result<Vec<TxoDetails>, TxoServiceError>;
+
+    /// List the Txos across every account in the wallet, along with each
+    /// Txo's status for every account it is associated with.
+    ///
+    /// Returns at most `limit` Txos (capped at MAX_TXOS_PER_REQUEST), starting
+    /// after the Txo whose offset_count is `offset`.
+    fn get_all_txos(
+        &self,
+        offset: Option<i32>,
+        limit: Option<u64>,
+    ) -> Result<Vec<(TxoDetails, Vec<AccountTxoStatus>)>, TxoServiceError>;
+
+    /// Set or clear the label of a Txo with respect to an account.
+    fn set_txo_label(
+        &self,
+        account_id: &AccountID,
+        txo_id: &TxoID,
+        label: Option<String>,
+    ) -> Result<TxoDetails, TxoServiceError>;
+
+    /// List the statuses a Txo has moved through with respect to an account,
+    /// oldest first.
+    fn get_txo_status_history(
+        &self,
+        account_id: &AccountID,
+        txo_id: &TxoID,
+    ) -> Result<Vec<TxoStatusChange>, TxoServiceError>;
+
+    /// Select unspent Txos received at one of the account's addresses whose
+    /// values sum to at least `target_value`, as when building a transaction.
+    fn select_unspent_txos_for_value_from_address(
+        &self,
+        account_id: &AccountID,
+        address: &str,
+        target_value: u64,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError>;
+
+    /// Preview the unspent Txos which building a transaction sending `value`
+    /// with the minimum fee would select, without building it.
+    ///
+    /// Txos held by a reservation are not selected. Reservations are held in
+    /// the memory of this process only, so Txos reserved by another process
+    /// sharing the database may still be selected.
+    fn preview_selection(
+        &self,
+        account_id: &AccountID,
+        value: u64,
+        max_spendable_value: Option<u64>,
+        strategy: SelectionStrategy,
+    ) -> Result<Vec<Txo>, TxoServiceError>;
+
+    /// Check whether the account can send `value` with the minimum fee in a
+    /// single transaction, by running input selection without building.
+    ///
+    /// As with preview_selection, Txos held by a reservation in this process
+    /// are not counted.
+    ///
+    /// Returns:
+    /// * (can_afford, shortfall), where shortfall is how much more pMOB the
+    ///   most valuable MAX_INPUTS spendable Txos would need to cover the value
+    ///   and fee, or 0 if the account can afford it.
+    fn can_afford(
+        &self,
+        account_id: &AccountID,
+        value: u64,
+        max_spendable_value: Option<u64>,
+    ) -> Result<(bool, u64), TxoServiceError>;
+
+    /// Count the Txos for a given account in each status.
+    ///
+    /// Every status is listed, in the order of TXO_STATUSES, including those
+    /// with no Txos.
+    fn get_txo_counts(&self, account_id: &AccountID)
+        -> Result<Vec<(String, u64)>, TxoServiceError>;
+
+    /// Remove the account's Txos which can never become spendable: orphaned
+    /// Txos received at a subaddress beyond PURGE_SUBADDRESS_LOOKAHEAD past the
+    /// account's next subaddress index, and invalidated Txos which are not in
+    /// the ledger although the WalletDb's invalidated_txo_purge_depth blocks
+    /// follow the block they were received in. Only the Txos' association
+    /// with the account is removed.
+    ///
+    /// If `dry_run` is true, nothing is removed.
+    ///
+    /// Returns:
+    /// * The IDs of the Txos which were, or would be, removed.
+    fn purge_unspendable_txos(
+        &self,
+        account_id: &AccountID,
+        dry_run: bool,
+    ) -> Result<Vec<String>, TxoServiceError>;
+
+    /// Make the account's dust Txos unspent again, so that they can be
+    /// selected for spending, for example by consolidating them with larger
+    /// Txos.
+    ///
+    /// Returns:
+    /// * The IDs of the Txos which were released.
+    fn release_dust(&self, account_id: &AccountID) -> Result<Vec<String>, TxoServiceError>;
 }
 
 impl<T, FPR> TxoService for WalletService<T, FPR>
@@ -59,10 +264,48 @@ where
     T: BlockchainConnection + UserTxConnection + 'static,
     FPR: FogPubkeyResolver + Send + Sync + 'static,
 {
-    fn list_txos(&self, account_id: &AccountID) -> Result<Vec<TxoDetails>, TxoServiceError> {
+    fn list_txos(
+        &self,
+        account_id: &AccountID,
+        exclude_spent: Option<bool>,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(Txo::list_for_account(
+            &account_id.to_string(),
+            exclude_spent.unwrap_or(false),
+            &conn,
+        )?)
+    }
+
+    fn list_secreted_txos(
+        &self,
+        account_id: &AccountID,
+        resolved: Option<bool>,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(
+            Txo::list_secreted(&account_id.to_string(), resolved.unwrap_or(false), &conn)?
+                .iter()
+                .map(|txo| Txo::get(&txo.txo_id_hex, &conn))
+                .collect::<Result<Vec<TxoDetails>, WalletDbError>>()?,
+        )
+    }
+
+    fn get_spent_txos_in_block(
+        &self,
+        account_id: &AccountID,
+        block_index: u64,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError> {
         let conn = self.wallet_db.get_conn()?;
 
-        Ok(Txo::list_for_account(&account_id.to_string(), &conn)?)
+        Ok(
+            Txo::list_spent_in_block(&account_id.to_string(), block_index, &conn)?
+                .iter()
+                .map(|txo| Txo::get(&txo.txo_id_hex, &conn))
+                .collect::<Result<Vec<TxoDetails>, WalletDbError>>()?,
+        )
     }
 
     fn get_txo(&self, txo_id: &TxoID) -> Result<TxoDetails, TxoServiceError> {
@@ -71,29 +314,370 @@ where
         Ok(Txo::get(&txo_id.to_string(), &conn)?)
     }
 
+    fn get_txo_global(
+        &self,
+        txo_id: &TxoID,
+    ) -> Result<(TxoDetails, Vec<AccountTxoStatus>), TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let txo_id_hex = txo_id.to_string();
+
+        let txo_details = match Txo::get(&txo_id_hex, &conn) {
+            Ok(txo_details) => txo_details,
+            // The per-account statuses below still describe every account.
+            Err(WalletDbError::TxoAssociatedWithTooManyAccounts(_)) => {
+                let (txo, _status) = Txo::select_by_id(&[txo_id_hex.clone()], &conn)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| WalletDbError::TxoNotFound(txo_id_hex.clone()))?;
+                TxoDetails {
+                    txo,
+                    received_to_account: None,
+                    received_to_assigned_subaddress: None,
+                    minted_from_account: None,
+                    is_change: Txo::is_change(&txo_id_hex, &conn)?,
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let statuses = AccountTxoStatus::get_all_associated_accounts(&txo_id_hex, &conn)?;
+
+        Ok((txo_details, statuses))
+    }
+
     fn get_all_txos_for_address(&self, address: &str) -> Result<Vec<TxoDetails>, TxoServiceError> {
         let conn = self.wallet_db.get_conn()?;
 
         Ok(Txo::list_for_address(address, &conn)?)
     }
+
+    fn get_all_txos(
+        &self,
+        offset: Option<i32>,
+        limit: Option<u64>,
+    ) -> Result<Vec<(TxoDetails, Vec<AccountTxoStatus>)>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+        let limit = limit
+            .unwrap_or(MAX_TXOS_PER_REQUEST)
+            .min(MAX_TXOS_PER_REQUEST);
+
+        Ok(Txo::list_all(offset, limit as i64, &conn)?
+            .into_iter()
+            .map(|(txo, statuses)| {
+                let received_to_account = statuses
+                    .iter()
+                    .find(|s| s.txo_type == TXO_TYPE_RECEIVED)
+                    .cloned();
+                let minted_from_account = statuses
+                    .iter()
+                    .find(|s| s.txo_type == TXO_TYPE_MINTED)
+                    .cloned();
+                let is_change = Txo::is_change(&txo.txo_id_hex, &conn)?;
+                Ok((
+                    TxoDetails {
+                        txo,
+                        received_to_account,
+                        received_to_assigned_subaddress: None,
+                        minted_from_account,
+                        is_change,
+                    },
+                    statuses,
+                ))
+            })
+            .collect::<Result<Vec<_>, WalletDbError>>()?)
+    }
+
+    fn set_txo_label(
+        &self,
+        account_id: &AccountID,
+        txo_id: &TxoID,
+        label: Option<String>,
+    ) -> Result<TxoDetails, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(conn.transaction::<TxoDetails, TxoServiceError, _>(|| {
+            let status =
+                AccountTxoStatus::get(&account_id.to_string(), &txo_id.to_string(), &conn)?;
+            status.set_label(label.as_deref(), &conn)?;
+            Ok(Txo::get(&txo_id.to_string(), &conn)?)
+        })?)
+    }
+
+    fn get_txo_status_history(
+        &self,
+        account_id: &AccountID,
+        txo_id: &TxoID,
+    ) -> Result<Vec<TxoStatusChange>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(
+            conn.transaction::<Vec<TxoStatusChange>, TxoServiceError, _>(|| {
+                // Fails if the Txo is not associated with the account.
+                AccountTxoStatus::get(&account_id.to_string(), &txo_id.to_string(), &conn)?;
+                Ok(TxoStatusChange::list_for_txo(
+                    &account_id.to_string(),
+                    &txo_id.to_string(),
+                    &conn,
+                )?)
+            })?,
+        )
+    }
+
+    fn select_unspent_txos_for_value_from_address(
+        &self,
+        account_id: &AccountID,
+        address: &str,
+        target_value: u64,
+    ) -> Result<Vec<TxoDetails>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let subaddress = AssignedSubaddress::get(address, &conn)?;
+        if subaddress.account_id_hex != account_id.to_string() {
+            return Err(TxoServiceError::AddressNotInAccount(
+                address.to_string(),
+                account_id.to_string(),
+            ));
+        }
+
+        let txos = Txo::select_unspent_txos_for_value(
+            &account_id.to_string(),
+            target_value,
+            None,
+            &SelectionOptions {
+                subaddress_index: Some(subaddress.subaddress_index),
+                ..Default::default()
+            },
+            &conn,
+        )?;
+        Ok(txos
+            .iter()
+            .map(|t| Txo::get(&t.txo_id_hex, &conn))
+            .collect::<Result<Vec<TxoDetails>, WalletDbError>>()?)
+    }
+
+    fn preview_selection(
+        &self,
+        account_id: &AccountID,
+        value: u64,
+        max_spendable_value: Option<u64>,
+        strategy: SelectionStrategy,
+    ) -> Result<Vec<Txo>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let account = Account::get(account_id, &conn)?;
+        let target_value = value
+            .checked_add(MINIMUM_FEE)
+            .ok_or(TxoServiceError::ValueTooLarge(value))?;
+
+        let spendable_txos = self.wallet_db.spendable_txos_cache().get_spendable_txos(
+            &account_id.to_string(),
+            self.ledger_db.num_blocks()?,
+            &conn,
+        )?;
+        // Txos reserved for another transaction are left for it.
+        let reserved_txo_ids = self.wallet_db.txo_reservations().reserved_txo_ids();
+        let unreserved_txos: Vec<Txo> = spendable_txos
+            .iter()
+            .filter(|txo| !reserved_txo_ids.contains(&txo.txo_id_hex))
+            .cloned()
+            .collect();
+        Ok(Txo::select_from_spendable(
+            &unreserved_txos,
+            target_value,
+            max_spendable_value.map(|v| v as i64),
+            &SelectionOptions {
+                auto_consolidate_threshold: account.auto_consolidate_threshold,
+                strategy,
+                ..Default::default()
+            },
+        )?)
+    }
+
+    fn can_afford(
+        &self,
+        account_id: &AccountID,
+        value: u64,
+        max_spendable_value: Option<u64>,
+    ) -> Result<(bool, u64), TxoServiceError> {
+        match self.preview_selection(
+            account_id,
+            value,
+            max_spendable_value,
+            SelectionStrategy::default(),
+        ) {
+            Ok(_txos) => Ok((true, 0)),
+            Err(TxoServiceError::Database(e))
+                if matches!(
+                    e,
+                    WalletDbError::NoSpendableTxos
+                        | WalletDbError::InsufficientFunds { .. }
+                        | WalletDbError::InsufficientFundsFragmentedTxos
+                        | WalletDbError::InsufficientFundsUnderMaxSpendable(_)
+                ) =>
+            {
+                let conn = self.wallet_db.get_conn()?;
+                let reserved_txo_ids = self.wallet_db.txo_reservations().reserved_txo_ids();
+                let mut values: Vec<u64> =
+                    Txo::list_by_status(&account_id.to_string(), TXO_STATUS_UNSPENT, &conn)?
+                        .iter()
+                        .filter(|txo| txo.subaddress_index.is_some() && txo.key_image.is_some())
+                        .filter(|txo| !reserved_txo_ids.contains(&txo.txo_id_hex))
+                        .map(|txo| txo.value as u64)
+                        .filter(|value| max_spendable_value.map_or(true, |max| *value <= max))
+                        .collect();
+                values.sort_unstable_by(|a, b| b.cmp(a));
+                let available = values
+                    .iter()
+                    .take(MAX_INPUTS as usize)
+                    .fold(0u64, |total, value| total.saturating_add(*value));
+                let target_value = value.saturating_add(MINIMUM_FEE);
+
+                Ok((false, target_value.saturating_sub(available)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_txo_counts(
+        &self,
+        account_id: &AccountID,
+    ) -> Result<Vec<(String, u64)>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        Ok(
+            conn.transaction::<Vec<(String, u64)>, TxoServiceError, _>(|| {
+                // Ensure the account exists.
+                Account::get(account_id, &conn)?;
+
+                let counts = AccountTxoStatus::count_by_status(&account_id.to_string(), &conn)?;
+                Ok(TXO_STATUSES
+                    .iter()
+                    .map(|status| {
+                        let count = counts
+                            .iter()
+                            .find(|(s, _count)| s.as_str() == *status)
+                            .map_or(0, |(_s, count)| *count as u64);
+                        (status.to_string(), count)
+                    })
+                    .collect())
+            })?,
+        )
+    }
+
+    fn purge_unspendable_txos(
+        &self,
+        account_id: &AccountID,
+        dry_run: bool,
+    ) -> Result<Vec<String>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let purged = conn.transaction::<Vec<String>, TxoServiceError, _>(|| {
+            let account = Account::get(account_id, &conn)?;
+            let account_key: AccountKey = mc_util_serial::decode(&account.account_key)?;
+
+            // The spend public keys of the subaddresses the account has assigned
+            // or may still assign.
+            let max_subaddress_index =
+                account.next_subaddress_index as u64 + PURGE_SUBADDRESS_LOOKAHEAD;
+            let recoverable_spend_keys: HashSet<Vec<u8>> = (0..max_subaddress_index)
+                .map(|index| {
+                    mc_util_serial::encode(account_key.subaddress(index).spend_public_key())
+                })
+                .collect();
+
+            let mut purged = Vec::new();
+            for txo in Txo::list_by_status(&account_id.to_string(), TXO_STATUS_ORPHANED, &conn)? {
+                let tx_out: TxOut = mc_util_serial::decode(&txo.txo)?;
+                let subaddress_spend_key = recover_public_subaddress_spend_key(
+                    account_key.view_private_key(),
+                    &RistrettoPublic::try_from(&tx_out.target_key)?,
+                    &RistrettoPublic::try_from(&tx_out.public_key)?,
+                );
+                if !recoverable_spend_keys.contains(&mc_util_serial::encode(&subaddress_spend_key))
+                {
+                    purged.push(txo.txo_id_hex);
+                }
+            }
+            let num_blocks = self.ledger_db.num_blocks()?;
+            let purge_depth = self.wallet_db.invalidated_txo_purge_depth();
+            for txo in Txo::list_by_status(&account_id.to_string(), TXO_STATUS_INVALIDATED, &conn)?
+            {
+                // A Txo invalidated by a recent rollback may still return.
+                match txo.received_block_index {
+                    Some(received_block_index)
+                        if num_blocks >= received_block_index as u64 + 1 + purge_depth => {}
+                    _ => continue,
+                }
+                let tx_out: TxOut = mc_util_serial::decode(&txo.txo)?;
+                // A Txo which is back in the ledger is restored by syncing.
+                match self
+                    .ledger_db
+                    .get_tx_out_index_by_public_key(&tx_out.public_key)
+                {
+                    Ok(_) => {}
+                    Err(mc_ledger_db::Error::NotFound) => purged.push(txo.txo_id_hex),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            if !dry_run {
+                for txo_id_hex in &purged {
+                    AccountTxoStatus::get(&account_id.to_string(), txo_id_hex, &conn)?
+                        .delete(&conn)?;
+                }
+            }
+            Ok(purged)
+        })?;
+
+        log::info!(
+            self.logger,
+            "{} {} unspendable Txos of account {}",
+            if dry_run { "Would purge" } else { "Purged" },
+            purged.len(),
+            account_id
+        );
+        Ok(purged)
+    }
+
+    fn release_dust(&self, account_id: &AccountID) -> Result<Vec<String>, TxoServiceError> {
+        let conn = self.wallet_db.get_conn()?;
+
+        let released = conn.transaction::<Vec<String>, TxoServiceError, _>(|| {
+            // Ensure the account exists.
+            Account::get(account_id, &conn)?;
+
+            let mut released = Vec::new();
+            for txo in Txo::list_by_status(&account_id.to_string(), TXO_STATUS_DUST, &conn)? {
+                AccountTxoStatus::get(&account_id.to_string(), &txo.txo_id_hex, &conn)?
+                    .set_unspent(&conn)?;
+                released.push(txo.txo_id_hex);
+            }
+            Ok(released)
+        })?;
+
+        log::info!(
+            self.logger,
+            "Released {} dust Txos of account {}",
+            released.len(),
+            account_id
+        );
+        Ok(released)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        db::{
-            b58_encode,
-            models::{
-                TXO_STATUS_PENDING, TXO_STATUS_SECRETED, TXO_STATUS_UNSPENT, TXO_TYPE_MINTED,
-                TXO_TYPE_RECEIVED,
-            },
-        },
+        db::{b58_decode, b58_encode},
         service::{
-            account::AccountService, balance::BalanceService, transaction::TransactionService,
+            account::{AccountService, CreateAccountOptions},
+            address::AddressService,
+            balance::BalanceService,
+            transaction::{TransactionBuildOptions, TransactionService},
         },
         test_utils::{
-            add_block_to_ledger_db, get_test_ledger, setup_wallet_service, wait_for_sync, MOB,
+            add_block_from_transaction_log, add_block_to_ledger_db, create_test_received_txo,
+            get_test_ledger, setup_wallet_service, wait_for_sync, MOB,
         },
     };
     use mc_account_keys::{AccountKey, PublicAddress};
@@ -104,7 +688,7 @@ mod tests {
     use mc_crypto_rand::RngCore;
     use mc_transaction_core::ring_signature::KeyImage;
     use rand::{rngs::StdRng, SeedableRng};
-    use std::iter::FromIterator;
+    use std::{iter::FromIterator, time::Duration};
 
     #[test_with_logger]
     fn test_txo_lifecycle(logger: Logger) {
@@ -115,7 +699,10 @@ mod tests {
 
         let service = setup_wallet_service(ledger_db.clone(), logger);
         let alice = service
-            .create_account(Some("Alice's Main Account".to_string()))
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Add a block with a transaction for this recipient
@@ -139,7 +726,7 @@ mod tests {
         assert_eq!(balance.unspent, 100 * MOB as u64);
 
         // Verify that we have 1 txo
-        let txos = service.list_txos(&alice_account_id).unwrap();
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
         assert_eq!(txos.len(), 1);
         assert_eq!(
             txos[0].received_to_account.as_ref().unwrap().txo_status,
@@ -148,7 +735,10 @@ mod tests {
 
         // Add another account
         let bob = service
-            .create_account(Some("Bob's Main Account".to_string()))
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
             .unwrap();
 
         // Construct a new transaction to Bob
@@ -162,6 +752,7 @@ mod tests {
                 None,
                 None,
                 None,
+                TransactionBuildOptions::default(),
             )
             .unwrap();
         let _submitted = service
@@ -171,7 +762,7 @@ mod tests {
         // We should now have 3 txos - one pending, two minted (one of which will be
         // change)
         let txos = service
-            .list_txos(&AccountID(alice.account_id_hex.clone()))
+            .list_txos(&AccountID(alice.account_id_hex.clone()), None)
             .unwrap();
         assert_eq!(txos.len(), 3);
         // The Pending Tx
@@ -222,4 +813,951 @@ mod tests {
 
         // FIXME: How to make the transaction actually hit the test ledger?
     }
+
+    #[test_with_logger]
+    fn test_list_txos_exclude_spent(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+        let received_txo_id = service.list_txos(&alice_account_id, Some(true)).unwrap()[0]
+            .txo
+            .txo_id_hex
+            .clone();
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let (transaction_log, _associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        // Alice has the spent input, the secreted output to Bob, and the change.
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 3);
+        assert!(txos.iter().any(|t| t.txo.txo_id_hex == received_txo_id));
+
+        // Only the change is left once spent and secreted Txos are excluded.
+        let live_txos = service.list_txos(&alice_account_id, Some(true)).unwrap();
+        assert_eq!(live_txos.len(), 1);
+        assert_ne!(live_txos[0].txo.txo_id_hex, received_txo_id);
+        assert_eq!(live_txos[0].txo.value, 58 * MOB - MINIMUM_FEE as i64);
+    }
+
+    #[test_with_logger]
+    fn test_set_txo_label(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let bob_account_id = AccountID::from(&bob_account_key);
+
+        // Send a Txo from Alice to Bob, so that it is associated with both accounts
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+        wait_for_sync(&ledger_db, &service.wallet_db, &bob_account_id, 14);
+
+        // Label the Txo for Bob only
+        let txo_id = TxoID(associated_txos.outputs[0].clone());
+        let labeled = service
+            .set_txo_label(&bob_account_id, &txo_id, Some("refund".to_string()))
+            .unwrap();
+        assert_eq!(
+            labeled.received_to_account.as_ref().unwrap().label,
+            Some("refund".to_string())
+        );
+        assert_eq!(labeled.minted_from_account.as_ref().unwrap().label, None);
+
+        // Label the same Txo differently for Alice
+        service
+            .set_txo_label(&alice_account_id, &txo_id, Some("to Bob".to_string()))
+            .unwrap();
+        let txo = service.get_txo(&txo_id).unwrap();
+        assert_eq!(
+            txo.received_to_account.as_ref().unwrap().label,
+            Some("refund".to_string())
+        );
+        assert_eq!(
+            txo.minted_from_account.as_ref().unwrap().label,
+            Some("to Bob".to_string())
+        );
+
+        // Bob cannot label a Txo which is only associated with Alice
+        let alice_input_id = TxoID(associated_txos.inputs[0].clone());
+        match service.set_txo_label(&bob_account_id, &alice_input_id, Some("mine".to_string())) {
+            Err(TxoServiceError::Database(WalletDbError::AccountTxoStatusNotFound(_))) => {}
+            Ok(_) => panic!("Should not be able to label another account's Txo"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Clear Bob's label
+        let cleared = service
+            .set_txo_label(&bob_account_id, &txo_id, None)
+            .unwrap();
+        assert_eq!(cleared.received_to_account.as_ref().unwrap().label, None);
+    }
+
+    #[test_with_logger]
+    fn test_get_txo_global(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+
+        // A Txo received by Alice, which is also found for Bob, as can happen when
+        // imported accounts overlap.
+        let (txo_id_hex, txo, key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            (10 * MOB) as u64,
+            13,
+            &mut rng,
+            &service.wallet_db,
+        );
+        Txo::create_received(
+            txo,
+            Some(0),
+            Some(key_image),
+            (10 * MOB) as u64,
+            13,
+            &bob.account_id_hex,
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        // get_txo only reports one of the receiving accounts.
+        let txo_id = TxoID(txo_id_hex.clone());
+        let txo_details = service.get_txo(&txo_id).unwrap();
+        assert!(txo_details.received_to_account.is_some());
+        assert!(txo_details.minted_from_account.is_none());
+
+        let (global_details, statuses) = service.get_txo_global(&txo_id).unwrap();
+        assert_eq!(global_details.txo.txo_id_hex, txo_id_hex);
+        assert_eq!(statuses.len(), 2);
+        let account_ids: HashSet<String> =
+            statuses.iter().map(|s| s.account_id_hex.clone()).collect();
+        assert!(account_ids.contains(&alice.account_id_hex));
+        assert!(account_ids.contains(&bob.account_id_hex));
+        for status in statuses {
+            assert_eq!(status.txo_type, TXO_TYPE_RECEIVED);
+            assert_eq!(status.txo_status, TXO_STATUS_UNSPENT);
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_all_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+
+        let (alice_txo_id_hex, _txo, _key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            (10 * MOB) as u64,
+            13,
+            &mut rng,
+            &service.wallet_db,
+        );
+        let (bob_txo_id_hex, _txo, _key_image) = create_test_received_txo(
+            &bob_account_key,
+            0,
+            (20 * MOB) as u64,
+            13,
+            &mut rng,
+            &service.wallet_db,
+        );
+        Txo::update_to_pending(
+            &TxoID(bob_txo_id_hex.clone()),
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+
+        let all_txos = service.get_all_txos(None, None).unwrap();
+        assert_eq!(all_txos.len(), 2);
+
+        let (alice_details, alice_statuses) = &all_txos[0];
+        assert_eq!(alice_details.txo.txo_id_hex, alice_txo_id_hex);
+        assert_eq!(alice_statuses.len(), 1);
+        assert_eq!(alice_statuses[0].account_id_hex, alice.account_id_hex);
+        assert_eq!(alice_statuses[0].txo_status, TXO_STATUS_UNSPENT);
+
+        let (bob_details, bob_statuses) = &all_txos[1];
+        assert_eq!(bob_details.txo.txo_id_hex, bob_txo_id_hex);
+        assert_eq!(bob_statuses.len(), 1);
+        assert_eq!(bob_statuses[0].account_id_hex, bob.account_id_hex);
+        assert_eq!(bob_statuses[0].txo_status, TXO_STATUS_PENDING);
+
+        // Page through the Txos one at a time.
+        let first_page = service.get_all_txos(None, Some(1)).unwrap();
+        assert_eq!(first_page.len(), 1);
+        assert_eq!(first_page[0].0.txo.txo_id_hex, alice_txo_id_hex);
+        let second_page = service
+            .get_all_txos(Some(first_page[0].0.txo.id), Some(1))
+            .unwrap();
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(second_page[0].0.txo.txo_id_hex, bob_txo_id_hex);
+        let last_page = service
+            .get_all_txos(Some(second_page[0].0.txo.id), Some(1))
+            .unwrap();
+        assert!(last_page.is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_txo_status_history(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_public_address.clone()],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 1);
+        let txo_id = TxoID(txos[0].txo.txo_id_hex.clone());
+
+        // Spend the Txo
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        let (transaction_log, associated_txos) = service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap(),
+                (42 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+        {
+            let conn = service.wallet_db.get_conn().unwrap();
+            add_block_from_transaction_log(&mut ledger_db, &conn, &transaction_log);
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let history: Vec<String> = service
+            .get_txo_status_history(&alice_account_id, &txo_id)
+            .unwrap()
+            .into_iter()
+            .map(|change| change.txo_status)
+            .collect();
+        assert_eq!(
+            history,
+            vec![TXO_STATUS_UNSPENT, TXO_STATUS_PENDING, TXO_STATUS_SPENT]
+        );
+
+        // Change is minted, then received back once it lands in the ledger.
+        let change_history: Vec<String> = service
+            .get_txo_status_history(&alice_account_id, &TxoID(associated_txos.change[0].clone()))
+            .unwrap()
+            .into_iter()
+            .map(|change| change.txo_status)
+            .collect();
+        assert_eq!(
+            change_history,
+            vec![TXO_STATUS_SECRETED, TXO_STATUS_UNSPENT]
+        );
+
+        // Bob has no history for Alice's Txo.
+        assert!(service
+            .get_txo_status_history(&AccountID(bob.account_id_hex), &txo_id)
+            .is_err());
+    }
+
+    #[test_with_logger]
+    fn test_select_unspent_txos_for_value_from_address(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        let address_a = service
+            .assign_address_for_account(&alice_account_id, None)
+            .unwrap();
+        let address_b = service
+            .assign_address_for_account(&alice_account_id, None)
+            .unwrap();
+
+        // Fund each address with 10 MOB, and address B with another 50 MOB.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![
+                b58_decode(&address_a.assigned_subaddress_b58).unwrap(),
+                b58_decode(&address_b.assigned_subaddress_b58).unwrap(),
+            ],
+            10 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![b58_decode(&address_b.assigned_subaddress_b58).unwrap()],
+            50 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        let selected = service
+            .select_unspent_txos_for_value_from_address(
+                &alice_account_id,
+                &address_a.assigned_subaddress_b58,
+                5 * MOB as u64,
+            )
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(
+            selected[0].txo.subaddress_index,
+            Some(address_a.subaddress_index)
+        );
+
+        let selected = service
+            .select_unspent_txos_for_value_from_address(
+                &alice_account_id,
+                &address_b.assigned_subaddress_b58,
+                55 * MOB as u64,
+            )
+            .unwrap();
+        assert_eq!(selected.len(), 2);
+        for txo_details in selected {
+            assert_eq!(
+                txo_details.txo.subaddress_index,
+                Some(address_b.subaddress_index)
+            );
+        }
+
+        // The account holds enough, but address A does not.
+        match service.select_unspent_txos_for_value_from_address(
+            &alice_account_id,
+            &address_a.assigned_subaddress_b58,
+            15 * MOB as u64,
+        ) {
+            Err(TxoServiceError::Database(WalletDbError::InsufficientFundsUnderMaxSpendable(
+                _,
+            ))) => {}
+            Ok(_) => panic!("Should not select Txos from another address"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+
+        // Another account's address is rejected.
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_address = service
+            .assign_address_for_account(&AccountID(bob.account_id_hex), None)
+            .unwrap();
+        match service.select_unspent_txos_for_value_from_address(
+            &alice_account_id,
+            &bob_address.assigned_subaddress_b58,
+            5 * MOB as u64,
+        ) {
+            Err(TxoServiceError::AddressNotInAccount(_, _)) => {}
+            Ok(_) => panic!("Should not select Txos for another account's address"),
+            Err(e) => panic!("Unexpected error {:?}", e),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_preview_selection_matches_build(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        for value in &[10, 20, 30, 40] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                value * MOB as u64,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 16);
+
+        let preview = service
+            .preview_selection(
+                &alice_account_id,
+                35 * MOB as u64,
+                None,
+                SelectionStrategy::default(),
+            )
+            .unwrap();
+        let mut values: Vec<i64> = preview.iter().map(|txo| txo.value).collect();
+        values.sort_unstable();
+        assert_eq!(values, vec![10 * MOB, 20 * MOB, 30 * MOB]);
+
+        // Building the same payment spends exactly the previewed Txos.
+        let tx_proposal = service
+            .build_transaction(
+                &alice.account_id_hex,
+                &b58_encode(&alice_public_address).unwrap(),
+                (35 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+            )
+            .unwrap();
+        let built: HashSet<String> = HashSet::from_iter(
+            tx_proposal
+                .utxos
+                .iter()
+                .map(|utxo| TxoID::from(&utxo.tx_out).to_string()),
+        );
+        let previewed: HashSet<String> =
+            HashSet::from_iter(preview.iter().map(|txo| txo.txo_id_hex.clone()));
+        assert_eq!(built, previewed);
+
+        // Minimizing the overshoot leaves the least change.
+        let preview = service
+            .preview_selection(
+                &alice_account_id,
+                35 * MOB as u64,
+                None,
+                SelectionStrategy::MinOvershoot,
+            )
+            .unwrap();
+        let values: Vec<i64> = preview.iter().map(|txo| txo.value).collect();
+        assert_eq!(values, vec![40 * MOB]);
+
+        // Txos above max_spendable_value are not selected.
+        let preview = service
+            .preview_selection(
+                &alice_account_id,
+                35 * MOB as u64,
+                Some(30 * MOB as u64),
+                SelectionStrategy::MinOvershoot,
+            )
+            .unwrap();
+        let mut values: Vec<i64> = preview.iter().map(|txo| txo.value).collect();
+        values.sort_unstable();
+        assert_eq!(values, vec![10 * MOB, 30 * MOB]);
+    }
+
+    // An underfunded account cannot afford a payment, and is told by how much.
+    #[test_with_logger]
+    fn test_can_afford(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID(alice.account_id_hex.clone());
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // An account with no Txos is short the whole value and fee.
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 10 * MOB as u64, None)
+                .unwrap(),
+            (false, 10 * MOB as u64 + MINIMUM_FEE)
+        );
+
+        for value in &[10, 20] {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                value * MOB as u64,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 25 * MOB as u64, None)
+                .unwrap(),
+            (true, 0)
+        );
+
+        // 30 MOB is held, so sending all of it leaves the fee uncovered.
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 30 * MOB as u64, None)
+                .unwrap(),
+            (false, MINIMUM_FEE)
+        );
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 35 * MOB as u64, None)
+                .unwrap(),
+            (false, 5 * MOB as u64 + MINIMUM_FEE)
+        );
+
+        // Only the 10 MOB Txo is at most the max spendable value.
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 15 * MOB as u64, Some(10 * MOB as u64))
+                .unwrap(),
+            (false, 5 * MOB as u64 + MINIMUM_FEE)
+        );
+
+        // A reserved Txo is neither counted nor previewed.
+        let twenty_mob_txo_id = Txo::list_by_status(
+            &alice.account_id_hex,
+            TXO_STATUS_UNSPENT,
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap()
+        .into_iter()
+        .find(|txo| txo.value == 20 * MOB)
+        .unwrap()
+        .txo_id_hex;
+        let reservation = service
+            .wallet_db
+            .txo_reservations()
+            .reserve::<WalletDbError>(
+                &alice.account_id_hex,
+                20 * MOB as u64,
+                Duration::from_secs(60),
+                |_reserved_txo_ids| Ok(vec![twenty_mob_txo_id.clone()]),
+            )
+            .unwrap();
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 25 * MOB as u64, None)
+                .unwrap(),
+            (false, 15 * MOB as u64 + MINIMUM_FEE)
+        );
+        let preview = service
+            .preview_selection(
+                &alice_account_id,
+                5 * MOB as u64,
+                None,
+                SelectionStrategy::MinOvershoot,
+            )
+            .unwrap();
+        let values: Vec<i64> = preview.iter().map(|txo| txo.value).collect();
+        assert_eq!(values, vec![10 * MOB]);
+
+        // Once released, it is counted again.
+        service
+            .wallet_db
+            .txo_reservations()
+            .release(&reservation.reservation_id);
+        assert_eq!(
+            service
+                .can_afford(&alice_account_id, 25 * MOB as u64, None)
+                .unwrap(),
+            (true, 0)
+        );
+    }
+
+    // The counts should match counting the account's Txo statuses one by one.
+    #[test_with_logger]
+    fn test_get_txo_counts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let alice_public_address = alice_account_key.subaddress(alice.main_subaddress_index as u64);
+
+        // An account without Txos has a zero count for every status.
+        let counts = service.get_txo_counts(&alice_account_id).unwrap();
+        assert_eq!(counts.len(), TXO_STATUSES.len());
+        assert!(counts.iter().all(|(_status, count)| *count == 0));
+
+        for value in [100 * MOB, 20 * MOB, 5 * MOB].iter() {
+            add_block_to_ledger_db(
+                &mut ledger_db,
+                &vec![alice_public_address.clone()],
+                *value as u64,
+                &vec![KeyImage::from(rng.next_u64())],
+                &mut rng,
+            );
+        }
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 15);
+
+        // Spend one Txo, so that the account has pending and secreted Txos as well.
+        let bob = service
+            .create_account(
+                Some("Bob's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let bob_account_key: AccountKey = mc_util_serial::decode(&bob.account_key).unwrap();
+        service
+            .build_and_submit(
+                &alice.account_id_hex,
+                &b58_encode(&bob_account_key.subaddress(bob.main_subaddress_index as u64)).unwrap(),
+                (50 * MOB).to_string(),
+                None,
+                None,
+                None,
+                None,
+                TransactionBuildOptions::default(),
+                None,
+            )
+            .unwrap();
+
+        let statuses = AccountTxoStatus::get_all_for_account(
+            &alice.account_id_hex,
+            &service.wallet_db.get_conn().unwrap(),
+        )
+        .unwrap();
+        let counts = service.get_txo_counts(&alice_account_id).unwrap();
+        assert_eq!(
+            counts
+                .iter()
+                .map(|(status, _count)| status.as_str())
+                .collect::<Vec<&str>>(),
+            TXO_STATUSES.to_vec()
+        );
+        for (status, count) in counts.iter() {
+            let expected = statuses.iter().filter(|s| &s.txo_status == status).count();
+            assert_eq!(*count, expected as u64, "count for {}", status);
+        }
+        assert_eq!(
+            counts.iter().map(|(_status, count)| count).sum::<u64>(),
+            statuses.len() as u64
+        );
+
+        // The 100 MOB Txo is pending, and the payment and change are secreted.
+        assert_eq!(counts[0], (TXO_STATUS_UNSPENT.to_string(), 2));
+        assert_eq!(counts[1], (TXO_STATUS_PENDING.to_string(), 1));
+        assert_eq!(counts[3], (TXO_STATUS_SECRETED.to_string(), 2));
+    }
+
+    // Orphaned Txos at subaddresses the account may still assign are kept, while
+    // those beyond the lookahead, and invalidated Txos not in the ledger once
+    // enough blocks follow them, are purged.
+    #[test_with_logger]
+    fn test_purge_unspendable_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+
+        // Both Txos are orphaned, as neither subaddress is assigned.
+        let recoverable_address = alice_account_key.subaddress(5);
+        let unrecoverable_address = alice_account_key
+            .subaddress(alice.next_subaddress_index as u64 + PURGE_SUBADDRESS_LOOKAHEAD);
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![recoverable_address.clone(), unrecoverable_address.clone()],
+            10 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+        let conn = service.wallet_db.get_conn().unwrap();
+        let orphaned_txos =
+            Txo::list_by_status(&alice_account_id.to_string(), TXO_STATUS_ORPHANED, &conn).unwrap();
+        assert_eq!(orphaned_txos.len(), 2);
+        let orphaned_txo_id = |address: &PublicAddress| {
+            orphaned_txos
+                .iter()
+                .find(|txo| {
+                    let tx_out: TxOut = mc_util_serial::decode(&txo.txo).unwrap();
+                    let subaddress_spend_key = recover_public_subaddress_spend_key(
+                        alice_account_key.view_private_key(),
+                        &RistrettoPublic::try_from(&tx_out.target_key).unwrap(),
+                        &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+                    );
+                    subaddress_spend_key == *address.spend_public_key()
+                })
+                .unwrap()
+                .txo_id_hex
+                .clone()
+        };
+        let recoverable_txo_id = orphaned_txo_id(&recoverable_address);
+        let unrecoverable_txo_id = orphaned_txo_id(&unrecoverable_address);
+
+        // A Txo which is not in the ledger.
+        let (invalidated_txo_id, _txo, _key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            20 * MOB as u64,
+            12,
+            &mut rng,
+            &service.wallet_db,
+        );
+        AccountTxoStatus::get(&alice_account_id.to_string(), &invalidated_txo_id, &conn)
+            .unwrap()
+            .set_invalidated(&conn)
+            .unwrap();
+
+        // The invalidated Txo is kept until a block follows the one it was
+        // received in.
+        service.wallet_db.set_invalidated_txo_purge_depth(1);
+        assert_eq!(
+            service
+                .purge_unspendable_txos(&alice_account_id, true)
+                .unwrap(),
+            vec![unrecoverable_txo_id.clone()]
+        );
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![AccountKey::random(&mut rng).default_subaddress()],
+            10 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 14);
+
+        // A dry run lists the Txos without removing them.
+        let mut expected = vec![unrecoverable_txo_id, invalidated_txo_id];
+        expected.sort();
+        let mut would_purge = service
+            .purge_unspendable_txos(&alice_account_id, true)
+            .unwrap();
+        would_purge.sort();
+        assert_eq!(would_purge, expected);
+        assert_eq!(service.list_txos(&alice_account_id, None).unwrap().len(), 3);
+
+        let mut purged = service
+            .purge_unspendable_txos(&alice_account_id, false)
+            .unwrap();
+        purged.sort();
+        assert_eq!(purged, expected);
+        let txos = service.list_txos(&alice_account_id, None).unwrap();
+        assert_eq!(txos.len(), 1);
+        assert_eq!(txos[0].txo.txo_id_hex, recoverable_txo_id);
+
+        // Nothing is left to purge.
+        assert!(service
+            .purge_unspendable_txos(&alice_account_id, false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_release_dust(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+        let alice = service
+            .create_account(
+                Some("Alice's Main Account".to_string()),
+                CreateAccountOptions::default(),
+            )
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+
+        let conn = service.wallet_db.get_conn().unwrap();
+        let (dust_txo_id, _txo, _key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            1000,
+            12,
+            &mut rng,
+            &service.wallet_db,
+        );
+        AccountTxoStatus::get(&alice_account_id.to_string(), &dust_txo_id, &conn)
+            .unwrap()
+            .set_dust(&conn)
+            .unwrap();
+        assert!(Txo::list_spendable(&alice_account_id.to_string(), &conn)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            service.release_dust(&alice_account_id).unwrap(),
+            vec![dust_txo_id.clone()]
+        );
+        let spendable = Txo::list_spendable(&alice_account_id.to_string(), &conn).unwrap();
+        assert_eq!(spendable.len(), 1);
+        assert_eq!(spendable[0].txo_id_hex, dust_txo_id);
+
+        // Nothing is left to release.
+        assert!(service.release_dust(&alice_account_id).unwrap().is_empty());
+    }
 }
@@ -2,7 +2,14 @@
 
 //! The Wallet Service for interacting with the wallet.
 
-use crate::{db::WalletDb, service::sync::SyncThread};
+use crate::{
+    db::WalletDb,
+    service::{
+        ledger::BlockTimestampCache,
+        sync::{SyncThread, SyncThreadConfig},
+        webhook::WebhookThread,
+    },
+};
 use mc_common::logger::{log, Logger};
 use mc_connection::{
     BlockchainConnection, ConnectionManager as McConnectionManager, UserTxConnection,
@@ -42,6 +49,9 @@ pub struct WalletService<
     /// Background ledger sync thread.
     _sync_thread: SyncThread,
 
+    /// Background thread delivering webhook notifications.
+    _webhook_thread: WebhookThread,
+
     /// Monotonically increasing counter. This is used for node round-robin
     /// selection.
     pub submit_node_offset: Arc<AtomicUsize>,
@@ -49,6 +59,9 @@ pub struct WalletService<
     /// Whether the service should run in offline mode.
     pub offline: bool,
 
+    /// Block timestamps read from the ledger.
+    pub block_timestamp_cache: BlockTimestampCache,
+
     /// Logger.
     pub logger: Logger,
 }
@@ -66,6 +79,7 @@ impl<
         network_state: Arc<RwLock<PollingNetworkState<T>>>,
         fog_resolver_factory: Arc<dyn Fn(&[FogUri]) -> Result<FPR, String> + Send + Sync>,
         num_workers: Option<usize>,
+        sync_config: SyncThreadConfig,
         offline: bool,
         logger: Logger,
     ) -> Self {
@@ -74,8 +88,11 @@ impl<
             ledger_db.clone(),
             wallet_db.clone(),
             num_workers,
+            sync_config,
             logger.clone(),
         );
+        log::info!(logger, "Starting Wallet Webhook Task Thread");
+        let webhook_thread = WebhookThread::start(wallet_db.clone(), logger.clone());
         let mut rng = rand::thread_rng();
         WalletService {
             wallet_db,
@@ -84,8 +101,10 @@ impl<
             network_state,
             fog_resolver_factory,
             _sync_thread: sync_thread,
+            _webhook_thread: webhook_thread,
             submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
             offline,
+            block_timestamp_cache: BlockTimestampCache::default(),
             logger,
         }
     }
@@ -0,0 +1,454 @@
+// Copyright (c) 2020-2021 MobileCoin Inc.
+
+//! Notifies an account's webhook URL of the Txos it receives, so that
+//! embedders can be pushed received funds rather than poll for them.
+//!
+//! Sync queues a notification in the database for each Txo an account with a
+//! webhook receives, in the same transaction as the Txo itself, and the
+//! WebhookThread delivers the queued notifications in the background, so that
+//! a slow or unreachable webhook does not hold up sync. A notification is
+//! removed from the queue once it is delivered, so notifications are delivered
+//! at least once, even across restarts.
+//!
+//! Each delivery is retried with exponential backoff. A notification which
+//! still cannot be delivered stays queued and counts as a failure, and once an
+//! account has MAX_WEBHOOK_FAILURES consecutive failures its notifications stop
+//! until the webhook URL is set again.
+
+use crate::db::{
+    account::{AccountID, AccountModel},
+    models::{Account, Txo, WebhookNotification},
+    txo::TxoModel,
+    webhook_notification::WebhookNotificationModel,
+    WalletDb, WalletDbError,
+};
+use mc_common::logger::{log, Logger};
+use reqwest::{
+    blocking::Client,
+    header::{HeaderValue, CONTENT_TYPE},
+};
+use serde_derive::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// The number of consecutive undelivered notifications after which an
+/// account's webhook is disabled.
+pub const MAX_WEBHOOK_FAILURES: i32 = 5;
+
+/// How many times a notification is attempted before it counts as a failure.
+const MAX_WEBHOOK_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry, doubled for each subsequent retry.
+const WEBHOOK_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// How long to wait for the webhook to respond to each attempt.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the WebhookThread checks for queued notifications.
+const WEBHOOK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The payload POSTed to an account's webhook URL for a received Txo.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReceivedTxoNotification {
+    pub account_id: String,
+    pub txo_id: String,
+    pub value_pmob: String,
+    pub block_index: String,
+}
+
+/// Whether an account's webhook has failed too many times in a row to be
+/// notified.
+pub fn is_webhook_disabled(account: &Account) -> bool {
+    account.webhook_failures >= MAX_WEBHOOK_FAILURES
+}
+
+/// Whether notifications should be queued for an account.
+pub fn is_webhook_enabled(account: &Account) -> bool {
+    account.webhook_url.is_some() && !is_webhook_disabled(account)
+}
+
+/// Background thread delivering the queued webhook notifications.
+pub struct WebhookThread {
+    join_handle: Option<thread::JoinHandle<()>>,
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl WebhookThread {
+    pub fn start(wallet_db: WalletDb, logger: Logger) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("webhook".to_string())
+                .spawn(move || {
+                    while !thread_stop_requested.load(Ordering::SeqCst) {
+                        if let Err(e) = deliver_all_queued_notifications(&wallet_db, &logger) {
+                            log::error!(logger, "Could not deliver webhook notifications: {:?}", e);
+                        }
+                        thread::sleep(WEBHOOK_POLL_INTERVAL);
+                    }
+                })
+                .expect("failed starting webhook thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("WebhookThread join failed");
+        }
+    }
+}
+
+impl Drop for WebhookThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Deliver the queued notifications of every account with an enabled webhook.
+fn deliver_all_queued_notifications(
+    wallet_db: &WalletDb,
+    logger: &Logger,
+) -> Result<(), WalletDbError> {
+    let accounts = Account::list_all(&wallet_db.get_conn()?)?;
+    for account in accounts.iter().filter(|a| is_webhook_enabled(a)) {
+        deliver_queued_notifications(wallet_db, &account.account_id_hex, logger)?;
+    }
+    Ok(())
+}
+
+/// Deliver an account's queued notifications, oldest first. Delivery stops at
+/// the first notification which cannot be delivered, which stays queued to be
+/// retried. Does nothing if the account has no webhook URL or its webhook is
+/// disabled.
+pub fn deliver_queued_notifications(
+    wallet_db: &WalletDb,
+    account_id_hex: &str,
+    logger: &Logger,
+) -> Result<(), WalletDbError> {
+    let conn = wallet_db.get_conn()?;
+    let account = Account::get(&AccountID(account_id_hex.to_string()), &conn)?;
+    let webhook_url = match &account.webhook_url {
+        Some(url) if !is_webhook_disabled(&account) => url.clone(),
+        _ => return Ok(()),
+    };
+
+    let notifications = WebhookNotification::list_for_account(account_id_hex, &conn)?;
+    if notifications.is_empty() {
+        return Ok(());
+    }
+
+    let client = match Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!(logger, "Could not build webhook client: {:?}", e);
+            return Ok(());
+        }
+    };
+
+    let mut webhook_failures = account.webhook_failures;
+    for notification in notifications {
+        let txo = Txo::get(&notification.txo_id_hex, &conn)?.txo;
+        let payload = ReceivedTxoNotification {
+            account_id: account_id_hex.to_string(),
+            txo_id: notification.txo_id_hex.clone(),
+            value_pmob: (txo.value as u64).to_string(),
+            block_index: notification.received_block_index.to_string(),
+        };
+
+        if deliver(&client, &webhook_url, &payload, logger) {
+            webhook_failures = 0;
+            notification.delete(&conn)?;
+        } else {
+            webhook_failures += 1;
+            if webhook_failures >= MAX_WEBHOOK_FAILURES {
+                log::warn!(
+                    logger,
+                    "Disabling the webhook for account {} after {} failed notifications",
+                    account_id_hex,
+                    webhook_failures
+                );
+            }
+            break;
+        }
+    }
+
+    if webhook_failures != account.webhook_failures {
+        account.update_webhook_failures(webhook_failures, &conn)?;
+    }
+    Ok(())
+}
+
+/// POST a notification, retrying with exponential backoff. Returns whether it
+/// was delivered.
+fn deliver(
+    client: &Client,
+    webhook_url: &str,
+    notification: &ReceivedTxoNotification,
+    logger: &Logger,
+) -> bool {
+    let body = match serde_json::to_vec(notification) {
+        Ok(body) => body,
+        Err(e) => {
+            log::error!(logger, "Could not serialize webhook notification: {:?}", e);
+            return false;
+        }
+    };
+
+    let mut delay = WEBHOOK_RETRY_BASE_DELAY;
+    for attempt in 1..=MAX_WEBHOOK_ATTEMPTS {
+        match client
+            .post(webhook_url)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(body.clone())
+            .send()
+        {
+            Ok(response) if response.status().is_success() => return true,
+            Ok(response) => log::warn!(
+                logger,
+                "Webhook {} responded with {} on attempt {}",
+                webhook_url,
+                response.status(),
+                attempt
+            ),
+            Err(e) => log::warn!(
+                logger,
+                "Could not notify webhook {} on attempt {}: {:?}",
+                webhook_url,
+                attempt,
+                e
+            ),
+        }
+        if attempt < MAX_WEBHOOK_ATTEMPTS {
+            thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::account::{AccountService, CreateAccountOptions},
+        test_utils::{
+            add_block_to_ledger_db, create_test_received_txo, get_test_ledger,
+            setup_wallet_service, wait_for_sync, WalletDbTestContext, MOB,
+        },
+    };
+    use mc_account_keys::{AccountKey, PublicAddress, RootIdentity};
+    use mc_common::logger::{test_with_logger, Logger};
+    use mc_crypto_rand::RngCore;
+    use mc_transaction_core::ring_signature::KeyImage;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    // Accept HTTP requests on a local port, responding with the given status
+    // and sending each request body to the returned channel.
+    fn start_http_sink(status: u16) -> (String, mpsc::Receiver<serde_json::Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/received", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    let lowercase = line.to_lowercase();
+                    if let Some(value) = lowercase.strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+                // Record the request before responding, so that it is received
+                // by the time the request returns.
+                if sender.send(serde_json::from_slice(&body).unwrap()).is_err() {
+                    break;
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    #[test_with_logger]
+    fn test_webhook_notified_on_receive(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let mut ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db.clone(), logger);
+        let alice = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+
+        let (url, receiver) = start_http_sink(200);
+        let alice = service
+            .set_webhook_url(&alice_account_id, Some(url))
+            .unwrap();
+        assert!(alice.webhook_url.is_some());
+
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![alice_account_key.subaddress(alice.main_subaddress_index as u64)],
+            100 * MOB as u64,
+            &vec![KeyImage::from(rng.next_u64())],
+            &mut rng,
+        );
+        wait_for_sync(&ledger_db, &service.wallet_db, &alice_account_id, 13);
+
+        let notification = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        let txo_id = notification["txo_id"].as_str().unwrap();
+        assert_eq!(notification["account_id"], alice_account_id.to_string());
+        assert_eq!(notification["value_pmob"], (100 * MOB).to_string());
+        assert_eq!(notification["block_index"], "12");
+        let conn = service.wallet_db.get_conn().unwrap();
+        Txo::get(txo_id, &conn).unwrap();
+
+        // The notification is removed from the queue once delivered.
+        let mut queued = 1;
+        for _ in 0..100 {
+            queued = WebhookNotification::list_for_account(&alice.account_id_hex, &conn)
+                .unwrap()
+                .len();
+            if queued == 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(queued, 0);
+    }
+
+    // A webhook which keeps failing should be disabled until it is set again.
+    #[test_with_logger]
+    fn test_webhook_disabled_after_failures(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // Deliver from a wallet without a WebhookThread, so that only the
+        // deliveries below reach the sink.
+        let db_test_context = WalletDbTestContext::default();
+        let wallet_db = db_test_context.get_db_instance(logger.clone());
+        let conn = wallet_db.get_conn().unwrap();
+
+        let root_id = RootIdentity::from_random(&mut rng);
+        let alice_account_key = AccountKey::from(&root_id);
+        let (alice_account_id, _public_address_b58) = Account::create_from_root_entropy(
+            &root_id.root_entropy,
+            Some(0),
+            None,
+            None,
+            "Alice",
+            None,
+            None,
+            None,
+            &conn,
+        )
+        .unwrap();
+        let (txo_id_hex, _txo, _key_image) =
+            create_test_received_txo(&alice_account_key, 0, MOB as u64, 12, &mut rng, &wallet_db);
+
+        let (url, receiver) = start_http_sink(500);
+        Account::get(&alice_account_id, &conn)
+            .unwrap()
+            .update_webhook_url(Some(&url), &conn)
+            .unwrap();
+        WebhookNotification::queue(&alice_account_id.to_string(), &txo_id_hex, 12, &conn).unwrap();
+
+        for _ in 0..MAX_WEBHOOK_FAILURES + 1 {
+            deliver_queued_notifications(&wallet_db, &alice_account_id.to_string(), &logger)
+                .unwrap();
+        }
+
+        // Every attempt of each failed delivery reached the sink, and no delivery
+        // was attempted once the webhook was disabled.
+        let num_attempts = receiver.try_iter().count() as u32;
+        assert_eq!(
+            num_attempts,
+            MAX_WEBHOOK_FAILURES as u32 * MAX_WEBHOOK_ATTEMPTS
+        );
+        let account = Account::get(&alice_account_id, &conn).unwrap();
+        assert!(is_webhook_disabled(&account));
+
+        // The undelivered notification stays queued.
+        let queued =
+            WebhookNotification::list_for_account(&alice_account_id.to_string(), &conn).unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].txo_id_hex, txo_id_hex);
+
+        // Setting the webhook again enables it.
+        account.update_webhook_url(Some(&url), &conn).unwrap();
+        let account = Account::get(&alice_account_id, &conn).unwrap();
+        assert!(!is_webhook_disabled(&account));
+    }
+
+    // Removing an account's webhook should drop its undelivered notifications.
+    #[test_with_logger]
+    fn test_removing_webhook_drops_queued_notifications(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let known_recipients: Vec<PublicAddress> = Vec::new();
+        let ledger_db = get_test_ledger(5, &known_recipients, 12, &mut rng);
+
+        let service = setup_wallet_service(ledger_db, logger);
+        let alice = service
+            .create_account(Some("Alice".to_string()), CreateAccountOptions::default())
+            .unwrap();
+        let alice_account_key: AccountKey = mc_util_serial::decode(&alice.account_key).unwrap();
+        let alice_account_id = AccountID::from(&alice_account_key);
+        let (txo_id_hex, _txo, _key_image) = create_test_received_txo(
+            &alice_account_key,
+            0,
+            MOB as u64,
+            12,
+            &mut rng,
+            &service.wallet_db,
+        );
+
+        let (url, _receiver) = start_http_sink(500);
+        service
+            .set_webhook_url(&alice_account_id, Some(url))
+            .unwrap();
+        let conn = service.wallet_db.get_conn().unwrap();
+        WebhookNotification::queue(&alice.account_id_hex, &txo_id_hex, 12, &conn).unwrap();
+
+        service.set_webhook_url(&alice_account_id, None).unwrap();
+        assert!(
+            WebhookNotification::list_for_account(&alice.account_id_hex, &conn)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -6,10 +6,13 @@ use crate::{
         models::{Account, TransactionLog, Txo, TXO_USED_AS_CHANGE, TXO_USED_AS_OUTPUT},
         transaction_log::TransactionLogModel,
         txo::TxoModel,
-        WalletDb, WalletDbError,
+        ConnectionOptions, WalletDb, WalletDbConnection, WalletDbError,
     },
     error::SyncError,
-    service::{sync::sync_account, transaction_builder::WalletTransactionBuilder},
+    service::{
+        sync::{sync_account, SyncThreadConfig},
+        transaction_builder::WalletTransactionBuilder,
+    },
     WalletService,
 };
 use diesel::{
@@ -20,10 +23,13 @@ use diesel_migrations::embed_migrations;
 use mc_account_keys::{AccountKey, PublicAddress, RootIdentity};
 use mc_attest_core::Verifier;
 use mc_common::logger::Logger;
-use mc_connection::{Connection, ConnectionManager, HardcodedCredentialsProvider, ThickClient};
+use mc_connection::{
+    BlockchainConnection, Connection, ConnectionManager, HardcodedCredentialsProvider, ThickClient,
+    UserTxConnection,
+};
 use mc_connection_test_utils::{test_client_uri, MockBlockchainConnection};
 use mc_consensus_scp::QuorumSet;
-use mc_crypto_keys::{RistrettoPrivate, RistrettoPublic};
+use mc_crypto_keys::{Ed25519Pair, RistrettoPrivate, RistrettoPublic};
 use mc_crypto_rand::{CryptoRng, RngCore};
 use mc_fog_report_validation::{FullyValidatedFogPubkey, MockFogPubkeyResolver};
 use mc_ledger_db::{Ledger, LedgerDB};
@@ -34,15 +40,19 @@ use mc_transaction_core::{
     onetime_keys::{create_onetime_public_key, recover_onetime_private_key},
     ring_signature::KeyImage,
     tx::{Tx, TxOut},
-    Block, BlockContents, BLOCK_VERSION,
+    Block, BlockContents, BlockID, BlockIndex, BlockSignature, BLOCK_VERSION,
 };
 use mc_util_from_random::FromRandom;
 use mc_util_uri::{ConnectionUri, FogUri};
 use rand::{distributions::Alphanumeric, rngs::StdRng, thread_rng, Rng, SeedableRng};
 use std::{
+    cmp::Ordering,
     convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Range,
     path::PathBuf,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
     time::Duration,
 };
 use tempdir::TempDir;
@@ -86,10 +96,24 @@ impl Default for WalletDbTestContext {
 }
 
 impl WalletDbTestContext {
+    /// The path of the database file.
+    pub fn db_path(&self) -> String {
+        format!("{}/{}", self.base_url, self.db_name)
+    }
+
     pub fn get_db_instance(&self, logger: Logger) -> WalletDb {
         // Note: Setting db_connections too high results in IO Error: Too many open
         // files.
-        WalletDb::new_from_url(&format!("{}/{}", self.base_url, self.db_name), 7, logger)
+        self.get_db_instance_with_options(7, ConnectionOptions::default(), logger)
+    }
+
+    pub fn get_db_instance_with_options(
+        &self,
+        db_connections: u32,
+        connection_options: ConnectionOptions,
+        logger: Logger,
+    ) -> WalletDb {
+        WalletDb::new_from_url(&self.db_path(), db_connections, connection_options, logger)
             .expect("failed creating new SqlRecoveryDb")
     }
 }
@@ -158,19 +182,21 @@ pub fn generate_ledger_db(path: &str) -> LedgerDB {
     db
 }
 
-fn append_test_block(ledger_db: &mut LedgerDB, block_contents: BlockContents) -> u64 {
+fn new_test_block(ledger_db: &LedgerDB, block_contents: &BlockContents) -> Block {
     let num_blocks = ledger_db.num_blocks().expect("failed to get block height");
 
-    let new_block;
     if num_blocks > 0 {
         let parent = ledger_db
             .get_block(num_blocks - 1)
             .expect("failed to get parent block");
-        new_block =
-            Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), &block_contents);
+        Block::new_with_parent(BLOCK_VERSION, &parent, &Default::default(), block_contents)
     } else {
-        new_block = Block::new_origin_block(&block_contents.outputs);
+        Block::new_origin_block(&block_contents.outputs)
     }
+}
+
+fn append_test_block(ledger_db: &mut LedgerDB, block_contents: BlockContents) -> u64 {
+    let new_block = new_test_block(ledger_db, &block_contents);
 
     ledger_db
         .append_block(&new_block, &block_contents, None)
@@ -213,6 +239,42 @@ pub fn add_block_to_ledger_db(
     append_test_block(ledger_db, block_contents)
 }
 
+/// Adds a block containing one txo for each provided recipient, signed at the
+/// given time, and returns new block height.
+pub fn add_signed_block_to_ledger_db(
+    ledger_db: &mut LedgerDB,
+    recipients: &[PublicAddress],
+    output_value: u64,
+    signed_at: u64,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> u64 {
+    let outputs: Vec<_> = recipients
+        .iter()
+        .map(|recipient| {
+            TxOut::new(
+                output_value,
+                recipient,
+                &RistrettoPrivate::from_random(rng),
+                Default::default(),
+            )
+            .unwrap()
+        })
+        .collect();
+
+    let block_contents = BlockContents::new(vec![KeyImage::from(rng.next_u64())], outputs);
+    let new_block = new_test_block(ledger_db, &block_contents);
+    let mut signature =
+        BlockSignature::from_block_and_keypair(&new_block, &Ed25519Pair::from_random(rng))
+            .expect("failed signing block");
+    signature.set_signed_at(signed_at);
+
+    ledger_db
+        .append_block(&new_block, &block_contents, Some(signature))
+        .expect("failed writing signed block");
+
+    ledger_db.num_blocks().expect("failed to get block height")
+}
+
 pub fn add_block_with_tx_proposal(ledger_db: &mut LedgerDB, tx_proposal: TxProposal) -> u64 {
     let block_contents = BlockContents::new(
         tx_proposal.tx.key_images(),
@@ -228,7 +290,7 @@ pub fn add_block_with_tx(ledger_db: &mut LedgerDB, tx: Tx) -> u64 {
 
 pub fn add_block_from_transaction_log(
     ledger_db: &mut LedgerDB,
-    conn: &PooledConnection<CM<SqliteConnection>>,
+    conn: &PooledConnection<CM<WalletDbConnection>>,
     transaction_log: &TransactionLog,
 ) -> u64 {
     let associated_txos = transaction_log.get_associated_txos(conn).unwrap();
@@ -306,6 +368,85 @@ pub fn setup_peer_manager_and_network_state(
     (peer_manager, network_state)
 }
 
+/// A MockBlockchainConnection whose proposed transactions fail with an error
+/// set by the test.
+#[derive(Clone)]
+pub struct FailingUserTxConnection {
+    inner: MockBlockchainConnection<LedgerDB>,
+
+    /// The error the next proposed transaction fails with, shared by every
+    /// connection to the peers. Transactions succeed while it is None.
+    pub next_propose_tx_error: Arc<Mutex<Option<mc_connection::Error>>>,
+}
+
+impl fmt::Display for FailingUserTxConnection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl PartialEq for FailingUserTxConnection {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl Eq for FailingUserTxConnection {}
+
+impl Hash for FailingUserTxConnection {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
+    }
+}
+
+impl PartialOrd for FailingUserTxConnection {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FailingUserTxConnection {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.inner.cmp(&other.inner)
+    }
+}
+
+impl Connection for FailingUserTxConnection {
+    type Uri = <MockBlockchainConnection<LedgerDB> as Connection>::Uri;
+
+    fn uri(&self) -> Self::Uri {
+        self.inner.uri()
+    }
+}
+
+impl BlockchainConnection for FailingUserTxConnection {
+    fn fetch_blocks(&mut self, range: Range<BlockIndex>) -> mc_connection::Result<Vec<Block>> {
+        self.inner.fetch_blocks(range)
+    }
+
+    fn fetch_block_ids(&mut self, range: Range<BlockIndex>) -> mc_connection::Result<Vec<BlockID>> {
+        self.inner.fetch_block_ids(range)
+    }
+
+    fn fetch_block_height(&mut self) -> mc_connection::Result<BlockIndex> {
+        self.inner.fetch_block_height()
+    }
+}
+
+impl UserTxConnection for FailingUserTxConnection {
+    fn propose_tx(&mut self, tx: &Tx) -> mc_connection::Result<u64> {
+        match self
+            .next_propose_tx_error
+            .lock()
+            .expect("mutex poisoned")
+            .take()
+        {
+            Some(error) => Err(error),
+            None => self.inner.propose_tx(tx),
+        }
+    }
+}
+
 pub fn add_block_with_db_txos(
     ledger_db: &mut LedgerDB,
     wallet_db: &WalletDb,
@@ -585,6 +726,7 @@ pub fn random_account_with_seed_values(
         assert_eq!(
             Txo::list_for_account(
                 &AccountID::from(&account_key).to_string(),
+                false,
                 &wallet_db.get_conn().unwrap(),
             )
             .unwrap()
@@ -643,6 +785,56 @@ pub fn get_resolver_factory(
     Ok(fog_pubkey_resolver_factory)
 }
 
+/// Set up a WalletService whose peers fail to accept a transaction with the
+/// error stored in the returned handle, if any.
+pub fn setup_wallet_service_with_failing_peers(
+    ledger_db: LedgerDB,
+    logger: Logger,
+) -> (
+    WalletService<FailingUserTxConnection, MockFogPubkeyResolver>,
+    Arc<Mutex<Option<mc_connection::Error>>>,
+) {
+    let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+    let db_test_context = WalletDbTestContext::default();
+    let wallet_db = db_test_context.get_db_instance(logger.clone());
+
+    let next_propose_tx_error = Arc::new(Mutex::new(None));
+    let peers: Vec<FailingUserTxConnection> = (1..=2)
+        .map(|i| FailingUserTxConnection {
+            inner: MockBlockchainConnection::new(test_client_uri(i), ledger_db.clone(), 0),
+            next_propose_tx_error: next_propose_tx_error.clone(),
+        })
+        .collect();
+    let quorum_set = QuorumSet::new_with_node_ids(
+        2,
+        peers
+            .iter()
+            .map(|peer| peer.uri().responder_id().unwrap())
+            .collect(),
+    );
+    let peer_manager = ConnectionManager::new(peers, logger.clone());
+    let network_state = Arc::new(RwLock::new(PollingNetworkState::new(
+        quorum_set,
+        peer_manager.clone(),
+        logger.clone(),
+    )));
+    network_state.write().unwrap().poll();
+
+    let service = WalletService::new(
+        wallet_db,
+        ledger_db,
+        peer_manager,
+        network_state,
+        get_resolver_factory(&mut rng).unwrap(),
+        None,
+        SyncThreadConfig::default(),
+        false,
+        logger,
+    );
+    (service, next_propose_tx_error)
+}
+
 pub fn setup_wallet_service(
     ledger_db: LedgerDB,
     logger: Logger,
@@ -661,6 +853,7 @@ pub fn setup_wallet_service(
         network_state,
         get_resolver_factory(&mut rng).unwrap(),
         None,
+        SyncThreadConfig::default(),
         false,
         logger,
     )